@@ -0,0 +1,5 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(init: doke::fuzzing::init(), |data: &[u8]| doke::fuzzing::markdown(data));
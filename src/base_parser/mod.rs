@@ -32,7 +32,7 @@ pub struct DokeBaseDocument<'a> {
 }
 
 /// Position in the source string
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct Position {
     pub start: usize,
     pub end: usize,
@@ -70,6 +70,8 @@ pub struct DokeStatement<'a> {
     pub full_position: Option<Position>,
     pub children_position: Option<Position>,
     pub code_blocks: Vec<CodeBlock<'a>>,
+    /// The statement starts a list item
+    pub list_item: bool,
 }
 
 pub struct DokeBaseParser;
@@ -192,16 +194,20 @@ impl DokeBaseParser {
             full_position: node.position().map(Self::convert_position),
             children_position: None,
             code_blocks,
+            list_item: false,
         }
     }
 
     fn parse_list_item<'a>(item: &'a Node) -> Option<DokeStatement<'a>> {
-        assert!(matches!(item, Node::ListItem(_)));
+        if !matches!(item, Node::ListItem(_)) {
+            return None;
+        }
 
         if let Some(kids) = item.children() {
             let substmts = Self::parse_sibling_blocks(kids);
             if !substmts.is_empty() {
                 let mut first = substmts[0].clone();
+                first.list_item = true;
                 first.children.extend(substmts.into_iter().skip(1));
                 first.children_position = first
                     .children
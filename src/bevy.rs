@@ -0,0 +1,261 @@
+// bevy.rs
+//
+// Converting built values into `bevy_reflect` dynamic values, so Bevy
+// projects can author their content with Doke too.
+//
+// Resources become `DynamicStruct`s of the type registered under their
+// type name, and numbers are cast to the type of the field they go in,
+// so the result can be applied to, or built with `FromReflect` into, the
+// real types. Vectors, rectangles and colors become structs of their components.
+
+use bevy_reflect::{
+    DynamicEnum, DynamicList, DynamicMap, DynamicStruct, DynamicTuple, DynamicVariant, Map,
+    PartialReflect, TypeInfo, TypeRegistry, VariantInfo,
+};
+use thiserror::Error;
+
+use crate::GodotValue;
+use crate::parsers::param_types::EXT_REF_TYPE;
+
+#[derive(Debug, Error)]
+pub enum BevyError {
+    #[error("No type registered for resource type {0}")]
+    UnregisteredType(String),
+
+    #[error("{0} is registered but isn't a struct")]
+    NotAStruct(String),
+
+    #[error("{type_name} has no field {field}")]
+    UnknownField { type_name: String, field: String },
+
+    #[error("Can't convert {value} to {expected}")]
+    Mismatch { value: String, expected: String },
+}
+
+/// Convert a built resource into a `DynamicStruct` representing its registered type.
+///
+/// Types are looked up by their short type path (`Item`), then their full type path.
+/// Fields starting with `doke_` are metadata and are left out, as are `Nil` fields
+/// that aren't `Option`s.
+/// ```
+/// use std::collections::HashMap;
+/// use bevy_reflect::{FromReflect, Reflect, TypeRegistry};
+/// use doke::GodotValue;
+/// use doke::bevy::to_dynamic_struct;
+///
+/// #[derive(Reflect, Debug, PartialEq)]
+/// struct Item {
+///     name: String,
+///     damage: u8,
+///     weight: f32,
+/// }
+///
+/// let mut registry = TypeRegistry::default();
+/// registry.register::<Item>();
+/// let sword = GodotValue::Resource {
+///     type_name: "Item".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::from([
+///         ("name".into(), GodotValue::String("Iron sword".into())),
+///         ("damage".into(), GodotValue::Int(5)),
+///         ("weight".into(), GodotValue::Float(2.5)),
+///         ("doke_tr_key".into(), GodotValue::String("IRON_SWORD".into())),
+///     ]),
+/// };
+/// let item = Item::from_reflect(&to_dynamic_struct(&sword, &registry).unwrap());
+/// assert_eq!(item, Some(Item { name: "Iron sword".into(), damage: 5, weight: 2.5 }));
+///
+/// let shield = GodotValue::Resource {
+///     type_name: "Shield".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::new(),
+/// };
+/// assert!(to_dynamic_struct(&shield, &registry).is_err());
+/// ```
+pub fn to_dynamic_struct(
+    resource: &GodotValue,
+    registry: &TypeRegistry,
+) -> Result<DynamicStruct, BevyError> {
+    let GodotValue::Resource {
+        type_name, fields, ..
+    } = resource
+    else {
+        return Err(mismatch(resource, "a resource"));
+    };
+    let registration = registry
+        .get_with_short_type_path(type_name)
+        .or_else(|| registry.get_with_type_path(type_name))
+        .ok_or_else(|| BevyError::UnregisteredType(type_name.clone()))?;
+    let TypeInfo::Struct(info) = registration.type_info() else {
+        return Err(BevyError::NotAStruct(type_name.clone()));
+    };
+
+    let mut names: Vec<&String> = fields.keys().collect();
+    names.sort();
+    let mut out = DynamicStruct::default();
+    out.set_represented_type(Some(registration.type_info()));
+    for name in names {
+        if name.starts_with("doke_") {
+            continue;
+        }
+        let field = info.field(name).ok_or_else(|| BevyError::UnknownField {
+            type_name: type_name.clone(),
+            field: name.clone(),
+        })?;
+        let expected = field.type_info();
+        if matches!(fields[name], GodotValue::Nil) && option_inner(expected).is_none() {
+            continue;
+        }
+        out.insert_boxed(
+            name.as_str(),
+            to_reflect(&fields[name], expected, registry)?,
+        );
+    }
+    Ok(out)
+}
+
+/// Convert any built value, as the type `expected` when given.
+///
+/// Without a type, numbers stay `i64`, `u64` or `f64`, and `ExtRef`s become
+/// the id they point at.
+pub fn to_reflect(
+    value: &GodotValue,
+    expected: Option<&'static TypeInfo>,
+    registry: &TypeRegistry,
+) -> Result<Box<dyn PartialReflect>, BevyError> {
+    if let Some(inner) = option_inner(expected) {
+        let mut out = match value {
+            GodotValue::Nil => DynamicEnum::new("None", DynamicVariant::Unit),
+            value => {
+                let mut some = DynamicTuple::default();
+                some.insert_boxed(to_reflect(value, inner, registry)?);
+                DynamicEnum::new("Some", DynamicVariant::Tuple(some))
+            }
+        };
+        out.set_represented_type(expected);
+        return Ok(Box::new(out));
+    }
+
+    let path = expected.map(TypeInfo::type_path);
+    Ok(match value {
+        GodotValue::Nil => Box::new(()),
+        GodotValue::Bool(b) => Box::new(*b),
+        GodotValue::Int(i) => integer(*i as i128, value, path)?,
+        GodotValue::UInt(u) => integer(*u as i128, value, path)?,
+        GodotValue::Float(f) => match path {
+            None | Some("f64") => Box::new(*f),
+            Some("f32") => Box::new(*f as f32),
+            Some(expected) => return Err(mismatch(value, expected)),
+        },
+        // unit enum variants are written as their name
+        GodotValue::String(s) => match expected {
+            Some(TypeInfo::Enum(info)) if info.contains_variant(s) => {
+                let mut out = DynamicEnum::new(s.as_str(), DynamicVariant::Unit);
+                out.set_represented_type(expected);
+                Box::new(out)
+            }
+            _ => Box::new(s.clone()),
+        },
+        GodotValue::Array(items) => {
+            let item = match expected {
+                Some(TypeInfo::List(info)) => info.item_info(),
+                _ => None,
+            };
+            let mut out = DynamicList::default();
+            out.set_represented_type(expected.filter(|info| matches!(info, TypeInfo::List(_))));
+            for value in items {
+                out.push_box(to_reflect(value, item, registry)?);
+            }
+            Box::new(out)
+        }
+        GodotValue::Dict(fields) => {
+            let item = match expected {
+                Some(TypeInfo::Map(info)) => info.value_info(),
+                _ => None,
+            };
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            let mut out = DynamicMap::default();
+            out.set_represented_type(expected.filter(|info| matches!(info, TypeInfo::Map(_))));
+            for name in names {
+                out.insert_boxed(
+                    Box::new(name.clone()),
+                    to_reflect(&fields[name], item, registry)?,
+                );
+            }
+            Box::new(out)
+        }
+        GodotValue::Resource {
+            type_name, fields, ..
+        } if type_name == EXT_REF_TYPE => match fields.get("id") {
+            Some(GodotValue::String(id)) => Box::new(id.clone()),
+            _ => return Err(mismatch(value, "a reference")),
+        },
+        GodotValue::Resource { .. } => Box::new(to_dynamic_struct(value, registry)?),
+        // structs with `x`, `y` (`width`, `height`) fields, like glam's `Vec2`
+        GodotValue::Vector2(..) | GodotValue::Rect2(..) | GodotValue::Color(..) => {
+            let info = match expected {
+                Some(TypeInfo::Struct(info)) => Some(info),
+                _ => None,
+            };
+            let components = value.components().unwrap_or_default();
+            let mut names: Vec<&String> = components.keys().collect();
+            names.sort();
+            let mut out = DynamicStruct::default();
+            out.set_represented_type(expected.filter(|_| info.is_some()));
+            for name in names {
+                let field = info.and_then(|info| info.field(name)?.type_info());
+                out.insert_boxed(
+                    name.as_str(),
+                    to_reflect(&components[name], field, registry)?,
+                );
+            }
+            Box::new(out)
+        }
+    })
+}
+
+/// The type inside `expected` if it is an `Option`
+fn option_inner(expected: Option<&'static TypeInfo>) -> Option<Option<&'static TypeInfo>> {
+    let Some(TypeInfo::Enum(info)) = expected else {
+        return None;
+    };
+    if !info.type_path().starts_with("core::option::Option<") {
+        return None;
+    }
+    match info.variant("Some") {
+        Some(VariantInfo::Tuple(some)) => Some(some.field_at(0).and_then(|f| f.type_info())),
+        _ => None,
+    }
+}
+
+fn integer(
+    i: i128,
+    value: &GodotValue,
+    expected: Option<&str>,
+) -> Result<Box<dyn PartialReflect>, BevyError> {
+    macro_rules! cast {
+        ($($t:ty),*) => {
+            match expected {
+                None => Ok(match value {
+                    GodotValue::UInt(u) => Box::new(*u),
+                    _ => Box::new(i as i64),
+                }),
+                Some("f32") => Ok(Box::new(i as f32)),
+                Some("f64") => Ok(Box::new(i as f64)),
+                $(Some(stringify!($t)) => <$t>::try_from(i)
+                    .map(|n| Box::new(n) as Box<dyn PartialReflect>)
+                    .map_err(|_| mismatch(value, stringify!($t))),)*
+                Some(expected) => Err(mismatch(value, expected)),
+            }
+        };
+    }
+    cast!(i8, i16, i32, i64, isize, u8, u16, u32, u64, usize)
+}
+
+fn mismatch(value: &GodotValue, expected: &str) -> BevyError {
+    BevyError::Mismatch {
+        value: value.to_string(),
+        expected: expected.to_string(),
+    }
+}
@@ -0,0 +1,79 @@
+// ci.rs
+//
+// Checks for pre-commit hooks: only the documents a commit touches are parsed,
+// so the hook stays fast on content bases too large to check in full.
+//
+// Each changed document must validate, and ids must be unique among them.
+// References to other documents aren't checked, that takes the whole
+// workspace: leave it to `doke check` in CI.
+
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use crate::DokePipe;
+use crate::workspace::{Workspace, WorkspaceDiagnostic};
+
+/// Workspace diagnostics that need every document to be right
+const WORKSPACE_WIDE: [&str; 2] = ["workspace-unresolved-ref", "workspace-dependency-cycle"];
+
+/// Check the documents at `paths` with `pipe`, see the module docs.
+///
+/// Files of another format than `pipe`'s, override files and deleted files are
+/// left out, so `paths` can be a raw list of changed files.
+/// ```no_run
+/// use doke::{DokePipe, ci};
+///
+/// let pipe = DokePipe::new();
+/// let changed = ci::staged_files("content".as_ref())?;
+/// for diagnostic in ci::check_changed(&changed, &pipe)? {
+///     eprintln!("{}", diagnostic);
+/// }
+/// # Ok::<(), std::io::Error>(())
+/// ```
+pub fn check_changed(paths: &[PathBuf], pipe: &DokePipe) -> io::Result<Vec<WorkspaceDiagnostic>> {
+    let extension = pipe.extension();
+    let overrides = format!(".override.{}", extension);
+    let mut workspace = Workspace::new();
+    for path in paths {
+        let checked = path.extension().is_some_and(|e| e == extension)
+            && !path.to_string_lossy().ends_with(&overrides)
+            && path.is_file();
+        if checked {
+            workspace.add_document(path, fs::read_to_string(path)?, pipe);
+        }
+    }
+    Ok(workspace
+        .check()
+        .into_iter()
+        .filter(|d| !WORKSPACE_WIDE.contains(&d.diagnostic.code))
+        .collect())
+}
+
+/// Files under `dir` staged in its git repository, added, copied, modified or
+/// renamed, as paths joined to `dir`
+pub fn staged_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
+    let output = Command::new("git")
+        .args([
+            "diff",
+            "--cached",
+            "--name-only",
+            "--diff-filter=ACMR",
+            "--relative",
+            "-z",
+        ])
+        .current_dir(dir)
+        .output()?;
+    if !output.status.success() {
+        return Err(io::Error::other(format!(
+            "git diff failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .split('\0')
+        .filter(|name| !name.is_empty())
+        .map(|name| dir.join(name))
+        .collect())
+}
@@ -0,0 +1,453 @@
+// codegen/mod.rs
+//
+// Types for the values a grammar builds, for programs consuming the pipeline's
+// output. `Schema` reads them off the dokedef sections: a section making
+// resources is a type whose fields are its phrases' parameters, and an abstract
+// type is one of its sections' types. A build script writes them as Rust,
+//
+//     let grammar = TypedSentencesParser::from_config_file(Path::new("Item.dokeconfig.yaml"))?;
+//     let out = Path::new(&env::var("OUT_DIR")?).join("doke_types.rs");
+//     fs::write(out, Schema::from_grammar(&grammar).to_rust())?;
+//
+// included with `include!(concat!(env!("OUT_DIR"), "/doke_types.rs"))`, so a
+// game server reads `effect.damage` instead of looking up a "damage" field.
+// The generated types convert from `GodotValue` with `TryFrom`, through the
+// `FromGodot` trait below.
+//
+// Web tools get the same types as TypeScript declarations of the JSON
+// `export::json` writes, with `to_typescript`.
+
+mod rust;
+mod typescript;
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use thiserror::Error;
+
+use crate::parsers::TypedSentencesParser;
+use crate::parsers::param_types::is_basic_type;
+use crate::parsers::sentence::{PhraseConfig, ReturnSpec};
+use crate::{DokeOut, GodotValue};
+
+/// A value of a generated type had another shape
+#[derive(Debug, Clone, PartialEq, Error)]
+#[error("{path}: expected {expected}, got {found}")]
+pub struct ConversionError {
+    /// Fields leading to the value, `effects.0.damage`
+    pub path: String,
+    pub expected: String,
+    pub found: String,
+}
+
+impl ConversionError {
+    pub fn new(expected: impl Into<String>, found: &GodotValue) -> Self {
+        let found = match found {
+            GodotValue::Resource { type_name, .. } => type_name.clone(),
+            value => value.kind().to_string(),
+        };
+        Self {
+            path: String::new(),
+            expected: expected.into(),
+            found,
+        }
+    }
+
+    /// The error of a value inside `field`
+    fn within(mut self, field: &str) -> Self {
+        self.path = match self.path.is_empty() {
+            true => field.to_string(),
+            false => format!("{}.{}", field, self.path),
+        };
+        self
+    }
+}
+
+/// Conversion of values built by the pipeline, implemented by generated types
+/// ```
+/// use doke::GodotValue;
+/// use doke::codegen::FromGodot;
+///
+/// assert_eq!(f64::from_godot(GodotValue::UInt(3)).unwrap(), 3.0);
+/// assert_eq!(f64::from_godot(GodotValue::Int(-2)).unwrap(), -2.0);
+/// assert!(i64::from_godot(GodotValue::UInt(u64::MAX)).is_err());
+/// ```
+pub trait FromGodot: Sized {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError>;
+}
+
+impl FromGodot for GodotValue {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        Ok(value)
+    }
+}
+
+impl FromGodot for i64 {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        match value {
+            GodotValue::Int(i) => Ok(i),
+            GodotValue::UInt(u) => {
+                i64::try_from(u).map_err(|_| ConversionError::new("int", &value))
+            }
+            value => Err(ConversionError::new("int", &value)),
+        }
+    }
+}
+
+impl FromGodot for f64 {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        match value {
+            GodotValue::Float(f) => Ok(f),
+            GodotValue::Int(i) => Ok(i as f64),
+            GodotValue::UInt(u) => Ok(u as f64),
+            value => Err(ConversionError::new("float", &value)),
+        }
+    }
+}
+
+impl FromGodot for bool {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        match value {
+            GodotValue::Bool(b) => Ok(b),
+            value => Err(ConversionError::new("bool", &value)),
+        }
+    }
+}
+
+impl FromGodot for String {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        match value {
+            GodotValue::String(s) => Ok(s),
+            value => Err(ConversionError::new("string", &value)),
+        }
+    }
+}
+
+/// `None` for a missing field or `Nil`
+impl<T: FromGodot> FromGodot for Option<T> {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        match value {
+            GodotValue::Nil => Ok(None),
+            value => T::from_godot(value).map(Some),
+        }
+    }
+}
+
+/// Empty for a missing field or `Nil`
+impl<T: FromGodot> FromGodot for Vec<T> {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        match value {
+            GodotValue::Nil => Ok(Vec::new()),
+            GodotValue::Array(items) => items
+                .into_iter()
+                .enumerate()
+                .map(|(i, item)| T::from_godot(item).map_err(|e| e.within(&i.to_string())))
+                .collect(),
+            value => Err(ConversionError::new("array", &value)),
+        }
+    }
+}
+
+impl<T: FromGodot> FromGodot for Box<T> {
+    fn from_godot(value: GodotValue) -> Result<Self, ConversionError> {
+        T::from_godot(value).map(Box::new)
+    }
+}
+
+/// The fields of `value`, a resource of type `type_name`. Used by generated code.
+pub fn resource_fields(
+    value: GodotValue,
+    type_name: &str,
+) -> Result<HashMap<String, GodotValue>, ConversionError> {
+    match value {
+        GodotValue::Resource {
+            type_name: t,
+            fields,
+            ..
+        } if t == type_name => Ok(fields),
+        value => Err(ConversionError::new(type_name, &value)),
+    }
+}
+
+/// The field `name` of a resource's `fields`, `Nil` when missing. Used by generated code.
+pub fn field<T: FromGodot>(
+    fields: &mut HashMap<String, GodotValue>,
+    name: &str,
+) -> Result<T, ConversionError> {
+    let value = fields.remove(name);
+    let missing = value.is_none();
+    T::from_godot(value.unwrap_or(GodotValue::Nil)).map_err(|mut e| {
+        if missing {
+            e.found = "nothing".into();
+        }
+        e.within(name)
+    })
+}
+
+/// The type of a resource. Used by generated code.
+pub fn resource_type(value: &GodotValue) -> Option<&str> {
+    match value {
+        GodotValue::Resource { type_name, .. } => Some(type_name),
+        _ => None,
+    }
+}
+
+/// What a field holds
+#[derive(Debug, Clone, PartialEq)]
+pub enum FieldKind {
+    Int,
+    Float,
+    Bool,
+    String,
+    /// Values generated types don't model (dates, conditions, colors...), kept as is
+    Value,
+    /// A resource type of the schema
+    Resource(String),
+    /// An abstract type of the schema, one of its resource types
+    Abstract(String),
+    /// `children` of a resource, any values
+    Children,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Field {
+    pub name: String,
+    pub kind: FieldKind,
+    /// Some phrases of the type don't set it
+    pub optional: bool,
+}
+
+/// A type of resource phrases make
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceType {
+    pub name: String,
+    /// The abstract type it's built as
+    pub abstract_type: String,
+    /// Parameters first, sorted, then `doke_tr_key` and `children`
+    pub fields: Vec<Field>,
+}
+
+/// The resource types and abstract types of a grammar
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Schema {
+    /// Sorted by name
+    pub resources: Vec<ResourceType>,
+    /// Abstract types and their resource types, sorted
+    pub abstract_types: BTreeMap<String, Vec<String>>,
+}
+
+/// Field of sentence results holding their translation key
+const TR_KEY_FIELD: &str = "doke_tr_key";
+
+impl Schema {
+    /// The types of the enabled rules of `grammar`
+    pub fn from_grammar(grammar: &TypedSentencesParser) -> Self {
+        Self::from_phrases(grammar.enabled_rules().flat_map(|rule| {
+            rule.sentence_parser
+                .phrases
+                .iter()
+                .map(move |phrase| (rule.target_type.as_str(), phrase))
+        }))
+    }
+
+    /// The types of one sentence parser
+    /// ```
+    /// use doke::codegen::{FieldKind, Schema};
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    ///   - "Deals {damage: int} {element: Element} damage"
+    /// Element: {fire: 1, ice: 2}
+    /// "#).unwrap();
+    /// let schema = Schema::from_parser(&parser);
+    /// let damage = &schema.resources[0];
+    /// assert_eq!(damage.fields[0].name, "damage");
+    /// assert_eq!(damage.fields[1].kind, FieldKind::Int);
+    /// assert!(damage.fields[1].optional);
+    /// assert_eq!(schema.abstract_types["Effect"], vec!["Damage".to_string()]);
+    /// ```
+    pub fn from_parser(parser: &crate::parsers::SentenceParser) -> Self {
+        let abstract_type = parser.abstract_type.as_str();
+        Self::from_phrases(parser.phrases.iter().map(|phrase| (abstract_type, phrase)))
+    }
+
+    fn from_phrases<'a>(phrases: impl Iterator<Item = (&'a str, &'a PhraseConfig)>) -> Self {
+        let phrases: Vec<(&str, &PhraseConfig)> = phrases.collect();
+        let abstract_types: HashSet<&str> = phrases.iter().map(|(t, _)| *t).collect();
+        // what each section name makes when used as a parameter type
+        let mut sections: HashMap<&str, Option<FieldKind>> = HashMap::new();
+        for (abstract_type, phrase) in &phrases {
+            let kind = match &phrase.return_spec {
+                ReturnSpec::Type(t) if t == *abstract_type => {
+                    FieldKind::Abstract(abstract_type.to_string())
+                }
+                ReturnSpec::Type(t) => FieldKind::Resource(t.clone()),
+                ReturnSpec::Literal(value) => literal_kind(value),
+                ReturnSpec::Format(_) => FieldKind::String,
+            };
+            sections
+                .entry(phrase.section.as_str())
+                .and_modify(|known| {
+                    if known.as_ref() != Some(&kind) {
+                        *known = None;
+                    }
+                })
+                .or_insert(Some(kind));
+        }
+
+        let mut schema = Schema::default();
+        // phrases of each resource type
+        let mut types: BTreeMap<&str, (&str, Vec<&PhraseConfig>)> = BTreeMap::new();
+        for (abstract_type, phrase) in &phrases {
+            if let ReturnSpec::Type(t) = &phrase.return_spec {
+                types
+                    .entry(t.as_str())
+                    .or_insert((abstract_type, Vec::new()))
+                    .1
+                    .push(phrase);
+            }
+        }
+        for (name, (abstract_type, type_phrases)) in &types {
+            let mut fields: BTreeMap<&str, Field> = BTreeMap::new();
+            for phrase in type_phrases {
+                for parameter in &phrase.parameters {
+                    let kind = match is_basic_type(&parameter.param_type) {
+                        true => basic_kind(&parameter.param_type),
+                        false => match sections.get(parameter.param_type.as_str()) {
+                            Some(kind) => kind.clone().unwrap_or(FieldKind::Value),
+                            None if abstract_types.contains(parameter.param_type.as_str()) => {
+                                FieldKind::Abstract(parameter.param_type.clone())
+                            }
+                            None => FieldKind::Value,
+                        },
+                    };
+                    let field = fields.entry(parameter.name.as_str()).or_insert(Field {
+                        name: parameter.name.clone(),
+                        kind: kind.clone(),
+                        optional: parameter.optional,
+                    });
+                    field.optional |= parameter.optional;
+                    if field.kind != kind {
+                        field.kind = FieldKind::Value;
+                    }
+                }
+            }
+            // parameters some phrases of the type don't have
+            for field in fields.values_mut() {
+                let everywhere = type_phrases
+                    .iter()
+                    .all(|phrase| phrase.parameters.iter().any(|p| p.name == field.name));
+                field.optional |= !everywhere;
+            }
+            let mut fields: Vec<Field> = fields.into_values().collect();
+            fields.push(Field {
+                name: TR_KEY_FIELD.into(),
+                kind: FieldKind::String,
+                optional: false,
+            });
+            fields.push(Field {
+                name: "children".into(),
+                kind: FieldKind::Children,
+                optional: true,
+            });
+            schema.resources.push(ResourceType {
+                name: name.to_string(),
+                abstract_type: abstract_type.to_string(),
+                fields,
+            });
+            schema
+                .abstract_types
+                .entry(abstract_type.to_string())
+                .or_default()
+                .push(name.to_string());
+        }
+        schema
+    }
+
+    /// The resource type `name`
+    pub fn resource(&self, name: &str) -> Option<&ResourceType> {
+        self.resources.iter().find(|r| r.name == name)
+    }
+
+    /// Rust structs for the resource types and enums for the abstract types,
+    /// converting from `GodotValue` with `TryFrom`, see `codegen`
+    /// ```
+    /// use doke::codegen::Schema;
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// Heal:
+    ///   - "Heals {amount: int} HP"
+    /// "#).unwrap();
+    /// let code = Schema::from_parser(&parser).to_rust();
+    /// assert!(code.contains("pub struct Damage {\n    pub damage: i64,"));
+    /// assert!(code.contains("pub enum Effect {\n    Damage(Damage),\n    Heal(Heal),"));
+    /// ```
+    pub fn to_rust(&self) -> String {
+        rust::write(self)
+    }
+
+    /// TypeScript interfaces for the resource types and unions for the abstract
+    /// types, matching their JSON from `export::json`
+    /// ```
+    /// use doke::codegen::Schema;
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    ///   - "Deals {damage: int} {element: string} damage"
+    /// Heal:
+    ///   - "Heals {amount: int} HP"
+    /// "#).unwrap();
+    /// let code = Schema::from_parser(&parser).to_typescript();
+    /// assert!(code.contains("export type Effect = Damage | Heal;"));
+    /// assert!(code.contains(
+    ///     "export interface Damage {\n  type: \"Damage\";\n  abstract_type: \"Effect\";\n  damage: number;\n  element?: string;"
+    /// ));
+    /// ```
+    pub fn to_typescript(&self) -> String {
+        typescript::write(self)
+    }
+}
+
+/// The type `name` is declared as, `Fire Damage` → `FireDamage`
+pub(super) fn type_ident(name: &str) -> String {
+    let ident: String = name
+        .split(|c: char| !c.is_alphanumeric() && c != '_')
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect();
+    match ident.starts_with(|c: char| c.is_ascii_digit()) || ident.is_empty() {
+        true => format!("T{}", ident),
+        false => ident,
+    }
+}
+
+fn basic_kind(param_type: &str) -> FieldKind {
+    match param_type.to_lowercase().as_str() {
+        "int" => FieldKind::Int,
+        "float" => FieldKind::Float,
+        "bool" => FieldKind::Bool,
+        "string" | "input_action" | "stat" => FieldKind::String,
+        _ => FieldKind::Value,
+    }
+}
+
+fn literal_kind(value: &GodotValue) -> FieldKind {
+    match value {
+        GodotValue::Int(_) => FieldKind::Int,
+        GodotValue::Float(_) => FieldKind::Float,
+        GodotValue::Bool(_) => FieldKind::Bool,
+        GodotValue::String(_) => FieldKind::String,
+        _ => FieldKind::Value,
+    }
+}
@@ -0,0 +1,167 @@
+// codegen/rust.rs
+//
+// Writes a `Schema` as Rust: a struct per resource type, an enum per abstract
+// type, each with `FromGodot` and `TryFrom<GodotValue>` implementations. Paths
+// are absolute (`::doke::GodotValue`), so the code can be `include!`d anywhere.
+
+use std::fmt::Write;
+
+use super::{FieldKind, ResourceType, Schema, TR_KEY_FIELD, type_ident};
+
+const RUST_KEYWORDS: &[&str] = &[
+    "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum", "extern",
+    "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move", "mut", "pub",
+    "ref", "return", "static", "struct", "super", "trait", "true", "type", "unsafe", "use",
+    "where", "while", "abstract", "become", "box", "do", "final", "gen", "macro", "override",
+    "priv", "try", "typeof", "unsized", "virtual", "yield",
+];
+
+pub(super) fn write(schema: &Schema) -> String {
+    let mut out =
+        String::from("// Generated by doke from the dokedef sections of a grammar, do not edit.\n");
+    for (name, types) in &schema.abstract_types {
+        // a resource type of the same name wins
+        if schema.resource(name).is_none() {
+            write_enum(name, types, &mut out);
+        }
+    }
+    for resource in &schema.resources {
+        write_struct(schema, resource, &mut out);
+    }
+    out
+}
+
+fn write_enum(name: &str, types: &[String], out: &mut String) {
+    let ident = type_ident(name);
+    writeln!(out, "\n/// One of the `{}` resources", name).unwrap();
+    writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+    writeln!(out, "pub enum {} {{", ident).unwrap();
+    for ty in types {
+        writeln!(out, "    {0}({0}),", type_ident(ty)).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    write_impl_header(&ident, out);
+    writeln!(
+        out,
+        "        match ::doke::codegen::resource_type(&value) {{"
+    )
+    .unwrap();
+    for ty in types {
+        writeln!(
+            out,
+            "            Some({:?}) => ::doke::codegen::FromGodot::from_godot(value).map(Self::{}),",
+            ty,
+            type_ident(ty)
+        )
+        .unwrap();
+    }
+    writeln!(
+        out,
+        "            _ => Err(::doke::codegen::ConversionError::new({:?}, &value)),",
+        name
+    )
+    .unwrap();
+    writeln!(out, "        }}\n    }}\n}}").unwrap();
+    write_try_from(&ident, out);
+}
+
+fn write_struct(schema: &Schema, resource: &ResourceType, out: &mut String) {
+    let ident = type_ident(&resource.name);
+    writeln!(out, "\n/// A `{}` resource", resource.name).unwrap();
+    writeln!(out, "#[derive(Debug, Clone, PartialEq)]").unwrap();
+    writeln!(out, "pub struct {} {{", ident).unwrap();
+    for field in &resource.fields {
+        let ty = match (&field.kind, field.optional) {
+            (FieldKind::Children, _) | (_, false) => rust_type(schema, &field.kind),
+            (kind, true) => format!("Option<{}>", rust_type(schema, kind)),
+        };
+        writeln!(out, "    pub {}: {},", field_ident(&field.name), ty).unwrap();
+    }
+    writeln!(out, "}}").unwrap();
+
+    write_impl_header(&ident, out);
+    writeln!(
+        out,
+        "        let mut fields = ::doke::codegen::resource_fields(value, {:?})?;",
+        resource.name
+    )
+    .unwrap();
+    writeln!(out, "        Ok(Self {{").unwrap();
+    for field in &resource.fields {
+        writeln!(
+            out,
+            "            {}: ::doke::codegen::field(&mut fields, {:?})?,",
+            field_ident(&field.name),
+            field.name
+        )
+        .unwrap();
+    }
+    writeln!(out, "        }})\n    }}\n}}").unwrap();
+    write_try_from(&ident, out);
+}
+
+fn write_impl_header(ident: &str, out: &mut String) {
+    writeln!(out, "\nimpl ::doke::codegen::FromGodot for {} {{", ident).unwrap();
+    writeln!(
+        out,
+        "    fn from_godot(value: ::doke::GodotValue) -> Result<Self, ::doke::codegen::ConversionError> {{"
+    )
+    .unwrap();
+}
+
+fn write_try_from(ident: &str, out: &mut String) {
+    writeln!(out, "\nimpl TryFrom<::doke::GodotValue> for {} {{", ident).unwrap();
+    writeln!(out, "    type Error = ::doke::codegen::ConversionError;\n").unwrap();
+    writeln!(
+        out,
+        "    fn try_from(value: ::doke::GodotValue) -> Result<Self, Self::Error> {{"
+    )
+    .unwrap();
+    writeln!(
+        out,
+        "        ::doke::codegen::FromGodot::from_godot(value)\n    }}\n}}"
+    )
+    .unwrap();
+}
+
+fn rust_type(schema: &Schema, kind: &FieldKind) -> String {
+    match kind {
+        FieldKind::Int => "i64".into(),
+        FieldKind::Float => "f64".into(),
+        FieldKind::Bool => "bool".into(),
+        FieldKind::String => "String".into(),
+        FieldKind::Value => "::doke::GodotValue".into(),
+        FieldKind::Children => "Vec<::doke::GodotValue>".into(),
+        // boxed, resources may hold resources of their own type
+        FieldKind::Resource(name) => format!("Box<{}>", type_ident(name)),
+        FieldKind::Abstract(name)
+            if schema.abstract_types.contains_key(name) || schema.resource(name).is_some() =>
+        {
+            format!("Box<{}>", type_ident(name))
+        }
+        FieldKind::Abstract(_) => "::doke::GodotValue".into(),
+    }
+}
+
+/// `max-hp` → `max_hp`, `type` → `r#type`, `doke_tr_key` → `tr_key`
+fn field_ident(name: &str) -> String {
+    if name == TR_KEY_FIELD {
+        return "tr_key".into();
+    }
+    let ident: String = name
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    match ident.as_str() {
+        "self" | "Self" | "super" | "crate" => format!("{}_", ident),
+        _ if RUST_KEYWORDS.contains(&ident.as_str()) => format!("r#{}", ident),
+        _ if ident.starts_with(|c: char| c.is_ascii_digit()) || ident.is_empty() => {
+            format!("_{}", ident)
+        }
+        _ => ident,
+    }
+}
@@ -0,0 +1,98 @@
+// codegen/typescript.rs
+//
+// Writes a `Schema` as TypeScript declarations of the JSON `export::json` writes:
+// an interface per resource type, with its fields plus `type` and `abstract_type`
+// as string literals, and a union per abstract type, so web tools can narrow on
+// `value.type`.
+
+use std::fmt::Write;
+
+use super::{FieldKind, ResourceType, Schema, type_ident};
+use crate::parsers::{DISPLAY_TEMPLATE_KEY, LITERALS_KEY};
+use crate::rich_text::DISPLAY_TEXT_KEY;
+
+pub(super) fn write(schema: &Schema) -> String {
+    let mut out = String::from(
+        "// Generated by doke from the dokedef sections of a grammar, do not edit.\n\n\
+         /** Any value in doke's JSON: resources and dicts are objects, vectors and colors {x, y}, {r, g, b, a}... */\n\
+         export type DokeValue = null | boolean | number | string | DokeValue[] | { [key: string]: DokeValue };\n",
+    );
+    for (name, types) in &schema.abstract_types {
+        // a resource type of the same name wins
+        if schema.resource(name).is_none() {
+            writeln!(out, "\n/** One of the `{}` resources */", name).unwrap();
+            writeln!(out, "export type {} = {};", type_ident(name), union(types)).unwrap();
+        }
+    }
+    for resource in &schema.resources {
+        write_interface(schema, resource, &mut out);
+    }
+    out
+}
+
+fn write_interface(schema: &Schema, resource: &ResourceType, out: &mut String) {
+    writeln!(out, "\n/** A `{}` resource */", resource.name).unwrap();
+    writeln!(out, "export interface {} {{", type_ident(&resource.name)).unwrap();
+    writeln!(out, "  type: {:?};", resource.name).unwrap();
+    writeln!(out, "  abstract_type: {:?};", resource.abstract_type).unwrap();
+    for field in &resource.fields {
+        let optional = match field.optional {
+            true => "?",
+            false => "",
+        };
+        writeln!(
+            out,
+            "  {}{}: {};",
+            property(&field.name),
+            optional,
+            ts_type(schema, &field.kind)
+        )
+        .unwrap();
+    }
+    // written by every sentence result, see `SentenceResult::to_godot`
+    writeln!(out, "  {}?: string;", DISPLAY_TEMPLATE_KEY).unwrap();
+    writeln!(out, "  {}?: string;", DISPLAY_TEXT_KEY).unwrap();
+    writeln!(
+        out,
+        "  {}?: {{ [parameter: string]: string }};",
+        LITERALS_KEY
+    )
+    .unwrap();
+    writeln!(out, "}}").unwrap();
+}
+
+fn ts_type(schema: &Schema, kind: &FieldKind) -> String {
+    match kind {
+        FieldKind::Int | FieldKind::Float => "number".into(),
+        FieldKind::Bool => "boolean".into(),
+        FieldKind::String => "string".into(),
+        FieldKind::Value => "DokeValue".into(),
+        FieldKind::Children => "DokeValue[]".into(),
+        FieldKind::Resource(name) => type_ident(name),
+        FieldKind::Abstract(name) if schema.resource(name).is_some() => type_ident(name),
+        FieldKind::Abstract(name) => match schema.abstract_types.get(name) {
+            Some(_) => type_ident(name),
+            None => "DokeValue".into(),
+        },
+    }
+}
+
+fn union(types: &[String]) -> String {
+    types
+        .iter()
+        .map(|ty| type_ident(ty))
+        .collect::<Vec<_>>()
+        .join(" | ")
+}
+
+/// The field as a property name, quoted unless it's an identifier
+fn property(name: &str) -> String {
+    let identifier = name.starts_with(|c: char| c.is_alphabetic() || c == '_' || c == '$')
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '_' || c == '$');
+    match identifier {
+        true => name.to_string(),
+        false => format!("{:?}", name),
+    }
+}
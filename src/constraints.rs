@@ -0,0 +1,278 @@
+// constraints.rs
+//
+// Semantic checks on a built resource, declared in the builder config:
+//
+//     constraints:
+//       - "damage >= 0"
+//       - "len(modifiers) <= 4"
+//       - "cooldown required if has_active_effect"
+//       - check: "action.range <= 10"
+//         message: "actions can't reach past the screen"
+//
+// They catch balance and data errors the grammar can't express.
+// Conditions use the `condition` parameter syntax, variables are field paths.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::GodotValue;
+use crate::Position;
+use crate::diagnostics::Diagnostic;
+use crate::parsers::expression::{self, Expr};
+
+static REQUIRED: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^\s*([A-Za-z_][\w.]*)\s+(?:is\s+)?required(?:\s+(?:if|when)\s+(.+?))?\s*$")
+        .unwrap()
+});
+
+/// A rule the built resource must follow
+/// ```
+/// use std::collections::HashMap;
+/// use doke::GodotValue;
+/// use doke::constraints::Constraint;
+/// use doke::file_builder::{Config, ResourceBuilder};
+///
+/// let item = |damage: i64| GodotValue::Resource {
+///     type_name: "Item".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::from([("damage".into(), GodotValue::Int(damage))]),
+/// };
+/// let positive = Constraint::parse("damage >= 0", None).unwrap();
+/// assert!(positive.holds(&item(5)));
+/// assert!(!positive.holds(&item(-2)));
+///
+/// let builder = ResourceBuilder::from_config(Config {
+///     root: "Item".into(),
+///     constraints: vec![Constraint::parse("cooldown required if damage > 10", None).unwrap()],
+///     ..Default::default()
+/// }).unwrap();
+/// assert!(builder.check_constraints(&item(5)).is_empty());
+/// let diagnostics = builder.check_constraints(&item(50));
+/// assert_eq!(diagnostics[0].code, "constraint-failed");
+/// assert_eq!(diagnostics[0].message, "constraint 'cooldown required if damage > 10' failed");
+///
+/// assert!(Constraint::parse("damage >=", None).is_err());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Constraint {
+    /// The constraint as written in the config
+    pub source: String,
+    /// Reported instead of the source when the constraint fails
+    pub message: Option<String>,
+    rule: Rule,
+}
+
+#[derive(Debug, Clone)]
+enum Rule {
+    Check(Expr),
+    /// "cooldown required if has_active_effect"
+    Required {
+        field: String,
+        when: Option<Expr>,
+    },
+}
+
+impl Constraint {
+    pub fn parse(source: &str, message: Option<String>) -> Result<Self, String> {
+        let rule = match REQUIRED.captures(source) {
+            Some(caps) => Rule::Required {
+                field: caps[1].replace('.', "/"),
+                when: caps
+                    .get(2)
+                    .map(|when| expression::parse_condition(when.as_str()))
+                    .transpose()?,
+            },
+            None => Rule::Check(expression::parse_condition(source)?),
+        };
+        Ok(Self {
+            source: source.trim().to_string(),
+            message,
+            rule,
+        })
+    }
+
+    /// Whether `resource` follows the constraint.
+    /// Comparisons with a missing field hold, presence is checked with `required`.
+    pub fn holds(&self, resource: &GodotValue) -> bool {
+        match &self.rule {
+            Rule::Check(expr) => condition(expr, resource) != Some(false),
+            Rule::Required { field, when } => {
+                let applies = when
+                    .as_ref()
+                    .is_none_or(|when| condition(when, resource) == Some(true));
+                !applies || truthy(resource.get_path(field))
+            }
+        }
+    }
+
+    /// An error on the statement of the first field the constraint reads, if it fails
+    pub(crate) fn check(
+        &self,
+        resource: &GodotValue,
+        spans: &HashMap<String, Position>,
+    ) -> Option<Diagnostic> {
+        if self.holds(resource) {
+            return None;
+        }
+        let message = self
+            .message
+            .clone()
+            .unwrap_or_else(|| format!("constraint '{}' failed", self.source));
+        let diagnostic = Diagnostic::error("constraint-failed", message);
+        let span = self
+            .fields()
+            .iter()
+            .find_map(|field| field.split('/').next().and_then(|root| spans.get(root)));
+        Some(match span {
+            Some(span) => diagnostic.with_span(span.clone()),
+            None => diagnostic,
+        })
+    }
+
+    /// Paths of the fields the constraint reads, in order
+    fn fields(&self) -> Vec<String> {
+        let mut out = Vec::new();
+        match &self.rule {
+            Rule::Check(expr) => collect_fields(expr, &mut out),
+            Rule::Required { field, when } => {
+                out.push(field.clone());
+                if let Some(when) = when {
+                    collect_fields(when, &mut out);
+                }
+            }
+        }
+        out
+    }
+}
+
+/// "the action range" → "action/range", "action.range" → "action/range"
+fn path(subject: &Option<String>, name: &str) -> String {
+    subject
+        .iter()
+        .flat_map(|s| s.split_whitespace())
+        .chain(std::iter::once(name))
+        .collect::<Vec<_>>()
+        .join("/")
+        .replace('.', "/")
+}
+
+fn collect_fields(expr: &Expr, out: &mut Vec<String>) {
+    match expr {
+        Expr::Var { subject, name } => out.push(path(subject, name)),
+        Expr::Is { subject, state } => out.push(path(subject, state)),
+        Expr::Has { subject, item } => out.push(path(subject, item)),
+        Expr::Call { argument, .. } => collect_fields(argument, out),
+        Expr::Unary { operand, .. } => collect_fields(operand, out),
+        Expr::Binary { left, right, .. } => {
+            collect_fields(left, out);
+            collect_fields(right, out);
+        }
+        Expr::Elided { right, .. } => collect_fields(right, out),
+        Expr::Number(_) | Expr::Percent(_) => {}
+    }
+}
+
+fn truthy(value: Option<&GodotValue>) -> bool {
+    match value {
+        None | Some(GodotValue::Nil) => false,
+        Some(GodotValue::Bool(b)) => *b,
+        Some(GodotValue::Int(i)) => *i != 0,
+        Some(GodotValue::UInt(u)) => *u != 0,
+        Some(GodotValue::Float(f)) => *f != 0.0,
+        Some(GodotValue::String(s)) => !s.is_empty(),
+        Some(GodotValue::Array(items)) => !items.is_empty(),
+        Some(GodotValue::Dict(map)) => !map.is_empty(),
+        Some(GodotValue::Resource { .. }) => true,
+        Some(GodotValue::Vector2(x, y)) => *x != 0.0 || *y != 0.0,
+        Some(GodotValue::Rect2(x, y, w, h)) => [x, y, w, h].iter().any(|c| **c != 0.0),
+        Some(GodotValue::Color(..)) => true,
+    }
+}
+
+/// `None` when a field it reads is missing
+fn number(expr: &Expr, resource: &GodotValue) -> Option<f64> {
+    match expr {
+        Expr::Number(n) => Some(*n),
+        Expr::Percent(p) => Some(p / 100.0),
+        Expr::Var { subject, name } => match resource.get_path(&path(subject, name))? {
+            GodotValue::Int(i) => Some(*i as f64),
+            GodotValue::UInt(u) => Some(*u as f64),
+            GodotValue::Float(f) => Some(*f),
+            GodotValue::Bool(b) => Some(if *b { 1.0 } else { 0.0 }),
+            _ => None,
+        },
+        Expr::Call { argument, .. } => {
+            let Expr::Var { subject, name } = argument.as_ref() else {
+                return None;
+            };
+            // len() of a missing field is 0
+            Some(match resource.get_path(&path(subject, name)) {
+                Some(GodotValue::Array(items)) => items.len(),
+                Some(GodotValue::Dict(map)) => map.len(),
+                Some(GodotValue::String(s)) => s.chars().count(),
+                Some(GodotValue::Nil) | None => 0,
+                Some(_) => 1,
+            } as f64)
+        }
+        Expr::Unary { op: "neg", operand } => number(operand, resource).map(|n| -n),
+        Expr::Binary { op, left, right } => {
+            let (l, r) = (number(left, resource)?, number(right, resource)?);
+            match *op {
+                "+" => Some(l + r),
+                "-" => Some(l - r),
+                "*" => Some(l * r),
+                "/" => Some(l / r),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+/// Three-valued: `None` when a compared field is missing
+fn condition(expr: &Expr, resource: &GodotValue) -> Option<bool> {
+    match expr {
+        Expr::Binary {
+            op: "and",
+            left,
+            right,
+        } => match (condition(left, resource), condition(right, resource)) {
+            (Some(false), _) | (_, Some(false)) => Some(false),
+            (Some(true), Some(true)) => Some(true),
+            _ => None,
+        },
+        Expr::Binary {
+            op: "or",
+            left,
+            right,
+        } => match (condition(left, resource), condition(right, resource)) {
+            (Some(true), _) | (_, Some(true)) => Some(true),
+            (Some(false), Some(false)) => Some(false),
+            _ => None,
+        },
+        Expr::Unary { op: "not", operand } => condition(operand, resource).map(|b| !b),
+        Expr::Binary { op, left, right } => {
+            let (l, r) = (number(left, resource)?, number(right, resource)?);
+            Some(match *op {
+                "<" => l < r,
+                "<=" => l <= r,
+                ">" => l > r,
+                ">=" => l >= r,
+                "==" => l == r,
+                _ => l != r,
+            })
+        }
+        // "the action is ZonedAction" also checks the type of a resource
+        Expr::Is { subject, state } => {
+            let is_type = subject.as_ref().is_some_and(|subject| {
+                matches!(resource.get_path(&path(&None, subject)),
+                    Some(GodotValue::Resource { type_name, .. }) if type_name.eq_ignore_ascii_case(state))
+            });
+            Some(is_type || truthy(resource.get_path(&path(subject, state))))
+        }
+        Expr::Has { subject, item } => Some(truthy(resource.get_path(&path(subject, item)))),
+        other => number(other, resource).map(|n| n != 0.0),
+    }
+}
@@ -0,0 +1,118 @@
+// context.rs
+//
+// What a parser can see around a node: `DokeParser::process` only gets the
+// node, but a statement like "Also deals 3 fire damage" means something only
+// next to the statement before it. A parser needing that walks the document
+// with `walk` in its `process_document`,
+//
+//     fn process_document(&self, nodes: &mut Vec<DokeNode>, frontmatter: &HashMap<String, GodotValue>) {
+//         context::walk(nodes, frontmatter, &mut |node, context| {
+//             if let Some(previous) = context.previous { ... }
+//         });
+//     }
+//
+// and gets each node with read-only access to its parent (everything but the
+// children, which are being walked) and its previous sibling. Nodes are visited
+// in document order, a node before its children, so both were already seen by
+// the same walk.
+
+use std::collections::HashMap;
+
+use crate::{DokeNode, DokeNodeState, Extensions, GodotValue, Position, Statement};
+
+/// Surroundings of a node during a `walk`
+#[derive(Debug, Clone, Copy)]
+pub struct NodeContext<'a> {
+    pub frontmatter: &'a HashMap<String, GodotValue>,
+    /// The node this one is a child of, `None` at the top level
+    pub parent: Option<ParentView<'a>>,
+    /// The sibling just before this node, with its children
+    pub previous: Option<&'a DokeNode>,
+    /// 0 at the top level
+    pub depth: usize,
+}
+
+/// A parent node, without the children being walked
+#[derive(Debug, Clone, Copy)]
+pub struct ParentView<'a> {
+    pub statement: &'a Statement,
+    pub state: &'a DokeNodeState,
+    pub parse_data: &'a HashMap<String, GodotValue>,
+    pub extensions: &'a Extensions,
+    pub span: &'a Position,
+}
+
+/// Call `f` on every node of `nodes` and their children, in document order,
+/// with their context
+/// ```
+/// use std::collections::HashMap;
+/// use doke::{DokeNode, DokeParser, DokePipe, GodotValue, context};
+///
+/// /// Marks statements continuing the one before them
+/// #[derive(Debug)]
+/// struct Continuations;
+/// impl DokeParser for Continuations {
+///     fn process(&self, _: &mut DokeNode, _: &HashMap<String, GodotValue>) {}
+///
+///     fn process_document(&self, nodes: &mut Vec<DokeNode>, frontmatter: &HashMap<String, GodotValue>) {
+///         context::walk(nodes, frontmatter, &mut |node, context| {
+///             if node.statement.starts_with("Also") && let Some(previous) = context.previous {
+///                 let continued = GodotValue::String(previous.statement.to_string());
+///                 node.parse_data.insert("continues".into(), continued);
+///             }
+///         });
+///     }
+/// }
+///
+/// let doc = DokePipe::new().add(Continuations).run_markdown("Deals 5 damage\n\nAlso burns");
+/// assert_eq!(
+///     doc.nodes[1].parse_data.get("continues"),
+///     Some(&GodotValue::String("Deals 5 damage".into()))
+/// );
+/// ```
+pub fn walk<F>(nodes: &mut [DokeNode], frontmatter: &HashMap<String, GodotValue>, f: &mut F)
+where
+    F: FnMut(&mut DokeNode, &NodeContext<'_>),
+{
+    walk_level(nodes, frontmatter, None, 0, f);
+}
+
+fn walk_level<F>(
+    nodes: &mut [DokeNode],
+    frontmatter: &HashMap<String, GodotValue>,
+    parent: Option<ParentView<'_>>,
+    depth: usize,
+    f: &mut F,
+) where
+    F: FnMut(&mut DokeNode, &NodeContext<'_>),
+{
+    for i in 0..nodes.len() {
+        let (before, rest) = nodes.split_at_mut(i);
+        let node = &mut rest[0];
+        let context = NodeContext {
+            frontmatter,
+            parent,
+            previous: before.last(),
+            depth,
+        };
+        f(node, &context);
+
+        let DokeNode {
+            statement,
+            state,
+            children,
+            parse_data,
+            extensions,
+            span,
+            ..
+        } = node;
+        let view = ParentView {
+            statement,
+            state,
+            parse_data,
+            extensions,
+            span,
+        };
+        walk_level(children, frontmatter, Some(view), depth + 1, f);
+    }
+}
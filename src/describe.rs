@@ -0,0 +1,227 @@
+// describe.rs
+//
+// Tooltip text composed back from built resources, the inverse of parsing: each
+// resource is described with its type's template, nested resources and lists
+// of them with their own.
+//
+//     # ItemModifier.dokedef.yaml
+//     Modifier:
+//       phrases:
+//         - "Adds {amount: int} {stat: Stat} to {target: Target}"
+//       description:
+//         en: "+{amount} {stat} ({target})"
+//         fr: "+{amount} {stat} ({target})"
+//
+// Templates use the fields of the resource as `{field}` placeholders, written
+// for the describer's locale (see the `locale` module). Types without a template
+// fall back on the English text of the phrase they were written with
+// (`display_template`). `Describer::annotate` stores the text in a `description`
+// field, next to the mechanical data.
+
+use std::collections::HashMap;
+
+use yaml_rust2::Yaml;
+
+use crate::GodotValue;
+use crate::locale::Locale;
+use crate::parsers::sentence::{
+    DISPLAY_TEMPLATE_KEY, PhraseConfig, SentenceParseError, perform_format_string,
+};
+
+/// Field `Describer::annotate` writes the description to
+pub const DESCRIPTION_KEY: &str = "description";
+
+/// The description templates of a type, by language
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Descriptions {
+    /// `(language, template)`, the language empty for a template without one
+    templates: Vec<(String, String)>,
+}
+
+impl Descriptions {
+    /// Accepts a template, or `{language: template}` pairs
+    pub(crate) fn from_yaml(yaml: &Yaml) -> Result<Self, SentenceParseError> {
+        let templates = match yaml {
+            Yaml::String(template) => vec![(String::new(), template.clone())],
+            Yaml::Hash(map) => map
+                .iter()
+                .map(|(language, template)| match (language, template) {
+                    (Yaml::String(language), Yaml::String(template)) => {
+                        Ok((language.to_lowercase(), template.clone()))
+                    }
+                    _ => Err(SentenceParseError::InvalidPattern(
+                        "descriptions must map languages to templates".into(),
+                    )),
+                })
+                .collect::<Result<_, _>>()?,
+            _ => {
+                return Err(SentenceParseError::InvalidPattern(
+                    "description must be a template or a mapping of languages".into(),
+                ));
+            }
+        };
+        Ok(Self { templates })
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.templates.is_empty()
+    }
+
+    /// The template for `locale`'s language, else the one without a language, else English
+    pub fn template(&self, locale: &Locale) -> Option<&str> {
+        self.find(&locale.language())
+            .or_else(|| self.find(""))
+            .or_else(|| self.find("en"))
+    }
+
+    fn find(&self, language: &str) -> Option<&str> {
+        self.templates
+            .iter()
+            .find(|(l, _)| l == language)
+            .map(|(_, template)| template.as_str())
+    }
+}
+
+/// Composes descriptions of resources from the templates of their types
+/// ```
+/// use doke::describe::Describer;
+/// use doke::{DokePipe, GodotValue, Locale, parsers::SentenceParser};
+///
+/// let grammar = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   phrases:
+///     - "Deals {damage: int} damage to {target: Target}"
+///   description:
+///     en: "{damage} damage to {target}"
+///     de: "{damage} Schaden an {target}"
+/// Target:
+///   phrases:
+///     - "enemies"
+///   description:
+///     de: "Feinde"
+/// "#).unwrap();
+/// let describer = grammar.describer().with_locale(Locale::from_tag("de").unwrap());
+/// let mut values = DokePipe::new().add(grammar).validate("Deals 1500 damage to enemies").unwrap();
+///
+/// assert_eq!(describer.describe(&values[0]).as_deref(), Some("1.500 Schaden an Feinde"));
+/// describer.annotate(&mut values[0]);
+/// let GodotValue::Resource { fields, .. } = &values[0] else { unreachable!() };
+/// assert_eq!(fields["description"], GodotValue::String("1.500 Schaden an Feinde".into()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct Describer {
+    /// Templates by type name
+    templates: HashMap<String, Descriptions>,
+    locale: Locale,
+}
+
+impl Default for Describer {
+    fn default() -> Self {
+        Self {
+            templates: HashMap::new(),
+            locale: Locale::from_tag("en").expect("English is a known locale"),
+        }
+    }
+}
+
+impl Describer {
+    /// The templates of `phrases`' sections, the first phrase of a section giving one wins
+    pub fn new<'a>(phrases: impl IntoIterator<Item = &'a PhraseConfig>) -> Self {
+        let mut describer = Self::default();
+        for phrase in phrases {
+            if !phrase.descriptions.is_empty() {
+                describer
+                    .templates
+                    .entry(phrase.section.clone())
+                    .or_insert_with(|| phrase.descriptions.clone());
+            }
+        }
+        describer
+    }
+
+    /// Describe resources of `type_name` with `template`, whatever the locale
+    pub fn with_template(mut self, type_name: &str, template: &str) -> Self {
+        self.templates.insert(
+            type_name.to_string(),
+            Descriptions {
+                templates: vec![(String::new(), template.to_string())],
+            },
+        );
+        self
+    }
+
+    /// Pick templates and write numbers and lists for `locale`, English by default
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    /// The description of a resource, `None` for other values and resources
+    /// without a template
+    pub fn describe(&self, value: &GodotValue) -> Option<String> {
+        let GodotValue::Resource {
+            type_name, fields, ..
+        } = value
+        else {
+            return None;
+        };
+        let template = self
+            .templates
+            .get(type_name)
+            .and_then(|descriptions| descriptions.template(&self.locale))
+            .or_else(|| match fields.get(DISPLAY_TEMPLATE_KEY) {
+                Some(GodotValue::String(template)) => Some(template.as_str()),
+                _ => None,
+            })?;
+        let params: HashMap<String, GodotValue> = fields
+            .iter()
+            .map(|(name, value)| (name.clone(), self.parameter(value)))
+            .collect();
+        Some(perform_format_string(
+            template,
+            &params,
+            &HashMap::new(),
+            Some(&self.locale),
+        ))
+    }
+
+    /// Add a `description` field to `value` and every resource in it that has one
+    pub fn annotate(&self, value: &mut GodotValue) {
+        match value {
+            GodotValue::Resource { fields, .. } => {
+                for field in fields.values_mut() {
+                    self.annotate(field);
+                }
+            }
+            GodotValue::Array(items) => {
+                for item in items {
+                    self.annotate(item);
+                }
+            }
+            GodotValue::Dict(entries) => {
+                for entry in entries.values_mut() {
+                    self.annotate(entry);
+                }
+            }
+            _ => return,
+        }
+        if let Some(description) = self.describe(value)
+            && let GodotValue::Resource { fields, .. } = value
+        {
+            fields.insert(DESCRIPTION_KEY.into(), GodotValue::String(description));
+        }
+    }
+
+    /// A field as written in a description: resources by their own description
+    fn parameter(&self, value: &GodotValue) -> GodotValue {
+        match value {
+            GodotValue::Resource { .. } => self
+                .describe(value)
+                .map_or(GodotValue::Nil, GodotValue::String),
+            GodotValue::Array(items) => {
+                GodotValue::Array(items.iter().map(|item| self.parameter(item)).collect())
+            }
+            other => other.clone(),
+        }
+    }
+}
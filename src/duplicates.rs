@@ -0,0 +1,86 @@
+// duplicates.rs
+//
+// Lint for content written twice, usually a copy-paste mistake: a list repeating
+// an effect doubles it in game, and a document copied to make a new item but never
+// edited ships two identical items under different ids.
+//
+//     - Deals 5 damage
+//     - Deals 5 damage        <- duplicate-statement
+//
+// Statements are the same when they're spelled the same by their phrase (so spacing
+// doesn't matter) and have the same children. Opt-in, since some
+// grammars repeat statements on purpose, see `Workspace::with_duplicate_lint`.
+
+use std::collections::HashSet;
+
+use crate::diagnostics::Diagnostic;
+use crate::parsers::CANONICAL_KEY;
+use crate::{DokeNode, GodotValue};
+
+/// Diagnostic code of a statement repeating an earlier sibling
+pub const DUPLICATE_STATEMENT_CODE: &str = "duplicate-statement";
+
+/// Diagnostic code of a document building the same values as another one
+pub const DUPLICATE_RESOURCE_CODE: &str = "workspace-duplicate-resource";
+
+/// A warning for each statement identical to an earlier one of the same list, at any depth
+/// ```
+/// use doke::{DokePipe, duplicates, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+/// let doc = pipe.run_markdown("- Deals 5 damage\n- Deals 3 damage\n- Deals  5 damage");
+/// let warnings = duplicates::duplicate_siblings(&doc.nodes);
+/// assert_eq!(warnings.len(), 1);
+/// assert_eq!(warnings[0].code, duplicates::DUPLICATE_STATEMENT_CODE);
+/// assert_eq!(
+///     warnings[0].message,
+///     "'Deals  5 damage' is already in this list, written twice?"
+/// );
+/// ```
+pub fn duplicate_siblings(nodes: &[DokeNode]) -> Vec<Diagnostic> {
+    let mut diagnostics = Vec::new();
+    collect(nodes, &mut diagnostics);
+    diagnostics
+}
+
+fn collect(nodes: &[DokeNode], diagnostics: &mut Vec<Diagnostic>) {
+    let mut seen = HashSet::new();
+    for node in nodes {
+        let key = identity(node);
+        if key.trim().is_empty() {
+            continue;
+        }
+        if !seen.insert(key) {
+            diagnostics.push(
+                Diagnostic::warning(
+                    DUPLICATE_STATEMENT_CODE,
+                    format!(
+                        "'{}' is already in this list, written twice?",
+                        node.statement.trim()
+                    ),
+                )
+                .with_span(node.span.clone()),
+            );
+        }
+        collect(&node.children, diagnostics);
+    }
+}
+
+/// The statement as its phrase spells it, and its children's, indented
+fn identity(node: &DokeNode) -> String {
+    let text = match node.parse_data.get(CANONICAL_KEY) {
+        Some(GodotValue::String(spelled)) => spelled.clone(),
+        _ => node
+            .statement
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" "),
+    };
+    node.children.iter().fold(text, |out, child| {
+        format!("{}\n  {}", out, identity(child).replace('\n', "\n  "))
+    })
+}
@@ -0,0 +1,195 @@
+// error.rs
+//
+// One error type for applications using several parts of the crate: each
+// module keeps its own error, and `doke::Error` wraps them all so `?` works
+// across loading a grammar, running a pipe, building and exporting.
+//
+// `Error::code` gives a stable kebab-case code for each failure, like the
+// codes of diagnostics, for applications that report or branch on them.
+//
+// The `try_` methods of `DokePipe` also turn panics of parsers into
+// `Error::Panic`, for hosts like editors that must outlive a broken plugin.
+
+use std::panic::{self, AssertUnwindSafe};
+
+use thiserror::Error;
+
+use crate::export::ExportError;
+use crate::file_builder::BuilderError;
+use crate::import::ImportError;
+use crate::migrate::MigrationError;
+use crate::parsers::{SentenceParseError, TypedSentencesError};
+use crate::semantic::{DokeValidationError, GodotValueError, PipeError};
+use crate::template::TemplateError;
+
+/// Any error of the crate, by what failed
+#[derive(Debug, Error)]
+pub enum Error {
+    /// Reading a grammar (a dokedef)
+    #[error(transparent)]
+    Sentence(#[from] SentenceParseError),
+
+    /// Loading a dokeconfig and its grammars
+    #[error(transparent)]
+    Grammar(#[from] TypedSentencesError),
+
+    /// Assembling a pipe, see `DokePipe::verify`
+    #[error(transparent)]
+    Pipe(#[from] PipeError),
+
+    /// Turning a parsed document into values
+    #[error(transparent)]
+    Validation(#[from] DokeValidationError),
+
+    /// Building the file resource from the values
+    #[error(transparent)]
+    Build(#[from] BuilderError),
+
+    #[error(transparent)]
+    Value(#[from] GodotValueError),
+
+    #[error(transparent)]
+    Export(#[from] ExportError),
+
+    #[error(transparent)]
+    Import(#[from] ImportError),
+
+    /// Rendering a template document
+    #[error(transparent)]
+    Template(#[from] TemplateError),
+
+    #[cfg(feature = "bundles")]
+    #[error(transparent)]
+    Bundle(#[from] crate::parsers::BundleError),
+
+    #[cfg(feature = "csv")]
+    #[error(transparent)]
+    Spreadsheet(#[from] crate::spreadsheet::SpreadsheetError),
+
+    #[cfg(feature = "bevy")]
+    #[error(transparent)]
+    Bevy(#[from] crate::bevy::BevyError),
+
+    #[cfg(feature = "testing")]
+    #[error(transparent)]
+    RoundTrip(#[from] crate::testing::RoundTripError),
+
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// A parser panicked, with its message
+    #[error("a parser panicked: {0}")]
+    Panic(String),
+}
+
+impl Error {
+    /// Stable identifier of the failure, e.g. `grammar-path-escape`
+    /// ```
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// fn load() -> Result<SentenceParser, doke::Error> {
+    ///     let grammar = SentenceParser::from_yaml("Effect".into(), "Damage: [\"Deals {x: int} damage\"]")
+    ///         .expect("a valid grammar");
+    ///     Ok(grammar.with_tr_key_format(Default::default())?)
+    /// }
+    /// assert!(load().is_ok());
+    ///
+    /// let error = doke::Error::from(std::io::Error::other("disk full"));
+    /// assert_eq!(error.code(), "io");
+    /// ```
+    pub fn code(&self) -> &'static str {
+        match self {
+            Error::Sentence(e) => match e {
+                SentenceParseError::YamlParseError(_) => "sentence-yaml",
+                SentenceParseError::EmptyYaml => "sentence-empty-yaml",
+                SentenceParseError::RegexError(..) => "sentence-regex",
+                SentenceParseError::InvalidPattern(_) => "sentence-invalid-pattern",
+                SentenceParseError::NoMatch(_) => "sentence-no-match",
+                SentenceParseError::MaxRecursionDepthExceeded(_) => "sentence-recursion-depth",
+                SentenceParseError::UnknownSection(_) => "sentence-unknown-section",
+                #[cfg(feature = "translate")]
+                SentenceParseError::TranslationWriteError(_) => "sentence-translation-file",
+                SentenceParseError::TrKeyCollision(..) => "sentence-tr-key-collision",
+                SentenceParseError::MessagesWriteError(_) => "sentence-messages-file",
+            },
+            Error::Grammar(e) => match e {
+                TypedSentencesError::YamlParseError(_) => "grammar-yaml",
+                TypedSentencesError::InvalidRule(_) => "grammar-invalid-rule",
+                TypedSentencesError::NoMatchingParser => "grammar-no-matching-parser",
+                TypedSentencesError::FileError(_) => "grammar-file",
+                TypedSentencesError::GlobError(_) => "grammar-glob",
+                TypedSentencesError::BundleError(_) => "grammar-bundle",
+                TypedSentencesError::PathEscape(_) => "grammar-path-escape",
+            },
+            Error::Validation(e) => match e {
+                DokeValidationError::NodeError(..) => "validation-node",
+                DokeValidationError::MissingField(..) => "validation-missing-field",
+                DokeValidationError::InvalidFieldType(..) => "validation-field-type",
+                DokeValidationError::HypothesisPromotionFailed(..) => "validation-promotion",
+                DokeValidationError::UnresolvedNode(_) => "validation-unresolved",
+                DokeValidationError::LowConfidence(..) => "validation-low-confidence",
+                DokeValidationError::MultipleErrors(_) => "validation-multiple",
+                DokeValidationError::ChildUsageFailed(_) => "validation-child",
+                DokeValidationError::DynamicError(_) => "validation-other",
+            },
+            Error::Build(e) => match e {
+                BuilderError::Yaml(_) => "build-yaml",
+                BuilderError::Io(_) => "build-io",
+                BuilderError::Config(_) => "build-config",
+                BuilderError::MissingField(..) => "build-missing-field",
+                BuilderError::TypeMismatch(..) => "build-type-mismatch",
+                BuilderError::UnknownProfile(_) => "build-unknown-profile",
+                BuilderError::MissingCapability(..) => "build-missing-capability",
+                BuilderError::Migration(MigrationError::Config(_)) => "build-migration-config",
+                BuilderError::Migration(MigrationError::UnknownVersion(_)) => {
+                    "build-unknown-schema-version"
+                }
+            },
+            Error::Value(e) => match e {
+                GodotValueError::InvalidChild(_) => "value-invalid-child",
+                GodotValueError::InvalidPath(..) => "value-invalid-path",
+            },
+            Error::Export(e) => match e {
+                ExportError::Io(_) => "export-io",
+                ExportError::Invalid(..) => "export-invalid",
+                ExportError::Build(..) => "export-build",
+                ExportError::Cycle(_) => "export-cycle",
+                ExportError::DuplicateId(..) => "export-duplicate-id",
+                #[cfg(feature = "sqlite")]
+                ExportError::Sqlite(_) => "export-sqlite",
+            },
+            Error::Import(ImportError::Parse(_)) => "import-parse",
+            Error::Pipe(PipeError::MissingPrerequisite { .. }) => "pipe-missing-prerequisite",
+            Error::Template(e) => match e {
+                TemplateError::NotATemplate => "template-not-a-template",
+                TemplateError::InvalidParams(_) => "template-invalid-params",
+                TemplateError::InvalidMatrix(_) => "template-invalid-matrix",
+                TemplateError::Arguments(..) => "template-arguments",
+            },
+            #[cfg(feature = "bundles")]
+            Error::Bundle(_) => "bundle",
+            #[cfg(feature = "csv")]
+            Error::Spreadsheet(_) => "spreadsheet",
+            #[cfg(feature = "bevy")]
+            Error::Bevy(_) => "bevy",
+            #[cfg(feature = "testing")]
+            Error::RoundTrip(_) => "round-trip",
+            Error::Io(_) => "io",
+            Error::Panic(_) => "panic",
+        }
+    }
+}
+
+/// Run `f`, a panic in it becoming `Error::Panic`
+pub(crate) fn catch_panic<T>(f: impl FnOnce() -> T) -> Result<T, Error> {
+    panic::catch_unwind(AssertUnwindSafe(f)).map_err(|payload| {
+        let message = match payload.downcast_ref::<&str>() {
+            Some(message) => message.to_string(),
+            None => match payload.downcast_ref::<String>() {
+                Some(message) => message.clone(),
+                None => "no message".into(),
+            },
+        };
+        Error::Panic(message)
+    })
+}
@@ -0,0 +1,286 @@
+// binary.rs
+//
+// A compact binary encoding of built values (`.dokb`), loaded in Godot by
+// `doke_binary_loader.gd` without parsing any text.
+//
+// All numbers are little-endian.
+//
+//     "DOKB" u32 version
+//     u32 string count, then each string: u32 byte length, UTF-8 bytes
+//     the root value
+//
+// A value is a tag byte followed by its payload, strings are indices in the table:
+//
+//     0 nil
+//     1 bool      u8
+//     2 int       i64
+//     3 uint      u64
+//     4 float     f64
+//     5 string    u32 index
+//     6 array     u32 count, values
+//     7 dict      u32 count, (u32 key index, value) pairs
+//     8 resource  u32 type index, u32 abstract type index, u32 count, (u32 key index, value) pairs
+//     9 vector2   f64 x, f64 y
+//    10 rect2     f64 x, f64 y, f64 width, f64 height
+//    11 color     f64 r, f64 g, f64 b, f64 a
+//
+// Keys of dicts and resources are sorted, so the same value always encodes the same.
+// References to other documents are `ExtRef` resources with an `id` and the
+// `path` of the referenced file, see `TresExport::to_binary`.
+
+use std::collections::HashMap;
+
+use crate::GodotValue;
+
+pub const MAGIC: &[u8; 4] = b"DOKB";
+pub const VERSION: u32 = 1;
+
+/// The GDScript loading `.dokb` files into resources and dictionaries
+pub const GDSCRIPT_LOADER: &str = include_str!("doke_binary_loader.gd");
+
+const NIL: u8 = 0;
+const BOOL: u8 = 1;
+const INT: u8 = 2;
+const UINT: u8 = 3;
+const FLOAT: u8 = 4;
+const STRING: u8 = 5;
+const ARRAY: u8 = 6;
+const DICT: u8 = 7;
+const RESOURCE: u8 = 8;
+const VECTOR2: u8 = 9;
+const RECT2: u8 = 10;
+const COLOR: u8 = 11;
+
+/// Encode `value` in the `.dokb` format
+pub fn to_binary(value: &GodotValue) -> Vec<u8> {
+    let mut strings = Strings::default();
+    let mut body = Vec::new();
+    encode(value, &mut strings, &mut body);
+
+    let mut out = Vec::with_capacity(body.len() + 64);
+    out.extend_from_slice(MAGIC);
+    out.extend_from_slice(&VERSION.to_le_bytes());
+    out.extend_from_slice(&(strings.list.len() as u32).to_le_bytes());
+    for s in &strings.list {
+        out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+        out.extend_from_slice(s.as_bytes());
+    }
+    out.extend_from_slice(&body);
+    out
+}
+
+/// Decode a `.dokb` file
+/// ```
+/// use std::collections::HashMap;
+/// use doke::GodotValue;
+/// use doke::export::binary::{from_binary, to_binary};
+///
+/// let sword = GodotValue::Resource {
+///     type_name: "Item".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::from([
+///         ("name".into(), GodotValue::String("Iron sword".into())),
+///         ("damage".into(), GodotValue::Int(-5)),
+///         ("tags".into(), GodotValue::Array(vec![GodotValue::String("blade".into())])),
+///         ("size".into(), GodotValue::Vector2(1.0, 2.5)),
+///     ]),
+/// };
+/// let bytes = to_binary(&sword);
+/// assert_eq!(from_binary(&bytes), Ok(sword));
+///
+/// let truncated = &bytes[..bytes.len() - 3];
+/// assert!(from_binary(truncated).unwrap_err().starts_with("truncated at byte"));
+/// assert_eq!(from_binary(b"PNG\0"), Err("not a .dokb file".into()));
+/// ```
+pub fn from_binary(bytes: &[u8]) -> Result<GodotValue, String> {
+    let mut reader = Reader { bytes, pos: 0 };
+    if reader.take(4)? != MAGIC {
+        return Err("not a .dokb file".into());
+    }
+    let version = reader.u32()?;
+    if version != VERSION {
+        return Err(format!("unsupported .dokb version {}", version));
+    }
+    let count = reader.u32()? as usize;
+    let mut strings = Vec::with_capacity(count.min(bytes.len()));
+    for _ in 0..count {
+        let len = reader.u32()? as usize;
+        let text = std::str::from_utf8(reader.take(len)?).map_err(|e| e.to_string())?;
+        strings.push(text.to_string());
+    }
+    reader.value(&strings, 0)
+}
+
+#[derive(Default)]
+struct Strings {
+    list: Vec<String>,
+    index: HashMap<String, u32>,
+}
+
+impl Strings {
+    fn id(&mut self, s: &str) -> u32 {
+        if let Some(&i) = self.index.get(s) {
+            return i;
+        }
+        let i = self.list.len() as u32;
+        self.list.push(s.to_string());
+        self.index.insert(s.to_string(), i);
+        i
+    }
+}
+
+fn encode(value: &GodotValue, strings: &mut Strings, out: &mut Vec<u8>) {
+    match value {
+        GodotValue::Nil => out.push(NIL),
+        GodotValue::Bool(b) => out.extend_from_slice(&[BOOL, *b as u8]),
+        GodotValue::Int(i) => {
+            out.push(INT);
+            out.extend_from_slice(&i.to_le_bytes());
+        }
+        GodotValue::UInt(u) => {
+            out.push(UINT);
+            out.extend_from_slice(&u.to_le_bytes());
+        }
+        GodotValue::Float(f) => {
+            out.push(FLOAT);
+            out.extend_from_slice(&f.to_le_bytes());
+        }
+        GodotValue::String(s) => {
+            out.push(STRING);
+            out.extend_from_slice(&strings.id(s).to_le_bytes());
+        }
+        GodotValue::Array(items) => {
+            out.push(ARRAY);
+            out.extend_from_slice(&(items.len() as u32).to_le_bytes());
+            for item in items {
+                encode(item, strings, out);
+            }
+        }
+        GodotValue::Dict(fields) => {
+            out.push(DICT);
+            encode_map(fields, strings, out);
+        }
+        GodotValue::Resource {
+            type_name,
+            abstract_type_name,
+            fields,
+        } => {
+            out.push(RESOURCE);
+            out.extend_from_slice(&strings.id(type_name).to_le_bytes());
+            out.extend_from_slice(&strings.id(abstract_type_name).to_le_bytes());
+            encode_map(fields, strings, out);
+        }
+        GodotValue::Vector2(x, y) => {
+            out.push(VECTOR2);
+            for component in [x, y] {
+                out.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+        GodotValue::Rect2(x, y, w, h) => {
+            out.push(RECT2);
+            for component in [x, y, w, h] {
+                out.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+        GodotValue::Color(r, g, b, a) => {
+            out.push(COLOR);
+            for component in [r, g, b, a] {
+                out.extend_from_slice(&component.to_le_bytes());
+            }
+        }
+    }
+}
+
+fn encode_map(map: &HashMap<String, GodotValue>, strings: &mut Strings, out: &mut Vec<u8>) {
+    let mut keys: Vec<&String> = map.keys().collect();
+    keys.sort();
+    out.extend_from_slice(&(keys.len() as u32).to_le_bytes());
+    for key in keys {
+        out.extend_from_slice(&strings.id(key).to_le_bytes());
+        encode(&map[key], strings, out);
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Result<&'a [u8], String> {
+        let end = self
+            .pos
+            .checked_add(len)
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| format!("truncated at byte {}", self.pos))?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    fn u64(&mut self) -> Result<[u8; 8], String> {
+        Ok(self.take(8)?.try_into().unwrap())
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_le_bytes(self.u64()?))
+    }
+
+    fn string(&mut self, strings: &[String]) -> Result<String, String> {
+        let i = self.u32()? as usize;
+        strings
+            .get(i)
+            .cloned()
+            .ok_or_else(|| format!("string {} out of the table", i))
+    }
+
+    fn map(
+        &mut self,
+        strings: &[String],
+        depth: usize,
+    ) -> Result<HashMap<String, GodotValue>, String> {
+        let count = self.u32()? as usize;
+        let mut map = HashMap::new();
+        for _ in 0..count {
+            let key = self.string(strings)?;
+            map.insert(key, self.value(strings, depth + 1)?);
+        }
+        Ok(map)
+    }
+
+    fn value(&mut self, strings: &[String], depth: usize) -> Result<GodotValue, String> {
+        if depth > 512 {
+            return Err("values nested too deep".into());
+        }
+        Ok(match self.take(1)?[0] {
+            NIL => GodotValue::Nil,
+            BOOL => GodotValue::Bool(self.take(1)?[0] != 0),
+            INT => GodotValue::Int(i64::from_le_bytes(self.u64()?)),
+            UINT => GodotValue::UInt(u64::from_le_bytes(self.u64()?)),
+            FLOAT => GodotValue::Float(self.f64()?),
+            STRING => GodotValue::String(self.string(strings)?),
+            ARRAY => {
+                let count = self.u32()? as usize;
+                let mut items = Vec::with_capacity(count.min(self.bytes.len()));
+                for _ in 0..count {
+                    items.push(self.value(strings, depth + 1)?);
+                }
+                GodotValue::Array(items)
+            }
+            DICT => GodotValue::Dict(self.map(strings, depth)?),
+            RESOURCE => GodotValue::Resource {
+                type_name: self.string(strings)?,
+                abstract_type_name: self.string(strings)?,
+                fields: self.map(strings, depth)?,
+            },
+            VECTOR2 => GodotValue::Vector2(self.f64()?, self.f64()?),
+            RECT2 => GodotValue::Rect2(self.f64()?, self.f64()?, self.f64()?, self.f64()?),
+            COLOR => GodotValue::Color(self.f64()?, self.f64()?, self.f64()?, self.f64()?),
+            tag => return Err(format!("unknown tag {} at byte {}", tag, self.pos - 1)),
+        })
+    }
+}
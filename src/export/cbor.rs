@@ -0,0 +1,76 @@
+// cbor.rs
+//
+// CBOR encoding of built values, for engines other than Godot.
+//
+// Same layout as `msgpack`: a resource is a map of its fields with two more
+// keys, `$type` and `$abstract_type`. Keys are sorted, so the same value
+// always encodes the same.
+
+use std::collections::HashMap;
+
+use ciborium::Value;
+
+use crate::GodotValue;
+
+/// Encode `value` as CBOR
+/// ```
+/// use std::collections::HashMap;
+/// use ciborium::Value;
+/// use doke::GodotValue;
+/// use doke::export::cbor::to_cbor;
+///
+/// let burn = GodotValue::Resource {
+///     type_name: "Burn".into(),
+///     abstract_type_name: "Effect".into(),
+///     fields: HashMap::from([("turns".into(), GodotValue::Int(2))]),
+/// };
+/// let decoded: Value = ciborium::from_reader(to_cbor(&burn).as_slice()).unwrap();
+/// let map = decoded.into_map().unwrap();
+/// assert_eq!(map[1], (Value::from("$type"), Value::from("Burn")));
+/// assert_eq!(map[2], (Value::from("turns"), Value::from(2)));
+/// ```
+pub fn to_cbor(value: &GodotValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    ciborium::into_writer(&to_value(value), &mut out).expect("writing to a Vec can't fail");
+    out
+}
+
+/// The CBOR value of `value`
+pub fn to_value(value: &GodotValue) -> Value {
+    match value {
+        GodotValue::Nil => Value::Null,
+        GodotValue::Bool(b) => Value::Bool(*b),
+        GodotValue::Int(i) => Value::Integer((*i).into()),
+        GodotValue::UInt(u) => Value::Integer((*u).into()),
+        GodotValue::Float(f) => Value::Float(*f),
+        GodotValue::String(s) => Value::Text(s.clone()),
+        GodotValue::Array(items) => Value::Array(items.iter().map(to_value).collect()),
+        GodotValue::Dict(fields) => Value::Map(entries(fields)),
+        GodotValue::Resource {
+            type_name,
+            abstract_type_name,
+            fields,
+        } => {
+            let mut map = vec![
+                (
+                    Value::Text("$abstract_type".into()),
+                    Value::Text(abstract_type_name.clone()),
+                ),
+                (Value::Text("$type".into()), Value::Text(type_name.clone())),
+            ];
+            map.extend(entries(fields));
+            Value::Map(map)
+        }
+        GodotValue::Vector2(..) | GodotValue::Rect2(..) | GodotValue::Color(..) => {
+            Value::Map(entries(&value.components().unwrap_or_default()))
+        }
+    }
+}
+
+fn entries(fields: &HashMap<String, GodotValue>) -> Vec<(Value, Value)> {
+    let mut keys: Vec<&String> = fields.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| (Value::Text(key.clone()), to_value(&fields[key])))
+        .collect()
+}
@@ -0,0 +1,110 @@
+# doke_binary_loader.gd
+#
+# Loads the .dokb files written by `doke export --binary`, see the format in
+# src/export/binary.rs.
+#
+#     var sword = DokeBinaryLoader.load_dokb("res://content/sword.dokb")
+#
+# Resources become instances of `script_dir/<snake_case type>.gd` when that
+# script exists, and Dictionaries with a "doke_type" key otherwise.
+# References to other documents are loaded once and shared.
+class_name DokeBinaryLoader
+extends RefCounted
+
+const MAGIC := "DOKB"
+const VERSION := 1
+const EXT_REF := "ExtRef"
+
+static var script_dir := "res://scripts"
+static var _cache := {}
+
+
+static func load_dokb(path: String) -> Variant:
+	if _cache.has(path):
+		return _cache[path]
+	var bytes := FileAccess.get_file_as_bytes(path)
+	if bytes.is_empty():
+		push_error("doke: can't read %s" % path)
+		return null
+	var value = decode(bytes)
+	_cache[path] = value
+	return value
+
+
+static func clear_cache() -> void:
+	_cache.clear()
+
+
+static func decode(bytes: PackedByteArray) -> Variant:
+	var stream := StreamPeerBuffer.new()
+	stream.data_array = bytes
+	stream.big_endian = false
+	if stream.get_data(4)[1].get_string_from_ascii() != MAGIC:
+		push_error("doke: not a .dokb file")
+		return null
+	var version := stream.get_u32()
+	if version != VERSION:
+		push_error("doke: unsupported .dokb version %d" % version)
+		return null
+	var strings := PackedStringArray()
+	for i in stream.get_u32():
+		strings.append(stream.get_utf8_string(stream.get_u32()))
+	return _value(stream, strings)
+
+
+static func _value(stream: StreamPeerBuffer, strings: PackedStringArray) -> Variant:
+	match stream.get_u8():
+		0:
+			return null
+		1:
+			return stream.get_u8() != 0
+		2:
+			return stream.get_64()
+		3:
+			return stream.get_u64()
+		4:
+			return stream.get_double()
+		5:
+			return strings[stream.get_u32()]
+		6:
+			var items := []
+			for i in stream.get_u32():
+				items.append(_value(stream, strings))
+			return items
+		7:
+			return _map(stream, strings)
+		8:
+			var type_name := strings[stream.get_u32()]
+			var abstract_type := strings[stream.get_u32()]
+			var fields := _map(stream, strings)
+			if type_name == EXT_REF:
+				return load_dokb(fields.get("path", ""))
+			return _resource(type_name, abstract_type, fields)
+		9:
+			return Vector2(stream.get_double(), stream.get_double())
+		10:
+			return Rect2(stream.get_double(), stream.get_double(), stream.get_double(), stream.get_double())
+		11:
+			return Color(stream.get_double(), stream.get_double(), stream.get_double(), stream.get_double())
+	push_error("doke: unknown tag at byte %d" % (stream.get_position() - 1))
+	return null
+
+
+static func _map(stream: StreamPeerBuffer, strings: PackedStringArray) -> Dictionary:
+	var map := {}
+	for i in stream.get_u32():
+		var key := strings[stream.get_u32()]
+		map[key] = _value(stream, strings)
+	return map
+
+
+static func _resource(type_name: String, abstract_type: String, fields: Dictionary) -> Variant:
+	var script_path := "%s/%s.gd" % [script_dir, type_name.to_snake_case()]
+	if not ResourceLoader.exists(script_path):
+		fields["doke_type"] = type_name
+		fields["doke_abstract_type"] = abstract_type
+		return fields
+	var resource: Object = load(script_path).new()
+	for key in fields:
+		resource.set(key, fields[key])
+	return resource
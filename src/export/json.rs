@@ -0,0 +1,97 @@
+// json.rs
+//
+// JSON writer for built values, in the layout `import::parse_json` reads back:
+// resources are objects with their fields plus `type` and `abstract_type`.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use crate::GodotValue;
+
+/// Write `value` as compact JSON, keys sorted
+pub fn to_json(value: &GodotValue) -> String {
+    let mut out = String::new();
+    write_value(value, &mut out);
+    out
+}
+
+fn write_value(value: &GodotValue, out: &mut String) {
+    match value {
+        GodotValue::Nil => out.push_str("null"),
+        GodotValue::Bool(b) => write!(out, "{}", b).unwrap(),
+        GodotValue::Int(i) => write!(out, "{}", i).unwrap(),
+        GodotValue::UInt(u) => write!(out, "{}", u).unwrap(),
+        // JSON has no NaN or infinities
+        GodotValue::Float(f) if !f.is_finite() => out.push_str("null"),
+        GodotValue::Float(f) => write!(out, "{:?}", f).unwrap(),
+        GodotValue::String(s) => out.push_str(&json_string(s)),
+        GodotValue::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_value(item, out);
+            }
+            out.push(']');
+        }
+        GodotValue::Dict(fields) => write_object(None, fields, out),
+        GodotValue::Resource {
+            type_name,
+            abstract_type_name,
+            fields,
+        } => write_object(Some((type_name, abstract_type_name)), fields, out),
+        GodotValue::Vector2(..) | GodotValue::Rect2(..) | GodotValue::Color(..) => {
+            write_object(None, &value.components().unwrap_or_default(), out)
+        }
+    }
+}
+
+fn write_object(
+    types: Option<(&String, &String)>,
+    fields: &HashMap<String, GodotValue>,
+    out: &mut String,
+) {
+    out.push('{');
+    let mut first = true;
+    if let Some((type_name, abstract_type_name)) = types {
+        write!(
+            out,
+            "\"abstract_type\":{},\"type\":{}",
+            json_string(abstract_type_name),
+            json_string(type_name)
+        )
+        .unwrap();
+        first = false;
+    }
+    let mut names: Vec<&String> = fields.keys().collect();
+    names.sort();
+    for name in names {
+        if !first {
+            out.push(',');
+        }
+        first = false;
+        out.push_str(&json_string(name));
+        out.push(':');
+        write_value(&fields[name], out);
+    }
+    out.push('}');
+}
+
+pub(crate) fn json_string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).unwrap(),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
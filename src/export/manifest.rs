@@ -0,0 +1,89 @@
+// manifest.rs
+//
+// The list of files a batch export wrote, so packaging can ship only what
+// changed and the game can check the content it loads.
+
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use super::json::json_string;
+
+/// What a batch export wrote
+/// ```
+/// use std::fs;
+/// use doke::{DokePipe, export::TresExport, parsers::SentenceParser, workspace::Workspace};
+/// use doke::file_builder::{Config, FieldConfig, FieldType, ResourceBuilder};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+/// let mut workspace = Workspace::new();
+/// workspace.add_document("sword.md", "Deals 5 damage".into(), &pipe);
+/// let builder = ResourceBuilder::from_config(Config {
+///     root: "Item".into(),
+///     children: vec![FieldConfig { name: "effects".into(), ty: FieldType::Array("Effect".into()), optional: false }],
+///     ..Default::default()
+/// }).unwrap();
+///
+/// let dir = std::env::temp_dir().join("doke_manifest");
+/// let export = TresExport::default().with_grammar_version("v3");
+/// let manifest = workspace.export_tres(&builder, &export, &dir).unwrap();
+/// assert_eq!(manifest.resources[0].path, "res://content/sword.tres");
+/// assert_eq!(manifest.resources[0].sha256.len(), 64);
+///
+/// let json = fs::read_to_string(dir.join("manifest.json")).unwrap();
+/// assert_eq!(json, manifest.to_json());
+/// assert!(json.contains("\"grammar_version\": \"v3\""));
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Manifest {
+    /// The grammar the content was built with, see `TypedSentencesParser::fingerprint`
+    pub grammar_version: String,
+    pub resources: Vec<ManifestEntry>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManifestEntry {
+    /// Id of the document
+    pub id: String,
+    /// `res://` path of the exported file
+    pub path: String,
+    pub uid: String,
+    /// The document it was built from, relative to the workspace
+    pub source: PathBuf,
+    /// Sha256 of the exported file, as Godot's `FileAccess.get_sha256` computes it
+    pub sha256: String,
+}
+
+impl Manifest {
+    pub fn to_json(&self) -> String {
+        let mut out = String::from("{\n");
+        writeln!(
+            out,
+            "  \"grammar_version\": {},",
+            json_string(&self.grammar_version)
+        )
+        .unwrap();
+        out.push_str("  \"resources\": [");
+        for (i, entry) in self.resources.iter().enumerate() {
+            out.push_str(if i == 0 { "\n" } else { ",\n" });
+            write!(
+                out,
+                "    {{\"id\": {}, \"path\": {}, \"uid\": {}, \"source\": {}, \"sha256\": {}}}",
+                json_string(&entry.id),
+                json_string(&entry.path),
+                json_string(&entry.uid),
+                json_string(&entry.source.to_string_lossy().replace('\\', "/")),
+                json_string(&entry.sha256)
+            )
+            .unwrap();
+        }
+        if !self.resources.is_empty() {
+            out.push_str("\n  ");
+        }
+        out.push_str("]\n}\n");
+        out
+    }
+}
@@ -0,0 +1,47 @@
+// export
+//
+// Writing built resources as Godot files. Counterpart of `import`.
+
+pub mod binary;
+#[cfg(feature = "cbor")]
+pub mod cbor;
+pub mod json;
+mod manifest;
+#[cfg(feature = "msgpack")]
+pub mod msgpack;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod tres;
+
+use std::path::PathBuf;
+
+use thiserror::Error;
+
+use crate::file_builder::BuilderError;
+
+pub use manifest::{Manifest, ManifestEntry};
+#[cfg(feature = "sqlite")]
+pub use sqlite::{write_sqlite, write_sqlite_to};
+pub use tres::TresExport;
+
+#[derive(Debug, Error)]
+pub enum ExportError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("{0} doesn't validate: {1}")]
+    Invalid(PathBuf, String),
+
+    #[error("Build error in {0}: {1}")]
+    Build(PathBuf, #[source] BuilderError),
+
+    #[error("Documents reference each other: {0}")]
+    Cycle(String),
+
+    #[error("{1} and {2} have the same id '{0}'")]
+    DuplicateId(String, PathBuf, PathBuf),
+
+    #[cfg(feature = "sqlite")]
+    #[error("SQLite error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+}
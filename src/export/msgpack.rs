@@ -0,0 +1,74 @@
+// msgpack.rs
+//
+// MessagePack encoding of built values, for engines other than Godot.
+//
+// Values map to their MessagePack counterpart. A resource is a map of its
+// fields with two more keys, `$type` and `$abstract_type`, which can't clash
+// with field names. Keys are sorted, so the same value always encodes the same.
+
+use std::collections::HashMap;
+
+use rmpv::Value;
+
+use crate::GodotValue;
+
+/// Encode `value` as MessagePack
+/// ```
+/// use std::collections::HashMap;
+/// use doke::GodotValue;
+/// use doke::export::msgpack::to_msgpack;
+///
+/// let burn = GodotValue::Resource {
+///     type_name: "Burn".into(),
+///     abstract_type_name: "Effect".into(),
+///     fields: HashMap::from([("turns".into(), GodotValue::Int(2))]),
+/// };
+/// let bytes = to_msgpack(&burn);
+/// let decoded = rmpv::decode::read_value(&mut bytes.as_slice()).unwrap();
+/// assert_eq!(decoded.to_string(), r#"{"$abstract_type": "Effect", "$type": "Burn", "turns": 2}"#);
+/// ```
+pub fn to_msgpack(value: &GodotValue) -> Vec<u8> {
+    let mut out = Vec::new();
+    rmpv::encode::write_value(&mut out, &to_value(value)).expect("writing to a Vec can't fail");
+    out
+}
+
+/// The MessagePack value of `value`
+pub fn to_value(value: &GodotValue) -> Value {
+    match value {
+        GodotValue::Nil => Value::Nil,
+        GodotValue::Bool(b) => Value::Boolean(*b),
+        GodotValue::Int(i) => Value::from(*i),
+        GodotValue::UInt(u) => Value::from(*u),
+        GodotValue::Float(f) => Value::F64(*f),
+        GodotValue::String(s) => Value::from(s.as_str()),
+        GodotValue::Array(items) => Value::Array(items.iter().map(to_value).collect()),
+        GodotValue::Dict(fields) => Value::Map(entries(fields)),
+        GodotValue::Resource {
+            type_name,
+            abstract_type_name,
+            fields,
+        } => {
+            let mut map = vec![
+                (
+                    Value::from("$abstract_type"),
+                    Value::from(abstract_type_name.as_str()),
+                ),
+                (Value::from("$type"), Value::from(type_name.as_str())),
+            ];
+            map.extend(entries(fields));
+            Value::Map(map)
+        }
+        GodotValue::Vector2(..) | GodotValue::Rect2(..) | GodotValue::Color(..) => {
+            Value::Map(entries(&value.components().unwrap_or_default()))
+        }
+    }
+}
+
+fn entries(fields: &HashMap<String, GodotValue>) -> Vec<(Value, Value)> {
+    let mut keys: Vec<&String> = fields.keys().collect();
+    keys.sort();
+    keys.into_iter()
+        .map(|key| (Value::from(key.as_str()), to_value(&fields[key])))
+        .collect()
+}
@@ -0,0 +1,226 @@
+// sqlite.rs
+//
+// Writing built content to a SQLite database, to query it at scale.
+//
+// Every resource, nested ones included, is a row of the table of its abstract
+// type (the root's table is named after its type). Rows have
+//
+//     document  id of the document it was built from
+//     path      where it is in the document, like `modifiers/0`, empty for the root
+//     parent    path of the resource holding it, null for the root
+//     type      its concrete type
+//     data      all its fields as JSON, see `export::json`
+//
+// and one column per field holding a number, string or bool, so
+// `SELECT * FROM ItemEffect WHERE damage > 50` works without JSON functions.
+
+use std::collections::BTreeMap;
+use std::path::Path;
+
+use rusqlite::Connection;
+use rusqlite::types::Value;
+
+use super::ExportError;
+use super::json::to_json;
+use crate::GodotValue;
+use crate::parsers::param_types::EXT_REF_TYPE;
+
+const COLUMNS: [&str; 5] = ["document", "path", "parent", "type", "data"];
+
+struct Row {
+    document: String,
+    path: String,
+    parent: Option<String>,
+    type_name: String,
+    data: String,
+    fields: Vec<(String, Value)>,
+}
+
+/// Write the built resource of each document to a new database at `path`,
+/// replacing any file there.
+pub fn write_sqlite<'a>(
+    path: &Path,
+    documents: impl IntoIterator<Item = (&'a str, &'a GodotValue)>,
+) -> Result<(), ExportError> {
+    if path.exists() {
+        std::fs::remove_file(path)?;
+    }
+    write_sqlite_to(&mut Connection::open(path)?, documents)
+}
+
+/// Write the built resource of each document to the tables of `conn`, which
+/// mustn't have them yet
+/// ```
+/// use std::collections::HashMap;
+/// use doke::GodotValue;
+/// use doke::export::write_sqlite_to;
+/// use rusqlite::Connection;
+///
+/// let burn = GodotValue::Resource {
+///     type_name: "Burn".into(),
+///     abstract_type_name: "ItemEffect".into(),
+///     fields: HashMap::from([("turns".into(), GodotValue::Int(3))]),
+/// };
+/// let sword = GodotValue::Resource {
+///     type_name: "Item".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::from([("effects".into(), GodotValue::Array(vec![burn]))]),
+/// };
+/// let mut conn = Connection::open_in_memory().unwrap();
+/// write_sqlite_to(&mut conn, [("iron_sword", &sword)]).unwrap();
+///
+/// let row: (String, String, i64) = conn
+///     .query_row("SELECT document, path, turns FROM ItemEffect WHERE type = 'Burn'", [], |row| {
+///         Ok((row.get(0)?, row.get(1)?, row.get(2)?))
+///     })
+///     .unwrap();
+/// assert_eq!(row, ("iron_sword".into(), "effects/0".into(), 3));
+/// ```
+pub fn write_sqlite_to<'a>(
+    conn: &mut Connection,
+    documents: impl IntoIterator<Item = (&'a str, &'a GodotValue)>,
+) -> Result<(), ExportError> {
+    let mut tables: BTreeMap<String, Vec<Row>> = BTreeMap::new();
+    for (id, resource) in documents {
+        collect(id, resource, String::new(), None, &mut tables);
+    }
+
+    let tx = conn.transaction()?;
+    for (table, rows) in &tables {
+        // every field seen in the table, typed by its first value
+        let mut columns: Vec<(&str, &str)> = Vec::new();
+        for row in rows {
+            for (name, value) in &row.fields {
+                if !columns.iter().any(|(c, _)| c == name) {
+                    columns.push((name, sql_type(value)));
+                }
+            }
+        }
+        columns.sort();
+
+        let mut create = format!(
+            "CREATE TABLE {} (document TEXT NOT NULL, path TEXT NOT NULL, parent TEXT, \
+             type TEXT NOT NULL, data TEXT NOT NULL",
+            quote(table)
+        );
+        for (name, ty) in &columns {
+            create.push_str(&format!(", {} {}", quote(name), ty));
+        }
+        create.push_str(", PRIMARY KEY (document, path))");
+        tx.execute(&create, [])?;
+
+        let names: Vec<String> = COLUMNS
+            .iter()
+            .copied()
+            .chain(columns.iter().map(|(name, _)| *name))
+            .map(quote)
+            .collect();
+        let insert = format!(
+            "INSERT INTO {} ({}) VALUES ({})",
+            quote(table),
+            names.join(", "),
+            vec!["?"; names.len()].join(", ")
+        );
+        let mut statement = tx.prepare(&insert)?;
+        for row in rows {
+            let mut values = vec![
+                Value::Text(row.document.clone()),
+                Value::Text(row.path.clone()),
+                row.parent.clone().map_or(Value::Null, Value::Text),
+                Value::Text(row.type_name.clone()),
+                Value::Text(row.data.clone()),
+            ];
+            for (name, _) in &columns {
+                let value = row.fields.iter().find(|(n, _)| n == name);
+                values.push(value.map_or(Value::Null, |(_, v)| v.clone()));
+            }
+            statement.execute(rusqlite::params_from_iter(values))?;
+        }
+    }
+    tx.commit()?;
+    Ok(())
+}
+
+fn collect(
+    document: &str,
+    value: &GodotValue,
+    path: String,
+    parent: Option<&str>,
+    tables: &mut BTreeMap<String, Vec<Row>>,
+) {
+    let child = |key: &str| {
+        if path.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}/{}", path, key)
+        }
+    };
+    match value {
+        GodotValue::Resource { type_name, .. } if type_name == EXT_REF_TYPE => {}
+        GodotValue::Resource {
+            type_name,
+            abstract_type_name,
+            fields,
+        } => {
+            let table = if parent.is_none() {
+                type_name
+            } else {
+                abstract_type_name
+            };
+            let mut names: Vec<&String> = fields.keys().collect();
+            names.sort();
+            let scalars = names
+                .iter()
+                .filter(|name| !COLUMNS.contains(&name.as_str()))
+                .filter_map(|name| Some((name.to_string(), scalar(&fields[*name])?)))
+                .collect();
+            tables.entry(table.clone()).or_default().push(Row {
+                document: document.to_string(),
+                path: path.clone(),
+                parent: parent.map(str::to_string),
+                type_name: type_name.clone(),
+                data: to_json(value),
+                fields: scalars,
+            });
+            for name in names {
+                collect(document, &fields[name], child(name), Some(&path), tables);
+            }
+        }
+        GodotValue::Array(items) => {
+            for (i, item) in items.iter().enumerate() {
+                collect(document, item, child(&i.to_string()), parent, tables);
+            }
+        }
+        GodotValue::Dict(fields) => {
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            for key in keys {
+                collect(document, &fields[key], child(key), parent, tables);
+            }
+        }
+        _ => {}
+    }
+}
+
+fn scalar(value: &GodotValue) -> Option<Value> {
+    Some(match value {
+        GodotValue::Bool(b) => Value::Integer(*b as i64),
+        GodotValue::Int(i) => Value::Integer(*i),
+        GodotValue::UInt(u) => i64::try_from(*u).map_or(Value::Real(*u as f64), Value::Integer),
+        GodotValue::Float(f) => Value::Real(*f),
+        GodotValue::String(s) => Value::Text(s.clone()),
+        _ => return None,
+    })
+}
+
+fn sql_type(value: &Value) -> &'static str {
+    match value {
+        Value::Integer(_) => "INTEGER",
+        Value::Real(_) => "REAL",
+        _ => "TEXT",
+    }
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
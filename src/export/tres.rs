@@ -0,0 +1,379 @@
+// tres.rs
+//
+// Writer for Godot 4 text resources (.tres). Nested resources become
+// sub_resources, `ExtRef`s become ext_resources pointing at the exported
+// file of the document they reference.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use super::binary;
+use crate::GodotValue;
+use crate::parsers::LITERALS_KEY;
+use crate::parsers::param_types::EXT_REF_TYPE;
+use crate::utility::{godot_uid, to_snake_case};
+
+/// Where and how resources are exported
+#[derive(Debug, Clone)]
+pub struct TresExport {
+    /// Mixed into every uid, so two projects don't share uids
+    pub uid_salt: String,
+    /// `res://` directory the exported documents go to
+    pub resource_dir: String,
+    /// `res://` directory of the resource scripts, `snake_case.gd` of their type
+    pub script_dir: String,
+    /// Recorded in the manifest, see `TypedSentencesParser::fingerprint`
+    pub grammar_version: String,
+}
+
+impl Default for TresExport {
+    fn default() -> Self {
+        Self {
+            uid_salt: String::new(),
+            resource_dir: "res://content".into(),
+            script_dir: "res://scripts".into(),
+            grammar_version: String::new(),
+        }
+    }
+}
+
+impl TresExport {
+    pub fn with_uid_salt(mut self, salt: impl Into<String>) -> Self {
+        self.uid_salt = salt.into();
+        self
+    }
+
+    pub fn with_resource_dir(mut self, dir: impl Into<String>) -> Self {
+        self.resource_dir = dir.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_script_dir(mut self, dir: impl Into<String>) -> Self {
+        self.script_dir = dir.into().trim_end_matches('/').to_string();
+        self
+    }
+
+    pub fn with_grammar_version(mut self, version: impl Into<String>) -> Self {
+        self.grammar_version = version.into();
+        self
+    }
+
+    /// The uid of the exported document `id`
+    pub fn uid(&self, id: &str) -> String {
+        godot_uid(&self.uid_salt, id)
+    }
+
+    /// The `res://` path of the exported document `id`
+    pub fn resource_path(&self, id: &str) -> String {
+        format!("{}/{}.tres", self.resource_dir, id)
+    }
+
+    /// The `res://` path of the binary export of document `id`
+    pub fn binary_path(&self, id: &str) -> String {
+        format!("{}/{}.dokb", self.resource_dir, id)
+    }
+
+    fn script_path(&self, type_name: &str) -> String {
+        format!("{}/{}.gd", self.script_dir, to_snake_case(type_name))
+    }
+
+    /// Write the resource of document `id`.
+    ///
+    /// `documents` maps ids that `ExtRef`s point at to the document declaring them,
+    /// ids missing from it are taken as document ids.
+    /// ```
+    /// use std::collections::HashMap;
+    /// use doke::parsers::{LITERALS_KEY, SentenceParser};
+    /// use doke::{DokePipe, GodotValue, export::TresExport};
+    ///
+    /// let burn = GodotValue::Resource {
+    ///     type_name: "Burn".into(),
+    ///     abstract_type_name: "Effect".into(),
+    ///     fields: HashMap::from([("turns".into(), GodotValue::Int(2))]),
+    /// };
+    /// let sword = GodotValue::Resource {
+    ///     type_name: "Item".into(),
+    ///     abstract_type_name: "root".into(),
+    ///     fields: HashMap::from([("effect".into(), burn)]),
+    /// };
+    /// let tres = TresExport::default().to_tres("sword", &sword, &HashMap::new());
+    /// assert!(tres.starts_with("[gd_resource type=\"Resource\" script_class=\"Item\""));
+    /// assert!(tres.contains("[sub_resource type=\"Resource\" id=\"Resource_1\"]"));
+    /// assert!(tres.contains("effect = SubResource(\"Resource_1\")"));
+    ///
+    /// // numbers are written as the author wrote them when Godot reads that back
+    /// let grammar = "Effect:\n  - \"Deals {damage: int} damage for {duration: float} s\"\n";
+    /// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap());
+    /// let effect = pipe.validate("Deals 0x1F damage for 1.50 s").unwrap().remove(0);
+    /// let tres = TresExport::default().to_tres("effect", &effect, &HashMap::new());
+    /// assert!(tres.contains("\ndamage = 31\n"));
+    /// assert!(tres.contains("\nduration = 1.50\n"));
+    /// assert!(!tres.contains("\ndoke_literals = "));
+    /// let imported = doke::import::parse_tres(&tres).unwrap();
+    /// assert_eq!(imported.get_path(LITERALS_KEY), effect.get_path(LITERALS_KEY));
+    /// ```
+    pub fn to_tres(
+        &self,
+        id: &str,
+        resource: &GodotValue,
+        documents: &HashMap<String, String>,
+    ) -> String {
+        let mut writer = Writer {
+            export: self,
+            documents,
+            ext_resources: Vec::new(),
+            sub_resources: Vec::new(),
+        };
+        let (type_name, body) = match resource {
+            GodotValue::Resource {
+                type_name, fields, ..
+            } => (type_name.as_str(), writer.properties(type_name, fields)),
+            other => ("Resource", format!("value = {}\n", writer.value(other))),
+        };
+
+        let mut out = format!(
+            "[gd_resource type=\"Resource\" script_class=\"{}\" load_steps={} format=3 uid=\"{}\"]\n\n",
+            type_name,
+            writer.ext_resources.len() + writer.sub_resources.len() + 1,
+            self.uid(id)
+        );
+        for (ext_id, kind, path, uid) in &writer.ext_resources {
+            match uid {
+                Some(uid) => writeln!(
+                    out,
+                    "[ext_resource type=\"{}\" uid=\"{}\" path=\"{}\" id=\"{}\"]",
+                    kind, uid, path, ext_id
+                ),
+                None => writeln!(
+                    out,
+                    "[ext_resource type=\"{}\" path=\"{}\" id=\"{}\"]",
+                    kind, path, ext_id
+                ),
+            }
+            .unwrap();
+        }
+        if !writer.ext_resources.is_empty() {
+            out.push('\n');
+        }
+        for (sub_id, body) in &writer.sub_resources {
+            writeln!(out, "[sub_resource type=\"Resource\" id=\"{}\"]", sub_id).unwrap();
+            out.push_str(body);
+            out.push('\n');
+        }
+        out.push_str("[resource]\n");
+        out.push_str(&body);
+        out
+    }
+
+    /// Encode the resource of document `id` in the `.dokb` format, see `export::binary`.
+    ///
+    /// `ExtRef`s get the id of the document they point at, as in `to_tres`,
+    /// and the `path` of its binary export.
+    pub fn to_binary(&self, resource: &GodotValue, documents: &HashMap<String, String>) -> Vec<u8> {
+        let mut resource = resource.clone();
+        self.link_binary(&mut resource, documents);
+        binary::to_binary(&resource)
+    }
+
+    fn link_binary(&self, value: &mut GodotValue, documents: &HashMap<String, String>) {
+        match value {
+            GodotValue::Resource {
+                type_name, fields, ..
+            } if type_name == EXT_REF_TYPE => {
+                if let Some(GodotValue::String(target)) = fields.get("id") {
+                    let document = documents.get(target).unwrap_or(target).clone();
+                    fields.insert(
+                        "path".into(),
+                        GodotValue::String(self.binary_path(&document)),
+                    );
+                    fields.insert("id".into(), GodotValue::String(document));
+                }
+            }
+            GodotValue::Resource { fields, .. } | GodotValue::Dict(fields) => {
+                for field in fields.values_mut() {
+                    self.link_binary(field, documents);
+                }
+            }
+            GodotValue::Array(items) => {
+                for item in items {
+                    self.link_binary(item, documents);
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+struct Writer<'a> {
+    export: &'a TresExport,
+    documents: &'a HashMap<String, String>,
+    /// (id, type, path, uid)
+    ext_resources: Vec<(String, &'static str, String, Option<String>)>,
+    /// (id, properties), written before the resources using them
+    sub_resources: Vec<(String, String)>,
+}
+
+impl Writer<'_> {
+    /// The id of an ext_resource, added if new
+    fn ext_resource(&mut self, kind: &'static str, path: String, uid: Option<String>) -> String {
+        if let Some((id, ..)) = self.ext_resources.iter().find(|(_, _, p, _)| *p == path) {
+            return id.clone();
+        }
+        let id = (self.ext_resources.len() + 1).to_string();
+        self.ext_resources.push((id.clone(), kind, path, uid));
+        id
+    }
+
+    /// `script = ...` then the fields, sorted. Numbers are written as the author wrote
+    /// them when Godot reads that back, all of their literals kept as metadata.
+    fn properties(&mut self, type_name: &str, fields: &HashMap<String, GodotValue>) -> String {
+        let script = self.ext_resource("Script", self.export.script_path(type_name), None);
+        let mut out = format!("script = ExtResource(\"{}\")\n", script);
+        // types like "stats/health" don't survive as a script class
+        if !type_name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+            writeln!(out, "metadata/doke_type = {}", string(type_name)).unwrap();
+        }
+        let literals = fields.get(LITERALS_KEY);
+        let mut names: Vec<&String> = fields.keys().filter(|name| *name != LITERALS_KEY).collect();
+        names.sort();
+        for name in names {
+            let literal = match literals.and_then(|literals| literals.get_path(name)) {
+                Some(GodotValue::String(literal)) => godot_literal(literal, &fields[name]),
+                _ => None,
+            };
+            let value = match literal {
+                Some(literal) => literal.to_string(),
+                None => self.value(&fields[name]),
+            };
+            writeln!(out, "{} = {}", name, value).unwrap();
+        }
+        if let Some(literals) = literals {
+            let literals = self.value(literals);
+            writeln!(out, "metadata/{} = {}", LITERALS_KEY, literals).unwrap();
+        }
+        out
+    }
+
+    fn value(&mut self, value: &GodotValue) -> String {
+        match value {
+            GodotValue::Nil => "null".into(),
+            GodotValue::Bool(b) => b.to_string(),
+            GodotValue::Int(i) => i.to_string(),
+            GodotValue::UInt(u) => u.to_string(),
+            GodotValue::Float(f) => float(*f),
+            GodotValue::String(s) => string(s),
+            GodotValue::Vector2(x, y) => format!("Vector2({}, {})", float(*x), float(*y)),
+            GodotValue::Rect2(x, y, w, h) => format!(
+                "Rect2({}, {}, {}, {})",
+                float(*x),
+                float(*y),
+                float(*w),
+                float(*h)
+            ),
+            GodotValue::Color(r, g, b, a) => format!(
+                "Color({}, {}, {}, {})",
+                float(*r),
+                float(*g),
+                float(*b),
+                float(*a)
+            ),
+            GodotValue::Array(items) => {
+                let items: Vec<String> = items.iter().map(|item| self.value(item)).collect();
+                format!("[{}]", items.join(", "))
+            }
+            GodotValue::Dict(map) => {
+                let mut keys: Vec<&String> = map.keys().collect();
+                keys.sort();
+                let entries: Vec<String> = keys
+                    .into_iter()
+                    .map(|key| format!("{}: {}", string(key), self.value(&map[key])))
+                    .collect();
+                if entries.is_empty() {
+                    "{}".into()
+                } else {
+                    format!("{{\n{}\n}}", entries.join(",\n"))
+                }
+            }
+            GodotValue::Resource {
+                type_name, fields, ..
+            } if type_name == EXT_REF_TYPE => {
+                let Some(GodotValue::String(target)) = fields.get("id") else {
+                    return "null".into();
+                };
+                let document = self.documents.get(target).unwrap_or(target);
+                let id = self.ext_resource(
+                    "Resource",
+                    self.export.resource_path(document),
+                    Some(self.export.uid(document)),
+                );
+                format!("ExtResource(\"{}\")", id)
+            }
+            GodotValue::Resource {
+                type_name, fields, ..
+            } => {
+                let body = self.properties(type_name, fields);
+                let id = format!("Resource_{}", self.sub_resources.len() + 1);
+                self.sub_resources.push((id.clone(), body));
+                format!("SubResource(\"{}\")", id)
+            }
+        }
+    }
+}
+
+/// `literal` if Godot reads it as `value`: decimal digits, with a `.` for floats
+fn godot_literal<'a>(literal: &'a str, value: &GodotValue) -> Option<&'a str> {
+    let digits = literal.strip_prefix('-').unwrap_or(literal);
+    let decimal = !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit() || c == '.');
+    let same = match value {
+        GodotValue::Int(i) => !digits.contains('.') && literal.parse() == Ok(*i),
+        GodotValue::UInt(u) => !literal.contains(['-', '.']) && literal.parse() == Ok(*u),
+        GodotValue::Float(f) => digits.contains('.') && literal.parse() == Ok(*f),
+        _ => false,
+    };
+    (decimal && same).then_some(literal)
+}
+
+/// Godot writes integral floats with a `.0`
+fn float(f: f64) -> String {
+    if f.is_nan() {
+        "nan".into()
+    } else if f.is_infinite() {
+        if f > 0.0 { "inf" } else { "-inf" }.into()
+    } else if f.fract() == 0.0 && f.abs() < 1e15 {
+        format!("{:.1}", f)
+    } else {
+        f.to_string()
+    }
+}
+
+/// A Godot string literal, quoted and escaped
+/// ```
+/// use std::collections::HashMap;
+/// use doke::{GodotValue, export::TresExport, import::parse_tres};
+///
+/// let note = "Say \"hi\"\r\nC:\\saves\tslot 1";
+/// let sign = GodotValue::Resource {
+///     type_name: "Sign".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::from([("note".into(), GodotValue::String(note.into()))]),
+/// };
+/// let tres = TresExport::default().to_tres("sign", &sign, &HashMap::new());
+/// assert!(tres.contains(r#"note = "Say \"hi\"\r\nC:\\saves\tslot 1""#));
+/// assert_eq!(parse_tres(&tres).unwrap().get_path("note"), Some(&GodotValue::String(note.into())));
+/// ```
+fn string(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
@@ -0,0 +1,91 @@
+// extensions.rs
+//
+// Typed data parsers attach to a node for themselves or the parsers after them,
+// one value per type:
+//
+//     node.extensions.insert(MatchedPattern(3));
+//     let pattern = node.extensions.get::<MatchedPattern>();
+//
+// Unlike `parse_data`, which holds `GodotValue`s and is meant to be exported,
+// extensions never reach the output, so they can be any Rust type. A parser
+// defines its own type to keep its data from clashing with another's.
+
+use std::any::{Any, TypeId};
+use std::collections::HashMap;
+use std::fmt;
+
+/// Type-keyed storage of a `DokeNode`, see `DokeNode::extensions`
+/// ```
+/// use doke::Extensions;
+///
+/// #[derive(Debug, PartialEq)]
+/// struct MatchedPattern(usize);
+///
+/// let mut extensions = Extensions::new();
+/// assert_eq!(extensions.insert(MatchedPattern(3)), None);
+/// assert_eq!(extensions.get::<MatchedPattern>(), Some(&MatchedPattern(3)));
+///
+/// extensions.get_mut::<MatchedPattern>().unwrap().0 += 1;
+/// assert_eq!(extensions.remove::<MatchedPattern>(), Some(MatchedPattern(4)));
+/// assert!(extensions.is_empty());
+/// ```
+#[derive(Default)]
+pub struct Extensions {
+    map: HashMap<TypeId, Box<dyn Any + Send + Sync>>,
+}
+
+impl Extensions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Store `value`, returning the value of the same type it replaces
+    pub fn insert<T: Any + Send + Sync>(&mut self, value: T) -> Option<T> {
+        self.map
+            .insert(TypeId::of::<T>(), Box::new(value))
+            .and_then(|old| old.downcast().ok().map(|old| *old))
+    }
+
+    pub fn get<T: Any + Send + Sync>(&self) -> Option<&T> {
+        self.map
+            .get(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_ref())
+    }
+
+    pub fn get_mut<T: Any + Send + Sync>(&mut self) -> Option<&mut T> {
+        self.map
+            .get_mut(&TypeId::of::<T>())
+            .and_then(|value| value.downcast_mut())
+    }
+
+    pub fn remove<T: Any + Send + Sync>(&mut self) -> Option<T> {
+        self.map
+            .remove(&TypeId::of::<T>())
+            .and_then(|value| value.downcast().ok().map(|value| *value))
+    }
+
+    pub fn contains<T: Any + Send + Sync>(&self) -> bool {
+        self.map.contains_key(&TypeId::of::<T>())
+    }
+
+    pub fn len(&self) -> usize {
+        self.map.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.map.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.map.clear();
+    }
+}
+
+impl fmt::Debug for Extensions {
+    /// Values aren't required to be `Debug`, only their count is shown
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Extensions")
+            .field("len", &self.map.len())
+            .finish()
+    }
+}
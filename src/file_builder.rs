@@ -1,6 +1,11 @@
-use crate::semantic::GodotValue;
-use std::{collections::{HashMap, HashSet}, fmt::format, fs, path::Path};
-use hashlink::LinkedHashMap;
+use crate::constraints::Constraint;
+use crate::diagnostics::Diagnostic;
+use crate::hierarchy::TypeHierarchy;
+use crate::migrate::{MigrationError, Migrations};
+use crate::parsers::sentence::yaml_to_godot_value;
+use crate::semantic::{GodotValue, MergeStrategy};
+use crate::Position;
+use std::{collections::{HashMap, HashSet}, fs, path::Path};
 use thiserror::Error;
 use yaml_rust2::{Yaml, YamlLoader};
 
@@ -20,6 +25,15 @@ pub enum BuilderError {
 
     #[error("Type mismatch for field '{0}': expected {1}, got {2}")]
     TypeMismatch(String, String, String),
+
+    #[error("Unknown profile '{0}'")]
+    UnknownProfile(String),
+
+    #[error("Field '{0}' requires the capability '{2}', which '{1}' doesn't have")]
+    MissingCapability(String, String, String),
+
+    #[error(transparent)]
+    Migration(#[from] MigrationError),
 }
 
 /// Normalized config after parsing/validation
@@ -27,6 +41,40 @@ pub enum BuilderError {
 pub struct Config {
     pub root: String,
     pub children: Vec<FieldConfig>,
+    pub profiles: HashMap<String, Profile>,
+    /// Checked on the built resource
+    pub constraints: Vec<Constraint>,
+    /// How array fields a document gives combine with the ones it inherits:
+    /// `Append` after the parent's items, or `Override` them (`inherit_arrays: replace`)
+    pub inherit_arrays: MergeStrategy,
+    /// Schema version of the content and how to reach the others, see `migrate`
+    pub migrations: Migrations,
+}
+
+/// No fields, and arrays replacing the inherited ones, as in a config not saying
+/// `inherit_arrays`
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            root: String::new(),
+            children: Vec::new(),
+            profiles: HashMap::new(),
+            constraints: Vec::new(),
+            inherit_arrays: MergeStrategy::Override,
+            migrations: Migrations::default(),
+        }
+    }
+}
+
+/// A shipped configuration (debug, mobile...) changing which fields are built
+#[derive(Debug, Clone, Default)]
+pub struct Profile {
+    /// Fields only built when this profile is selected
+    pub include: Vec<String>,
+    /// Fields left out when this profile is selected
+    pub exclude: Vec<String>,
+    /// Values of fields the document doesn't give
+    pub defaults: HashMap<String, GodotValue>,
 }
 
 #[derive(Debug, Clone)]
@@ -45,6 +93,12 @@ pub enum FieldType {
 #[derive(Debug)]
 pub struct ResourceBuilder {
     config: Config,
+    /// Selected profiles, later ones win
+    profiles: Vec<String>,
+    /// Schema version `migrate` gives resources, the config's when `None`
+    schema_version: Option<u32>,
+    /// Resource fields also take subtypes of their type
+    hierarchy: TypeHierarchy,
 }
 
 impl ResourceBuilder {
@@ -70,7 +124,95 @@ impl ResourceBuilder {
             }
         }
 
-        Ok(Self { config })
+        Ok(Self { config, profiles: Vec::new(), schema_version: None, hierarchy: TypeHierarchy::new() })
+    }
+
+    /// Build with the profile `name` of the config, on top of the ones already selected
+    /// ```
+    /// use std::collections::HashMap;
+    /// use doke::GodotValue;
+    /// use doke::file_builder::{Config, FieldConfig, FieldType, Profile, ResourceBuilder};
+    ///
+    /// let field = |name: &str, ty: &str| FieldConfig { name: name.into(), ty: FieldType::Single(ty.into()), optional: true };
+    /// let config = Config {
+    ///     root: "Item".into(),
+    ///     children: vec![field("damage", "int"), field("debug_note", "String")],
+    ///     profiles: HashMap::from([
+    ///         ("debug".to_string(), Profile { include: vec!["debug_note".into()], ..Default::default() }),
+    ///         ("mobile".to_string(), Profile {
+    ///             defaults: HashMap::from([("damage".to_string(), GodotValue::Int(1))]),
+    ///             ..Default::default()
+    ///         }),
+    ///     ]),
+    ///     ..Default::default()
+    /// };
+    /// let note = || vec![GodotValue::String("placeholder".into())];
+    ///
+    /// let release = ResourceBuilder::from_config(config.clone()).unwrap().build_file_resource(note()).unwrap();
+    /// assert_eq!(release.get_path("debug_note"), None);
+    ///
+    /// let debug = ResourceBuilder::from_config(config.clone()).unwrap().with_profile("debug");
+    /// let item = debug.build_file_resource(note()).unwrap();
+    /// assert_eq!(item.get_path("debug_note"), Some(&GodotValue::String("placeholder".into())));
+    ///
+    /// let mobile = ResourceBuilder::from_config(config.clone()).unwrap().with_profile("mobile");
+    /// assert_eq!(mobile.build_file_resource(vec![]).unwrap().get_path("damage"), Some(&GodotValue::Int(1)));
+    ///
+    /// let unknown = ResourceBuilder::from_config(config).unwrap().with_profile("console");
+    /// assert!(unknown.build_file_resource(vec![]).is_err());
+    /// ```
+    pub fn with_profile(mut self, name: impl Into<String>) -> Self {
+        self.profiles.push(name.into());
+        self
+    }
+
+    /// Migrate resources to schema version `version`, see `migrate`
+    pub fn with_schema_version(mut self, version: u32) -> Self {
+        self.schema_version = Some(version);
+        self
+    }
+
+    /// Let fields typed by a resource type take its subtypes, and fields typed by
+    /// a capability (`Triggerable`, `DamageEffect + Triggerable`) the types having it,
+    /// see `hierarchy`
+    /// ```
+    /// use std::collections::HashMap;
+    /// use doke::file_builder::{Config, FieldConfig, FieldType, ResourceBuilder};
+    /// use doke::GodotValue;
+    /// use doke::hierarchy::TypeHierarchy;
+    ///
+    /// let config = Config {
+    ///     root: "Item".into(),
+    ///     children: vec![FieldConfig {
+    ///         name: "effects".into(),
+    ///         ty: FieldType::Array("DamageEffect + Triggerable".into()),
+    ///         optional: false,
+    ///     }],
+    ///     ..Default::default()
+    /// };
+    /// let builder = ResourceBuilder::from_config(config)
+    ///     .unwrap()
+    ///     .with_hierarchy(
+    ///         TypeHierarchy::from_pairs([
+    ///             ("FireDamageEffect", "DamageEffect"),
+    ///             ("ShockEffect", "DamageEffect"),
+    ///         ])
+    ///         .with_capabilities("FireDamageEffect", ["Triggerable"]),
+    ///     );
+    /// let effect = |type_name: &str| GodotValue::Resource {
+    ///     type_name: type_name.into(),
+    ///     abstract_type_name: "ItemEffect".into(),
+    ///     fields: HashMap::new(),
+    /// };
+    /// let item = builder.build_file_resource(vec![effect("FireDamageEffect")]).unwrap();
+    /// assert_eq!(item.get_path("effects/0"), Some(&effect("FireDamageEffect")));
+    ///
+    /// let shock = vec![effect("FireDamageEffect"), effect("ShockEffect")];
+    /// assert!(builder.build_file_resource(shock).is_err());
+    /// ```
+    pub fn with_hierarchy(mut self, hierarchy: TypeHierarchy) -> Self {
+        self.hierarchy = hierarchy;
+        self
     }
 
     pub fn from_file(path: &Path) -> Result<Self, BuilderError> {
@@ -145,28 +287,257 @@ impl ResourceBuilder {
             children.push(FieldConfig { name, ty, optional });
         }
 
-        Ok(Config { root, children })
+        // profiles
+        let mut profiles = HashMap::new();
+        if let Some(profiles_yaml) = y["profiles"].as_hash() {
+            for (raw_name, value) in profiles_yaml {
+                let name = raw_name
+                    .as_str()
+                    .ok_or_else(|| BuilderError::Config("Profile name must be string".into()))?;
+                let profile = Self::parse_profile(name, value, &children)?;
+                profiles.insert(name.to_string(), profile);
+            }
+        } else if !y["profiles"].is_badvalue() {
+            return Err(BuilderError::Config("'profiles' must be a map".into()));
+        }
+
+        // constraints
+        let mut constraints = Vec::new();
+        if let Some(constraints_yaml) = y["constraints"].as_vec() {
+            for entry in constraints_yaml {
+                let (source, message) = match entry {
+                    Yaml::String(source) => (source.as_str(), None),
+                    Yaml::Hash(_) => (
+                        entry["check"].as_str().ok_or_else(|| {
+                            BuilderError::Config("Constraint map needs a 'check' string".into())
+                        })?,
+                        entry["message"].as_str().map(str::to_string),
+                    ),
+                    _ => {
+                        return Err(BuilderError::Config(
+                            "Each constraint must be a string or a map".into(),
+                        ))
+                    }
+                };
+                let constraint = Constraint::parse(source, message)
+                    .map_err(|e| BuilderError::Config(format!("Invalid constraint '{}': {}", source, e)))?;
+                constraints.push(constraint);
+            }
+        } else if !y["constraints"].is_badvalue() {
+            return Err(BuilderError::Config("'constraints' must be a sequence".into()));
+        }
+
+        // inheritance
+        let inherit_arrays = match &y["inherit_arrays"] {
+            Yaml::BadValue => MergeStrategy::Override,
+            Yaml::String(s) if s == "replace" => MergeStrategy::Override,
+            Yaml::String(s) if s == "append" => MergeStrategy::Append,
+            _ => return Err(BuilderError::Config("'inherit_arrays' must be 'append' or 'replace'".into())),
+        };
+
+        let migrations = Migrations::from_yaml(y)?;
+
+        Ok(Config { root, children, profiles, constraints, inherit_arrays, migrations })
+    }
+
+    fn parse_profile(name: &str, y: &Yaml, children: &[FieldConfig]) -> Result<Profile, BuilderError> {
+        let known = |field: &str| -> Result<String, BuilderError> {
+            if children.iter().any(|c| c.name == field) {
+                Ok(field.to_string())
+            } else {
+                Err(BuilderError::Config(format!(
+                    "Profile {} refers to unknown field {}",
+                    name, field
+                )))
+            }
+        };
+        let fields = |key: &str| -> Result<Vec<String>, BuilderError> {
+            match &y[key] {
+                Yaml::BadValue => Ok(Vec::new()),
+                Yaml::Array(items) => items
+                    .iter()
+                    .map(|item| {
+                        let field = item.as_str().ok_or_else(|| {
+                            BuilderError::Config(format!("Profile {} {} must list field names", name, key))
+                        })?;
+                        known(field)
+                    })
+                    .collect(),
+                _ => Err(BuilderError::Config(format!(
+                    "Profile {} {} must be a sequence",
+                    name, key
+                ))),
+            }
+        };
+
+        let mut defaults = HashMap::new();
+        if let Some(defaults_yaml) = y["defaults"].as_hash() {
+            for (field, value) in defaults_yaml {
+                let field = field
+                    .as_str()
+                    .ok_or_else(|| BuilderError::Config("Default field name must be string".into()))?;
+                defaults.insert(known(field)?, yaml_to_godot_value(value));
+            }
+        }
+
+        Ok(Profile {
+            include: fields("include")?,
+            exclude: fields("exclude")?,
+            defaults,
+        })
     }
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+
     pub fn build_file_resource(&self, values: Vec<GodotValue>) -> Result<GodotValue, BuilderError> {
+        let values = values.into_iter().map(|v| (v, None)).collect();
+        self.build(values, None).map(|(resource, _)| resource)
+    }
+
+    /// Build a document inheriting from `parent`, a resource built by this builder:
+    /// fields the document doesn't give are the parent's, array fields combine
+    /// with the parent's as the config's `inherit_arrays` says.
+    /// ```
+    /// use doke::file_builder::{Config, FieldConfig, FieldType, ResourceBuilder};
+    /// use doke::{GodotValue, MergeStrategy};
+    ///
+    /// let field = |name: &str, ty| FieldConfig { name: name.into(), ty, optional: false };
+    /// let config = Config {
+    ///     root: "Item".into(),
+    ///     children: vec![
+    ///         field("damage", FieldType::Single("int".into())),
+    ///         field("tags", FieldType::Array("String".into())),
+    ///     ],
+    ///     inherit_arrays: MergeStrategy::Append,
+    ///     ..Default::default()
+    /// };
+    /// let builder = ResourceBuilder::from_config(config).unwrap();
+    /// let iron_sword = builder
+    ///     .build_file_resource(vec![GodotValue::Int(5), GodotValue::String("blade".into())])
+    ///     .unwrap();
+    /// let flaming_sword = builder
+    ///     .build_file_resource_inheriting(vec![GodotValue::String("fire".into())], &iron_sword)
+    ///     .unwrap();
+    /// assert_eq!(flaming_sword.get_path("damage"), Some(&GodotValue::Int(5)));
+    /// assert_eq!(flaming_sword.get_path("tags/1"), Some(&GodotValue::String("fire".into())));
+    /// ```
+    pub fn build_file_resource_inheriting(
+        &self,
+        values: Vec<GodotValue>,
+        parent: &GodotValue,
+    ) -> Result<GodotValue, BuilderError> {
+        let values = values.into_iter().map(|v| (v, None)).collect();
+        self.build(values, Some(parent)).map(|(resource, _)| resource)
+    }
+
+    /// Build the resource and check the config's constraints on it,
+    /// reporting failures on the statements of the fields they read
+    pub fn build_file_resource_checked(
+        &self,
+        values: Vec<(GodotValue, Position)>,
+    ) -> Result<(GodotValue, Vec<Diagnostic>), BuilderError> {
+        let values = values.into_iter().map(|(v, span)| (v, Some(span))).collect();
+        let (resource, spans) = self.build(values, None)?;
+        let diagnostics = self
+            .config
+            .constraints
+            .iter()
+            .filter_map(|c| c.check(&resource, &spans))
+            .collect();
+        Ok((resource, diagnostics))
+    }
+
+    /// A built resource as the schema version selected with `with_schema_version` expects it.
+    /// Resources are built for the config's version, as documents are written.
+    pub fn migrate(&self, resource: &mut GodotValue) -> Result<(), BuilderError> {
+        if let Some(version) = self.schema_version {
+            self.config.migrations.migrate(resource, version)?;
+        }
+        Ok(())
+    }
+
+    /// Failed constraints of a built resource
+    pub fn check_constraints(&self, resource: &GodotValue) -> Vec<Diagnostic> {
+        self.config
+            .constraints
+            .iter()
+            .filter_map(|c| c.check(resource, &HashMap::new()))
+            .collect()
+    }
+
+    /// The resource, and the span of the statements of each field.
+    /// Fields missing from `values` are taken from `parent` first.
+    fn build(
+        &self,
+        values: Vec<(GodotValue, Option<Position>)>,
+        parent: Option<&GodotValue>,
+    ) -> Result<(GodotValue, HashMap<String, Position>), BuilderError> {
         let mut fields: HashMap<String, GodotValue> = HashMap::new();
+        let mut spans: HashMap<String, Position> = HashMap::new();
         let mut unused = values;
 
+        let profiles = self
+            .profiles
+            .iter()
+            .map(|name| {
+                self.config
+                    .profiles
+                    .get(name)
+                    .ok_or_else(|| BuilderError::UnknownProfile(name.clone()))
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        // the parent's field, else a default from the last selected profile giving one
+        let inherited = |name: &str| parent.and_then(|p| p.get_path(name)).cloned();
+        let default = |name: &str| {
+            inherited(name).or_else(|| {
+                profiles
+                    .iter()
+                    .rev()
+                    .find_map(|p| p.defaults.get(name).cloned())
+            })
+        };
+
         for fc in &self.config.children {
+            // fields some profile includes are only built with one of them
+            let conditional = self.config.profiles.values().any(|p| p.include.contains(&fc.name));
+            let skipped = (conditional && !profiles.iter().any(|p| p.include.contains(&fc.name)))
+                || profiles.iter().any(|p| p.exclude.contains(&fc.name));
+
             match &fc.ty {
                 FieldType::Array(ty) => {
                     let mut collected = Vec::new();
                     let mut keep = Vec::new();
-                    for v in unused {
-                        if matches_type(&v, ty) {
+                    for (v, span) in unused {
+                        if matches_type(&v, ty, &self.hierarchy) {
                             collected.push(v);
+                            if let Some(span) = span {
+                                spans
+                                    .entry(fc.name.clone())
+                                    .and_modify(|s| s.end = s.end.max(span.end))
+                                    .or_insert(span);
+                            }
                         } else {
-                            keep.push(v);
+                            keep.push((v, span));
                         }
                     }
                     unused = keep;
 
+                    if skipped {
+                        spans.remove(&fc.name);
+                        continue;
+                    }
                     if !collected.is_empty() {
-                        fields.insert(fc.name.clone(), GodotValue::Array(collected));
+                        let mut value = GodotValue::Array(collected);
+                        if let Some(mut items) = inherited(&fc.name)
+                            && self.config.inherit_arrays == MergeStrategy::Append
+                        {
+                            items.merge(value, MergeStrategy::Append);
+                            value = items;
+                        }
+                        fields.insert(fc.name.clone(), value);
+                    } else if let Some(value) = default(&fc.name) {
+                        fields.insert(fc.name.clone(), value);
                     } else if fc.optional {
                         // Optional arrays default to empty
                         fields.insert(fc.name.clone(), GodotValue::Array(vec![]));
@@ -176,15 +547,23 @@ impl ResourceBuilder {
                 }
                 FieldType::Single(ty) => {
                     let mut found_idx = None;
-                    for (i, v) in unused.iter().enumerate() {
-                        if matches_type(v, ty) {
+                    for (i, (v, _)) in unused.iter().enumerate() {
+                        if matches_type(v, ty, &self.hierarchy) {
                             found_idx = Some(i);
                             break;
                         }
                     }
 
-                    if let Some(idx) = found_idx {
-                        let v = unused.remove(idx);
+                    let found = found_idx.map(|idx| unused.remove(idx));
+                    if skipped {
+                        continue;
+                    }
+                    if let Some((v, span)) = found {
+                        fields.insert(fc.name.clone(), v);
+                        if let Some(span) = span {
+                            spans.insert(fc.name.clone(), span);
+                        }
+                    } else if let Some(v) = default(&fc.name) {
                         fields.insert(fc.name.clone(), v);
                     } else if fc.optional {
                         // Optional singletons default to Nil
@@ -195,26 +574,53 @@ impl ResourceBuilder {
                 }
             }
         }
-        Ok(GodotValue::Resource {
+        // a value of a field's type without its capabilities would be left out silently
+        for (v, _) in &unused {
+            let GodotValue::Resource { type_name, .. } = v else { continue };
+            for fc in &self.config.children {
+                let (FieldType::Single(ty) | FieldType::Array(ty)) = &fc.ty;
+                if let Some(capability) = missing_capability(v, ty, &self.hierarchy) {
+                    return Err(BuilderError::MissingCapability(fc.name.clone(), type_name.clone(), capability.to_string()));
+                }
+            }
+        }
+        let resource = GodotValue::Resource {
             type_name: self.config.root.clone(),
             abstract_type_name: "root".to_string(),
-            fields : fields,
-        })
+            fields,
+        };
+        Ok((resource, spans))
     }
 }
-/// Helper: check whether a GodotValue matches the expected type name
-fn matches_type(v: &GodotValue, ty: &str) -> bool {
+/// Helper: check whether a GodotValue matches the expected type name.
+/// Resources match a type they are, a capability they have, or `Type + Capability...`
+fn matches_type(v: &GodotValue, ty: &str, hierarchy: &TypeHierarchy) -> bool {
     match v {
         GodotValue::Int(_) => ty.eq_ignore_ascii_case("int"),
+        GodotValue::UInt(_) => ty.eq_ignore_ascii_case("int") || ty.eq_ignore_ascii_case("uint"),
         GodotValue::Float(_) => ty.eq_ignore_ascii_case("float"),
         GodotValue::String(_) => ty.eq_ignore_ascii_case("string"),
         GodotValue::Array(_) => ty.eq_ignore_ascii_case("array"),
         GodotValue::Dict(_) => ty.eq_ignore_ascii_case("dict"),
         GodotValue::Bool(_) => ty.eq_ignore_ascii_case("bool"),
-        GodotValue::Resource { type_name, abstract_type_name, .. } => {
-            type_name == ty || abstract_type_name == ty
-        }
+        GodotValue::Resource { type_name, abstract_type_name, .. } => ty.split('+').map(str::trim).all(|part| {
+            [type_name, abstract_type_name].iter().any(|t| hierarchy.is_a(t, part) || hierarchy.has_capability(t, part))
+        }),
         GodotValue::Nil => ty.eq_ignore_ascii_case("nil"),
+        GodotValue::Vector2(..) => ty.eq_ignore_ascii_case("vector2"),
+        GodotValue::Rect2(..) => ty.eq_ignore_ascii_case("rect2"),
+        GodotValue::Color(..) => ty.eq_ignore_ascii_case("color"),
+    }
+}
+
+/// The capability a resource of the type of `ty` (`Type + Capability...`) lacks to match it
+fn missing_capability<'a>(v: &GodotValue, ty: &'a str, hierarchy: &TypeHierarchy) -> Option<&'a str> {
+    let GodotValue::Resource { type_name, abstract_type_name, .. } = v else { return None };
+    let (base, capabilities) = ty.split_once('+')?;
+    let types = [type_name, abstract_type_name];
+    if !types.iter().any(|t| hierarchy.is_a(t, base.trim())) {
+        return None;
     }
+    capabilities.split('+').map(str::trim).find(|capability| !types.iter().any(|t| hierarchy.has_capability(t, capability)))
 }
 
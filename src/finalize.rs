@@ -0,0 +1,123 @@
+// finalize.rs
+//
+// Finalizers transform the values of a document after validation, before they
+// are built into a resource, for changes that concern the whole value tree
+// rather than a statement: converting units, renaming keys an older GDScript
+// expects, stripping debug fields from release builds.
+//
+//     let pipe = DokePipe::new()
+//         .add(parser)
+//         .finalize_with(RenameFields::new([("dmg", "damage")]))
+//         .finalize_with(StripFields::new(["debug_note"]));
+//
+// They run in the order they were added, on each top-level value.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+
+use crate::GodotValue;
+
+/// Transforms the values of documents, see `DokePipe::finalize_with`
+pub trait DokeFinalizer: Debug + Send + Sync {
+    fn finalize(&self, value: &mut GodotValue, frontmatter: &HashMap<String, GodotValue>);
+}
+
+/// Renames the keys of dicts and the fields of resources, at any depth
+/// ```
+/// use std::collections::HashMap;
+/// use doke::GodotValue;
+/// use doke::finalize::{DokeFinalizer, RenameFields};
+///
+/// let mut value = GodotValue::Dict(HashMap::from([("damage".into(), GodotValue::Int(5))]));
+/// RenameFields::new([("damage", "dmg")]).finalize(&mut value, &HashMap::new());
+/// assert_eq!(value.get_path("dmg"), Some(&GodotValue::Int(5)));
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct RenameFields {
+    names: HashMap<String, String>,
+}
+
+impl RenameFields {
+    /// From `(name, new name)` pairs
+    pub fn new<I, S, T>(names: I) -> Self
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: Into<String>,
+        T: Into<String>,
+    {
+        Self {
+            names: names
+                .into_iter()
+                .map(|(name, renamed)| (name.into(), renamed.into()))
+                .collect(),
+        }
+    }
+}
+
+impl DokeFinalizer for RenameFields {
+    fn finalize(&self, value: &mut GodotValue, _frontmatter: &HashMap<String, GodotValue>) {
+        visit_fields(value, &mut |fields| {
+            for (name, renamed) in &self.names {
+                if let Some(field) = fields.remove(name) {
+                    fields.insert(renamed.clone(), field);
+                }
+            }
+        });
+    }
+}
+
+/// Removes the keys of dicts and the fields of resources with these names, at any depth
+/// ```
+/// use std::collections::HashMap;
+/// use doke::GodotValue;
+/// use doke::finalize::{DokeFinalizer, StripFields};
+///
+/// let mut value = GodotValue::Dict(HashMap::from([
+///     ("damage".into(), GodotValue::Int(5)),
+///     ("debug_note".into(), GodotValue::String("too strong?".into())),
+/// ]));
+/// StripFields::new(["debug_note"]).finalize(&mut value, &HashMap::new());
+/// assert_eq!(value.get_path("debug_note"), None);
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct StripFields {
+    names: Vec<String>,
+}
+
+impl StripFields {
+    pub fn new<I, S>(names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        Self {
+            names: names.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl DokeFinalizer for StripFields {
+    fn finalize(&self, value: &mut GodotValue, _frontmatter: &HashMap<String, GodotValue>) {
+        visit_fields(value, &mut |fields| {
+            fields.retain(|name, _| !self.names.contains(name));
+        });
+    }
+}
+
+/// Call `f` on the keys of every dict and resource of `value`, outermost first
+fn visit_fields(value: &mut GodotValue, f: &mut dyn FnMut(&mut HashMap<String, GodotValue>)) {
+    match value {
+        GodotValue::Dict(fields) | GodotValue::Resource { fields, .. } => {
+            f(fields);
+            for field in fields.values_mut() {
+                visit_fields(field, f);
+            }
+        }
+        GodotValue::Array(items) => {
+            for item in items {
+                visit_fields(item, f);
+            }
+        }
+        _ => {}
+    }
+}
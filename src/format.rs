@@ -0,0 +1,162 @@
+// format.rs
+//
+// Rewrites a document in place into its canonical form.
+// Only statements are touched, everything the pipeline doesn't read
+// (blank lines, quotes, tables, code) is written back as is.
+
+use std::collections::HashMap;
+
+use markdown::mdast::Node;
+
+use crate::base_parser::DokeStatement;
+use crate::normalization::Normalization;
+use crate::parsers::CANONICAL_KEY;
+use crate::{DokeNode, GodotValue};
+
+/// Statements spelled by the phrase they matched, by span
+pub(crate) fn canonical_spellings(nodes: &[DokeNode]) -> HashMap<(usize, usize), String> {
+    fn visit(node: &DokeNode, out: &mut HashMap<(usize, usize), String>) {
+        if let Some(GodotValue::String(spelled)) = node.parse_data.get(CANONICAL_KEY) {
+            out.insert((node.span.start, node.span.end), spelled.clone());
+        }
+        // constituents share their parent's span, only children have their own
+        for child in &node.children {
+            visit(child, out);
+        }
+    }
+
+    let mut out = HashMap::new();
+    for node in nodes {
+        visit(node, &mut out);
+    }
+    out
+}
+
+/// Rewrite the statements of `source`:
+/// - statements that matched a phrase are spelled like it
+/// - other statements get the pipeline's normalization
+/// - bullets use `-`
+/// - headings never skip a level
+pub(crate) fn format_markdown(
+    source: &str,
+    statements: &[DokeStatement],
+    spellings: &HashMap<(usize, usize), String>,
+    normalization: &Normalization,
+) -> String {
+    let mut edits: Vec<(usize, usize, String)> = Vec::new();
+    let mut heading_level = None;
+    for stmt in statements {
+        collect_edits(
+            stmt,
+            source,
+            spellings,
+            normalization,
+            &mut heading_level,
+            &mut edits,
+        );
+    }
+
+    edits.sort_by_key(|(start, _, _)| *start);
+    let mut out = String::with_capacity(source.len());
+    let mut last = 0;
+    for (start, end, text) in edits {
+        if start < last {
+            continue;
+        }
+        out.push_str(&source[last..start]);
+        out.push_str(&text);
+        last = end;
+    }
+    out.push_str(&source[last..]);
+    out
+}
+
+fn collect_edits(
+    stmt: &DokeStatement,
+    source: &str,
+    spellings: &HashMap<(usize, usize), String>,
+    normalization: &Normalization,
+    heading_level: &mut Option<u8>,
+    edits: &mut Vec<(usize, usize, String)>,
+) {
+    let Some(span) = &stmt.statement_position else {
+        return;
+    };
+    let Some(text) = source.get(span.start..span.end) else {
+        return;
+    };
+
+    if let Some(marker) = bullet_marker(source, span.start) {
+        edits.push((marker.0, marker.1, "- ".into()));
+    }
+
+    // Statements with template placeholders were matched after substitution,
+    // their spelling would bake the frontmatter values in.
+    let spelled = spellings
+        .get(&(span.start, span.end))
+        .filter(|_| !text.contains('{'));
+
+    match stmt.node {
+        Node::Code(_) => {}
+        Node::Heading(heading) => {
+            // a heading can go at most one level deeper than the previous one
+            let level = match *heading_level {
+                Some(previous) => heading.depth.min(previous + 1),
+                None => heading.depth,
+            };
+            *heading_level = Some(level);
+            let content = text
+                .trim_start_matches('#')
+                .trim_end()
+                .trim_end_matches('#')
+                .trim();
+            let content = normalization.normalize(content);
+            edits.push((
+                span.start,
+                span.end,
+                format!("{} {}", "#".repeat(level as usize), content),
+            ));
+        }
+        _ => {
+            let formatted = spelled
+                .cloned()
+                .unwrap_or_else(|| normalization.normalize(text));
+            if formatted != text {
+                edits.push((span.start, span.end, formatted));
+            }
+        }
+    }
+
+    for child in &stmt.children {
+        collect_edits(
+            child,
+            source,
+            spellings,
+            normalization,
+            heading_level,
+            edits,
+        );
+    }
+}
+
+/// The byte range of the list marker (and the spaces after it)
+/// before a statement starting at `start`, if it isn't already `- `
+fn bullet_marker(source: &str, start: usize) -> Option<(usize, usize)> {
+    let line_start = source[..start].rfind('\n').map_or(0, |i| i + 1);
+    let prefix = &source[line_start..start];
+    let indent = prefix.len() - prefix.trim_start().len();
+    let marker = prefix.trim_start();
+    let bullet = marker.trim_end();
+
+    let is_bullet = matches!(bullet, "*" | "+")
+        || (bullet.len() > 1
+            && bullet.ends_with(['.', ')'])
+            && bullet[..bullet.len() - 1]
+                .bytes()
+                .all(|b| b.is_ascii_digit()));
+    if is_bullet || (bullet == "-" && marker != "- ") {
+        Some((line_start + indent, start))
+    } else {
+        None
+    }
+}
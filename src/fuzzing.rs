@@ -0,0 +1,120 @@
+// fuzzing.rs
+//
+// Entry points for the `cargo fuzz` targets in `fuzz/`. Documents and grammars
+// coming from mods are untrusted, so no input may make the pipeline panic.
+//
+// Each function takes raw fuzzer bytes, ignores what isn't UTF-8, and runs the
+// input through as much of the pipeline as it can.
+//
+// The markdown crate panics on a few inputs (`"é\n-\n--\nx\n-\n"`). The pipeline
+// catches these and reports a `markdown-error`, `init` keeps the fuzzer from
+// aborting on them.
+
+use std::panic;
+use std::sync::LazyLock;
+
+use crate::input::{self, AsciiDoc, PlainText};
+use crate::parsers::{
+    CurveParser, DialogueParser, FrontmatterTemplateParser, SentenceParser, StateMachineParser,
+    WeightedTableParser,
+};
+use crate::semantic::DokeValidate;
+use crate::{DokeParser, DokePipe, GodotValue, Normalization, Position};
+
+const GRAMMAR: &str = r#"
+Effect:
+  - "Deals {damage: int} damage to {target: Target}"
+  - "Heals {amount: float} health if {condition: condition}"
+  - "Costs {cost: formula} mana"
+  - "Summons {who: ref} on {day: datetime}"
+  - "{effect: Effect}, then {next: Effect}": Chain
+  - "Has {flag: bool} {name: string}"
+Target: {allies: 1, enemies: 2, self: 0}
+"#;
+
+static SENTENCES: LazyLock<SentenceParser> =
+    LazyLock::new(|| SentenceParser::from_yaml("Effect".into(), GRAMMAR).unwrap());
+
+/// Keep panics raised in the markdown crate from the current panic hook,
+/// which aborts under libFuzzer. Call it in the `init:` of fuzz targets.
+pub fn init() {
+    let hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        let upstream = info
+            .location()
+            .is_some_and(|location| location.file().contains("markdown-"));
+        if !upstream {
+            hook(info);
+        }
+    }));
+}
+
+/// A pipe with every built-in parser, reading documents with `configure`
+fn pipe(configure: impl FnOnce(DokePipe) -> DokePipe) -> DokePipe {
+    configure(DokePipe::new())
+        .add(FrontmatterTemplateParser)
+        .add(CurveParser::new())
+        .add(WeightedTableParser::new().with_item_constituents())
+        .add(DialogueParser::new())
+        .add(StateMachineParser::new())
+        .add(SENTENCES.clone())
+}
+
+/// Read, parse, validate and format a markdown document, plain, in Obsidian
+/// mode, and as AsciiDoc and plain text
+/// ```
+/// use doke::{DokePipe, parsers};
+///
+/// doke::fuzzing::init();
+/// // panics inside the markdown crate
+/// let input = "é\n-\n--\nx\n-\n";
+/// doke::fuzzing::markdown(input.as_bytes());
+///
+/// let document = DokePipe::new().add(parsers::FrontmatterTemplateParser).run_markdown(input);
+/// assert_eq!(document.diagnostics[0].code, "markdown-error");
+/// assert!(document.nodes.is_empty());
+/// ```
+pub fn markdown(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let pipes = [
+        pipe(|p| p),
+        pipe(|p| {
+            p.with_obsidian(true)
+                .with_normalization(Normalization::typographic().with_case_fold(true))
+        }),
+        pipe(|p| p.with_input_format(AsciiDoc)),
+        pipe(|p| p.with_input_format(PlainText::new())),
+    ];
+    for pipe in &pipes {
+        let mut document = pipe.run_markdown(text);
+        let _ = document.validate_spanned();
+    }
+    let _ = pipes[0].format(text);
+    let _ = pipes[0].run_markdown_with_overrides(text, &[text]);
+}
+
+/// Match a statement against a grammar: the input is a YAML grammar, a line
+/// with `---`, then the statement. Without the separator, the whole input is a
+/// statement of a built-in grammar.
+pub fn sentence(data: &[u8]) {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    let (parser, statement) = match text.split_once("\n---\n") {
+        Some((grammar, statement)) => match SentenceParser::from_yaml("Fuzz".into(), grammar) {
+            Ok(parser) => (parser, statement),
+            Err(_) => return,
+        },
+        None => (SENTENCES.clone(), text),
+    };
+    let span = Position {
+        start: 0,
+        end: statement.len(),
+    };
+    let mut node = input::statement(statement, span);
+    let frontmatter = [("level".to_string(), GodotValue::Int(3))].into();
+    parser.process(&mut node, &frontmatter);
+    let _ = DokeValidate::validate_tree(&mut [node], &frontmatter);
+}
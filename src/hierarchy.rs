@@ -0,0 +1,150 @@
+// hierarchy.rs
+//
+// Is-a relationships between resource types. A dokedef section can say which type
+// it specializes:
+//
+//     FireDamageEffect:
+//       phrases:
+//         - "Burns for {damage: int} fire damage"
+//       is_a: DamageEffect
+//
+// and, in the grammar of `ItemEffect`, `DamageEffect` is an `ItemEffect`. A builder
+// field typed `DamageEffect` then takes `FireDamageEffect` resources, and one typed
+// `ItemEffect` takes both, without listing every concrete type.
+//
+// Beyond one parent, sections can declare capabilities, inherited by their subtypes:
+//
+//     Poison:
+//       phrases: ["Poisons for {turns: int} turns"]
+//       capabilities: [Stackable, Triggerable]
+//
+// A builder field typed `Triggerable` takes resources of any type with that
+// capability, and one typed `ItemEffect + Triggerable` only the item effects with it.
+
+use std::collections::{HashMap, HashSet};
+
+/// The type each type specializes and their capabilities, see `TypedSentencesParser::hierarchy`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TypeHierarchy {
+    parents: HashMap<String, String>,
+    /// Capabilities each type declares, without the inherited ones
+    capabilities: HashMap<String, Vec<String>>,
+}
+
+impl TypeHierarchy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// From `(type, the type it is)` pairs
+    pub fn from_pairs<I, S, T>(pairs: I) -> Self
+    where
+        I: IntoIterator<Item = (S, T)>,
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let mut hierarchy = Self::new();
+        for (ty, parent) in pairs {
+            hierarchy.insert(ty, parent);
+        }
+        hierarchy
+    }
+
+    /// Declare that `ty` is a `parent`, replacing what it was declared to be.
+    /// A type isn't its own parent.
+    pub fn insert(&mut self, ty: impl Into<String>, parent: impl Into<String>) {
+        let (ty, parent) = (ty.into(), parent.into());
+        if ty != parent {
+            self.parents.insert(ty, parent);
+        }
+    }
+
+    /// Declare that `ty` has `capability`
+    pub fn add_capability(&mut self, ty: impl Into<String>, capability: impl Into<String>) {
+        let capabilities = self.capabilities.entry(ty.into()).or_default();
+        let capability = capability.into();
+        if !capabilities.contains(&capability) {
+            capabilities.push(capability);
+        }
+    }
+
+    /// Declare that `ty` has these capabilities
+    pub fn with_capabilities<I, S>(mut self, ty: &str, capabilities: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        for capability in capabilities {
+            self.add_capability(ty, capability);
+        }
+        self
+    }
+
+    /// Add the declarations of `other`: its parents win over these ones, capabilities add up
+    pub fn extend(&mut self, other: &TypeHierarchy) {
+        for (ty, parent) in &other.parents {
+            self.insert(ty.clone(), parent.clone());
+        }
+        for (ty, capabilities) in &other.capabilities {
+            for capability in capabilities {
+                self.add_capability(ty.clone(), capability.clone());
+            }
+        }
+    }
+
+    /// The type `ty` specializes, if declared
+    pub fn parent(&self, ty: &str) -> Option<&str> {
+        self.parents.get(ty).map(String::as_str)
+    }
+
+    /// The types `ty` is, closest first, without `ty`. Stops at a cycle.
+    pub fn ancestors<'a>(&'a self, ty: &'a str) -> Vec<&'a str> {
+        let mut seen = HashSet::from([ty]);
+        let mut ancestors = Vec::new();
+        let mut current = ty;
+        while let Some(parent) = self.parent(current) {
+            if !seen.insert(parent) {
+                break;
+            }
+            ancestors.push(parent);
+            current = parent;
+        }
+        ancestors
+    }
+
+    /// Whether `ty` is `ancestor` or one of its subtypes
+    /// ```
+    /// use doke::hierarchy::TypeHierarchy;
+    ///
+    /// let hierarchy = TypeHierarchy::from_pairs([
+    ///     ("FireDamageEffect", "DamageEffect"),
+    ///     ("DamageEffect", "ItemEffect"),
+    /// ]);
+    /// assert!(hierarchy.is_a("FireDamageEffect", "ItemEffect"));
+    /// assert!(hierarchy.is_a("DamageEffect", "DamageEffect"));
+    /// assert!(!hierarchy.is_a("DamageEffect", "FireDamageEffect"));
+    /// ```
+    pub fn is_a(&self, ty: &str, ancestor: &str) -> bool {
+        ty == ancestor || self.ancestors(ty).contains(&ancestor)
+    }
+
+    /// Whether `ty` or one of the types it is declares `capability`
+    /// ```
+    /// use doke::hierarchy::TypeHierarchy;
+    ///
+    /// let hierarchy = TypeHierarchy::from_pairs([("DeadlyPoison", "Poison")])
+    ///     .with_capabilities("Poison", ["Stackable", "Triggerable"]);
+    /// assert!(hierarchy.has_capability("DeadlyPoison", "Stackable"));
+    /// assert!(!hierarchy.has_capability("DeadlyPoison", "Equippable"));
+    /// ```
+    pub fn has_capability(&self, ty: &str, capability: &str) -> bool {
+        std::iter::once(ty)
+            .chain(self.ancestors(ty))
+            .filter_map(|ty| self.capabilities.get(ty))
+            .any(|capabilities| capabilities.iter().any(|c| c == capability))
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.parents.is_empty() && self.capabilities.is_empty()
+    }
+}
@@ -0,0 +1,401 @@
+// import
+//
+// Reverse conversion, to migrate existing data into markdown:
+// a resource read from a `.tres` or JSON file is written back as statements,
+// using the grammar's most specific phrase for each value.
+
+mod tres;
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::GodotValue;
+use crate::diagnostics::Diagnostic;
+use crate::file_builder::ResourceBuilder;
+use crate::parsers::TypedSentencesParser;
+use crate::parsers::color::to_hex;
+use crate::parsers::param_types::is_basic_type;
+use crate::parsers::sentence::{PhraseConfig, ReturnSpec, phrase_specificity};
+use crate::parsers::{DISPLAY_TEMPLATE_KEY, LITERALS_KEY};
+use crate::rich_text::DISPLAY_TEXT_KEY;
+
+pub use tres::parse_tres;
+
+#[derive(Debug, Error)]
+pub enum ImportError {
+    #[error("Parse error: {0}")]
+    Parse(String),
+}
+
+/// How deep resources are nested before giving up
+const MAX_DEPTH: usize = 32;
+
+/// Read a JSON export into a `GodotValue`.
+///
+/// Objects with a `"type"` key become resources of that type,
+/// with an optional `"abstract_type"`. Other objects are dictionaries.
+/// ```
+/// use std::collections::HashMap;
+/// use doke::{GodotValue, export::json::to_json, import::parse_json};
+///
+/// let burn = GodotValue::Resource {
+///     type_name: "Burn".into(),
+///     abstract_type_name: "Effect".into(),
+///     fields: HashMap::from([("turns".into(), GodotValue::Int(2))]),
+/// };
+/// assert_eq!(parse_json(&to_json(&burn)).unwrap(), burn);
+/// assert!(parse_json("{\"type\": \"Burn\", ").is_err());
+/// ```
+pub fn parse_json(text: &str) -> Result<GodotValue, ImportError> {
+    // JSON is valid YAML
+    let docs = YamlLoader::load_from_str(text).map_err(|e| ImportError::Parse(e.to_string()))?;
+    let doc = docs
+        .into_iter()
+        .next()
+        .ok_or_else(|| ImportError::Parse("empty document".into()))?;
+    Ok(json_to_godot(doc))
+}
+
+fn json_to_godot(y: Yaml) -> GodotValue {
+    match y {
+        Yaml::Real(r) => r
+            .parse()
+            .map(GodotValue::Float)
+            .unwrap_or(GodotValue::String(r)),
+        Yaml::Integer(i) => GodotValue::Int(i),
+        Yaml::String(s) => GodotValue::String(s),
+        Yaml::Boolean(b) => GodotValue::Bool(b),
+        Yaml::Array(a) => GodotValue::Array(a.into_iter().map(json_to_godot).collect()),
+        Yaml::Hash(h) => {
+            let mut fields: HashMap<String, GodotValue> = h
+                .into_iter()
+                .filter_map(|(k, v)| match k {
+                    Yaml::String(k) => Some((k, json_to_godot(v))),
+                    _ => None,
+                })
+                .collect();
+            match fields.remove("type") {
+                Some(GodotValue::String(type_name)) => {
+                    let abstract_type_name = match fields.remove("abstract_type") {
+                        Some(GodotValue::String(t)) => t,
+                        _ => type_name.clone(),
+                    };
+                    GodotValue::Resource {
+                        type_name,
+                        abstract_type_name,
+                        fields,
+                    }
+                }
+                Some(other) => {
+                    fields.insert("type".into(), other);
+                    GodotValue::Dict(fields)
+                }
+                None => GodotValue::Dict(fields),
+            }
+        }
+        _ => GodotValue::Nil,
+    }
+}
+
+/// Write `root` as a markdown document with the phrases of `grammar`.
+///
+/// Each field of the root becomes statements (one per element for arrays),
+/// in the order of `builder`'s config if given. Fields of nested resources that
+/// no phrase parameter covers but hold resources are written as child bullets.
+/// Values the grammar can't express are kept as comments, with a warning.
+/// ```
+/// # #[cfg(feature = "fs")] {
+/// use std::{collections::HashMap, fs};
+/// use doke::{DokePipe, GodotValue, export::TresExport, parsers::TypedSentencesParser};
+/// use doke::import::{parse_tres, to_markdown};
+///
+/// let dir = std::env::temp_dir().join("doke_import_round_trip");
+/// fs::create_dir_all(&dir).unwrap();
+/// fs::write(dir.join("Effect.dokedef.yaml"), r#"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// Heal:
+///   - "Heals {amount: int} HP"
+/// "#).unwrap();
+/// let config = "rules:\n  - for: Effect\n    parser: \"*.dokedef.yaml\"\n";
+/// let grammar = || TypedSentencesParser::from_config(config, &dir).unwrap();
+/// let pipe = DokePipe::new().add(grammar());
+/// let statements = |markdown: &str| -> Vec<String> {
+///     let doc = pipe.run_markdown(markdown);
+///     doc.nodes.iter().map(|node| node.statement.to_string()).collect()
+/// };
+///
+/// let source = "- Deals 5 damage\n- Heals 3 HP\n";
+/// let item = GodotValue::Resource {
+///     type_name: "Item".into(),
+///     abstract_type_name: "root".into(),
+///     fields: HashMap::from([("effects".into(), GodotValue::Array(pipe.validate(source).unwrap()))]),
+/// };
+/// let tres = TresExport::default().to_tres("sword", &item, &HashMap::new());
+///
+/// let (markdown, diagnostics) = to_markdown(&parse_tres(&tres).unwrap(), &grammar(), None);
+/// assert!(diagnostics.is_empty());
+/// assert_eq!(statements(&markdown), statements(source));
+/// # }
+/// ```
+pub fn to_markdown(
+    root: &GodotValue,
+    grammar: &TypedSentencesParser,
+    builder: Option<&ResourceBuilder>,
+) -> (String, Vec<Diagnostic>) {
+    let writer = Writer {
+        phrases: grammar
+            .enabled_rules()
+            .flat_map(|rule| rule.sentence_parser.phrases.iter())
+            .collect(),
+    };
+    let mut diagnostics = Vec::new();
+    let mut out = String::new();
+
+    let GodotValue::Resource { fields, .. } = root else {
+        diagnostics.push(Diagnostic::error(
+            "import-not-a-resource",
+            format!("expected a resource, got {}", root),
+        ));
+        return (out, diagnostics);
+    };
+
+    if let Some(GodotValue::String(name)) = fields.get("resource_name") {
+        out.push_str(&format!("---\ntitle: {}\n---\n\n", name));
+    }
+
+    let mut names: Vec<&String> = fields
+        .keys()
+        .filter(|k| !is_metadata(k) && *k != "resource_name")
+        .collect();
+    names.sort();
+    if let Some(builder) = builder {
+        let order: Vec<&str> = builder
+            .config()
+            .children
+            .iter()
+            .map(|f| f.name.as_str())
+            .collect();
+        names.sort_by_key(|name| order.iter().position(|o| o == name).unwrap_or(order.len()));
+    }
+
+    for name in names {
+        let items = match &fields[name] {
+            GodotValue::Nil => continue,
+            GodotValue::Array(items) => items.iter().collect(),
+            value => vec![value],
+        };
+        for item in items {
+            writer.statement(item, name, 0, &mut out, &mut diagnostics);
+        }
+    }
+    (out, diagnostics)
+}
+
+/// Same phrase as exported, fields covered, phrase specificity
+type PhraseScore = (bool, usize, (usize, usize));
+
+struct Writer<'a> {
+    phrases: Vec<&'a PhraseConfig>,
+}
+
+impl Writer<'_> {
+    /// Write `value` as a statement, and its uncovered resource fields as bullets below it
+    fn statement(
+        &self,
+        value: &GodotValue,
+        field: &str,
+        depth: usize,
+        out: &mut String,
+        diagnostics: &mut Vec<Diagnostic>,
+    ) {
+        let indent = "  ".repeat(depth.saturating_sub(1));
+        let bullet = if depth > 0 { "- " } else { "" };
+
+        let Some((text, uncovered)) = self.spell(value, 0) else {
+            diagnostics.push(Diagnostic::warning(
+                "import-unwritten-value",
+                format!("no phrase writes the '{}' value {}", field, value),
+            ));
+            out.push_str(&format!("{}<!-- {}: {} -->\n", indent, field, value));
+            return;
+        };
+        out.push_str(&format!("{}{}{}\n", indent, bullet, text));
+
+        if let GodotValue::Resource { fields, .. } = value {
+            for name in uncovered {
+                let children: Vec<&GodotValue> = match &fields[&name] {
+                    GodotValue::Array(items) => items.iter().collect(),
+                    other => vec![other],
+                };
+                if depth < MAX_DEPTH
+                    && children
+                        .iter()
+                        .all(|c| matches!(c, GodotValue::Resource { .. }))
+                {
+                    for child in children {
+                        self.statement(child, &name, depth + 1, out, diagnostics);
+                    }
+                } else {
+                    diagnostics.push(Diagnostic::warning(
+                        "import-unwritten-field",
+                        format!(
+                            "no phrase parameter writes the '{}' field {}",
+                            name, fields[&name]
+                        ),
+                    ));
+                }
+            }
+        }
+        if depth == 0 {
+            out.push('\n');
+        }
+    }
+
+    /// The most specific phrase for `value` spelled with its fields,
+    /// and the fields it didn't cover
+    fn spell(&self, value: &GodotValue, depth: usize) -> Option<(String, Vec<String>)> {
+        if depth > MAX_DEPTH {
+            return None;
+        }
+        let GodotValue::Resource {
+            type_name, fields, ..
+        } = value
+        else {
+            // a phrase returning this exact value, e.g. "enemies" : 1
+            return self
+                .phrases
+                .iter()
+                .find(|p| {
+                    p.parameters.is_empty()
+                        && matches!(&p.return_spec, ReturnSpec::Literal(v) if v == value)
+                })
+                .map(|p| (p.spell(&HashMap::new()), Vec::new()));
+        };
+
+        let literals = match fields.get(LITERALS_KEY) {
+            Some(GodotValue::Dict(literals)) => Some(literals),
+            _ => None,
+        };
+        let mut best: Option<(PhraseScore, String, Vec<String>)> = None;
+        for phrase in &self.phrases {
+            if !matches!(&phrase.return_spec, ReturnSpec::Type(t) if t == type_name) {
+                continue;
+            }
+            let mut values = HashMap::new();
+            let writable = phrase.parameters.iter().all(|param| {
+                let Some(field) = fields.get(&param.name) else {
+                    return false;
+                };
+                let literal = literals.and_then(|l| match l.get(&param.name) {
+                    Some(GodotValue::String(s)) => Some(s.clone()),
+                    _ => None,
+                });
+                let text = if param.param_type.eq_ignore_ascii_case("price") {
+                    literal.or_else(|| match field.get_path("value") {
+                        Some(GodotValue::Int(value)) => Some(phrase.currency.spell(*value)),
+                        _ => None,
+                    })
+                } else if is_basic_type(&param.param_type) {
+                    literal.or_else(|| basic_text(field, &param.param_type))
+                } else {
+                    self.spell(field, depth + 1).map(|(text, _)| text)
+                };
+                text.map(|text| values.insert(param.name.clone(), text))
+                    .is_some()
+            });
+            if !writable {
+                continue;
+            }
+
+            let uncovered: Vec<String> = fields
+                .keys()
+                .filter(|k| !is_metadata(k) && !values.contains_key(*k))
+                .cloned()
+                .collect();
+            // values exported by doke remember the phrase they were written with
+            let same_phrase = matches!(fields.get("doke_tr_key"),
+                Some(GodotValue::String(key)) if *key == phrase.make_tr_key());
+            let score = (same_phrase, values.len(), phrase_specificity(phrase));
+            if best
+                .as_ref()
+                .is_none_or(|(best_score, ..)| *best_score < score)
+            {
+                best = Some((score, phrase.spell(&values), uncovered));
+            }
+        }
+        best.map(|(_, text, mut uncovered)| {
+            uncovered.sort();
+            (text, uncovered)
+        })
+    }
+}
+
+/// Fields doke adds to results, that no phrase writes
+fn is_metadata(key: &str) -> bool {
+    key.starts_with("doke_") || key == DISPLAY_TEXT_KEY || key == DISPLAY_TEMPLATE_KEY
+}
+
+/// Text a basic parameter parses back into `value`
+fn basic_text(value: &GodotValue, param_type: &str) -> Option<String> {
+    let field = |name: &str| match value.get_path(name) {
+        Some(GodotValue::Int(i)) => Some(*i),
+        _ => None,
+    };
+    match (param_type.to_lowercase().as_str(), value) {
+        ("int", GodotValue::Int(i)) => Some(i.to_string()),
+        ("int", GodotValue::UInt(u)) => Some(u.to_string()),
+        ("int", GodotValue::Float(f)) if f.fract() == 0.0 => Some((*f as i64).to_string()),
+        ("float", GodotValue::Float(f)) => Some(f.to_string()),
+        ("float", GodotValue::Int(i)) => Some(i.to_string()),
+        ("bool", GodotValue::Bool(b)) => Some(b.to_string()),
+        ("string", GodotValue::String(s)) => Some(s.clone()),
+        ("date", GodotValue::Dict(_)) => Some(format!(
+            "{:04}-{:02}-{:02}",
+            field("year")?,
+            field("month")?,
+            field("day")?
+        )),
+        ("datetime", GodotValue::Dict(_)) => {
+            let mut text = format!(
+                "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}",
+                field("year")?,
+                field("month")?,
+                field("day")?,
+                field("hour")?,
+                field("minute")?,
+                field("second")?
+            );
+            if let Some(bias) = field("bias") {
+                let sign = if bias < 0 { '-' } else { '+' };
+                text.push_str(&format!(
+                    "{}{:02}:{:02}",
+                    sign,
+                    bias.abs() / 60,
+                    bias.abs() % 60
+                ));
+            }
+            Some(text)
+        }
+        ("ref", GodotValue::Resource { .. }) => match value.get_path("id") {
+            Some(GodotValue::String(id)) => Some(id.clone()),
+            _ => None,
+        },
+        ("formula", GodotValue::Resource { .. }) => match value.get_path("source") {
+            Some(GodotValue::String(source)) => Some(source.clone()),
+            _ => None,
+        },
+        ("vec2", GodotValue::Vector2(x, y)) => Some(format!("({}, {})", x, y)),
+        ("rect", GodotValue::Rect2(x, y, w, h)) => {
+            Some(format!("({}, {}) to ({}, {})", x, y, x + w, y + h))
+        }
+        ("color", GodotValue::Color(r, g, b, a)) => Some(to_hex(*r, *g, *b, *a)),
+        ("gdexpr", GodotValue::Resource { .. }) => match value.get_path("source") {
+            Some(GodotValue::String(source)) => Some(format!("`{}`", source)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
@@ -0,0 +1,409 @@
+// tres.rs
+//
+// Reader for Godot's text resource format (.tres):
+//
+//     [gd_resource type="Resource" script_class="Item" load_steps=3 format=3]
+//     [ext_resource type="Script" path="res://items/damage_effect.gd" id="1_dmg"]
+//     [sub_resource type="Resource" id="Resource_a1"]
+//     script = ExtResource("1_dmg")
+//     damage = 12
+//     [resource]
+//     effects = [SubResource("Resource_a1")]
+//
+// Sub resources are written before the resources using them, so references
+// are resolved as they are read.
+
+use std::collections::HashMap;
+
+use crate::GodotValue;
+use crate::parsers::LITERALS_KEY;
+use crate::utility::to_pascal_case;
+
+use super::ImportError;
+
+struct Section {
+    tag: String,
+    attributes: HashMap<String, GodotValue>,
+    properties: Vec<(String, GodotValue)>,
+}
+
+struct Reader<'a> {
+    text: &'a str,
+    pos: usize,
+    /// ext_resource id → path
+    external: HashMap<String, String>,
+    /// sub_resource id → resource
+    internal: HashMap<String, GodotValue>,
+}
+
+/// Read a `.tres` file into the resource it describes
+/// ```
+/// use doke::GodotValue;
+/// use doke::import::parse_tres;
+///
+/// let tres = "[gd_resource type=\"Resource\" format=3]\n[resource]\ndamage = 5\n";
+/// assert_eq!(parse_tres(tres).unwrap().get_path("damage"), Some(&GodotValue::Int(5)));
+///
+/// let dangling = "[gd_resource type=\"Resource\"]\n[resource]\neffect = SubResource(\"missing\")\n";
+/// let error = parse_tres(dangling).unwrap_err();
+/// assert_eq!(error.to_string(), "Parse error: line 3: unknown sub_resource 'missing'");
+/// assert!(parse_tres("[gd_resource type=").is_err());
+/// ```
+pub fn parse_tres(text: &str) -> Result<GodotValue, ImportError> {
+    let mut reader = Reader {
+        text,
+        pos: 0,
+        external: HashMap::new(),
+        internal: HashMap::new(),
+    };
+
+    let mut root_class = None;
+    while let Some(section) = reader.section()? {
+        match section.tag.as_str() {
+            "gd_resource" => root_class = section.attributes.get("script_class").cloned(),
+            "ext_resource" => {
+                if let (Some(GodotValue::String(id)), Some(GodotValue::String(path))) =
+                    (section.attributes.get("id"), section.attributes.get("path"))
+                {
+                    reader.external.insert(id.clone(), path.clone());
+                }
+            }
+            "sub_resource" => {
+                let id = match section.attributes.get("id") {
+                    Some(GodotValue::String(id)) => id.clone(),
+                    _ => return Err(reader.error("sub_resource without an id")),
+                };
+                let resource = reader.resource(&section, None);
+                reader.internal.insert(id, resource);
+            }
+            "resource" => {
+                let class = match &root_class {
+                    Some(GodotValue::String(class)) => Some(class.as_str()),
+                    _ => None,
+                };
+                return Ok(reader.resource(&section, class));
+            }
+            _ => {}
+        }
+    }
+    Err(ImportError::Parse("no [resource] section".into()))
+}
+
+impl Reader<'_> {
+    fn error(&self, message: &str) -> ImportError {
+        let line = self.text[..self.pos].matches('\n').count() + 1;
+        ImportError::Parse(format!("line {}: {}", line, message))
+    }
+
+    fn rest(&self) -> &str {
+        &self.text[self.pos..]
+    }
+
+    fn peek(&self) -> Option<char> {
+        self.rest().chars().next()
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        if self.peek() == Some(c) {
+            self.pos += c.len_utf8();
+            true
+        } else {
+            false
+        }
+    }
+
+    fn expect(&mut self, c: char) -> Result<(), ImportError> {
+        self.skip_blank();
+        if self.eat(c) {
+            Ok(())
+        } else {
+            Err(self.error(&format!("expected '{}'", c)))
+        }
+    }
+
+    /// Skip whitespace and `;` comments
+    fn skip_blank(&mut self) {
+        loop {
+            let rest = self.rest();
+            let trimmed = rest.trim_start();
+            let skip = rest.len() - trimmed.len()
+                + if trimmed.starts_with(';') {
+                    trimmed.find('\n').unwrap_or(trimmed.len())
+                } else {
+                    0
+                };
+            if skip == 0 {
+                return;
+            }
+            self.pos += skip;
+        }
+    }
+
+    /// A property or attribute name, e.g. `metadata/_editor_note`
+    fn identifier(&mut self) -> Result<String, ImportError> {
+        self.skip_blank();
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_alphanumeric() || "_/:.".contains(c)))
+            .unwrap_or(self.rest().len());
+        if len == 0 {
+            return Err(self.error("expected a name"));
+        }
+        let name = self.rest()[..len].to_string();
+        self.pos += len;
+        Ok(name)
+    }
+
+    fn section(&mut self) -> Result<Option<Section>, ImportError> {
+        self.skip_blank();
+        if self.peek().is_none() {
+            return Ok(None);
+        }
+        self.expect('[')?;
+        let tag = self.identifier()?;
+        let mut attributes = HashMap::new();
+        loop {
+            self.skip_blank();
+            if self.eat(']') {
+                break;
+            }
+            let key = self.identifier()?;
+            self.expect('=')?;
+            let value = self.value()?;
+            attributes.insert(key, value);
+        }
+
+        let mut properties = Vec::new();
+        loop {
+            self.skip_blank();
+            if matches!(self.peek(), None | Some('[')) {
+                break;
+            }
+            let key = self.identifier()?;
+            self.expect('=')?;
+            let value = self.value()?;
+            properties.push((key, value));
+        }
+        Ok(Some(Section {
+            tag,
+            attributes,
+            properties,
+        }))
+    }
+
+    /// Turn a section's properties into a resource. Its type is the script's
+    /// class if known, then the name of its script file, then the Godot type.
+    fn resource(&self, section: &Section, class: Option<&str>) -> GodotValue {
+        let mut type_name = class.map(str::to_string);
+        let mut fields = HashMap::new();
+        for (key, value) in &section.properties {
+            if key == "script" {
+                if type_name.is_none()
+                    && let GodotValue::String(path) = value
+                {
+                    type_name = Some(class_from_script(path));
+                }
+            } else if key == "metadata/doke_type"
+                && let GodotValue::String(doke_type) = value
+            {
+                // written by doke for types that aren't a script class
+                type_name = Some(doke_type.clone());
+            } else if key.strip_prefix("metadata/") == Some(LITERALS_KEY) {
+                fields.insert(LITERALS_KEY.into(), value.clone());
+            } else if !key.starts_with("metadata/") && key != "resource_local_to_scene" {
+                fields.insert(key.clone(), value.clone());
+            }
+        }
+        let type_name = type_name
+            .or_else(|| match section.attributes.get("type") {
+                Some(GodotValue::String(t)) => Some(t.clone()),
+                _ => None,
+            })
+            .unwrap_or_else(|| "Resource".into());
+        GodotValue::Resource {
+            abstract_type_name: type_name.clone(),
+            type_name,
+            fields,
+        }
+    }
+
+    fn value(&mut self) -> Result<GodotValue, ImportError> {
+        self.skip_blank();
+        match self.peek() {
+            Some('"') => Ok(GodotValue::String(self.string()?)),
+            // StringName
+            Some('&') => {
+                self.pos += 1;
+                Ok(GodotValue::String(self.string()?))
+            }
+            Some('[') => {
+                self.pos += 1;
+                let mut items = Vec::new();
+                loop {
+                    self.skip_blank();
+                    if self.eat(']') {
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_blank();
+                    self.eat(',');
+                }
+                Ok(GodotValue::Array(items))
+            }
+            Some('{') => {
+                self.pos += 1;
+                let mut map = HashMap::new();
+                loop {
+                    self.skip_blank();
+                    if self.eat('}') {
+                        break;
+                    }
+                    let key = match self.value()? {
+                        GodotValue::String(s) => s,
+                        other => other.to_string(),
+                    };
+                    self.expect(':')?;
+                    map.insert(key, self.value()?);
+                    self.skip_blank();
+                    self.eat(',');
+                }
+                Ok(GodotValue::Dict(map))
+            }
+            Some(c) if c.is_ascii_digit() || c == '-' || c == '+' || c == '.' => self.number(),
+            Some(_) => self.word(),
+            None => Err(self.error("expected a value")),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, ImportError> {
+        self.expect('"')?;
+        let mut out = String::new();
+        let mut chars = self.rest().char_indices();
+        while let Some((i, c)) = chars.next() {
+            match c {
+                '"' => {
+                    self.pos += i + 1;
+                    return Ok(out);
+                }
+                '\\' => match chars.next() {
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, other)) => out.push(other),
+                    None => break,
+                },
+                c => out.push(c),
+            }
+        }
+        Err(self.error("unterminated string"))
+    }
+
+    fn number(&mut self) -> Result<GodotValue, ImportError> {
+        let len = self
+            .rest()
+            .find(|c: char| !(c.is_ascii_alphanumeric() || "+-.".contains(c)))
+            .unwrap_or(self.rest().len());
+        let literal = &self.rest()[..len];
+        let value = if let Ok(i) = literal.parse::<i64>() {
+            GodotValue::Int(i)
+        } else if let Ok(f) = literal.parse::<f64>() {
+            GodotValue::Float(f)
+        } else {
+            return Err(self.error(&format!("invalid number '{}'", literal)));
+        };
+        self.pos += len;
+        Ok(value)
+    }
+
+    /// Keywords and constructors: `true`, `null`, `SubResource("id")`, `Vector2(1, 2)`...
+    fn word(&mut self) -> Result<GodotValue, ImportError> {
+        let name = self.identifier()?;
+        match name.as_str() {
+            "true" => return Ok(GodotValue::Bool(true)),
+            "false" => return Ok(GodotValue::Bool(false)),
+            "null" => return Ok(GodotValue::Nil),
+            "inf" => return Ok(GodotValue::Float(f64::INFINITY)),
+            "nan" => return Ok(GodotValue::Float(f64::NAN)),
+            _ => {}
+        }
+
+        // typed collections: Array[int]([1, 2])
+        self.skip_blank();
+        if self.eat('[') {
+            let end = self
+                .rest()
+                .find(']')
+                .ok_or_else(|| self.error("unterminated type"))?;
+            self.pos += end + 1;
+        }
+
+        self.expect('(')?;
+        let mut args = Vec::new();
+        loop {
+            self.skip_blank();
+            if self.eat(')') {
+                break;
+            }
+            args.push(self.value()?);
+            self.skip_blank();
+            self.eat(',');
+        }
+
+        let first_id = || match args.first() {
+            Some(GodotValue::String(id)) => Ok(id.clone()),
+            _ => Err(self.error(&format!("{} needs an id", name))),
+        };
+        Ok(match name.as_str() {
+            "SubResource" => {
+                let id = first_id()?;
+                self.internal
+                    .get(&id)
+                    .cloned()
+                    .ok_or_else(|| self.error(&format!("unknown sub_resource '{}'", id)))?
+            }
+            "ExtResource" => {
+                let id = first_id()?;
+                GodotValue::String(self.external.get(&id).cloned().unwrap_or(id))
+            }
+            "Array" | "Dictionary" | "NodePath" | "StringName" if args.len() == 1 => args.remove(0),
+            "Vector2" | "Vector2i" | "Rect2" | "Rect2i" => {
+                let components: Vec<f64> = args.iter().filter_map(number).collect();
+                match components[..] {
+                    [x, y] => GodotValue::Vector2(x, y),
+                    [x, y, w, h] => GodotValue::Rect2(x, y, w, h),
+                    _ => return Err(self.error(&format!("invalid {}", name))),
+                }
+            }
+            "Color" => {
+                let components: Vec<f64> = args.iter().filter_map(number).collect();
+                match components[..] {
+                    [r, g, b] => GodotValue::Color(r, g, b, 1.0),
+                    [r, g, b, a] => GodotValue::Color(r, g, b, a),
+                    _ => return Err(self.error("invalid Color")),
+                }
+            }
+            _ => GodotValue::Array(args),
+        })
+    }
+}
+
+/// The float of a constructor argument like `Vector2(1, 2.5)`
+fn number(arg: &GodotValue) -> Option<f64> {
+    match arg {
+        GodotValue::Int(i) => Some(*i as f64),
+        GodotValue::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+/// "res://items/damage_effect.gd" → "DamageEffect"
+fn class_from_script(path: &str) -> String {
+    let stem = path
+        .rsplit('/')
+        .next()
+        .unwrap_or(path)
+        .split('.')
+        .next()
+        .unwrap_or_default();
+    to_pascal_case(stem)
+}
@@ -0,0 +1,276 @@
+// input/asciidoc.rs
+//
+// AsciiDoc documents (`.adoc`), read line by line into the same statements
+// as markdown:
+// - paragraphs are statements
+// - section titles are statements written with `#`s (`== Effects` is `## Effects`)
+// - list items (`*`, `-`, `.`, `1.`) are statements, nested by marker (`**`), and a
+//   list right after a statement is its children, as in markdown
+// - listing and literal blocks are code statements written as fences (`[source,lua]`
+//   gives the language)
+// - example, sidebar, quote and open blocks are read as if they weren't delimited
+// - attribute entries (`:rarity: rare`) are frontmatter keys
+//
+// Comments, block titles, tables and passthrough blocks are left out.
+
+use std::ops::Range;
+use std::sync::{Arc, LazyLock};
+
+use regex::Regex;
+
+use super::{InputDocument, InputFormat, nest, statement};
+use crate::obsidian::field_value;
+use crate::semantic::BlockKind;
+use crate::{DokeNode, GodotValue, Position, Statement};
+
+static DELIMITER: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"^(-{4,}|\.{4,}|/{4,}|={4,}|\*{4,}|_{4,}|\+{4,}|--|\|===)$").unwrap()
+});
+static SECTION: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^(={1,6})\s+(\S.*)$").unwrap());
+static LIST_ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s*(\*+|-|\.+|\d+\.)\s+(\S.*)$").unwrap());
+static ATTRIBUTE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^:(\w[\w-]*)(!?):(?:\s+(.*))?$").unwrap());
+static BLOCK_ATTRIBUTES: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\[(.*)\]$").unwrap());
+static BLOCK_TITLE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\.[^.\s]").unwrap());
+
+/// The AsciiDoc input format, see `DokePipe::with_input_format`
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AsciiDoc;
+
+impl InputFormat for AsciiDoc {
+    fn extension(&self) -> &'static str {
+        "adoc"
+    }
+
+    fn read(&self, body: &str) -> InputDocument {
+        let mut offset = 0;
+        let lines = body
+            .split_inclusive('\n')
+            .map(|line| {
+                let start = offset;
+                offset += line.len();
+                (start, line.trim_end())
+            })
+            .collect::<Vec<_>>();
+        let mut reader = Reader {
+            body,
+            source: Arc::from(body),
+            lines,
+            fields: Vec::new(),
+        };
+        let nodes = reader.blocks(0..reader.lines.len());
+        InputDocument {
+            nodes,
+            fields: reader.fields,
+            diagnostics: Vec::new(),
+        }
+    }
+}
+
+struct Reader<'a> {
+    body: &'a str,
+    /// `body`, shared by the statements
+    source: Arc<str>,
+    /// (offset, line without its end)
+    lines: Vec<(usize, &'a str)>,
+    fields: Vec<(String, GodotValue)>,
+}
+
+impl Reader<'_> {
+    /// Statements of the lines in `range`
+    fn blocks(&mut self, range: Range<usize>) -> Vec<DokeNode> {
+        let mut nodes: Vec<DokeNode> = Vec::new();
+        // the statement a following list belongs to
+        let mut parent: Option<usize> = None;
+        // language of the next listing block
+        let mut language = None;
+        let mut i = range.start;
+        while i < range.end {
+            let (offset, line) = self.lines[i];
+            if line.is_empty() || (line.starts_with("//") && !line.starts_with("////")) {
+                i += 1;
+            } else if DELIMITER.is_match(line) {
+                let close = (i + 1..range.end)
+                    .find(|j| self.lines[*j].1 == line)
+                    .unwrap_or(range.end);
+                match &line[..1] {
+                    "-" | "." if line != "--" => {
+                        let content = self.text(i + 1..close);
+                        let end = match close < range.end {
+                            true => self.lines[close].0 + line.len(),
+                            false => self.end(close - 1),
+                        };
+                        let language = language.take();
+                        let fence = format!(
+                            "```{}\n{}{}```",
+                            language.as_deref().unwrap_or_default(),
+                            content,
+                            if content.is_empty() { "" } else { "\n" }
+                        );
+                        let mut node = statement(fence, Position { start: offset, end });
+                        node.block = BlockKind::Code { language };
+                        nodes.push(node);
+                        parent = Some(nodes.len() - 1);
+                    }
+                    "/" | "+" | "|" => {}
+                    _ => {
+                        nodes.extend(self.blocks(i + 1..close));
+                        parent = None;
+                    }
+                }
+                language = None;
+                i = close + 1;
+            } else if let Some(c) = ATTRIBUTE.captures(line) {
+                if c[2].is_empty() {
+                    let value = match c.get(3).map(|v| v.as_str().trim()) {
+                        None | Some("") => GodotValue::Bool(true),
+                        Some(value) => field_value(value),
+                    };
+                    self.fields.push((c[1].to_string(), value));
+                }
+                i += 1;
+            } else if let Some(c) = BLOCK_ATTRIBUTES.captures(line) {
+                let mut attributes = c[1].split(',').map(str::trim);
+                if attributes.next() == Some("source") {
+                    language = attributes.next().map(str::to_string);
+                }
+                i += 1;
+            } else if BLOCK_TITLE.is_match(line) {
+                i += 1;
+            } else if let Some(c) = SECTION.captures(line) {
+                let title = format!("{} {}", "#".repeat(c[1].len()), &c[2]);
+                let span = Position {
+                    start: offset,
+                    end: offset + line.len(),
+                };
+                let mut node = statement(title, span);
+                node.block = BlockKind::Heading {
+                    depth: c[1].len() as u8,
+                };
+                nodes.push(node);
+                parent = Some(nodes.len() - 1);
+                language = None;
+                i += 1;
+            } else if LIST_ITEM.is_match(line) {
+                let (items, next) = self.list(i, range.end);
+                match parent {
+                    Some(parent) => nodes[parent].children.extend(items),
+                    None => nodes.extend(items),
+                }
+                language = None;
+                i = next;
+            } else {
+                let last = self.paragraph_end(i, range.end);
+                let start = offset + (line.len() - line.trim_start().len());
+                let span = Position {
+                    start,
+                    end: self.end(last),
+                };
+                let mut node = statement(self.shared(&span), span);
+                node.block = BlockKind::Paragraph;
+                nodes.push(node);
+                parent = Some(nodes.len() - 1);
+                language = None;
+                i = last + 1;
+            }
+        }
+        nodes
+    }
+
+    /// The items of the list starting at line `start`, and the line after the list
+    fn list(&self, start: usize, end: usize) -> (Vec<DokeNode>, usize) {
+        let mut markers: Vec<String> = Vec::new();
+        let mut items = Vec::new();
+        let mut i = start;
+        while i < end {
+            let (offset, line) = self.lines[i];
+            let Some(c) = LIST_ITEM.captures(line) else {
+                break;
+            };
+            let marker = marker_kind(&c[1]);
+            let depth = match markers.iter().position(|m| *m == marker) {
+                Some(depth) => {
+                    markers.truncate(depth + 1);
+                    depth
+                }
+                None => {
+                    markers.push(marker);
+                    markers.len() - 1
+                }
+            };
+
+            let last = self.paragraph_end(i, end);
+            let span = Position {
+                start: offset + c.get(2).unwrap().start(),
+                end: self.end(last),
+            };
+            let mut item = statement(self.shared(&span), span);
+            item.block = BlockKind::ListItem;
+            items.push((depth, item));
+
+            // after blank lines, a list of another kind is another list
+            i = last + 1;
+            let next = (i..end)
+                .find(|j| !matches!(self.lines[*j].1, "" | "+"))
+                .unwrap_or(end);
+            if let Some(c) = self
+                .lines
+                .get(next)
+                .and_then(|(_, l)| LIST_ITEM.captures(l))
+                && next < end
+                && (next == i || markers.contains(&marker_kind(&c[1])))
+            {
+                i = next;
+            }
+        }
+        (nest(items), i)
+    }
+
+    /// Last line of the paragraph starting at line `start`
+    fn paragraph_end(&self, start: usize, end: usize) -> usize {
+        let mut last = start;
+        while last + 1 < end {
+            let line = self.lines[last + 1].1;
+            if line.is_empty()
+                || line == "+"
+                || LIST_ITEM.is_match(line)
+                || DELIMITER.is_match(line)
+                || BLOCK_ATTRIBUTES.is_match(line)
+                || line.starts_with("//")
+            {
+                break;
+            }
+            last += 1;
+        }
+        last
+    }
+
+    /// Offset of the end of line `i`, line break left out
+    fn end(&self, i: usize) -> usize {
+        let (offset, line) = self.lines[i];
+        offset + line.len()
+    }
+
+    /// The text at `span`, sharing `body`
+    fn shared(&self, span: &Position) -> Statement {
+        Statement::shared(&self.source, span.start..span.end)
+    }
+
+    /// The lines in `range`, without the last line break
+    fn text(&self, range: Range<usize>) -> &str {
+        if range.is_empty() {
+            return "";
+        }
+        let (start, _) = self.lines[range.start];
+        &self.body[start..self.end(range.end - 1)]
+    }
+}
+
+/// Nesting key of a list marker, numbered items being the same kind
+fn marker_kind(marker: &str) -> String {
+    match marker.ends_with('.') && marker.starts_with(|c: char| c.is_ascii_digit()) {
+        true => "1.".to_string(),
+        false => marker.to_string(),
+    }
+}
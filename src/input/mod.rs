@@ -0,0 +1,235 @@
+// input/mod.rs
+//
+// Input formats: how the body of a document (after its YAML frontmatter)
+// becomes the tree of statements the semantic parsers run on.
+//
+// Markdown is read by default, `DokePipe::with_input_format` picks another
+// format. Every format shares the frontmatter, and produces the same
+// unresolved `DokeNode`s, so parsers don't know which one a document used.
+
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Arc;
+
+use markdown::mdast::Node;
+use markdown::{Constructs, ParseOptions};
+
+use crate::base_parser::{DokeBaseParser, DokeStatement};
+use crate::diagnostics::Diagnostic;
+use crate::semantic::{BlockKind, DokeNodeState};
+use crate::{DokeNode, Extensions, GodotValue, Position, Statement, obsidian, rich_text};
+
+mod asciidoc;
+mod plain;
+pub(crate) mod segment;
+pub use asciidoc::AsciiDoc;
+pub use plain::PlainText;
+pub use segment::Segmentation;
+
+/// A format documents can be written in
+pub trait InputFormat: Debug + Send + Sync {
+    /// Extension of the documents, without the dot (`md`)
+    fn extension(&self) -> &'static str;
+
+    /// Read the body of a document, spans being byte offsets into `body`
+    fn read(&self, body: &str) -> InputDocument;
+}
+
+/// The statements of a document body
+#[derive(Debug, Default)]
+pub struct InputDocument {
+    /// Unresolved statements
+    pub nodes: Vec<DokeNode>,
+    /// Frontmatter keys set in the body (like AsciiDoc attributes), added to the
+    /// frontmatter unless it already has them
+    pub fields: Vec<(String, GodotValue)>,
+    /// Problems reading the document
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// An unresolved statement
+pub(crate) fn statement(text: impl Into<Statement>, span: Position) -> DokeNode {
+    DokeNode {
+        statement: text.into(),
+        state: DokeNodeState::Unresolved,
+        children: Vec::new(),
+        parse_data: HashMap::new(),
+        extensions: Extensions::new(),
+        constituents: HashMap::new(),
+        span,
+        diagnostics: Vec::new(),
+        block: BlockKind::Other,
+    }
+}
+
+/// Turn statements with their depth, in document order, into a tree:
+/// each statement is a child of the last one before it that is less deep.
+pub(crate) fn nest(statements: impl IntoIterator<Item = (usize, DokeNode)>) -> Vec<DokeNode> {
+    fn close(stack: &mut Vec<(usize, DokeNode)>, roots: &mut Vec<DokeNode>) {
+        let (_, node) = stack.pop().unwrap();
+        match stack.last_mut() {
+            Some((_, parent)) => parent.children.push(node),
+            None => roots.push(node),
+        }
+    }
+
+    let mut roots = Vec::new();
+    let mut stack: Vec<(usize, DokeNode)> = Vec::new();
+    for (depth, node) in statements {
+        while stack.last().is_some_and(|(d, _)| *d >= depth) {
+            close(&mut stack, &mut roots);
+        }
+        stack.push((depth, node));
+    }
+    while !stack.is_empty() {
+        close(&mut stack, &mut roots);
+    }
+    roots
+}
+
+/// The parts of `ParseOptions` a pipe keeps. The MDX hooks are boxed closures
+/// that aren't `Send`, leaving them out lets pipes be shared across threads.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MarkdownOptions {
+    constructs: Constructs,
+    gfm_strikethrough_single_tilde: bool,
+    math_text_single_dollar: bool,
+}
+
+impl From<ParseOptions> for MarkdownOptions {
+    fn from(options: ParseOptions) -> Self {
+        Self {
+            constructs: options.constructs,
+            gfm_strikethrough_single_tilde: options.gfm_strikethrough_single_tilde,
+            math_text_single_dollar: options.math_text_single_dollar,
+        }
+    }
+}
+
+impl MarkdownOptions {
+    pub(crate) fn parse_options(&self) -> ParseOptions {
+        ParseOptions {
+            constructs: self.constructs.clone(),
+            gfm_strikethrough_single_tilde: self.gfm_strikethrough_single_tilde,
+            math_text_single_dollar: self.math_text_single_dollar,
+            ..ParseOptions::default()
+        }
+    }
+}
+
+/// Read a markdown body, the default format
+pub(crate) fn read_markdown(
+    body: &str,
+    options: &ParseOptions,
+    obsidian: bool,
+    rich_text: bool,
+    segmentation: Segmentation,
+) -> InputDocument {
+    let (obsidian_str, blanked) = match obsidian {
+        true => {
+            let (text, blanked) = obsidian::preprocess(body);
+            (Some(text), Some(blanked))
+        }
+        false => (None, None),
+    };
+    let body = obsidian_str.as_deref().unwrap_or(body);
+
+    // Convert markdown into MD AST using configured ParseOptions
+    let mut root_node = match to_mdast(body, options) {
+        Ok(root_node) => root_node,
+        Err(e) => {
+            return InputDocument {
+                diagnostics: vec![Diagnostic::error("markdown-error", e)],
+                ..Default::default()
+            };
+        }
+    };
+    if let Some(blanked) = &blanked {
+        obsidian::unwrap_callouts(&mut root_node, blanked);
+    }
+    let doc = match DokeBaseParser::parse_document(&root_node, None) {
+        Ok(doc) => doc,
+        Err(e) => {
+            return InputDocument {
+                diagnostics: vec![Diagnostic::error("markdown-error", e.to_string())],
+                ..Default::default()
+            };
+        }
+    };
+
+    // statements share one copy of the body
+    fn statements_to_nodes(
+        stmts: &[DokeStatement],
+        input: &Arc<str>,
+        rich: bool,
+        segmentation: Segmentation,
+    ) -> Vec<DokeNode> {
+        let mut nodes = Vec::new();
+        for stmt in stmts {
+            let spans = match &stmt.statement_position {
+                Some(pos) => segment::segments(segmentation, stmt.node, input, pos),
+                None => vec![Position { start: 0, end: 0 }],
+            };
+            for span in spans {
+                let statement_text = match &stmt.statement_position {
+                    Some(_) => Statement::shared(input, span.start..span.end),
+                    None => Statement::default(),
+                };
+
+                let mut node = match rich && !matches!(stmt.node, Node::Code(_)) {
+                    true => {
+                        let (plain, bbcode) = rich_text::render(stmt.node, input, &span);
+                        let mut node = statement(plain, span);
+                        node.parse_data.insert(
+                            rich_text::DISPLAY_TEXT_KEY.into(),
+                            GodotValue::String(bbcode),
+                        );
+                        node
+                    }
+                    false => statement(statement_text, span),
+                };
+                node.block = block_kind(stmt);
+                nodes.push(node);
+            }
+            // a list after a paragraph follows its last statement
+            if let Some(last) = nodes.last_mut() {
+                last.children = statements_to_nodes(&stmt.children, input, rich, segmentation);
+            }
+        }
+        nodes
+    }
+
+    let mut nodes = statements_to_nodes(&doc.statements, &Arc::from(body), rich_text, segmentation);
+    let fields = match &blanked {
+        Some(blanked) => obsidian::apply(&mut nodes, blanked),
+        None => Vec::new(),
+    };
+    InputDocument {
+        nodes,
+        fields,
+        diagnostics: Vec::new(),
+    }
+}
+
+fn block_kind(stmt: &DokeStatement) -> BlockKind {
+    match stmt.node {
+        Node::Heading(heading) => BlockKind::Heading {
+            depth: heading.depth,
+        },
+        Node::Code(code) => BlockKind::Code {
+            language: code.lang.clone(),
+        },
+        Node::Paragraph(_) if stmt.list_item => BlockKind::ListItem,
+        Node::Paragraph(_) => BlockKind::Paragraph,
+        _ => BlockKind::Other,
+    }
+}
+
+/// `markdown::to_mdast`, turning the panics some inputs cause in it into errors
+pub(crate) fn to_mdast(markdown: &str, options: &ParseOptions) -> Result<Node, String> {
+    match panic::catch_unwind(AssertUnwindSafe(|| markdown::to_mdast(markdown, options))) {
+        Ok(result) => result.map_err(|e| e.to_string()),
+        Err(_) => Err("the markdown parser failed on this document".into()),
+    }
+}
@@ -0,0 +1,83 @@
+// input/plain.rs
+//
+// Plain text (`.txt`), markdown left aside: every non-empty line is a
+// statement, and a line indented more than the one before it is its child.
+// Quick notes, chat logs and generated sentences read as they are.
+
+use std::sync::Arc;
+
+use regex::Regex;
+
+use super::{InputDocument, InputFormat, nest, statement};
+use crate::{Position, Statement};
+
+/// The plain text input format, see `DokePipe::with_input_format`
+#[derive(Debug, Clone, Default)]
+pub struct PlainText {
+    /// Removed from the start of lines, like the time and name of chat messages
+    line_prefix: Option<Regex>,
+}
+
+impl PlainText {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Leave out what matches `pattern` at the start of each line, e.g.
+    /// `\[[\d:]+\] \w+: ` for `[12:03] ana: Deals 5 damage`.
+    /// ```
+    /// use doke::{DokePipe, input::PlainText};
+    ///
+    /// let format = PlainText::new().with_line_prefix(r"\[[\d:]+\] \w+: ").unwrap();
+    /// let doc = DokePipe::new()
+    ///     .with_input_format(format)
+    ///     .run_markdown("[12:03] ana: Deals 5 damage\n[12:04] bo:   to enemies\n");
+    /// assert_eq!(doc.nodes[0].statement, "Deals 5 damage");
+    /// assert_eq!(doc.nodes[0].children[0].statement, "to enemies");
+    /// ```
+    pub fn with_line_prefix(mut self, pattern: &str) -> Result<Self, regex::Error> {
+        self.line_prefix = Some(Regex::new(&format!("^(?:{})", pattern))?);
+        Ok(self)
+    }
+}
+
+impl InputFormat for PlainText {
+    fn extension(&self) -> &'static str {
+        "txt"
+    }
+
+    fn read(&self, body: &str) -> InputDocument {
+        let source: Arc<str> = Arc::from(body);
+        let mut offset = 0;
+        let mut statements = Vec::new();
+        for line in body.split_inclusive('\n') {
+            let start = offset;
+            offset += line.len();
+
+            let prefix = match &self.line_prefix {
+                Some(prefix) => prefix.find(line).map_or(0, |m| m.end()),
+                None => 0,
+            };
+            let text = line[prefix..].trim_end();
+            let content = text.trim_start();
+            if content.is_empty() {
+                continue;
+            }
+            let indent = text[..text.len() - content.len()]
+                .chars()
+                .map(|c| if c == '\t' { 4 } else { 1 })
+                .sum::<usize>();
+            let start = start + prefix + text.len() - content.len();
+            let span = Position {
+                start,
+                end: start + content.len(),
+            };
+            let text = Statement::shared(&source, span.start..span.end);
+            statements.push((indent, statement(text, span)));
+        }
+        InputDocument {
+            nodes: nest(statements),
+            ..Default::default()
+        }
+    }
+}
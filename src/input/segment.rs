@@ -0,0 +1,111 @@
+// input/segment.rs
+//
+// Where a markdown paragraph is cut into statements, see `DokePipe::with_segmentation`.
+// By default a paragraph is one statement. Teams writing prose split it by sentence
+// or by line instead:
+//
+//     Deals 5 damage. Burns the target
+//     for 2 turns.
+//     -> Sentence   "Deals 5 damage." | "Burns the target\nfor 2 turns."
+//     -> Line       "Deals 5 damage. Burns the target" | "for 2 turns."
+//
+// Cuts are only made in plain text, never inside emphasis, links or inline code,
+// so `uses \`a.b\`` stays whole. Headings and code blocks are never cut, and a list
+// following the paragraph belongs to its last statement.
+
+use markdown::mdast::Node;
+
+use crate::Position;
+
+/// How markdown paragraphs and list items are cut into statements
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Segmentation {
+    /// One statement per paragraph
+    #[default]
+    Paragraph,
+    /// One statement per sentence, ending with `.`, `!` or `?` followed by a space
+    Sentence,
+    /// One statement per line, hard breaks included
+    Line,
+}
+
+/// Spans of the statements the paragraph `node` at `span` is cut into
+pub(crate) fn segments(
+    segmentation: Segmentation,
+    node: &Node,
+    source: &str,
+    span: &Position,
+) -> Vec<Position> {
+    if segmentation == Segmentation::Paragraph || !matches!(node, Node::Paragraph(_)) {
+        return vec![span.clone()];
+    }
+    let mut cuts = Vec::new();
+    for child in node.children().into_iter().flatten() {
+        let Some(position) = child.position() else {
+            continue;
+        };
+        let (start, end) = (position.start.offset, position.end.offset);
+        match child {
+            Node::Break(_) if segmentation == Segmentation::Line => cuts.push((start, end)),
+            Node::Text(_) => {
+                let text = source.get(start..end).unwrap_or_default();
+                cuts.extend(
+                    text_cuts(segmentation, text)
+                        .into_iter()
+                        .map(|(a, b)| (start + a, start + b)),
+                );
+            }
+            _ => {}
+        }
+    }
+
+    let mut spans = Vec::new();
+    let mut from = span.start;
+    for (cut_start, cut_end) in cuts.into_iter().chain([(span.end, span.end)]) {
+        if let Some(segment) = trimmed(source, from, cut_start) {
+            spans.push(segment);
+        }
+        from = cut_end;
+    }
+    match spans.is_empty() {
+        true => vec![span.clone()],
+        false => spans,
+    }
+}
+
+/// Byte ranges of `text` between two statements, the terminator stays with its sentence
+pub(crate) fn text_cuts(segmentation: Segmentation, text: &str) -> Vec<(usize, usize)> {
+    let mut cuts = Vec::new();
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        let cut_start = match (segmentation, c) {
+            (Segmentation::Line, '\n') => i,
+            (Segmentation::Sentence, '.' | '!' | '?')
+                if chars.peek().is_some_and(|(_, next)| next.is_whitespace()) =>
+            {
+                i + c.len_utf8()
+            }
+            _ => continue,
+        };
+        let mut cut_end = cut_start;
+        while let Some((j, next)) = chars.peek().copied()
+            && next.is_whitespace()
+        {
+            cut_end = j + next.len_utf8();
+            chars.next();
+        }
+        cuts.push((cut_start, cut_end.max(i + c.len_utf8())));
+    }
+    cuts
+}
+
+/// `start..end` without surrounding whitespace, unless nothing is left
+fn trimmed(source: &str, start: usize, end: usize) -> Option<Position> {
+    let text = source.get(start..end)?;
+    let trimmed_start = text.len() - text.trim_start().len();
+    let trimmed = text.trim();
+    (!trimmed.is_empty()).then(|| Position {
+        start: start + trimmed_start,
+        end: start + trimmed_start + trimmed.len(),
+    })
+}
@@ -1,23 +1,168 @@
 #![allow(dead_code)]
 mod base_parser;
+#[cfg(feature = "bevy")]
+pub mod bevy;
+pub mod ci;
+pub mod codegen;
+pub mod constraints;
+pub mod context;
+pub mod describe;
+pub mod diagnostics;
+pub mod duplicates;
+mod error;
+pub mod export;
+pub mod extensions;
 pub mod file_builder;
+pub mod finalize;
+mod format;
+#[cfg(feature = "fuzzing")]
+pub mod fuzzing;
+pub mod hierarchy;
+pub mod import;
+pub mod input;
+pub mod limits;
+pub mod locale;
+pub mod metrics;
+pub mod migrate;
+pub mod normalization;
+mod numeric;
+pub mod obsidian;
+mod overrides;
 pub mod parsers;
+pub mod profile;
+pub mod rich_text;
+pub mod scaffold;
 pub mod semantic;
+#[cfg(feature = "serde")]
+mod serialize;
+#[cfg(feature = "serve")]
+pub mod serve;
+mod shared;
+#[cfg(feature = "csv")]
+pub mod spreadsheet;
+mod statement;
+pub mod strictness;
+pub mod suppress;
+pub mod template;
+#[cfg(feature = "testing")]
+pub mod testing;
 pub mod utility;
+pub mod workspace;
+mod yaml;
 
-use crate::base_parser::Position;
+pub use crate::base_parser::Position;
+use crate::diagnostics::Diagnostic;
 use crate::semantic::{DokeNodeState, DokeValidate, DokeValidationError};
-use base_parser::{DokeBaseParser, DokeStatement};
+use base_parser::DokeBaseParser;
+pub use error::Error;
+pub use extensions::Extensions;
+use finalize::DokeFinalizer;
+use input::{InputFormat, MarkdownOptions, Segmentation};
+pub use limits::{LongStatements, MatchLimits};
+pub use locale::Locale;
 use markdown::ParseOptions;
-pub use semantic::GodotValue;
-pub use semantic::{DokeNode, DokeOut, DokeParser, Hypo};
+use metrics::MetricsSink;
+pub use normalization::Normalization;
+pub use profile::Profile;
+pub use semantic::{DokeNode, DokeOut, DokeParser, Hypo, PipeError};
+pub use semantic::{GodotValue, MergeStrategy};
+pub use shared::SharedPipe;
+pub use statement::Statement;
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::Arc;
+use std::time::Instant;
+pub use strictness::Strictness;
 
 #[derive(Debug)]
 /// Normalized DokeDocument returned from the pipeline
 pub struct DokeDocument {
     pub nodes: Vec<DokeNode>,
     pub frontmatter: HashMap<String, GodotValue>,
+    /// Document level diagnostics, e.g. about the frontmatter
+    pub diagnostics: Vec<Diagnostic>,
+    /// Diagnostics silenced by `doke-allow` directives, see `suppress`
+    pub suppressed: Vec<Diagnostic>,
+}
+
+impl DokeDocument {
+    /// Document diagnostics followed by the diagnostics of every node, depth-first
+    pub fn collect_diagnostics(&self) -> Vec<&Diagnostic> {
+        fn visit<'a>(node: &'a DokeNode, out: &mut Vec<&'a Diagnostic>) {
+            out.extend(node.diagnostics.iter());
+            for child in &node.children {
+                visit(child, out);
+            }
+            for constituent in node.constituents.values() {
+                visit(constituent, out);
+            }
+        }
+
+        let mut out: Vec<&Diagnostic> = self.diagnostics.iter().collect();
+        for node in &self.nodes {
+            visit(node, &mut out);
+        }
+        out
+    }
+
+    /// Like `DokePipe::validate`, with the span of the statement each value comes from,
+    /// for `ResourceBuilder::build_file_resource_checked`
+    pub fn validate_spanned(&mut self) -> Result<Vec<(GodotValue, Position)>, DokeValidationError> {
+        let results = DokeValidate::validate_roots(&mut self.nodes, &self.frontmatter, None);
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for (result, node) in results.into_iter().zip(&self.nodes) {
+            match result {
+                Ok(Some(value)) => values.push((value, node.span.clone())),
+                Ok(None) => {}
+                Err(e) => errors.push(e),
+            }
+        }
+        match errors.len() {
+            0 => Ok(values),
+            1 => Err(errors.remove(0)),
+            _ => Err(DokeValidationError::MultipleErrors(errors.into())),
+        }
+    }
+
+    /// Like `validate_spanned`, only as strict as `strictness`: when enough statements
+    /// resolve, the values of those that did, the others getting a warning in the
+    /// document's diagnostics
+    pub fn validate_spanned_with(
+        &mut self,
+        strictness: &Strictness,
+    ) -> Result<Vec<(GodotValue, Position)>, DokeValidationError> {
+        let results = DokeValidate::validate_roots(
+            &mut self.nodes,
+            &self.frontmatter,
+            strictness.min_confidence,
+        );
+        // skipped statements count for nothing
+        let skipped = results.iter().filter(|r| matches!(r, Ok(None))).count();
+        let resolved = results.iter().filter(|result| result.is_ok()).count() - skipped;
+        let accepted = strictness.accepts(resolved, results.len() - skipped);
+        let mut values = Vec::new();
+        let mut errors = Vec::new();
+        for (result, node) in results.into_iter().zip(&self.nodes) {
+            match result {
+                Ok(Some(value)) => values.push((value, node.span.clone())),
+                Ok(None) => {}
+                Err(e) if accepted => self.diagnostics.push(
+                    Diagnostic::warning(
+                        strictness::DROPPED_CODE,
+                        format!("left out of the document's values: {}", e),
+                    )
+                    .with_span(node.span.clone()),
+                ),
+                Err(e) => errors.push(e),
+            }
+        }
+        match errors.len() {
+            0 => Ok(values),
+            1 => Err(errors.remove(0)),
+            _ => Err(DokeValidationError::MultipleErrors(errors.into())),
+        }
+    }
 }
 
 /// A pipe of semantic parsers.
@@ -28,14 +173,53 @@ pub struct DokeDocument {
 #[derive(Debug)]
 pub struct DokePipe {
     parsers: Vec<Box<dyn DokeParser + Send + Sync + 'static>>,
-    parse_options: ParseOptions,
+    /// Run on the values after validation, see `finalize_with`
+    finalizers: Vec<Box<dyn DokeFinalizer>>,
+    /// Frontmatter keys parsed as statements, see `with_frontmatter_statements`
+    frontmatter_statements: Vec<(String, Option<String>)>,
+    parse_options: MarkdownOptions,
+    normalization: Normalization,
+    /// Normalized frontmatter key → canonical key
+    key_aliases: HashMap<String, String>,
+    /// Read Obsidian syntax, see `with_obsidian`
+    obsidian: bool,
+    /// Keep emphasis for display, see `with_rich_text`
+    rich_text: bool,
+    /// Where paragraphs are cut into statements, see `with_segmentation`
+    segmentation: Segmentation,
+    /// Format of the documents, markdown when `None`
+    input_format: Option<Box<dyn InputFormat>>,
+    limits: MatchLimits,
+    /// Locale of the documents without one, see `with_locale`
+    locale: Option<Locale>,
+    metrics: Option<Arc<dyn MetricsSink>>,
+    /// How much of a document must resolve, see `with_strictness`
+    strictness: Strictness,
+}
+
+impl Default for DokePipe {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DokePipe {
     pub fn new() -> Self {
         Self {
             parsers: vec![],
-            parse_options: ParseOptions::default(),
+            finalizers: vec![],
+            frontmatter_statements: vec![],
+            parse_options: MarkdownOptions::default(),
+            normalization: Normalization::default(),
+            key_aliases: HashMap::new(),
+            obsidian: false,
+            rich_text: false,
+            segmentation: Segmentation::default(),
+            input_format: None,
+            limits: MatchLimits::default(),
+            locale: None,
+            metrics: None,
+            strictness: Strictness::default(),
         }
     }
     /// Validates the tree to try and produce a value
@@ -57,13 +241,33 @@ impl DokePipe {
     /// This builds a single object from all the parsed nodes,
     /// or collects errors to display.
     pub fn validate(&self, input: &str) -> Result<Vec<GodotValue>, DokeValidationError> {
-        let doc = self.run_markdown(input);
+        let mut doc = self.run_markdown(input);
+        self.validate_document(&mut doc)
+            .map(|values| values.into_iter().map(|(value, _)| value).collect())
+    }
+
+    /// Like `DokeDocument::validate_spanned`, the values going through the
+    /// finalizers of the pipe
+    pub fn validate_document(
+        &self,
+        doc: &mut DokeDocument,
+    ) -> Result<Vec<(GodotValue, Position)>, DokeValidationError> {
+        let strictness = self.strictness.for_document(&doc.frontmatter);
+        let mut values = doc.validate_spanned_with(&strictness)?;
+        for finalizer in &self.finalizers {
+            for (value, _) in values.iter_mut() {
+                finalizer.finalize(value, &doc.frontmatter);
+            }
+        }
+        Ok(values)
+    }
 
-        // Run validator on parsed nodes
-        let mut nodes = doc.nodes;
-        DokeValidate::validate_tree(&mut nodes, &doc.frontmatter)
+    /// Like `validate`, a panicking parser giving `Error::Panic`, see `try_run_markdown`
+    pub fn try_validate(&self, input: &str) -> Result<Vec<GodotValue>, Error> {
+        Ok(error::catch_panic(|| self.validate(input))??)
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn add<P>(mut self, parser: P) -> Self
     where
         P: DokeParser + Send + Sync + 'static,
@@ -72,6 +276,56 @@ impl DokePipe {
         self
     }
 
+    /// Parse the values of the frontmatter `keys` with the parsers of the pipe,
+    /// like statements of the body, replacing them by what they build. A list of
+    /// strings gives a list of values. Grammars declare theirs with
+    /// `frontmatter_statements:` in the dokeconfig, with the type they must build.
+    /// ```
+    /// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Grant:
+    ///   - "Grants +{amount: int} {stat}"
+    /// "#).unwrap();
+    /// let pipe = DokePipe::new().add(parser).with_frontmatter_statements(["on_equip"]);
+    ///
+    /// let doc = pipe.run_markdown("---\non_equip: [\"Grants +2 STR\", \"Grants +1 AGI\"]\n---\n");
+    /// let on_equip = &doc.frontmatter["on_equip"];
+    /// assert_eq!(on_equip.get_path("1/amount"), Some(&GodotValue::Int(1)));
+    /// ```
+    pub fn with_frontmatter_statements<I, S>(mut self, keys: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.frontmatter_statements
+            .extend(keys.into_iter().map(|key| (key.into(), None)));
+        self
+    }
+
+    /// Transform the values of documents after validation, before they are built,
+    /// see `finalize`
+    /// ```
+    /// use doke::{DokePipe, GodotValue, finalize::StripFields, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let pipe = DokePipe::new()
+    ///     .add(parser)
+    ///     .finalize_with(StripFields::new(["damage"]));
+    /// let values = pipe.validate("Deals 5 damage").unwrap();
+    /// assert_eq!(values[0].get_path("damage"), None);
+    /// ```
+    pub fn finalize_with<F>(mut self, finalizer: F) -> Self
+    where
+        F: DokeFinalizer + 'static,
+    {
+        self.finalizers.push(Box::new(finalizer));
+        self
+    }
+
     pub fn map<P>(mut self, parser: P) -> Self
     where
         P: DokeParser + Send + Sync + 'static,
@@ -88,84 +342,669 @@ impl DokePipe {
                     self.process(child, frontmatter);
                 }
             }
+
+            fn name(&self) -> &'static str {
+                self.parser.name()
+            }
+
+            fn provides(&self) -> &[&'static str] {
+                self.parser.provides()
+            }
+
+            fn requires(&self) -> &[&'static str] {
+                self.parser.requires()
+            }
+
+            fn frontmatter_statements(&self) -> &[(String, Option<String>)] {
+                self.parser.frontmatter_statements()
+            }
         }
 
         self.parsers.push(Box::new(Mapper { parser }));
         self
     }
 
+    /// The pipe, when each parser comes after the parsers providing the annotations
+    /// it requires (see `DokeParser::requires`). A missing prerequisite would
+    /// otherwise only show as empty results.
+    /// ```
+    /// use std::collections::HashMap;
+    /// use doke::{DokeNode, DokeParser, DokePipe, GodotValue};
+    ///
+    /// #[derive(Debug)]
+    /// struct TagsByType;
+    /// impl DokeParser for TagsByType {
+    ///     fn process(&self, _: &mut DokeNode, _: &HashMap<String, GodotValue>) {}
+    ///     fn requires(&self) -> &[&'static str] {
+    ///         &["abstract_type"]
+    ///     }
+    /// }
+    ///
+    /// let error = DokePipe::new().add(TagsByType).verify().unwrap_err();
+    /// assert_eq!(
+    ///     error.to_string(),
+    ///     "TagsByType requires the 'abstract_type' annotation, which no parser before it provides"
+    /// );
+    /// ```
+    pub fn verify(self) -> Result<Self, PipeError> {
+        let mut provided: Vec<&'static str> = Vec::new();
+        if self.rich_text {
+            provided.push(rich_text::DISPLAY_TEXT_KEY);
+        }
+        if self.obsidian {
+            provided.extend([
+                obsidian::CALLOUT_KEY,
+                obsidian::FIELDS_KEY,
+                obsidian::LINKS_KEY,
+            ]);
+        }
+        for parser in &self.parsers {
+            if let Some(annotation) = parser
+                .requires()
+                .iter()
+                .find(|annotation| !provided.contains(annotation))
+            {
+                return Err(PipeError::MissingPrerequisite {
+                    parser: parser.name(),
+                    annotation,
+                });
+            }
+            provided.extend(parser.provides());
+        }
+        Ok(self)
+    }
+
     /// Run pipeline on a Markdown string and return a DokeDocument
+    /// ```
+    /// use doke::{DokePipe, GodotValue};
+    ///
+    /// let doc = DokePipe::new().run_markdown(
+    ///     "---\nbase: &base {damage: 5, speed: 2}\nsword:\n  <<: *base\n  speed: 3\ntier: 1\ntier: 2\n---\n",
+    /// );
+    /// let sword = &doc.frontmatter["sword"];
+    /// assert_eq!(sword.get_path("damage"), Some(&GodotValue::Int(5)));
+    /// assert_eq!(sword.get_path("speed"), Some(&GodotValue::Int(3)));
+    /// // the first of duplicate keys is kept, with a warning
+    /// assert_eq!(doc.frontmatter["tier"], GodotValue::Int(1));
+    /// assert_eq!(doc.diagnostics[0].code, "frontmatter-duplicate-key");
+    /// ```
     pub fn run_markdown(&self, input: &str) -> DokeDocument {
-        // Extract frontmatter and remaining markdown
-        let (frontmatter_str, markdown_str) = extract_frontmatter(input);
+        let started = Instant::now();
+        let doc = self.read_markdown(input);
+        self.run_parsers(doc, started)
+    }
+
+    /// Like `run_markdown`, a panicking parser giving `Error::Panic` instead of
+    /// unwinding into the caller. The panic hook still runs (and prints by default).
+    /// ```
+    /// use std::collections::HashMap;
+    /// use doke::{DokeNode, DokeParser, DokePipe, GodotValue};
+    ///
+    /// #[derive(Debug)]
+    /// struct Broken;
+    /// impl DokeParser for Broken {
+    ///     fn process(&self, _: &mut DokeNode, _: &HashMap<String, GodotValue>) {
+    ///         panic!("unfinished");
+    ///     }
+    /// }
+    ///
+    /// let pipe = DokePipe::new().add(Broken);
+    /// # std::panic::set_hook(Box::new(|_| {}));
+    /// let error = pipe.try_run_markdown("Deals 5 damage").unwrap_err();
+    /// assert_eq!(error.code(), "panic");
+    /// assert_eq!(error.to_string(), "a parser panicked: unfinished");
+    /// ```
+    pub fn try_run_markdown(&self, input: &str) -> Result<DokeDocument, Error> {
+        error::catch_panic(|| self.run_markdown(input))
+    }
+
+    /// Run pipeline on a Markdown string like `run_markdown`, timing each parser
+    /// and the slowest statements, to find what makes builds slow (see `profile`).
+    /// Statements are parsed a second time for this, keep it out of normal builds.
+    /// ```
+    /// use doke::{DokePipe, parsers::FrontmatterTemplateParser};
+    ///
+    /// let pipe = DokePipe::new().add(FrontmatterTemplateParser);
+    /// let profile = pipe.run_profiled("Deals 5 damage\n\nHeals 2");
+    /// assert_eq!(profile.document.nodes.len(), 2);
+    /// assert_eq!(profile.parsers[0].parser, "FrontmatterTemplateParser");
+    /// assert_eq!(profile.hot_spots.len(), 2);
+    /// println!("{}", profile);
+    /// ```
+    pub fn run_profiled(&self, input: &str) -> Profile {
+        let started = Instant::now();
+        let doc = self.read_markdown(input);
+        let read = started.elapsed();
+        let (document, parsers) = self.run_parsers_timed(doc, started);
+
+        let mut again = self.read_markdown(input);
+        let hot_spots = profile::hot_spots(&mut again.nodes, &self.parsers, &again.frontmatter);
+        Profile {
+            document,
+            read,
+            parsers,
+            hot_spots,
+        }
+    }
 
-        // Convert markdown into MD AST using configured ParseOptions
-        let root_node = markdown::to_mdast(&markdown_str, &self.parse_options).unwrap();
+    /// Run pipeline on a document patched by override files
+    /// (e.g. `my_item.override.md` for a platform or a difficulty).
+    ///
+    /// Override frontmatter is merged over the base's, and statements are merged
+    /// before any parser runs, so the result builds into a single resource:
+    /// - a statement anchored with `{#id}` replaces the base statement with the same anchor
+    /// - other statements under a heading replace the statements of the base section
+    ///   with the same heading path (`Effects / Fire`), sections missing from the base are added
+    /// - statements before the first heading replace the base's
+    ///
+    /// ```
+    /// use doke::DokePipe;
+    ///
+    /// let base = "Deals 5 damage {#dmg}\n\n## Hard\n\nSlow attacks\n";
+    /// let hard = "Deals 9 damage {#dmg}\n";
+    /// let doc = DokePipe::new().run_markdown_with_overrides(base, &[hard]);
+    /// assert_eq!(doc.nodes[0].statement, "Deals 9 damage {#dmg}");
+    /// assert_eq!(doc.nodes.len(), 3);
+    /// ```
+    pub fn run_markdown_with_overrides(&self, input: &str, overrides: &[&str]) -> DokeDocument {
+        let started = Instant::now();
+        let mut doc = self.read_markdown(input);
+        for (i, text) in overrides.iter().enumerate() {
+            let mut patch = self.read_markdown(text);
+            overrides::mark_source(&mut patch.nodes, i);
+            overrides::merge_frontmatter(&mut doc.frontmatter, patch.frontmatter);
+            doc.diagnostics.extend(patch.diagnostics);
+            overrides::merge_nodes(&mut doc.nodes, patch.nodes);
+        }
+        self.run_parsers(doc, started)
+    }
 
-        let doc = DokeBaseParser::parse_document(&root_node, frontmatter_str).unwrap();
+    /// Run the pipeline on the document the template `source` renders for `values`,
+    /// see `template::instantiate`
+    pub fn run_template(
+        &self,
+        source: &str,
+        values: &[&str],
+    ) -> Result<DokeDocument, template::TemplateError> {
+        Ok(self.run_markdown(&template::instantiate(source, values)?))
+    }
+
+    /// Like `validate`, on a document patched by override files
+    pub fn validate_with_overrides(
+        &self,
+        input: &str,
+        overrides: &[&str],
+    ) -> Result<Vec<GodotValue>, DokeValidationError> {
+        let mut doc = self.run_markdown_with_overrides(input, overrides);
+        self.validate_document(&mut doc)
+            .map(|values| values.into_iter().map(|(value, _)| value).collect())
+    }
+
+    /// Like `run_markdown_with_overrides`, a panicking parser giving `Error::Panic`
+    pub fn try_run_markdown_with_overrides(
+        &self,
+        input: &str,
+        overrides: &[&str],
+    ) -> Result<DokeDocument, Error> {
+        error::catch_panic(|| self.run_markdown_with_overrides(input, overrides))
+    }
+
+    /// Turn a document into unresolved nodes and its frontmatter
+    fn read_markdown(&self, input: &str) -> DokeDocument {
+        // Extract frontmatter and remaining body
+        let (frontmatter_str, body) = extract_frontmatter(input);
+        let frontmatter_str = frontmatter_str.map(|fm| self.normalization.unicode(fm));
+        let read = match &self.input_format {
+            Some(format) => format.read(body),
+            None => input::read_markdown(
+                body,
+                &self.parse_options.parse_options(),
+                self.obsidian,
+                self.rich_text,
+                self.segmentation,
+            ),
+        };
 
         // Convert frontmatter YAML → normalized HashMap<String, GodotValue>
         let mut fm_map = HashMap::new();
-        if let Some(fm) = &doc.frontmatter {
-            if let yaml_rust2::Yaml::Hash(h) = fm {
-                for (k, v) in h {
-                    if let yaml_rust2::Yaml::String(s) = k {
-                        let key = normalize_key(s);
-                        fm_map.insert(key, yaml_value_to_godot(v.clone()));
-                    }
+        let mut diagnostics = read.diagnostics;
+        let loaded = match frontmatter_str.as_deref().map(yaml::load) {
+            Some(Ok((yaml, warnings))) => {
+                diagnostics.extend(warnings);
+                Some(yaml)
+            }
+            Some(Err(e)) => {
+                diagnostics.push(Diagnostic::warning(
+                    "frontmatter-invalid-yaml",
+                    format!("the frontmatter isn't valid YAML, ignored: {}", e),
+                ));
+                None
+            }
+            None => None,
+        };
+        if let Some(yaml_rust2::Yaml::Hash(h)) = loaded {
+            for (k, v) in h {
+                if let yaml_rust2::Yaml::String(s) = k {
+                    let key = self.frontmatter_key(&s);
+                    let value = yaml_value_to_godot(v, &key, self, &mut diagnostics);
+                    insert_frontmatter(&mut fm_map, &s, key, value, &mut diagnostics);
                 }
             }
         }
 
-        fn statements_to_nodes(stmts: &[DokeStatement], input: &str) -> Vec<DokeNode> {
-            stmts
-                .iter()
-                .map(|stmt| {
-                    let statement_position = stmt
-                        .statement_position
-                        .clone()
-                        .unwrap_or(Position { start: 0, end: 0 });
-                    let statement_text = if let Some(pos) = &stmt.statement_position {
-                        // Safely slice the input string using byte offsets
-                        input
-                            .get(pos.start..pos.end)
-                            .unwrap_or_default()
-                            .to_string()
-                    } else {
-                        "".to_string()
-                    };
+        for (raw_key, value) in read.fields {
+            let key = self.frontmatter_key(&raw_key);
+            match fm_map.entry(key) {
+                Entry::Occupied(_) => diagnostics.push(Diagnostic::warning(
+                    "duplicate-field",
+                    format!("field '{}' is already in the frontmatter, ignored", raw_key),
+                )),
+                Entry::Vacant(entry) => {
+                    entry.insert(value);
+                }
+            }
+        }
+        let mut nodes = read.nodes;
+        suppress::read_directives(&mut nodes, body);
+        self.normalization.apply(&mut nodes);
+        self.limits.apply(&mut nodes);
+        if let Some(locale) = &self.locale {
+            locale::apply(locale, &mut nodes);
+        }
 
-                    DokeNode {
-                        statement: statement_text,
-                        state: DokeNodeState::Unresolved,
-                        children: statements_to_nodes(&stmt.children, input),
-                        parse_data: HashMap::new(),
-                        constituents: HashMap::new(),
-                        span: statement_position,
-                    }
-                })
-                .collect()
+        DokeDocument {
+            nodes,
+            frontmatter: fm_map,
+            diagnostics,
+            suppressed: Vec::new(),
         }
+    }
 
-        let mut nodes = statements_to_nodes(&doc.statements, markdown_str);
+    fn run_parsers(&self, doc: DokeDocument, started: Instant) -> DokeDocument {
+        self.run_parsers_timed(doc, started).0
+    }
 
+    /// `run_parsers`, also returning the time each parser took
+    fn run_parsers_timed(
+        &self,
+        mut doc: DokeDocument,
+        started: Instant,
+    ) -> (DokeDocument, Vec<profile::ParserTime>) {
+        let mut times = Vec::with_capacity(self.parsers.len());
+        self.parse_frontmatter_statements(&mut doc);
         for parser in &self.parsers {
-            for node in nodes.iter_mut() {
-                parser.process(node, &fm_map);
+            let parser_started = Instant::now();
+            parser.process_document(&mut doc.nodes, &doc.frontmatter);
+            let time = parser_started.elapsed();
+            if let Some(metrics) = &self.metrics {
+                metrics.time(metrics::PARSER_SECONDS, &[("parser", parser.name())], time);
             }
+            times.push(profile::ParserTime {
+                parser: parser.name(),
+                time,
+            });
+        }
+        suppress::apply(&mut doc);
+        if let Some(metrics) = &self.metrics {
+            metrics.time(metrics::DOCUMENT_SECONDS, &[], started.elapsed());
+            metrics.count(metrics::DOCUMENTS, &[], 1);
+            let (statements, unresolved) = metrics::statement_counts(&doc.nodes);
+            metrics.count(metrics::STATEMENTS, &[], statements);
+            metrics.count(metrics::UNRESOLVED, &[], unresolved);
         }
+        (doc, times)
+    }
 
-        DokeDocument {
-            nodes,
-            frontmatter: fm_map,
+    /// Replace the frontmatter values that are statements by the values they build
+    fn parse_frontmatter_statements(&self, doc: &mut DokeDocument) {
+        let keys = self.frontmatter_statements.iter().chain(
+            self.parsers
+                .iter()
+                .flat_map(|parser| parser.frontmatter_statements()),
+        );
+        for (key, ty) in keys {
+            let key = self.frontmatter_key(key);
+            let (texts, is_list): (Vec<&str>, bool) = match doc.frontmatter.get(&key) {
+                Some(GodotValue::String(text)) => (vec![text.as_str()], false),
+                Some(GodotValue::Array(items)) => {
+                    let texts = items
+                        .iter()
+                        .map(|item| match item {
+                            GodotValue::String(text) => Some(text.as_str()),
+                            _ => None,
+                        })
+                        .collect::<Option<_>>();
+                    match texts {
+                        Some(texts) => (texts, true),
+                        // already built, or not statements
+                        None => continue,
+                    }
+                }
+                _ => continue,
+            };
+            let mut nodes: Vec<DokeNode> = texts
+                .into_iter()
+                .map(|text| input::statement(text.to_string(), Position { start: 0, end: 0 }))
+                .collect();
+            for parser in &self.parsers {
+                parser.process_document(&mut nodes, &doc.frontmatter);
+            }
+            let values = DokeValidate::validate_tree(&mut nodes, &doc.frontmatter)
+                .map_err(|e| e.to_string())
+                .and_then(|values| match ty {
+                    Some(ty) => values
+                        .into_iter()
+                        .map(|value| match &value {
+                            GodotValue::Resource {
+                                type_name,
+                                abstract_type_name,
+                                ..
+                            } if type_name == ty || abstract_type_name == ty => Ok(value),
+                            GodotValue::Resource {
+                                type_name,
+                                abstract_type_name,
+                                ..
+                            } => Err(format!(
+                                "expected a {}, got a {} ({})",
+                                ty, type_name, abstract_type_name
+                            )),
+                            other => Err(format!("expected a {}, got {}", ty, other)),
+                        })
+                        .collect(),
+                    None => Ok(values),
+                });
+            match values {
+                Ok(mut values) => {
+                    let value = match is_list {
+                        true => GodotValue::Array(values),
+                        false => values.pop().unwrap_or(GodotValue::Nil),
+                    };
+                    doc.frontmatter.insert(key, value);
+                }
+                Err(e) => doc.diagnostics.push(Diagnostic::error(
+                    "frontmatter-statement",
+                    format!("frontmatter '{}': {}", key, e),
+                )),
+            }
         }
     }
 
-    /// Optional: allow setting parse options in the future
+    /// Rewrite a document into canonical form, for `doke fmt`:
+    /// statements that match a phrase are spelled like the phrase (spacing, capitalization),
+    /// bullets use `-`, headings don't skip levels and the pipeline's normalization is applied.
+    /// Everything else in the document is left as written.
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let pipe = DokePipe::new().add(parser);
+    /// assert_eq!(pipe.format("* deals   5 DAMAGE"), "- deals   5 DAMAGE");
+    /// assert_eq!(pipe.format("* Deals   5 damage"), "- Deals 5 damage");
+    /// ```
+    pub fn format(&self, input: &str) -> String {
+        let (_, markdown_str) = extract_frontmatter(input);
+        let spellings = format::canonical_spellings(&self.run_markdown(input).nodes);
+
+        let Ok(root_node) = input::to_mdast(markdown_str, &self.parse_options.parse_options())
+        else {
+            return input.to_string();
+        };
+        let Ok(doc) = DokeBaseParser::parse_document(&root_node, None) else {
+            return input.to_string();
+        };
+        let body = format::format_markdown(
+            markdown_str,
+            &doc.statements,
+            &spellings,
+            &self.normalization,
+        );
+
+        // markdown_str is the tail of input, after the frontmatter
+        let header = &input[..input.len() - markdown_str.len()];
+        format!("{}{}", header, body)
+    }
+
+    /// Like `format`, a panicking parser giving `Error::Panic`
+    pub fn try_format(&self, input: &str) -> Result<String, Error> {
+        error::catch_panic(|| self.format(input))
+    }
+
+    /// Optional: allow setting parse options in the future.
+    /// The MDX expression and ESM hooks are ignored, pipes must stay `Send + Sync`.
     pub fn with_parse_options(mut self, opts: ParseOptions) -> Self {
-        self.parse_options = opts;
+        self.parse_options = opts.into();
+        self
+    }
+
+    /// Clean up the typography of statements before any parser runs,
+    /// and choose the trailing characters ignored when matching them.
+    /// ```
+    /// use doke::{DokePipe, Normalization};
+    ///
+    /// let pipe = DokePipe::new().with_normalization(Normalization::typographic());
+    /// let doc = pipe.run_markdown("Deals 5\u{a0}damage \u{2013} twice!");
+    /// assert_eq!(doc.nodes[0].statement, "Deals 5 damage - twice!");
+    /// ```
+    pub fn with_normalization(mut self, normalization: Normalization) -> Self {
+        self.normalization = normalization;
+        self
+    }
+
+    /// Report documents parsed and time spent per parser to `sink`, for
+    /// long-running services. See `metrics` for what is reported.
+    /// ```
+    /// use std::sync::Arc;
+    /// use doke::{DokePipe, metrics::MetricsSink};
+    ///
+    /// #[derive(Debug)]
+    /// struct Log;
+    ///
+    /// impl MetricsSink for Log {
+    ///     fn count(&self, name: &'static str, _: &[(&'static str, &str)], value: u64) {
+    ///         println!("{} +{}", name, value);
+    ///     }
+    ///     fn time(&self, name: &'static str, labels: &[(&'static str, &str)], duration: std::time::Duration) {
+    ///         println!("{} {:?} {:?}", name, labels, duration);
+    ///     }
+    /// }
+    ///
+    /// let pipe = DokePipe::new().with_metrics(Arc::new(Log));
+    /// pipe.run_markdown("Deals 5 damage");
+    /// ```
+    pub fn with_metrics(mut self, sink: Arc<dyn MetricsSink>) -> Self {
+        self.metrics = Some(sink);
         self
     }
+
+    /// Keep pathological statements from hanging the pipeline: statements past
+    /// `max_statement_len` are left unparsed (or split, or kept as prose, see
+    /// `LongStatements`), and sentence grammars give up on a statement after `timeout`,
+    /// both with a warning diagnostic.
+    /// ```
+    /// use doke::{DokePipe, LongStatements, MatchLimits};
+    ///
+    /// let pipe = DokePipe::new().with_match_limits(MatchLimits::default().with_max_statement_len(10));
+    /// let doc = pipe.run_markdown("Deals 5 damage to every enemy");
+    /// assert_eq!(doc.nodes[0].diagnostics[0].code, "statement-too-long");
+    ///
+    /// let pipe = DokePipe::new().with_match_limits(
+    ///     MatchLimits::default()
+    ///         .with_max_statement_len(20)
+    ///         .with_long_statements(LongStatements::Split),
+    /// );
+    /// let doc = pipe.run_markdown("Deals 5 damage. Burns the target for 2 turns.");
+    /// assert_eq!(doc.nodes[0].statement, "Deals 5 damage.");
+    /// assert_eq!(doc.nodes[1].statement, "Burns the target for");
+    /// assert_eq!(doc.nodes[2].statement, "2 turns.");
+    /// ```
+    pub fn with_match_limits(mut self, limits: MatchLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Validate documents even when some of their statements don't resolve, or refuse
+    /// unsure hypotheses, see the `strictness` module. Documents override it with
+    /// `min_resolved` and `min_confidence` frontmatter keys.
+    /// ```
+    /// use doke::{DokePipe, Strictness, parsers::SentenceParser};
+    ///
+    /// let grammar = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let notes = "Deals 5 damage\n\nDeals 3 damage\n\nShould it stun too?";
+    ///
+    /// let strict = DokePipe::new().add(grammar.clone());
+    /// assert!(strict.validate(notes).is_err());
+    ///
+    /// let lax = DokePipe::new()
+    ///     .add(grammar)
+    ///     .with_strictness(Strictness::default().with_min_resolved(0.6));
+    /// assert_eq!(lax.validate(notes).unwrap().len(), 2);
+    /// assert!(lax.validate(&format!("---\nmin_resolved: 100%\n---\n{}", notes)).is_err());
+    /// ```
+    pub fn with_strictness(mut self, strictness: Strictness) -> Self {
+        self.strictness = strictness;
+        self
+    }
+
+    /// Write numbers and lists of `Format` sentences for `locale`, in the documents
+    /// without a `locale` frontmatter key, see the `locale` module.
+    /// ```
+    /// use doke::{DokePipe, GodotValue, Locale, parsers::SentenceParser};
+    ///
+    /// let grammar = SentenceParser::from_yaml("Text".into(), r#"
+    /// Gold:
+    ///   - "Costs {amount: float} gold": f"{amount} gold coins"
+    /// "#).unwrap();
+    /// let pipe = DokePipe::new()
+    ///     .add(grammar)
+    ///     .with_locale(Locale::from_tag("de").unwrap());
+    /// let values = pipe.validate("Costs 1250.5 gold").unwrap();
+    /// assert_eq!(values[0], GodotValue::String("1.250,5 gold coins".into()));
+    /// ```
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = Some(locale);
+        self
+    }
+
+    /// Read Obsidian syntax, for design docs kept in a vault:
+    /// `%%comments%%` are left out, callouts are read as statements, wikilinks match
+    /// `ref` parameters through their target and dataview fields become frontmatter keys
+    /// or `parse_data`, see the `obsidian` module.
+    /// ```
+    /// use doke::{DokePipe, GodotValue, obsidian};
+    ///
+    /// let pipe = DokePipe::new().with_obsidian(true);
+    /// let doc = pipe.run_markdown("rarity:: rare\n\n> [!tip] Combo\n> Upgrades into [[axe|the Axe]] %%todo%%");
+    /// assert_eq!(doc.frontmatter.get("rarity"), Some(&GodotValue::String("rare".into())));
+    /// assert_eq!(doc.nodes[0].statement, "Upgrades into [[axe]]");
+    /// assert_eq!(
+    ///     doc.nodes[0].parse_data.get(obsidian::CALLOUT_KEY),
+    ///     Some(&GodotValue::String("tip".into()))
+    /// );
+    /// ```
+    pub fn with_obsidian(mut self, enabled: bool) -> Self {
+        self.obsidian = enabled;
+        self
+    }
+
+    /// Match statements without their emphasis markers (`**`, `*`, `~~`), and keep the
+    /// author's formatting as BBCode for Godot's RichTextLabel, in `parse_data` and in the
+    /// `display_text` field of sentence results, see the `rich_text` module.
+    /// ```
+    /// use doke::{DokePipe, GodotValue, rich_text};
+    ///
+    /// let pipe = DokePipe::new().with_rich_text(true);
+    /// let doc = pipe.run_markdown("Deals **5** damage to *burning* enemies");
+    /// assert_eq!(doc.nodes[0].statement, "Deals 5 damage to burning enemies");
+    /// assert_eq!(
+    ///     doc.nodes[0].parse_data.get(rich_text::DISPLAY_TEXT_KEY),
+    ///     Some(&GodotValue::String("Deals [b]5[/b] damage to [i]burning[/i] enemies".into()))
+    /// );
+    /// ```
+    pub fn with_rich_text(mut self, enabled: bool) -> Self {
+        self.rich_text = enabled;
+        self
+    }
+
+    /// Make a statement of each sentence or each line of a paragraph, instead of the whole
+    /// paragraph. Spans point at each part, and a list after the paragraph belongs to its
+    /// last part. Headings and code blocks stay whole, see `input::Segmentation`.
+    /// ```
+    /// use doke::{DokePipe, input::Segmentation};
+    ///
+    /// let pipe = DokePipe::new().with_segmentation(Segmentation::Sentence);
+    /// let doc = pipe.run_markdown("Deals 5 damage. Burns for 2 turns!\n- Spreads");
+    /// assert_eq!(doc.nodes[0].statement, "Deals 5 damage.");
+    /// assert_eq!(doc.nodes[1].statement, "Burns for 2 turns!");
+    /// assert_eq!((doc.nodes[1].span.start, doc.nodes[1].span.end), (16, 34));
+    /// assert_eq!(doc.nodes[1].children[0].statement, "Spreads");
+    /// ```
+    pub fn with_segmentation(mut self, segmentation: Segmentation) -> Self {
+        self.segmentation = segmentation;
+        self
+    }
+
+    /// Read documents written in another format than markdown.
+    /// The YAML frontmatter is the same in every format, and `format`,
+    /// `with_obsidian`, `with_rich_text` and `with_segmentation` only apply to markdown.
+    /// ```
+    /// use doke::{DokePipe, input::AsciiDoc};
+    ///
+    /// let pipe = DokePipe::new().with_input_format(AsciiDoc);
+    /// let doc = pipe.run_markdown("== Effects\n\nDeals 5 damage\n* Burns for 2 turns\n");
+    /// assert_eq!(doc.nodes[0].statement, "## Effects");
+    /// assert_eq!(doc.nodes[1].children[0].statement, "Burns for 2 turns");
+    /// ```
+    pub fn with_input_format(mut self, format: impl InputFormat + 'static) -> Self {
+        self.input_format = Some(Box::new(format));
+        self
+    }
+
+    /// Extension of the documents the pipe reads, without the dot (`md`)
+    pub fn extension(&self) -> &'static str {
+        match &self.input_format {
+            Some(format) => format.extension(),
+            None => "md",
+        }
+    }
+
+    /// Rename frontmatter keys written in other languages to the keys templates
+    /// and builders expect, e.g. `("prix", "price")`. Applies to nested keys too.
+    /// ```
+    /// use doke::{DokePipe, GodotValue};
+    ///
+    /// let pipe = DokePipe::new().with_key_aliases([("prix", "price"), ("points de vie", "health")]);
+    /// let doc = pipe.run_markdown("---\nPrix: 12\n---\nA sword");
+    /// assert_eq!(doc.frontmatter.get("price"), Some(&GodotValue::Int(12)));
+    /// ```
+    pub fn with_key_aliases<I, K, V>(mut self, aliases: I) -> Self
+    where
+        I: IntoIterator<Item = (K, V)>,
+        K: AsRef<str>,
+        V: AsRef<str>,
+    {
+        for (alias, canonical) in aliases {
+            self.key_aliases.insert(
+                normalize_key(&self.normalization.unicode(alias.as_ref())),
+                normalize_key(canonical.as_ref()),
+            );
+        }
+        self
+    }
+
+    /// Normalized and de-aliased frontmatter key
+    fn frontmatter_key(&self, key: &str) -> String {
+        let key = normalize_key(key);
+        match self.key_aliases.get(&key) {
+            Some(canonical) => canonical.clone(),
+            None => key,
+        }
+    }
 }
 
 /// Normalize frontmatter keys: lowercase + spaces → _
@@ -173,6 +1012,27 @@ fn normalize_key(key: &str) -> String {
     key.trim().to_lowercase().replace(' ', "_")
 }
 
+/// Insert a frontmatter entry, warning when two keys end up with the same name
+fn insert_frontmatter(
+    map: &mut HashMap<String, GodotValue>,
+    raw_key: &str,
+    key: String,
+    value: GodotValue,
+    diagnostics: &mut Vec<Diagnostic>,
+) {
+    if map.contains_key(&key) {
+        diagnostics.push(Diagnostic::warning(
+            "frontmatter-duplicate-key",
+            format!(
+                "frontmatter '{}' is another name for '{}', ignored",
+                raw_key, key
+            ),
+        ));
+        return;
+    }
+    map.insert(key, value);
+}
+
 /// Extract frontmatter from a markdown string.
 /// Returns (Some(frontmatter_str), rest_of_markdown) if frontmatter exists.
 fn extract_frontmatter(input: &str) -> (Option<&str>, &str) {
@@ -184,10 +1044,7 @@ fn extract_frontmatter(input: &str) -> (Option<&str>, &str) {
     // Second part is frontmatter
     if let Some(fm) = parts.next() {
         // Third part is the rest of the markdown
-        let rest = parts
-            .next()
-            .unwrap_or("")
-            .trim_start_matches(|c| c == '\r' || c == '\n');
+        let rest = parts.next().unwrap_or("").trim_start_matches(['\r', '\n']);
         return (Some(fm.trim()), rest);
     }
 
@@ -196,20 +1053,51 @@ fn extract_frontmatter(input: &str) -> (Option<&str>, &str) {
 }
 
 /// Convert yaml_rust2::Yaml → GodotValue
-fn yaml_value_to_godot(y: yaml_rust2::Yaml) -> GodotValue {
+///
+/// `path` is the `/` separated frontmatter key, used in numeric diagnostics.
+/// Keys are normalized and de-aliased by `pipe`.
+fn yaml_value_to_godot(
+    y: yaml_rust2::Yaml,
+    path: &str,
+    pipe: &DokePipe,
+    diagnostics: &mut Vec<Diagnostic>,
+) -> GodotValue {
     match y {
         yaml_rust2::Yaml::String(s) => GodotValue::String(s),
         yaml_rust2::Yaml::Integer(i) => GodotValue::Int(i),
-        yaml_rust2::Yaml::Real(f) => GodotValue::Float(f.parse().unwrap_or(0.0)),
+        yaml_rust2::Yaml::Real(f) => match numeric::parse_yaml_real(&f) {
+            Ok(parsed) => {
+                if let Some(warning) = parsed.warning {
+                    diagnostics.push(Diagnostic::warning(
+                        "numeric-precision",
+                        format!("frontmatter '{}': {}", path, warning),
+                    ));
+                }
+                parsed.value
+            }
+            Err(e) => {
+                diagnostics.push(Diagnostic::error(
+                    "numeric-overflow",
+                    format!("frontmatter '{}': {}", path, e),
+                ));
+                GodotValue::Nil
+            }
+        },
         yaml_rust2::Yaml::Boolean(b) => GodotValue::Bool(b),
-        yaml_rust2::Yaml::Array(a) => {
-            GodotValue::Array(a.into_iter().map(yaml_value_to_godot).collect())
-        }
+        yaml_rust2::Yaml::Array(a) => GodotValue::Array(
+            a.into_iter()
+                .enumerate()
+                .map(|(i, v)| yaml_value_to_godot(v, &format!("{}/{}", path, i), pipe, diagnostics))
+                .collect(),
+        ),
         yaml_rust2::Yaml::Hash(h) => {
             let mut map = HashMap::new();
             for (k, v) in h {
                 if let yaml_rust2::Yaml::String(s) = k {
-                    map.insert(normalize_key(&s), yaml_value_to_godot(v));
+                    let key = pipe.frontmatter_key(&s);
+                    let value =
+                        yaml_value_to_godot(v, &format!("{}/{}", path, key), pipe, diagnostics);
+                    insert_frontmatter(&mut map, &s, key, value, diagnostics);
                 }
             }
             GodotValue::Dict(map)
@@ -0,0 +1,279 @@
+// limits.rs
+//
+// Guards against pathological statements, so one bad paragraph can't hang an
+// editor plugin or a validation service: statements past a length are left
+// unparsed, and phrase matching gives up on a statement after a time budget.
+//
+// Documents mixing rules with design essays can have long statements split
+// between sentences instead, or kept as prose that nothing parses:
+//
+//     MatchLimits::default()
+//         .with_max_statement_len(500)
+//         .with_long_statements(LongStatements::Split)
+//
+// The budget reaches parsers through `parse_data`, like the normalization settings.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::time::{Duration, Instant};
+
+use crate::diagnostics::Diagnostic;
+use crate::input::Segmentation;
+use crate::input::segment::text_cuts;
+use crate::rich_text::DISPLAY_TEXT_KEY;
+use crate::semantic::DokeNodeState;
+use crate::{DokeNode, Extensions, GodotValue, Position};
+
+/// `parse_data` key holding the matching budget of a statement, in milliseconds
+pub const TIMEOUT_KEY: &str = "doke_match_timeout_ms";
+
+/// Diagnostic code of statements whose matching ran out of time
+pub const TIMEOUT_CODE: &str = "match-timeout";
+
+/// Diagnostic code of statements past `MatchLimits::max_statement_len`
+pub const TOO_LONG_CODE: &str = "statement-too-long";
+
+/// What becomes of statements past `MatchLimits::max_statement_len`
+/// ```
+/// use doke::{DokePipe, LongStatements, MatchLimits, parsers::SentenceParser};
+///
+/// let grammar = "Effect:\n  - \"Deals {damage: int} damage\"\n";
+/// let pipe = |policy| {
+///     let limits = MatchLimits::default()
+///         .with_max_statement_len(30)
+///         .with_long_statements(policy);
+///     DokePipe::new()
+///         .add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap())
+///         .with_match_limits(limits)
+/// };
+/// let text = "Long ago the smiths of the north forged this blade in dragon fire.\n- Deals 5 damage\n";
+///
+/// assert!(pipe(LongStatements::Reject).validate(text).is_err());
+///
+/// let prose = pipe(LongStatements::Prose);
+/// let doc = prose.run_markdown(text);
+/// assert_eq!(doc.nodes[0].diagnostics[0].code, "statement-too-long");
+/// assert_eq!(doc.nodes[1].statement, "Deals 5 damage");
+/// assert_eq!(prose.validate(text).unwrap().len(), 1);
+///
+/// // a paragraph of non-breaking spaces has nothing to split
+/// let blank = format!("Deals 5 damage\n\n{}\n", "\u{2003}".repeat(12));
+/// let doc = pipe(LongStatements::Split).run_markdown(&blank);
+/// assert_eq!(doc.nodes.len(), 2);
+/// assert!(doc.nodes[1].diagnostics.is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LongStatements {
+    /// Left unparsed, failing validation
+    #[default]
+    Reject,
+    /// Cut between sentences, or between words for endless sentences, into statements
+    /// under the limit. The list after the statement belongs to its last part.
+    Split,
+    /// Kept as prose: skipped by parsers and left out by validation.
+    /// The list after the statement follows it instead.
+    Prose,
+}
+
+/// Limits a `DokePipe` puts on statements, none by default
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MatchLimits {
+    /// Statements longer than this many bytes aren't parsed
+    pub max_statement_len: Option<usize>,
+    /// What becomes of the longer ones
+    pub long_statements: LongStatements,
+    /// Time a sentence grammar may spend matching one statement
+    pub timeout: Option<Duration>,
+}
+
+impl MatchLimits {
+    pub fn with_max_statement_len(mut self, len: usize) -> Self {
+        self.max_statement_len = Some(len);
+        self
+    }
+
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    pub fn with_long_statements(mut self, policy: LongStatements) -> Self {
+        self.long_statements = policy;
+        self
+    }
+
+    /// Deal with statements that are too long, and record the budget of the others
+    pub(crate) fn apply(&self, nodes: &mut Vec<DokeNode>) {
+        if let Some(max) = self.max_statement_len {
+            *nodes = std::mem::take(nodes)
+                .into_iter()
+                .flat_map(|node| self.limit_length(node, max))
+                .collect();
+        }
+        for node in nodes {
+            if let Some(timeout) = self.timeout {
+                node.parse_data.insert(
+                    TIMEOUT_KEY.into(),
+                    GodotValue::Int(timeout.as_millis().try_into().unwrap_or(i64::MAX)),
+                );
+            }
+            self.apply(&mut node.children);
+        }
+    }
+
+    /// `node`, or its parts when it's split
+    fn limit_length(&self, mut node: DokeNode, max: usize) -> Vec<DokeNode> {
+        let len = node.statement.len();
+        // `doke-skip` already left it out
+        if len <= max || matches!(node.state, DokeNodeState::Skipped(_)) {
+            return vec![node];
+        }
+        let too_long = |outcome: &str| {
+            Diagnostic::warning(
+                TOO_LONG_CODE,
+                format!(
+                    "statement is {} bytes long, past the limit of {}, {}",
+                    len, max, outcome
+                ),
+            )
+            .with_span(node.span.clone())
+        };
+        match self.long_statements {
+            LongStatements::Reject => {
+                let diagnostic = too_long(
+                    "left unparsed: split it, or mark it as prose with <!-- doke-skip: prose -->",
+                );
+                node.state = DokeNodeState::Error(Box::new(std::io::Error::other(
+                    diagnostic.message.clone(),
+                )));
+                node.diagnostics.push(diagnostic);
+                vec![node]
+            }
+            LongStatements::Prose => {
+                node.diagnostics.push(too_long("kept as prose"));
+                node.state = DokeNodeState::Skipped(format!("prose, {} bytes", len));
+                // a list after an essay is more likely rules than part of it
+                let children = std::mem::take(&mut node.children);
+                std::iter::once(node)
+                    .chain(children.into_iter().flat_map(|c| self.limit_length(c, max)))
+                    .collect()
+            }
+            LongStatements::Split => {
+                let ranges = chunks(&node.statement, max);
+                let diagnostic = too_long(&format!("split into {} statements", ranges.len()));
+                split(node, &ranges, diagnostic)
+            }
+        }
+    }
+}
+
+/// The parts of `node` at `ranges` of its statement, each spanning its text.
+/// Spans are approximate when normalization rewrote the statement.
+/// A statement without any text to split (only whitespace) is kept as it is.
+fn split(mut node: DokeNode, ranges: &[Range<usize>], diagnostic: Diagnostic) -> Vec<DokeNode> {
+    if ranges.is_empty() {
+        return vec![node];
+    }
+    // the rendering of the whole statement
+    node.parse_data.remove(DISPLAY_TEXT_KEY);
+    let mut parts: Vec<DokeNode> = ranges
+        .iter()
+        .map(|range| DokeNode {
+            statement: node.statement.slice_of(&node.statement[range.clone()]),
+            state: DokeNodeState::Unresolved,
+            children: Vec::new(),
+            parse_data: node.parse_data.clone(),
+            extensions: Extensions::new(),
+            constituents: HashMap::new(),
+            span: Position {
+                start: (node.span.start + range.start).min(node.span.end),
+                end: (node.span.start + range.end).min(node.span.end),
+            },
+            diagnostics: Vec::new(),
+            block: node.block.clone(),
+        })
+        .collect();
+    if let Some(first) = parts.first_mut() {
+        first.extensions = std::mem::take(&mut node.extensions);
+        first.diagnostics = std::mem::take(&mut node.diagnostics);
+        first.diagnostics.push(diagnostic);
+    }
+    if let Some(last) = parts.last_mut() {
+        last.children = node.children;
+    }
+    parts
+}
+
+/// Ranges of `text` of at most `max` bytes, cut between sentences when possible,
+/// else between words, else anywhere
+fn chunks(text: &str, max: usize) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut from = 0;
+    for (start, end) in text_cuts(Segmentation::Sentence, text)
+        .into_iter()
+        .chain([(text.len(), text.len())])
+    {
+        sentences.push(from..start);
+        from = end;
+    }
+    let mut chunks: Vec<Range<usize>> = Vec::new();
+    for piece in sentences.into_iter().flat_map(|s| words(text, s, max)) {
+        match chunks.last_mut() {
+            Some(last) if piece.end - last.start <= max => last.end = piece.end,
+            _ => chunks.push(piece),
+        }
+    }
+    chunks.retain(|range| !text[range.clone()].trim().is_empty());
+    chunks
+}
+
+/// `range` of `text` cut between words into pieces of at most `max` bytes
+fn words(text: &str, mut range: Range<usize>, max: usize) -> Vec<Range<usize>> {
+    let mut pieces = Vec::new();
+    while range.end - range.start > max {
+        let mut cut = range.start + max;
+        while !text.is_char_boundary(cut) {
+            cut -= 1;
+        }
+        if cut == range.start {
+            // a character longer than the limit
+            cut += text[cut..].chars().next().map_or(1, char::len_utf8);
+        }
+        let cut = match text[cut..].starts_with(char::is_whitespace) {
+            true => cut,
+            false => text[range.start..cut]
+                .rfind(char::is_whitespace)
+                .filter(|i| *i > 0)
+                .map_or(cut, |i| range.start + i),
+        };
+        pieces.push(range.start..cut);
+        range.start = cut + (text[cut..range.end].len() - text[cut..range.end].trim_start().len());
+    }
+    pieces.push(range);
+    pieces
+}
+
+/// When matching `node` must stop, if its pipeline set a budget
+pub(crate) fn deadline(node: &DokeNode) -> Option<Instant> {
+    match node.parse_data.get(TIMEOUT_KEY) {
+        Some(GodotValue::Int(ms)) => {
+            Instant::now().checked_add(Duration::from_millis((*ms).max(0) as u64))
+        }
+        _ => None,
+    }
+}
+
+/// Report that matching `node` ran out of time, leaving it unresolved
+pub(crate) fn time_out(node: &mut DokeNode) {
+    let message = match node.parse_data.get(TIMEOUT_KEY) {
+        Some(GodotValue::Int(ms)) => format!("matching took more than {}ms, gave up", ms),
+        _ => "matching took too long, gave up".to_string(),
+    };
+    node.diagnostics
+        .push(Diagnostic::warning(TIMEOUT_CODE, message).with_span(node.span.clone()));
+}
+
+/// Whether matching `node` ran out of time
+pub(crate) fn timed_out(node: &DokeNode) -> bool {
+    node.diagnostics.iter().any(|d| d.code == TIMEOUT_CODE)
+}
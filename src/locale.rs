@@ -0,0 +1,155 @@
+// locale.rs
+//
+// Numbers and lists in `Format` sentences written the way players of a locale
+// read them, for description strings generated in their language:
+//
+//     "Deals {damage} damage to {targets}"
+//     en  Deals 1,250.5 damage to goblins, orcs and trolls
+//     fr  Deals 1 250,5 damage to goblins, orcs et trolls
+//
+// A document picks its locale with a `locale` frontmatter key, a `DokePipe` gives
+// one to the documents without it (`with_locale`). Without either, values are
+// written as before. The pipe's locale reaches parsers through `parse_data`,
+// like the matching budget.
+
+use std::collections::HashMap;
+
+use crate::{DokeNode, GodotValue};
+
+/// Frontmatter key choosing the locale of a document (`locale: fr`)
+pub const FRONTMATTER_KEY: &str = "locale";
+
+/// `parse_data` key holding the locale a pipe gives to its documents
+pub const LOCALE_KEY: &str = "doke_locale";
+
+/// How a language writes numbers and lists
+#[derive(Debug, Clone, PartialEq)]
+pub struct Locale {
+    /// Language tag, as given to `from_tag`
+    pub tag: String,
+    pub decimal_separator: &'static str,
+    pub group_separator: &'static str,
+    /// Integers with fewer digits aren't grouped ("1000" in Spanish)
+    pub min_grouping_digits: usize,
+    /// Word before the last item of a list
+    pub conjunction: &'static str,
+}
+
+/// Language, decimal and group separators, minimum digits to group, conjunction
+const LOCALES: &[(&str, &str, &str, usize, &str)] = &[
+    ("en", ".", ",", 4, "and"),
+    ("fr", ",", "\u{202F}", 4, "et"),
+    ("de", ",", ".", 4, "und"),
+    ("es", ",", ".", 5, "y"),
+    ("it", ",", ".", 4, "e"),
+    ("pt", ",", ".", 4, "e"),
+    ("nl", ",", ".", 4, "en"),
+    ("pl", ",", "\u{00A0}", 5, "i"),
+    ("ru", ",", "\u{00A0}", 4, "и"),
+];
+
+impl Locale {
+    /// The locale of a language tag (`fr`, `fr-CA`, `pt_BR`), by its language
+    /// ```
+    /// use doke::locale::Locale;
+    ///
+    /// let fr = Locale::from_tag("fr-CA").unwrap();
+    /// assert_eq!(fr.format_number(-1250.5), "-1\u{202F}250,5");
+    /// assert_eq!(fr.join(&["gobelins".into(), "orcs".into(), "trolls".into()]), "gobelins, orcs et trolls");
+    /// assert!(Locale::from_tag("tlh").is_none());
+    /// ```
+    pub fn from_tag(tag: &str) -> Option<Self> {
+        let language = language(tag);
+        let (_, decimal, group, min_grouping, conjunction) =
+            LOCALES.iter().find(|(name, ..)| *name == language)?;
+        Some(Self {
+            tag: tag.to_string(),
+            decimal_separator: decimal,
+            group_separator: group,
+            min_grouping_digits: *min_grouping,
+            conjunction,
+        })
+    }
+
+    /// The language of the tag, lowercase (`pt` for `pt_BR`)
+    pub fn language(&self) -> String {
+        language(&self.tag)
+    }
+
+    /// `value` with the locale's separators, as many decimals as it needs
+    pub fn format_number(&self, value: f64) -> String {
+        let text = value.to_string();
+        let (integer, decimals) = match text.split_once('.') {
+            Some((integer, decimals)) => (integer, Some(decimals)),
+            None => (text.as_str(), None),
+        };
+        let mut out = self.group(integer);
+        if let Some(decimals) = decimals {
+            out.push_str(self.decimal_separator);
+            out.push_str(decimals);
+        }
+        out
+    }
+
+    /// `value` with the locale's group separator
+    pub fn format_integer(&self, value: impl ToString) -> String {
+        self.group(&value.to_string())
+    }
+
+    /// "A", "A and B", "A, B and C"
+    pub fn join(&self, items: &[String]) -> String {
+        match items {
+            [] => String::new(),
+            [only] => only.clone(),
+            [init @ .., last] => format!("{} {} {}", init.join(", "), self.conjunction, last),
+        }
+    }
+
+    /// Digits of an integer (sign included) in groups of three
+    fn group(&self, integer: &str) -> String {
+        let (sign, digits) = match integer.strip_prefix('-') {
+            Some(digits) => ("-", digits),
+            None => ("", integer),
+        };
+        if digits.len() < self.min_grouping_digits || !digits.bytes().all(|b| b.is_ascii_digit()) {
+            return integer.to_string();
+        }
+        let mut out = String::from(sign);
+        for (i, digit) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i) % 3 == 0 {
+                out.push_str(self.group_separator);
+            }
+            out.push(digit);
+        }
+        out
+    }
+}
+
+fn language(tag: &str) -> String {
+    tag.split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+/// The locale of `node`: the document's `locale` frontmatter, else its pipe's
+pub(crate) fn of(node: &DokeNode, frontmatter: &HashMap<String, GodotValue>) -> Option<Locale> {
+    [
+        frontmatter.get(FRONTMATTER_KEY),
+        node.parse_data.get(LOCALE_KEY),
+    ]
+    .into_iter()
+    .find_map(|value| match value {
+        Some(GodotValue::String(tag)) => Locale::from_tag(tag),
+        _ => None,
+    })
+}
+
+/// Record `locale` for parsers on `nodes` and their children
+pub(crate) fn apply(locale: &Locale, nodes: &mut [DokeNode]) {
+    for node in nodes {
+        node.parse_data
+            .insert(LOCALE_KEY.into(), GodotValue::String(locale.tag.clone()));
+        apply(locale, &mut node.children);
+    }
+}
@@ -1,45 +1,454 @@
+use doke::codegen::Schema;
+use doke::export::TresExport;
 use doke::file_builder::ResourceBuilder; // <- import your new builder
+use doke::input::{AsciiDoc, PlainText};
 use doke::parsers::{self, DebugPrinter};
-use doke::{DokePipe, parsers::TypedSentencesParser};
+use doke::workspace::{BASELINE_FILE, Baseline, Workspace};
+use doke::{
+    DokePipe, Strictness, ci, import, parsers::TypedSentencesParser, scaffold, strictness, template,
+};
 use std::env;
+use std::fs;
 use std::io::{self, Read};
-use std::path::Path;
+use std::path::{Path, PathBuf};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parse command line arguments
-    let args: Vec<String> = env::args().collect();
+    let mut args: Vec<String> = env::args().collect();
 
-    if args.len() != 3 || args[1] != "--typed" {
-        eprintln!("Usage: {} --typed <dokeconfig_file_path>", args[0]);
+    // Build profiles of the config, `--profile mobile`
+    let mut profiles = Vec::new();
+    while let Some(i) = args.iter().position(|a| a == "--profile") {
+        args.remove(i);
+        if i < args.len() {
+            profiles.push(args.remove(i));
+        }
+    }
+
+    // Mixed into exported uids, `--uid-salt my_game`
+    let mut uid_salt = String::new();
+    if let Some(i) = args.iter().position(|a| a == "--uid-salt") {
+        args.remove(i);
+        if i < args.len() {
+            uid_salt = args.remove(i);
+        }
+    }
+
+    // Migrate built resources to another schema version, `--schema-version 1`
+    let mut schema_version = None;
+    if let Some(i) = args.iter().position(|a| a == "--schema-version") {
+        args.remove(i);
+        if i < args.len() {
+            schema_version = Some(args.remove(i).parse::<u32>()?);
+        }
+    }
+
+    // Validate documents when enough of their statements resolve, `--min-resolved 95%`,
+    // and refuse unsure hypotheses, `--min-confidence 0.5`
+    let mut strictness = Strictness::default();
+    if let Some(i) = args.iter().position(|a| a == "--min-resolved") {
+        args.remove(i);
+        if i < args.len() {
+            let fraction = args.remove(i);
+            strictness = strictness.with_min_resolved(match fraction.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>()? / 100.0,
+                None => fraction.parse::<f64>()?,
+            });
+        }
+    }
+    if let Some(i) = args.iter().position(|a| a == "--min-confidence") {
+        args.remove(i);
+        if i < args.len() {
+            strictness = strictness.with_min_confidence(args.remove(i).parse::<f32>()?);
+        }
+    }
+
+    // Export `.dokb` files instead of `.tres`, `--binary`
+    let binary = match args.iter().position(|a| a == "--binary") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Export to a SQLite database, `--sqlite` (the out path is then the database file)
+    let sqlite = match args.iter().position(|a| a == "--sqlite") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Read Obsidian syntax, `--obsidian`
+    let obsidian = match args.iter().position(|a| a == "--obsidian") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Match statements without emphasis, keeping it as BBCode, `--rich-text`
+    let rich_text = match args.iter().position(|a| a == "--rich-text") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Read AsciiDoc documents instead of markdown, `--asciidoc`
+    let asciidoc = match args.iter().position(|a| a == "--asciidoc") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Read every line as a statement, `--plain`
+    let plain = match args.iter().position(|a| a == "--plain") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Only check the documents staged in git, `check --staged`
+    let staged = match args.iter().position(|a| a == "--staged") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Warn about content written twice in `check`, `--lint-duplicates`
+    let lint_duplicates = match args.iter().position(|a| a == "--lint-duplicates") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Record the diagnostics of `check` as known ones, `--write-baseline`
+    let write_baseline = match args.iter().position(|a| a == "--write-baseline") {
+        Some(i) => {
+            args.remove(i);
+            true
+        }
+        None => false,
+    };
+
+    // Known diagnostics `check` doesn't fail on, `--baseline file`
+    // (`doke.baseline` in the checked directory by default)
+    let mut baseline_path = None;
+    if let Some(i) = args.iter().position(|a| a == "--baseline") {
+        args.remove(i);
+        if i < args.len() {
+            baseline_path = Some(PathBuf::from(args.remove(i)));
+        }
+    }
+
+    let usage_ok = match args.get(1).map(String::as_str) {
+        Some("--typed") => args.len() >= 3,
+        Some("fmt") => args.len() == 3,
+        Some("types") => args.len() == 3 || args.len() == 4,
+        Some("instantiate") => args.len() >= 3,
+        Some("new") | Some("import") | Some("check") | Some("graph") => args.len() == 4,
+        Some("export") => args.len() == 5,
+        Some("serve") => args.len() == 3 || args.len() == 4,
+        _ => false,
+    };
+    if !usage_ok {
+        eprintln!(
+            "Usage: {} --typed <dokeconfig_file_path> [--profile name] [--schema-version n] [--min-resolved fraction] [--min-confidence c] [--obsidian] [--rich-text] [--asciidoc | --plain] [override.md...]",
+            args[0]
+        );
+        eprintln!("       {} fmt <dokeconfig_file_path>", args[0]);
+        eprintln!(
+            "       {} types <dokeconfig_file_path> [rust | typescript]",
+            args[0]
+        );
+        eprintln!("       {} instantiate <template.md> [value...]", args[0]);
+        eprintln!(
+            "       {} new <dokeconfig_file_path> <new_file.md>",
+            args[0]
+        );
+        eprintln!(
+            "       {} check <dokeconfig_file_path> <directory> [--staged] [--baseline file | --write-baseline] [--lint-duplicates] [--min-resolved fraction] [--min-confidence c] [--obsidian] [--rich-text] [--asciidoc | --plain]",
+            args[0]
+        );
+        eprintln!(
+            "       {} graph <dokeconfig_file_path> <directory>",
+            args[0]
+        );
+        eprintln!(
+            "       {} export <dokeconfig_file_path> <directory> <out_directory> [--profile name] [--schema-version n] [--uid-salt salt] [--binary | --sqlite]",
+            args[0]
+        );
+        eprintln!(
+            "       {} serve <dokeconfig_file_path> [address] [--obsidian] [--rich-text] [--asciidoc | --plain]",
+            args[0]
+        );
         std::process::exit(1);
     }
 
+    // Write the document a template renders for the values to stdout
+    if args[1] == "instantiate" {
+        let source = fs::read_to_string(&args[2])?;
+        let values: Vec<&str> = args[3..].iter().map(String::as_str).collect();
+        print!("{}", template::instantiate(&source, &values)?);
+        return Ok(());
+    }
+
     let config_path = &args[2];
     let config_path = Path::new(config_path);
 
+    // Write a skeleton document for the config
+    if args[1] == "new" {
+        let target = Path::new(&args[3]);
+        if target.exists() {
+            eprintln!("{} already exists", target.display());
+            std::process::exit(1);
+        }
+        let typed_parser = TypedSentencesParser::from_config_file(config_path)?;
+        let file_builder = ResourceBuilder::from_file(config_path)?;
+        let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+        let title = scaffold::title_from_file_stem(&stem);
+        fs::write(
+            target,
+            scaffold::new_document(&file_builder, &typed_parser, &title),
+        )?;
+        return Ok(());
+    }
+
+    // Write an existing resource as a markdown document to stdout
+    if args[1] == "import" {
+        let source = Path::new(&args[3]);
+        let text = fs::read_to_string(source)?;
+        let value = match source.extension().and_then(|e| e.to_str()) {
+            Some("json") => import::parse_json(&text)?,
+            _ => import::parse_tres(&text)?,
+        };
+        let typed_parser = TypedSentencesParser::from_config_file(config_path)?;
+        let file_builder = ResourceBuilder::from_file(config_path)?;
+        let (markdown, diagnostics) =
+            import::to_markdown(&value, &typed_parser, Some(&file_builder));
+        for diagnostic in diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        print!("{}", markdown);
+        return Ok(());
+    }
+
+    // Write the types the config builds to stdout, as Rust or TypeScript
+    if args[1] == "types" {
+        let typed_parser = TypedSentencesParser::from_config_file(config_path)?;
+        let schema = Schema::from_grammar(&typed_parser);
+        match args.get(3).map(String::as_str) {
+            None | Some("rust") => print!("{}", schema.to_rust()),
+            Some("typescript") | Some("ts") => print!("{}", schema.to_typescript()),
+            Some(other) => return Err(format!("unknown language {}", other).into()),
+        }
+        return Ok(());
+    }
+
+    // Validate documents sent over HTTP, until killed
+    if args[1] == "serve" {
+        #[cfg(feature = "serve")]
+        {
+            let typed_parser = TypedSentencesParser::from_config_file(config_path)?;
+            let mut pipe = DokePipe::new()
+                .with_obsidian(obsidian)
+                .with_rich_text(rich_text);
+            if asciidoc {
+                pipe = pipe.with_input_format(AsciiDoc);
+            } else if plain {
+                pipe = pipe.with_input_format(PlainText::new());
+            }
+            let pipe = pipe
+                .add(parsers::FrontmatterTemplateParser)
+                .add(typed_parser.clone());
+            // Parsing metrics at /metrics
+            #[cfg(feature = "prometheus")]
+            let metrics = std::sync::Arc::new(doke::metrics::PrometheusSink::new());
+            #[cfg(feature = "prometheus")]
+            let pipe = pipe.with_metrics(metrics.clone());
+            let service = doke::serve::Service::new(pipe, typed_parser);
+            #[cfg(feature = "prometheus")]
+            let service = service.with_metrics(metrics);
+            let address = args.get(3).map_or("127.0.0.1:8080", String::as_str);
+            eprintln!("listening on http://{}", address);
+            service.serve(address)?;
+            return Ok(());
+        }
+        #[cfg(not(feature = "serve"))]
+        return Err("doke was built without the serve feature".into());
+    }
+
+    // Check every document of a directory together,
+    // or write which ones reference which in Graphviz format
+    if args[1] == "check" || args[1] == "graph" || args[1] == "export" {
+        let typed_parser = TypedSentencesParser::from_config_file(config_path)?;
+        let grammar_version = typed_parser.fingerprint();
+        let hierarchy = typed_parser.hierarchy().clone();
+        let mut pipe = DokePipe::new()
+            .with_obsidian(obsidian)
+            .with_rich_text(rich_text)
+            .with_strictness(strictness);
+        if asciidoc {
+            pipe = pipe.with_input_format(AsciiDoc);
+        } else if plain {
+            pipe = pipe.with_input_format(PlainText::new());
+        }
+        let pipe = pipe
+            .add(parsers::FrontmatterTemplateParser)
+            .add(typed_parser);
+        let root = Path::new(&args[3]);
+        // A pre-commit hook checks the staged documents without the others
+        let (diagnostics, suppressed) = if args[1] == "check" && staged {
+            if write_baseline {
+                return Err("--write-baseline needs every document, not only staged ones".into());
+            }
+            (ci::check_changed(&ci::staged_files(root)?, &pipe)?, 0)
+        } else {
+            let workspace = Workspace::from_dir(root, &pipe)?.with_duplicate_lint(lint_duplicates);
+            if args[1] == "graph" {
+                print!("{}", workspace.dependency_graph().to_dot());
+                return Ok(());
+            }
+            // Write every document as a .tres or .dokb, referenced ones first
+            if args[1] == "export" {
+                let mut builder = profiles.into_iter().fold(
+                    ResourceBuilder::from_file(config_path)?.with_hierarchy(hierarchy),
+                    |builder, profile| builder.with_profile(profile),
+                );
+                if let Some(version) = schema_version {
+                    builder = builder.with_schema_version(version);
+                }
+                if sqlite {
+                    #[cfg(feature = "sqlite")]
+                    {
+                        workspace.export_sqlite(&builder, Path::new(&args[4]))?;
+                        eprintln!("wrote {}", args[4]);
+                        return Ok(());
+                    }
+                    #[cfg(not(feature = "sqlite"))]
+                    return Err("doke was built without the sqlite feature".into());
+                }
+                let export = TresExport::default()
+                    .with_uid_salt(uid_salt)
+                    .with_grammar_version(grammar_version);
+                let out = Path::new(&args[4]);
+                let manifest = if binary {
+                    workspace.export_binary(&builder, &export, out)?
+                } else {
+                    workspace.export_tres(&builder, &export, out)?
+                };
+                for entry in manifest.resources {
+                    eprintln!("wrote {} from {}", entry.path, entry.source.display());
+                }
+                return Ok(());
+            }
+            (workspace.check(), workspace.suppressed().len())
+        };
+
+        // Record the current diagnostics, or only report the ones that aren't known
+        let baseline_path = baseline_path.unwrap_or_else(|| root.join(BASELINE_FILE));
+        if write_baseline {
+            let baseline = Baseline::from_diagnostics(&diagnostics, root);
+            baseline.write(&baseline_path)?;
+            eprintln!(
+                "wrote {} diagnostics to {}",
+                baseline.len(),
+                baseline_path.display()
+            );
+            return Ok(());
+        }
+        let diagnostics = match baseline_path.exists() {
+            true => Baseline::from_file(&baseline_path)?.new_diagnostics(diagnostics, root),
+            false => diagnostics,
+        };
+        for diagnostic in &diagnostics {
+            eprintln!("{}", diagnostic);
+        }
+        if suppressed > 0 {
+            eprintln!("{} diagnostics suppressed by doke-allow", suppressed);
+        }
+        if diagnostics.iter().any(|d| d.diagnostic.is_error()) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     // Read entire stdin into a string
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
 
     // Load both the typed parser and the builder from the same config file
     let typed_parser = TypedSentencesParser::from_config_file(config_path)?;
-    let file_builder = ResourceBuilder::from_file(config_path)?;
+
+    // Rewrite the document in canonical form to stdout
+    if args[1] == "fmt" {
+        let pipe = DokePipe::new().add(typed_parser);
+        print!("{}", pipe.format(&input));
+        return Ok(());
+    }
+
+    let mut file_builder = profiles.into_iter().fold(
+        ResourceBuilder::from_file(config_path)?.with_hierarchy(typed_parser.hierarchy().clone()),
+        |builder, profile| builder.with_profile(profile),
+    );
+    if let Some(version) = schema_version {
+        file_builder = file_builder.with_schema_version(version);
+    }
 
     // Build the pipeline
-    let pipe = DokePipe::new()
+    let mut pipe = DokePipe::new()
+        .with_obsidian(obsidian)
+        .with_rich_text(rich_text)
+        .with_strictness(strictness);
+    if asciidoc {
+        pipe = pipe.with_input_format(AsciiDoc);
+    } else if plain {
+        pipe = pipe.with_input_format(PlainText::new());
+    }
+    let pipe = pipe
         .add(parsers::FrontmatterTemplateParser)
         .add(typed_parser)
         .add(DebugPrinter);
 
+    // Override files given after the config are merged over the document
+    let overrides = args[3..]
+        .iter()
+        .map(fs::read_to_string)
+        .collect::<Result<Vec<_>, _>>()?;
+    let overrides: Vec<&str> = overrides.iter().map(String::as_str).collect();
+
     // Get the godot values from the document
-    match pipe.validate(&input) {
+    let mut doc = pipe.run_markdown_with_overrides(&input, &overrides);
+    match pipe.validate_document(&mut doc) {
         Err(e) => {
             eprint!("{}", e);
         }
         Ok(values) => {
+            for diagnostic in &doc.diagnostics {
+                if diagnostic.code == strictness::DROPPED_CODE {
+                    eprintln!("{}", diagnostic);
+                }
+            }
             // Build the final file resource using the builder
-            match file_builder.build_file_resource(values) {
-                Ok(resource) => {
+            match file_builder.build_file_resource_checked(values) {
+                Ok((mut resource, diagnostics)) => {
+                    for diagnostic in diagnostics {
+                        eprintln!("{}", diagnostic);
+                    }
+                    file_builder.migrate(&mut resource)?;
                     dbg!(resource);
                 }
                 Err(e) => {
@@ -0,0 +1,188 @@
+// metrics.rs
+//
+// Counters and timings for services running a pipe for a long time, to watch
+// throughput and spot the grammar that got slow. A `DokePipe` reports to the
+// `MetricsSink` given to `with_metrics`, the application forwards the numbers
+// to its monitoring.
+//
+// With the `prometheus` feature, `PrometheusSink` keeps them in memory and
+// renders them in Prometheus' text format (`doke serve` answers `/metrics`).
+
+use std::fmt::Debug;
+use std::time::Duration;
+
+use crate::{DokeNode, suppress};
+
+/// Documents that went through the pipe
+pub const DOCUMENTS: &str = "doke_documents_total";
+/// Statements of those documents, children included
+pub const STATEMENTS: &str = "doke_statements_total";
+/// Statements that no parser recognized
+pub const UNRESOLVED: &str = "doke_unresolved_statements_total";
+/// Time to read and parse a document
+pub const DOCUMENT_SECONDS: &str = "doke_document_seconds";
+/// Time a parser spent on a document, labelled `parser`
+pub const PARSER_SECONDS: &str = "doke_parser_seconds";
+
+/// Where a pipe reports what it does
+pub trait MetricsSink: Debug + Send + Sync {
+    /// A counter went up by `value`
+    fn count(&self, name: &'static str, labels: &[(&'static str, &str)], value: u64);
+
+    /// Something took `duration`
+    fn time(&self, name: &'static str, labels: &[(&'static str, &str)], duration: Duration);
+}
+
+/// Statements of `nodes` and their children, and how many no parser recognized
+pub(crate) fn statement_counts(nodes: &[DokeNode]) -> (u64, u64) {
+    nodes.iter().fold((0, 0), |(all, unresolved), node| {
+        let (children, children_unresolved) = statement_counts(&node.children);
+        let own = suppress::unrecognized(node) as u64;
+        (all + 1 + children, unresolved + own + children_unresolved)
+    })
+}
+
+#[cfg(feature = "prometheus")]
+pub use prometheus::PrometheusSink;
+
+#[cfg(feature = "prometheus")]
+mod prometheus {
+    use std::collections::BTreeMap;
+    use std::fmt::Write;
+    use std::sync::{Mutex, PoisonError};
+    use std::time::Duration;
+
+    use super::MetricsSink;
+
+    /// Upper bounds of the timing buckets, in seconds
+    const BUCKETS: [f64; 12] = [
+        0.0005, 0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 5.0,
+    ];
+
+    type Series = (&'static str, Vec<(&'static str, String)>);
+
+    #[derive(Debug, Default)]
+    struct Histogram {
+        /// Observations at or under each bucket
+        buckets: [u64; BUCKETS.len()],
+        count: u64,
+        sum: f64,
+    }
+
+    /// Metrics kept in memory, for a `/metrics` endpoint
+    #[derive(Debug, Default)]
+    pub struct PrometheusSink {
+        counters: Mutex<BTreeMap<Series, u64>>,
+        histograms: Mutex<BTreeMap<Series, Histogram>>,
+    }
+
+    fn series(name: &'static str, labels: &[(&'static str, &str)]) -> Series {
+        let labels = labels
+            .iter()
+            .map(|(key, value)| (*key, value.to_string()))
+            .collect();
+        (name, labels)
+    }
+
+    /// `{a="1",b="2"}`, with `extra` last
+    fn label_set(labels: &[(&'static str, String)], extra: Option<(&str, &str)>) -> String {
+        let mut pairs: Vec<String> = labels
+            .iter()
+            .map(|(key, value)| format!("{}=\"{}\"", key, escape(value)))
+            .collect();
+        if let Some((key, value)) = extra {
+            pairs.push(format!("{}=\"{}\"", key, value));
+        }
+        match pairs.is_empty() {
+            true => String::new(),
+            false => format!("{{{}}}", pairs.join(",")),
+        }
+    }
+
+    fn escape(value: &str) -> String {
+        value
+            .replace('\\', "\\\\")
+            .replace('"', "\\\"")
+            .replace('\n', "\\n")
+    }
+
+    impl PrometheusSink {
+        pub fn new() -> Self {
+            Self::default()
+        }
+
+        /// Every metric in Prometheus' text exposition format
+        /// ```
+        /// use doke::metrics::{DOCUMENTS, MetricsSink, PrometheusSink};
+        ///
+        /// let sink = PrometheusSink::new();
+        /// sink.count(DOCUMENTS, &[], 2);
+        /// assert!(sink.render().contains("doke_documents_total 2\n"));
+        /// ```
+        pub fn render(&self) -> String {
+            let mut out = String::new();
+            let mut typed = None;
+            for ((name, labels), value) in self
+                .counters
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+            {
+                if typed != Some(*name) {
+                    writeln!(out, "# TYPE {} counter", name).unwrap();
+                    typed = Some(*name);
+                }
+                writeln!(out, "{}{} {}", name, label_set(labels, None), value).unwrap();
+            }
+            for ((name, labels), histogram) in self
+                .histograms
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .iter()
+            {
+                if typed != Some(*name) {
+                    writeln!(out, "# TYPE {} histogram", name).unwrap();
+                    typed = Some(*name);
+                }
+                for (bound, count) in BUCKETS.iter().zip(histogram.buckets) {
+                    let le = bound.to_string();
+                    let labels = label_set(labels, Some(("le", &le)));
+                    writeln!(out, "{}_bucket{} {}", name, labels, count).unwrap();
+                }
+                let labels_inf = label_set(labels, Some(("le", "+Inf")));
+                writeln!(out, "{}_bucket{} {}", name, labels_inf, histogram.count).unwrap();
+                let labels = label_set(labels, None);
+                writeln!(out, "{}_sum{} {}", name, labels, histogram.sum).unwrap();
+                writeln!(out, "{}_count{} {}", name, labels, histogram.count).unwrap();
+            }
+            out
+        }
+    }
+
+    impl MetricsSink for PrometheusSink {
+        fn count(&self, name: &'static str, labels: &[(&'static str, &str)], value: u64) {
+            *self
+                .counters
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .entry(series(name, labels))
+                .or_default() += value;
+        }
+
+        fn time(&self, name: &'static str, labels: &[(&'static str, &str)], duration: Duration) {
+            let seconds = duration.as_secs_f64();
+            let mut histograms = self
+                .histograms
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner);
+            let histogram = histograms.entry(series(name, labels)).or_default();
+            for (bound, count) in BUCKETS.iter().zip(histogram.buckets.iter_mut()) {
+                if seconds <= *bound {
+                    *count += 1;
+                }
+            }
+            histogram.count += 1;
+            histogram.sum += seconds;
+        }
+    }
+}
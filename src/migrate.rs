@@ -0,0 +1,235 @@
+// migrate.rs
+//
+// Schema versions of built resources. The builder config says which version the
+// content is written for, and how each version changes the fields of the one
+// before it:
+//
+//     schema_version: 2
+//     migrations:
+//       - version: 2                   # from 1 to 2
+//         rename: {dmg: damage}
+//         wrap_in_array: [effects]
+//         defaults: {rarity: common}
+//
+// Exporting for another version applies the changes up to it, or undoes them
+// down to it, so a game build still reading `dmg` gets `dmg` and a newer one gets
+// the fields it expects from older content. Every change can be undone: a renamed
+// field gets its old name back, a wrapped field its first item, a default is removed.
+// Changes apply to the fields of the root resource.
+
+use std::collections::HashMap;
+
+use thiserror::Error;
+use yaml_rust2::Yaml;
+
+use crate::GodotValue;
+use crate::parsers::sentence::yaml_to_godot_value;
+
+#[derive(Debug, Error)]
+pub enum MigrationError {
+    #[error("Invalid migrations: {0}")]
+    Config(String),
+
+    #[error("No migration to schema version {0}")]
+    UnknownVersion(u32),
+}
+
+/// One change of a migration
+#[derive(Debug, Clone, PartialEq)]
+pub enum Step {
+    /// The field `from` is now named `to`
+    Rename { from: String, to: String },
+    /// The field holds an array of what it held
+    WrapInArray(String),
+    /// The field is new, with this value for resources without it
+    Default(String, GodotValue),
+}
+
+/// The changes from the version before `version` to `version`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Migration {
+    pub version: u32,
+    pub steps: Vec<Step>,
+}
+
+/// The schema version of the content, and the migrations of the schema
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Migrations {
+    /// Version the content is written for
+    pub schema_version: u32,
+    /// Sorted by version
+    pub migrations: Vec<Migration>,
+}
+
+impl Migrations {
+    /// From the `schema_version` and `migrations` keys of a builder config
+    pub fn from_yaml(y: &Yaml) -> Result<Self, MigrationError> {
+        let version = |y: &Yaml, what: &str| match y {
+            Yaml::Integer(v) if *v >= 0 => Ok(*v as u32),
+            _ => Err(MigrationError::Config(format!(
+                "{} must be a positive integer",
+                what
+            ))),
+        };
+        let schema_version = match &y["schema_version"] {
+            Yaml::BadValue => 1,
+            v => version(v, "'schema_version'")?,
+        };
+
+        let mut migrations = Vec::new();
+        match &y["migrations"] {
+            Yaml::BadValue => {}
+            Yaml::Array(entries) => {
+                for entry in entries {
+                    migrations.push(Migration {
+                        version: version(&entry["version"], "A migration's 'version'")?,
+                        steps: Self::parse_steps(entry)?,
+                    });
+                }
+            }
+            _ => {
+                return Err(MigrationError::Config(
+                    "'migrations' must be a sequence".into(),
+                ));
+            }
+        }
+        migrations.sort_by_key(|m| m.version);
+        Ok(Self {
+            schema_version,
+            migrations,
+        })
+    }
+
+    fn parse_steps(entry: &Yaml) -> Result<Vec<Step>, MigrationError> {
+        let name = |y: &Yaml| {
+            y.as_str()
+                .map(str::to_string)
+                .ok_or_else(|| MigrationError::Config("Field names must be strings".into()))
+        };
+        let mut steps = Vec::new();
+        if let Some(renames) = entry["rename"].as_hash() {
+            for (from, to) in renames {
+                steps.push(Step::Rename {
+                    from: name(from)?,
+                    to: name(to)?,
+                });
+            }
+        }
+        if let Some(fields) = entry["wrap_in_array"].as_vec() {
+            for field in fields {
+                steps.push(Step::WrapInArray(name(field)?));
+            }
+        }
+        if let Some(defaults) = entry["defaults"].as_hash() {
+            for (field, value) in defaults {
+                steps.push(Step::Default(name(field)?, yaml_to_godot_value(value)));
+            }
+        }
+        Ok(steps)
+    }
+
+    /// `resource`, built for `schema_version`, as version `target` expects it
+    /// ```
+    /// use std::collections::HashMap;
+    /// use doke::GodotValue;
+    /// use doke::migrate::{Migration, Migrations, Step};
+    ///
+    /// let migrations = Migrations {
+    ///     schema_version: 2,
+    ///     migrations: vec![Migration {
+    ///         version: 2,
+    ///         steps: vec![
+    ///             Step::Rename { from: "dmg".into(), to: "damage".into() },
+    ///             Step::WrapInArray("effects".into()),
+    ///         ],
+    ///     }],
+    /// };
+    /// let mut resource = GodotValue::Resource {
+    ///     type_name: "Item".into(),
+    ///     abstract_type_name: "root".into(),
+    ///     fields: HashMap::from([
+    ///         ("damage".into(), GodotValue::Int(5)),
+    ///         ("effects".into(), GodotValue::Array(vec![GodotValue::String("burn".into())])),
+    ///     ]),
+    /// };
+    /// migrations.migrate(&mut resource, 1).unwrap();
+    /// assert_eq!(resource.get_path("dmg"), Some(&GodotValue::Int(5)));
+    /// assert_eq!(resource.get_path("effects"), Some(&GodotValue::String("burn".into())));
+    ///
+    /// assert!(migrations.migrate(&mut resource, 3).is_err());
+    /// ```
+    pub fn migrate(&self, resource: &mut GodotValue, target: u32) -> Result<(), MigrationError> {
+        let GodotValue::Resource { fields, .. } = resource else {
+            return Ok(());
+        };
+        let missing = |version: u32| match self.migrations.iter().any(|m| m.version == version) {
+            true => Ok(()),
+            false => Err(MigrationError::UnknownVersion(version)),
+        };
+        // check before changing anything, a resource is migrated fully or not at all
+        match target >= self.schema_version {
+            true => (self.schema_version + 1..=target).try_for_each(missing)?,
+            false => (target + 1..=self.schema_version).try_for_each(missing)?,
+        }
+        for migration in &self.migrations {
+            if migration.version > self.schema_version && migration.version <= target {
+                migration
+                    .steps
+                    .iter()
+                    .for_each(|step| upgrade(step, fields));
+            }
+        }
+        for migration in self.migrations.iter().rev() {
+            if migration.version <= self.schema_version && migration.version > target {
+                migration
+                    .steps
+                    .iter()
+                    .rev()
+                    .for_each(|step| downgrade(step, fields));
+            }
+        }
+        Ok(())
+    }
+}
+
+fn upgrade(step: &Step, fields: &mut HashMap<String, GodotValue>) {
+    match step {
+        Step::Rename { from, to } => {
+            if let Some(value) = fields.remove(from) {
+                fields.insert(to.clone(), value);
+            }
+        }
+        Step::WrapInArray(field) => {
+            if let Some(value) = fields.remove(field) {
+                let wrapped = match value {
+                    GodotValue::Nil => GodotValue::Array(Vec::new()),
+                    value => GodotValue::Array(vec![value]),
+                };
+                fields.insert(field.clone(), wrapped);
+            }
+        }
+        Step::Default(field, value) => {
+            fields.entry(field.clone()).or_insert_with(|| value.clone());
+        }
+    }
+}
+
+fn downgrade(step: &Step, fields: &mut HashMap<String, GodotValue>) {
+    match step {
+        Step::Rename { from, to } => {
+            if let Some(value) = fields.remove(to) {
+                fields.insert(from.clone(), value);
+            }
+        }
+        Step::WrapInArray(field) => {
+            if let Some(value @ GodotValue::Array(_)) = fields.get_mut(field)
+                && let GodotValue::Array(items) = std::mem::replace(value, GodotValue::Nil)
+            {
+                *value = items.into_iter().next().unwrap_or(GodotValue::Nil);
+            }
+        }
+        Step::Default(field, _) => {
+            fields.remove(field);
+        }
+    }
+}
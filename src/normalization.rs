@@ -0,0 +1,193 @@
+// normalization.rs
+//
+// Typography cleanup applied to statements before parsers see them.
+// Text pasted from word processors comes with smart quotes, en dashes and
+// non-breaking spaces that no phrase written in a text editor will match.
+
+use std::borrow::Cow;
+
+use unicode_normalization::{UnicodeNormalization, is_nfc_quick};
+
+use crate::overrides;
+use crate::{DokeNode, GodotValue};
+
+/// Characters trimmed from the end of a statement before it is matched, by default
+pub const DEFAULT_TRAILING: &str = ".:";
+
+/// `parse_data` key under which the pipeline records its trailing characters
+pub const TRAILING_KEY: &str = "doke_trailing";
+
+/// `parse_data` key set when the pipeline matches statements ignoring case
+pub const CASE_FOLD_KEY: &str = "doke_case_fold";
+
+/// How a `DokePipe` normalizes statements.
+///
+/// The default only composes accents (NFC) and trims trailing `.` and `:`
+/// when matching, use [`Normalization::typographic`] for pasted content.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Normalization {
+    /// Compose accents (Unicode NFC) in statements and frontmatter,
+    /// so "é" typed as `e` + combining accent matches a grammar's "é"
+    pub nfc: bool,
+    /// Match statements ignoring case, beyond ASCII ("ÉPÉE" matches "épée")
+    pub case_fold: bool,
+    /// Replace curly quotes with straight ones
+    pub smart_quotes: bool,
+    /// Replace en/em dashes and minus signs with `-`
+    pub dashes: bool,
+    /// Replace non-breaking and narrow spaces with regular spaces
+    pub nbsp: bool,
+    /// Collapse runs of whitespace into one space
+    pub collapse_whitespace: bool,
+    /// Characters ignored at the end of a statement when matching
+    pub trailing: String,
+}
+
+impl Default for Normalization {
+    fn default() -> Self {
+        Self {
+            nfc: true,
+            case_fold: false,
+            smart_quotes: false,
+            dashes: false,
+            nbsp: false,
+            collapse_whitespace: false,
+            trailing: DEFAULT_TRAILING.into(),
+        }
+    }
+}
+
+impl Normalization {
+    /// Every typography fix, and trailing `!` ignored along with `.` and `:`
+    pub fn typographic() -> Self {
+        Self {
+            nfc: true,
+            case_fold: false,
+            smart_quotes: true,
+            dashes: true,
+            nbsp: true,
+            collapse_whitespace: true,
+            trailing: ".:!".into(),
+        }
+    }
+
+    /// Set the characters ignored at the end of statements
+    pub fn with_trailing(mut self, chars: impl Into<String>) -> Self {
+        self.trailing = chars.into();
+        self
+    }
+
+    /// Match statements ignoring case
+    /// ```
+    /// use doke::{DokePipe, GodotValue, Normalization, parsers::SentenceParser};
+    ///
+    /// let grammar = r#"
+    /// Sword:
+    ///   - "Forges an épée of {weight: int} kg"
+    /// "#;
+    /// let parser = || SentenceParser::from_yaml("Item".into(), grammar).unwrap();
+    /// // "é" typed as "e" and a combining accent
+    /// let decomposed = "FORGES AN E\u{301}PE\u{301}E OF 3 KG";
+    /// assert!(DokePipe::new().add(parser()).validate(decomposed).is_err());
+    ///
+    /// let folding = DokePipe::new()
+    ///     .with_normalization(Normalization::default().with_case_fold(true))
+    ///     .add(parser());
+    /// let values = folding.validate(decomposed).unwrap();
+    /// assert_eq!(values[0].get_path("weight"), Some(&GodotValue::Int(3)));
+    /// ```
+    pub fn with_case_fold(mut self, case_fold: bool) -> Self {
+        self.case_fold = case_fold;
+        self
+    }
+
+    /// Compose `text` to NFC if enabled
+    pub fn unicode<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if !self.nfc || is_nfc_quick(text.chars()) == unicode_normalization::IsNormalized::Yes {
+            Cow::Borrowed(text)
+        } else {
+            Cow::Owned(text.nfc().collect())
+        }
+    }
+
+    /// Apply the Unicode and typography fixes to `text`
+    pub fn normalize(&self, text: &str) -> String {
+        let text = self.unicode(text);
+        let mut out = String::with_capacity(text.len());
+        let mut last_space = false;
+        for c in text.chars() {
+            let c = match c {
+                '\u{201C}' | '\u{201D}' | '\u{201E}' | '\u{201F}' | '\u{00AB}' | '\u{00BB}'
+                    if self.smart_quotes =>
+                {
+                    '"'
+                }
+                '\u{2018}' | '\u{2019}' | '\u{201A}' | '\u{201B}' if self.smart_quotes => '\'',
+                '\u{2010}' | '\u{2011}' | '\u{2012}' | '\u{2013}' | '\u{2014}' | '\u{2212}'
+                    if self.dashes =>
+                {
+                    '-'
+                }
+                '\u{00A0}' | '\u{2007}' | '\u{202F}' if self.nbsp => ' ',
+                c => c,
+            };
+            if self.collapse_whitespace && c.is_whitespace() {
+                if !last_space {
+                    out.push(' ');
+                }
+                last_space = true;
+            } else {
+                out.push(c);
+                last_space = false;
+            }
+        }
+        out
+    }
+
+    /// Normalize the statements of `nodes` and their children in place
+    pub(crate) fn apply(&self, nodes: &mut [DokeNode]) {
+        for node in nodes {
+            let normalized = self.normalize(&node.statement);
+            // statements already normal keep sharing the document's text
+            if node.statement != normalized {
+                node.statement = normalized.into();
+            }
+            if self.trailing != DEFAULT_TRAILING {
+                node.parse_data.insert(
+                    TRAILING_KEY.into(),
+                    GodotValue::String(self.trailing.clone()),
+                );
+            }
+            if self.case_fold {
+                node.parse_data
+                    .insert(CASE_FOLD_KEY.into(), GodotValue::Bool(true));
+            }
+            self.apply(&mut node.children);
+        }
+    }
+}
+
+/// The statement of `node` as parsers should match it:
+/// trimmed, without its `{#id}` anchor and the trailing characters its pipeline ignores.
+pub fn matching_text(node: &DokeNode) -> &str {
+    let trailing = match node.parse_data.get(TRAILING_KEY) {
+        Some(GodotValue::String(s)) => s.as_str(),
+        _ => DEFAULT_TRAILING,
+    };
+    let statement = match overrides::ANCHOR.find(&node.statement) {
+        Some(anchor) => &node.statement[..anchor.start()],
+        None => node.statement.as_str(),
+    };
+    statement
+        .trim()
+        .trim_end_matches(|c| trailing.contains(c))
+        .trim_end()
+}
+
+/// Whether the pipeline of `node` matches statements ignoring case
+pub fn folds_case(node: &DokeNode) -> bool {
+    matches!(
+        node.parse_data.get(CASE_FOLD_KEY),
+        Some(GodotValue::Bool(true))
+    )
+}
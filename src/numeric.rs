@@ -0,0 +1,144 @@
+// numeric.rs
+//
+// Numeric literal parsing shared by frontmatter conversion and sentence parameters.
+//
+// Ints stay i64 when they fit, fall back to u64 for big unsigned values,
+// and floats report when the literal can't be represented exactly by an f64
+// instead of silently collapsing to 0.0.
+
+use crate::GodotValue;
+
+/// A parsed number plus an optional non-fatal precision warning.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParsedNumber {
+    pub value: GodotValue,
+    pub warning: Option<String>,
+}
+
+/// Parse an integer literal (decimal, `0x`, `0o` or `0b` prefixed).
+/// Values above `i64::MAX` become `GodotValue::UInt`.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+///
+/// let values = pipe.validate("Deals 18446744073709551615 damage").unwrap();
+/// assert_eq!(values[0].get_path("damage"), Some(&GodotValue::UInt(u64::MAX)));
+///
+/// let doc = pipe.run_markdown("Deals 99999999999999999999 damage");
+/// let diagnostic = &doc.nodes[0].diagnostics[0];
+/// assert_eq!(diagnostic.code, "invalid-parameter");
+/// assert!(diagnostic.message.contains("overflows a 64 bit integer"));
+/// ```
+pub fn parse_int(text: &str) -> Result<GodotValue, String> {
+    let trimmed = text.trim();
+    let (negative, digits) = match trimmed.strip_prefix('-') {
+        Some(rest) => (true, rest),
+        None => (false, trimmed.strip_prefix('+').unwrap_or(trimmed)),
+    };
+    let (radix, digits) = match digits.get(..2) {
+        Some("0b" | "0B") => (2, &digits[2..]),
+        Some("0o" | "0O") => (8, &digits[2..]),
+        Some("0x" | "0X") => (16, &digits[2..]),
+        _ => (10, digits),
+    };
+
+    let magnitude = u64::from_str_radix(digits, radix).map_err(|e| match e.kind() {
+        std::num::IntErrorKind::PosOverflow => {
+            format!("'{}' overflows a 64 bit integer", trimmed)
+        }
+        _ => format!("'{}' is not a valid integer", trimmed),
+    })?;
+
+    if negative {
+        if magnitude > i64::MAX as u64 + 1 {
+            return Err(format!("'{}' underflows a 64 bit integer", trimmed));
+        }
+        Ok(GodotValue::Int((magnitude as i64).wrapping_neg()))
+    } else if magnitude > i64::MAX as u64 {
+        Ok(GodotValue::UInt(magnitude))
+    } else {
+        Ok(GodotValue::Int(magnitude as i64))
+    }
+}
+
+/// Parse a float literal, warning when the literal has more precision than an f64 keeps.
+/// ```
+/// use doke::{DokePipe, diagnostics::Severity, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Chance:
+///   - "Procs {chance: float} of the time"
+/// "#).unwrap();
+/// let doc = DokePipe::new().add(parser).run_markdown("Procs 0.1000000000000000000001 of the time");
+/// let diagnostic = &doc.nodes[0].diagnostics[0];
+/// assert_eq!((diagnostic.severity, diagnostic.code), (Severity::Warning, "numeric-precision"));
+/// assert!(diagnostic.message.contains("loses precision as a 64 bit float (0.1)"));
+/// ```
+pub fn parse_float(text: &str) -> Result<ParsedNumber, String> {
+    let trimmed = text.trim();
+    let value: f64 = trimmed
+        .parse()
+        .map_err(|_| format!("'{}' is not a valid float", trimmed))?;
+
+    if value.is_infinite() {
+        return Err(format!("'{}' overflows a 64 bit float", trimmed));
+    }
+
+    let warning = (significant_digits(trimmed) != significant_digits(&format!("{:e}", value)))
+        .then(|| {
+            format!(
+                "'{}' loses precision as a 64 bit float ({})",
+                trimmed, value
+            )
+        });
+
+    Ok(ParsedNumber {
+        value: GodotValue::Float(value),
+        warning,
+    })
+}
+
+/// Parse a YAML scalar yaml_rust2 classified as `Real`. Integers too big for an
+/// i64 also end up there, so those are kept as ints when possible.
+pub fn parse_yaml_real(text: &str) -> Result<ParsedNumber, String> {
+    let looks_integral = text
+        .trim()
+        .trim_start_matches(['-', '+'])
+        .chars()
+        .all(|c| c.is_ascii_digit());
+    if looks_integral && let Ok(value) = parse_int(text) {
+        return Ok(ParsedNumber {
+            value,
+            warning: None,
+        });
+    }
+    parse_float(text)
+}
+
+/// The mantissa digits of a decimal literal without leading or trailing zeros,
+/// so "0.10", "1e-1" and "1.0E-1" all compare equal.
+fn significant_digits(literal: &str) -> String {
+    let mantissa = literal.split(['e', 'E']).next().unwrap_or_default();
+    let digits: String = mantissa.chars().filter(|c| c.is_ascii_digit()).collect();
+    digits
+        .trim_start_matches('0')
+        .trim_end_matches('0')
+        .to_string()
+}
+
+/// Whether `literal` would be printed back differently from its parsed value,
+/// meaning the author's text must be kept around to re-emit it verbatim.
+pub fn literal_differs(literal: &str, value: &GodotValue) -> bool {
+    let canonical = match value {
+        GodotValue::Int(i) => i.to_string(),
+        GodotValue::UInt(u) => u.to_string(),
+        GodotValue::Float(f) => f.to_string(),
+        _ => return false,
+    };
+    literal.trim().trim_start_matches('+') != canonical
+}
@@ -0,0 +1,264 @@
+// obsidian.rs
+//
+// Obsidian syntax, for design docs kept in a vault (`DokePipe::with_obsidian`):
+// - `%%comments%%` are left out
+// - callouts (`> [!note] Title`) are read as statements, recording their kind
+// - `[[target|alias]]` wikilinks are matched as `[[target]]`, recording the alias
+// - dataview fields: a `key:: value` statement sets a frontmatter key (or a field
+//   of its parent statement when nested), `[key:: value]` in a statement sets a field
+//
+// Comments and callout markers are blanked out before the markdown is parsed,
+// so spans still point into the original document, then the quotes holding
+// callout content are unwrapped.
+
+use std::collections::HashMap;
+use std::ops::Range;
+use std::sync::LazyLock;
+
+use markdown::mdast::Node;
+use regex::Regex;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::{DokeNode, GodotValue};
+
+/// `parse_data` key holding the kind of the callout a statement is in (`note`, `warning`...)
+pub const CALLOUT_KEY: &str = "obsidian_callout";
+
+/// `parse_data` key holding the dataview fields of a statement, a `Dict`
+pub const FIELDS_KEY: &str = "obsidian_fields";
+
+/// `parse_data` key holding the wikilinks of a statement, an `Array` of
+/// `Dict`s with a `target` and an optional `alias`
+pub const LINKS_KEY: &str = "obsidian_links";
+
+static CALLOUT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^ {0,3}>\s*\[!([\w-]+)\][+-]?").unwrap());
+static QUOTE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^ {0,3}>").unwrap());
+static WIKILINK: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\[\[([^\[\]|#]+)(?:#[^\[\]|]*)?(?:\|([^\[\]]*))?\]\]").unwrap());
+static INLINE_FIELD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"[\[(]\s*([\w][\w -]*?)\s*::\s*([^\[\]()]*?)\s*[\])]").unwrap());
+static LINE_FIELD: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^([\w][\w -]*?)\s*::\s*(.*)$").unwrap());
+
+/// What `preprocess` blanked out, by byte range
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Blanked {
+    comments: Vec<Range<usize>>,
+    /// (range, kind)
+    callouts: Vec<(Range<usize>, String)>,
+}
+
+/// Blank out comments and callout markers, keeping every byte offset
+pub(crate) fn preprocess(markdown: &str) -> (String, Blanked) {
+    let mut blanked = Blanked::default();
+    let mut out = String::with_capacity(markdown.len());
+    let mut comment_start = None;
+    let mut rest = markdown;
+    while !rest.is_empty() {
+        let (text, after) = match rest.find("%%") {
+            Some(i) => (&rest[..i], Some(&rest[i + 2..])),
+            None => (rest, None),
+        };
+        match comment_start {
+            Some(_) => out.push_str(&blank(text)),
+            None => out.push_str(text),
+        }
+        match after {
+            Some(after) => {
+                out.push_str("  ");
+                comment_start = match comment_start {
+                    Some(start) => {
+                        blanked.comments.push(start..out.len());
+                        None
+                    }
+                    None => Some(out.len() - 2),
+                };
+                rest = after;
+            }
+            None => rest = "",
+        }
+    }
+    if let Some(start) = comment_start {
+        blanked.comments.push(start..out.len());
+    }
+
+    let mut lines = String::with_capacity(out.len());
+    let mut offset = 0;
+    for line in out.split_inclusive('\n') {
+        if let Some(c) = CALLOUT.captures(line) {
+            blanked
+                .callouts
+                .push((offset..offset + line.len(), c[1].to_lowercase()));
+            lines.push_str(&blank(line));
+        } else if let Some((range, _)) = blanked.callouts.last_mut()
+            && range.end == offset
+            && QUOTE.is_match(line)
+        {
+            range.end += line.len();
+            lines.push_str(line);
+        } else {
+            lines.push_str(line);
+        }
+        offset += line.len();
+    }
+    (lines, blanked)
+}
+
+/// Read the content of callouts as if it wasn't quoted.
+///
+/// Callout markers are blanked out, so their content is a quote of its own.
+pub(crate) fn unwrap_callouts(node: &mut Node, blanked: &Blanked) {
+    let Some(children) = node.children_mut() else {
+        return;
+    };
+    let mut i = 0;
+    while i < children.len() {
+        let in_callout = matches!(children[i], Node::Blockquote(_))
+            && children[i].position().is_some_and(|p| {
+                blanked
+                    .callouts
+                    .iter()
+                    .any(|(range, _)| range.contains(&p.start.offset))
+            });
+        if in_callout {
+            let quote = children.remove(i);
+            let content = quote.children().cloned().unwrap_or_default();
+            let count = content.len();
+            children.splice(i..i, content);
+            i += count;
+        } else {
+            unwrap_callouts(&mut children[i], blanked);
+            i += 1;
+        }
+    }
+}
+
+/// Spaces for every byte of `text`, newlines kept
+fn blank(text: &str) -> String {
+    text.chars()
+        .map(|c| match c {
+            '\n' | '\r' => c.to_string(),
+            c => " ".repeat(c.len_utf8()),
+        })
+        .collect()
+}
+
+/// Record callouts, links and fields in `parse_data`, removing field statements.
+///
+/// Returns the fields of top level `key:: value` statements, for the frontmatter.
+pub(crate) fn apply(nodes: &mut Vec<DokeNode>, blanked: &Blanked) -> Vec<(String, GodotValue)> {
+    let mut page_fields = Vec::new();
+    nodes.retain_mut(|node| match line_field(&node.statement) {
+        Some(field) => {
+            page_fields.push(field);
+            false
+        }
+        None => {
+            apply_node(node, blanked);
+            true
+        }
+    });
+    page_fields
+}
+
+fn apply_node(node: &mut DokeNode, blanked: &Blanked) {
+    if let Some((_, kind)) = blanked
+        .callouts
+        .iter()
+        .find(|(range, _)| range.contains(&node.span.start))
+    {
+        node.parse_data
+            .insert(CALLOUT_KEY.into(), GodotValue::String(kind.clone()));
+    }
+    // a comment inside the statement left spaces
+    if blanked
+        .comments
+        .iter()
+        .any(|c| c.start < node.span.end && node.span.start < c.end)
+    {
+        node.statement = node
+            .statement
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into();
+    }
+
+    let mut fields = HashMap::new();
+    node.children
+        .retain(|child| match line_field(&child.statement) {
+            Some((key, value)) => {
+                fields.insert(key, value);
+                false
+            }
+            None => true,
+        });
+    for c in INLINE_FIELD.captures_iter(&node.statement) {
+        fields.insert(c[1].to_string(), field_value(&c[2]));
+    }
+    if !fields.is_empty() {
+        let statement = INLINE_FIELD.replace_all(&node.statement, "");
+        node.statement = statement
+            .split_whitespace()
+            .collect::<Vec<_>>()
+            .join(" ")
+            .into();
+        node.parse_data
+            .insert(FIELDS_KEY.into(), GodotValue::Dict(fields));
+    }
+
+    let links: Vec<GodotValue> = WIKILINK
+        .captures_iter(&node.statement)
+        .map(|c| {
+            let mut link = HashMap::from([(
+                "target".to_string(),
+                GodotValue::String(c[1].trim().to_string()),
+            )]);
+            if let Some(alias) = c.get(2) {
+                link.insert(
+                    "alias".into(),
+                    GodotValue::String(alias.as_str().trim().into()),
+                );
+            }
+            GodotValue::Dict(link)
+        })
+        .collect();
+    if !links.is_empty() {
+        node.statement = WIKILINK
+            .replace_all(&node.statement, |c: &regex::Captures| {
+                format!("[[{}]]", c[1].trim())
+            })
+            .into();
+        node.parse_data
+            .insert(LINKS_KEY.into(), GodotValue::Array(links));
+    }
+
+    node.statement = node.statement.slice_of(node.statement.trim());
+    for child in &mut node.children {
+        apply_node(child, blanked);
+    }
+}
+
+/// `key:: value` as a whole statement
+fn line_field(statement: &str) -> Option<(String, GodotValue)> {
+    let c = LINE_FIELD.captures(statement.trim())?;
+    Some((c[1].to_string(), field_value(&c[2])))
+}
+
+/// Numbers and bools as such, anything else as text
+pub(crate) fn field_value(text: &str) -> GodotValue {
+    let text = text.trim();
+    match YamlLoader::load_from_str(text)
+        .ok()
+        .and_then(|d| d.into_iter().next())
+    {
+        Some(Yaml::Integer(i)) => GodotValue::Int(i),
+        Some(Yaml::Real(r)) => r
+            .parse()
+            .map(GodotValue::Float)
+            .unwrap_or_else(|_| GodotValue::String(text.into())),
+        Some(Yaml::Boolean(b)) => GodotValue::Bool(b),
+        _ => GodotValue::String(text.into()),
+    }
+}
@@ -0,0 +1,160 @@
+// overrides.rs
+//
+// Merging override documents (`my_item.override.md`) over a base document.
+// Both are merged as unresolved nodes, so parsers only ever see one document.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::semantic::BlockKind;
+use crate::{DokeNode, GodotValue, MergeStrategy};
+
+/// `parse_data` key holding the index of the override file a node comes from
+pub const OVERRIDE_KEY: &str = "doke_override";
+
+pub(crate) static ANCHOR: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\s*\{#([\w-]+)\}\s*$").unwrap());
+static HEADING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^(#{1,6})\s+(.*?)[\s#]*$").unwrap());
+
+/// The `{#id}` a statement ends with
+pub(crate) fn anchor(statement: &str) -> Option<&str> {
+    ANCHOR
+        .captures(statement)
+        .and_then(|caps| caps.get(1))
+        .map(|m| m.as_str())
+}
+
+/// Level and title of a heading statement, read from the text for nodes that aren't
+/// markdown blocks
+fn heading(node: &DokeNode) -> Option<(usize, String)> {
+    let caps = HEADING.captures(node.statement.trim())?;
+    match node.block {
+        BlockKind::Heading { .. } | BlockKind::Other => {
+            Some((caps[1].len(), caps[2].trim().to_lowercase()))
+        }
+        _ => None,
+    }
+}
+
+/// Record which override file the nodes come from, since their spans point into it
+pub(crate) fn mark_source(nodes: &mut [DokeNode], index: usize) {
+    for node in nodes {
+        node.parse_data
+            .insert(OVERRIDE_KEY.into(), GodotValue::Int(index as i64));
+        mark_source(&mut node.children, index);
+    }
+}
+
+/// Override values win, dictionaries are merged key by key
+pub(crate) fn merge_frontmatter(
+    base: &mut HashMap<String, GodotValue>,
+    patch: HashMap<String, GodotValue>,
+) {
+    for (key, value) in patch {
+        match base.get_mut(&key) {
+            Some(existing) => existing.merge(value, MergeStrategy::Override),
+            None => {
+                base.insert(key, value);
+            }
+        }
+    }
+}
+
+/// Statements of a document between two headings
+struct Section {
+    /// Lowercase titles of the heading and its parents, empty before the first heading
+    path: Vec<String>,
+    heading: Option<DokeNode>,
+    body: Vec<DokeNode>,
+}
+
+pub(crate) fn merge_nodes(base: &mut Vec<DokeNode>, patch: Vec<DokeNode>) {
+    // anchored statements replace their counterpart wherever it is
+    let mut rest = Vec::new();
+    for node in patch {
+        let existing = anchor(&node.statement).and_then(|id| find_anchored(base, id));
+        match existing {
+            Some(existing) => *existing = node,
+            None => rest.push(node),
+        }
+    }
+
+    let mut sections = split_sections(std::mem::take(base));
+    for patch_section in split_sections(rest) {
+        match sections.iter_mut().find(|s| s.path == patch_section.path) {
+            Some(section) => {
+                // lists right under a heading are its children
+                let patch_children = patch_section
+                    .heading
+                    .map(|heading| heading.children)
+                    .unwrap_or_default();
+                if !patch_section.body.is_empty() || !patch_children.is_empty() {
+                    section.body = patch_section.body;
+                    if let Some(heading) = &mut section.heading {
+                        heading.children = patch_children;
+                    }
+                }
+            }
+            None => {
+                // new sections go after the last section of their parent
+                let parent = &patch_section.path[..patch_section.path.len() - 1];
+                let position = sections
+                    .iter()
+                    .rposition(|s| s.path.starts_with(parent))
+                    .map_or(sections.len(), |i| i + 1);
+                sections.insert(position, patch_section);
+            }
+        }
+    }
+
+    for section in sections {
+        base.extend(section.heading);
+        base.extend(section.body);
+    }
+}
+
+/// The node anchored with `id`, anywhere in the tree
+fn find_anchored<'a>(nodes: &'a mut [DokeNode], id: &str) -> Option<&'a mut DokeNode> {
+    for node in nodes {
+        if anchor(&node.statement) == Some(id) {
+            return Some(node);
+        }
+        if let Some(found) = find_anchored(&mut node.children, id) {
+            return Some(found);
+        }
+    }
+    None
+}
+
+fn split_sections(nodes: Vec<DokeNode>) -> Vec<Section> {
+    let mut sections = vec![Section {
+        path: Vec::new(),
+        heading: None,
+        body: Vec::new(),
+    }];
+    let mut stack: Vec<(usize, String)> = Vec::new();
+    for node in nodes {
+        match heading(&node) {
+            Some((level, title)) => {
+                while stack.last().is_some_and(|(l, _)| *l >= level) {
+                    stack.pop();
+                }
+                stack.push((level, title));
+                sections.push(Section {
+                    path: stack.iter().map(|(_, t)| t.clone()).collect(),
+                    heading: Some(node),
+                    body: Vec::new(),
+                });
+            }
+            None => sections
+                .last_mut()
+                .expect("there is always a first section")
+                .body
+                .push(node),
+        }
+    }
+    sections
+}
@@ -0,0 +1,311 @@
+// bounds.rs
+//
+// Ranges numeric parameters must fall in, written after their type like Rust ranges:
+//
+//     Damage:
+//       - "Deals {damage: int(0..999)} damage"
+//     Crit:
+//       - "Crits with {chance: float(0..=1)} chance"
+//
+// `0..999` excludes 999, `0..=1` includes 1, and either end may be left out (`1..`).
+// A value outside its range is an `out-of-range` error naming the allowed range,
+// and is left out of the statement's values instead of reaching game data.
+//
+// Strings take a length and a pattern the whole value must match, for names the UI
+// has room for and ids usable as save-file keys:
+//
+//     Title:
+//       - 'Called {name: string(max=32, pattern="[A-Za-z '']+")}'
+//
+// A pattern can't contain `}`, which ends the parameter.
+
+use std::fmt;
+
+use regex::Regex;
+
+use crate::GodotValue;
+
+/// Diagnostic code of parameters outside their bounds
+pub const OUT_OF_RANGE_CODE: &str = "out-of-range";
+
+/// Diagnostic code of strings too long, too short or not matching their pattern
+pub const STRING_CONSTRAINT_CODE: &str = "string-constraint";
+
+/// Range of the values a numeric parameter takes, see `ParameterDefinition::bounds`
+/// ```
+/// use doke::parsers::Bounds;
+///
+/// let chance = Bounds::parse("0..=1").unwrap();
+/// assert!(chance.contains(1.0));
+/// assert!(!chance.contains(1.5));
+/// assert!(!Bounds::parse("0..999").unwrap().contains(999.0));
+/// assert_eq!(Bounds::parse("1..").unwrap().to_string(), "1..");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Bounds {
+    pub min: Option<f64>,
+    pub max: Option<f64>,
+    /// `max` is allowed, `..=`
+    pub inclusive: bool,
+}
+
+impl Bounds {
+    /// `0..999`, `0..=1`, `1..` or `..10`
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let invalid = || format!("Invalid bounds '{}', expected a range like 0..999", text);
+        let (min, max, inclusive) = match text.split_once("..=") {
+            Some((min, max)) => (min, max, true),
+            None => {
+                let (min, max) = text.split_once("..").ok_or_else(invalid)?;
+                (min, max, false)
+            }
+        };
+        let end = |end: &str| match end.trim() {
+            "" => Ok(None),
+            end => end.parse::<f64>().map(Some).map_err(|_| invalid()),
+        };
+        let bounds = Self {
+            min: end(min)?,
+            max: end(max)?,
+            inclusive,
+        };
+        if inclusive && bounds.max.is_none() {
+            return Err(invalid());
+        }
+        if let (Some(min), Some(max)) = (bounds.min, bounds.max)
+            && (min > max || (min == max && !inclusive))
+        {
+            return Err(format!("Empty bounds '{}'", text));
+        }
+        Ok(bounds)
+    }
+
+    pub fn contains(&self, value: f64) -> bool {
+        let above_min = self.min.is_none_or(|min| value >= min);
+        let below_max = match (self.max, self.inclusive) {
+            (None, _) => true,
+            (Some(max), true) => value <= max,
+            (Some(max), false) => value < max,
+        };
+        above_min && below_max
+    }
+
+    /// Whether a parsed value is outside the bounds, values that aren't numbers aren't
+    pub(crate) fn excludes(&self, value: &GodotValue) -> bool {
+        match value {
+            GodotValue::Int(i) => !self.contains(*i as f64),
+            GodotValue::UInt(u) => !self.contains(*u as f64),
+            GodotValue::Float(f) => !self.contains(*f),
+            _ => false,
+        }
+    }
+
+    /// `example`, or the lowest allowed value when it's outside
+    pub(crate) fn example(&self, example: &str) -> String {
+        match example.parse::<f64>().map(|v| self.contains(v)) {
+            Ok(false) => match self.min {
+                Some(min) => min.to_string(),
+                None => self
+                    .max
+                    .map(|max| (max - 1.0).to_string())
+                    .unwrap_or_default(),
+            },
+            _ => example.to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Bounds {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(min) = self.min {
+            write!(f, "{}", min)?;
+        }
+        write!(f, "..")?;
+        if self.inclusive {
+            write!(f, "=")?;
+        }
+        if let Some(max) = self.max {
+            write!(f, "{}", max)?;
+        }
+        Ok(())
+    }
+}
+
+/// Length and pattern of the values a string parameter takes, see `ParameterDefinition::text`
+/// ```
+/// use doke::parsers::TextConstraints;
+///
+/// let name = TextConstraints::parse(r#"max=8, pattern="[a-z_]+""#).unwrap();
+/// assert_eq!(name.check("iron_sword"), Err("'iron_sword' is 10 characters, at most 8".into()));
+/// assert_eq!(name.check("sword!"), Err("'sword!' doesn't match [a-z_]+".into()));
+/// assert_eq!(name.check("sword"), Ok(()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct TextConstraints {
+    /// Characters, not bytes
+    pub min_len: Option<usize>,
+    pub max_len: Option<usize>,
+    /// Matched against the whole value
+    pub pattern: Option<Regex>,
+}
+
+impl PartialEq for TextConstraints {
+    fn eq(&self, other: &Self) -> bool {
+        self.min_len == other.min_len
+            && self.max_len == other.max_len
+            && self.pattern.as_ref().map(Regex::as_str) == other.pattern.as_ref().map(Regex::as_str)
+    }
+}
+
+impl TextConstraints {
+    /// `min=1, max=32, pattern="[A-Za-z ]+"`, each optional
+    pub fn parse(text: &str) -> Result<Self, String> {
+        let mut constraints = Self {
+            min_len: None,
+            max_len: None,
+            pattern: None,
+        };
+        for argument in split_arguments(text) {
+            let (key, value) = argument
+                .split_once('=')
+                .ok_or_else(|| format!("Expected key=value, got '{}'", argument.trim()))?;
+            let value = unquote(value.trim());
+            let length = || {
+                value
+                    .parse::<usize>()
+                    .map_err(|_| format!("Invalid length '{}'", value))
+            };
+            match key.trim() {
+                "min" => constraints.min_len = Some(length()?),
+                "max" => constraints.max_len = Some(length()?),
+                "pattern" => {
+                    let anchored = format!("^(?:{})$", value);
+                    let regex = Regex::new(&anchored)
+                        .map_err(|e| format!("Invalid pattern '{}': {}", value, e))?;
+                    constraints.pattern = Some(regex);
+                }
+                key => {
+                    return Err(format!(
+                        "Unknown string constraint '{}', expected min, max or pattern",
+                        key
+                    ));
+                }
+            }
+        }
+        Ok(constraints)
+    }
+
+    /// Why `value` isn't allowed, if it isn't
+    pub fn check(&self, value: &str) -> Result<(), String> {
+        let length = value.chars().count();
+        if let Some(max) = self.max_len.filter(|max| length > *max) {
+            return Err(format!(
+                "'{}' is {} characters, at most {}",
+                value, length, max
+            ));
+        }
+        if let Some(min) = self.min_len.filter(|min| length < *min) {
+            return Err(format!(
+                "'{}' is {} characters, at least {}",
+                value, length, min
+            ));
+        }
+        match &self.pattern {
+            Some(pattern) if !pattern.is_match(value) => Err(format!(
+                "'{}' doesn't match {}",
+                value,
+                self.pattern_source().unwrap_or_default()
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// The pattern as declared, without the anchors
+    fn pattern_source(&self) -> Option<&str> {
+        self.pattern
+            .as_ref()
+            .map(|p| &p.as_str()["^(?:".len()..p.as_str().len() - ")$".len()])
+    }
+}
+
+impl fmt::Display for TextConstraints {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut arguments = Vec::new();
+        if let Some(min) = self.min_len {
+            arguments.push(format!("min={}", min));
+        }
+        if let Some(max) = self.max_len {
+            arguments.push(format!("max={}", max));
+        }
+        if let Some(pattern) = self.pattern_source() {
+            arguments.push(format!("pattern={:?}", pattern));
+        }
+        write!(f, "{}", arguments.join(", "))
+    }
+}
+
+/// Split on the commas outside quotes
+fn split_arguments(text: &str) -> Vec<&str> {
+    let mut arguments = Vec::new();
+    let mut quote = None;
+    let mut start = 0;
+    for (i, c) in text.char_indices() {
+        match (c, quote) {
+            ('"' | '\'', None) => quote = Some(c),
+            (c, Some(q)) if c == q => quote = None,
+            (',', None) => {
+                arguments.push(&text[start..i]);
+                start = i + 1;
+            }
+            _ => {}
+        }
+    }
+    arguments.push(&text[start..]);
+    arguments.retain(|argument| !argument.trim().is_empty());
+    arguments
+}
+
+fn unquote(value: &str) -> &str {
+    ['"', '\'']
+        .iter()
+        .find_map(|q| value.strip_prefix(*q)?.strip_suffix(*q))
+        .unwrap_or(value)
+}
+
+/// A parameter type as declared, `int(0..999)` or `string(max=32)`
+pub(crate) struct DeclaredType {
+    pub param_type: String,
+    pub bounds: Option<Bounds>,
+    pub text: Option<TextConstraints>,
+}
+
+/// Split a declared type into the type and its constraints.
+/// `int` and `float` take bounds, `string` text constraints.
+pub(crate) fn split_type(declared: &str) -> Result<DeclaredType, String> {
+    let Some((ty, rest)) = declared.split_once('(') else {
+        return Ok(DeclaredType {
+            param_type: declared.to_string(),
+            bounds: None,
+            text: None,
+        });
+    };
+    let ty = ty.trim();
+    let arguments = rest
+        .strip_suffix(')')
+        .ok_or_else(|| format!("Unclosed constraints in '{}'", declared))?;
+    let (bounds, text) = match ty.to_lowercase().as_str() {
+        "int" | "float" => (Some(Bounds::parse(arguments)?), None),
+        "string" => (None, Some(TextConstraints::parse(arguments)?)),
+        _ => {
+            return Err(format!(
+                "Only int, float and string parameters take constraints, not '{}'",
+                declared
+            ));
+        }
+    };
+    Ok(DeclaredType {
+        param_type: ty.to_string(),
+        bounds,
+        text,
+    })
+}
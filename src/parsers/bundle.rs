@@ -0,0 +1,187 @@
+// parsers/bundle.rs
+//
+// Packaged grammar bundles: `.zip`, `.tar` or `.tar.gz` archives of
+// `.dokedef.yaml` files, loaded from disk or (with the `http` feature)
+// from a registry URL, and verified against a sha256 checksum.
+
+use std::io::{Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use glob::Pattern;
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::parsers::typed_sentences::BundleSource;
+
+#[derive(Debug, Error)]
+pub enum BundleError {
+    #[error("Could not read bundle '{0}': {1}")]
+    Io(String, std::io::Error),
+
+    #[error("Could not download bundle '{0}': {1}")]
+    Http(String, String),
+
+    #[error("Remote bundle '{0}' must declare a sha256 checksum")]
+    MissingChecksum(String),
+
+    #[error("Checksum mismatch for bundle '{location}': expected {expected}, got {actual}")]
+    ChecksumMismatch {
+        location: String,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Unsupported bundle format: '{0}' (expected .zip, .tar, .tar.gz or .tgz)")]
+    UnsupportedFormat(String),
+
+    #[error("Invalid archive '{0}': {1}")]
+    Archive(String, String),
+
+    #[error("Invalid pattern '{0}': {1}")]
+    Pattern(String, String),
+}
+
+impl BundleSource {
+    /// Fetch the archive, verify it, and return every file inside it
+    /// matching `pattern`, as (path inside the archive, content) pairs.
+    pub fn read_matching(
+        &self,
+        base_dir: &Path,
+        pattern: &str,
+    ) -> Result<Vec<(PathBuf, String)>, BundleError> {
+        let bytes = self.fetch(base_dir)?;
+        self.verify(&bytes)?;
+
+        let pattern = Pattern::new(pattern)
+            .map_err(|e| BundleError::Pattern(pattern.into(), e.to_string()))?;
+        let files = self.extract(bytes)?;
+
+        Ok(files
+            .into_iter()
+            .filter(|(path, _)| pattern.matches_path(path))
+            .collect())
+    }
+
+    fn fetch(&self, base_dir: &Path) -> Result<Vec<u8>, BundleError> {
+        if self.is_remote() {
+            if self.sha256.is_none() {
+                return Err(BundleError::MissingChecksum(self.location.clone()));
+            }
+            return fetch_remote(&self.location);
+        }
+
+        std::fs::read(base_dir.join(&self.location))
+            .map_err(|e| BundleError::Io(self.location.clone(), e))
+    }
+
+    fn verify(&self, bytes: &[u8]) -> Result<(), BundleError> {
+        let Some(expected) = &self.sha256 else {
+            return Ok(());
+        };
+        let actual = format!("{:x}", Sha256::digest(bytes));
+        if actual.eq_ignore_ascii_case(expected.trim()) {
+            Ok(())
+        } else {
+            Err(BundleError::ChecksumMismatch {
+                location: self.location.clone(),
+                expected: expected.clone(),
+                actual,
+            })
+        }
+    }
+
+    fn extract(&self, bytes: Vec<u8>) -> Result<Vec<(PathBuf, String)>, BundleError> {
+        // Strip any query string from registry URLs before looking at the extension
+        let name = self
+            .location
+            .split(['?', '#'])
+            .next()
+            .unwrap_or_default()
+            .to_lowercase();
+
+        if name.ends_with(".zip") {
+            self.extract_zip(bytes)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            self.extract_tar(flate2::read::GzDecoder::new(Cursor::new(bytes)))
+        } else if name.ends_with(".tar") {
+            self.extract_tar(Cursor::new(bytes))
+        } else {
+            Err(BundleError::UnsupportedFormat(self.location.clone()))
+        }
+    }
+
+    fn extract_zip(&self, bytes: Vec<u8>) -> Result<Vec<(PathBuf, String)>, BundleError> {
+        let archive_err =
+            |e: &dyn std::fmt::Display| BundleError::Archive(self.location.clone(), e.to_string());
+        let mut archive = zip::ZipArchive::new(Cursor::new(bytes)).map_err(|e| archive_err(&e))?;
+        let mut files = Vec::new();
+
+        for i in 0..archive.len() {
+            let mut entry = archive.by_index(i).map_err(|e| archive_err(&e))?;
+            // enclosed_name rejects absolute paths and `..` components
+            let Some(path) = entry.enclosed_name() else {
+                continue;
+            };
+            if !entry.is_file() {
+                continue;
+            }
+            let mut content = String::new();
+            entry
+                .read_to_string(&mut content)
+                .map_err(|e| archive_err(&e))?;
+            files.push((path, content));
+        }
+
+        Ok(files)
+    }
+
+    fn extract_tar<R: Read>(&self, reader: R) -> Result<Vec<(PathBuf, String)>, BundleError> {
+        let archive_err =
+            |e: &dyn std::fmt::Display| BundleError::Archive(self.location.clone(), e.to_string());
+        let mut archive = tar::Archive::new(reader);
+        let mut files = Vec::new();
+
+        for entry in archive.entries().map_err(|e| archive_err(&e))? {
+            let mut entry = entry.map_err(|e| archive_err(&e))?;
+            if !entry.header().entry_type().is_file() {
+                continue;
+            }
+            let path = entry.path().map_err(|e| archive_err(&e))?.into_owned();
+            if path.is_absolute()
+                || path
+                    .components()
+                    .any(|c| matches!(c, std::path::Component::ParentDir))
+            {
+                continue;
+            }
+            let mut content = String::new();
+            entry
+                .read_to_string(&mut content)
+                .map_err(|e| archive_err(&e))?;
+            files.push((path, content));
+        }
+
+        Ok(files)
+    }
+}
+
+#[cfg(feature = "http")]
+fn fetch_remote(url: &str) -> Result<Vec<u8>, BundleError> {
+    let response = ureq::get(url)
+        .call()
+        .map_err(|e| BundleError::Http(url.into(), e.to_string()))?;
+    let mut bytes = Vec::new();
+    response
+        .into_reader()
+        .read_to_end(&mut bytes)
+        .map_err(|e| BundleError::Http(url.into(), e.to_string()))?;
+    Ok(bytes)
+}
+
+#[cfg(not(feature = "http"))]
+fn fetch_remote(url: &str) -> Result<Vec<u8>, BundleError> {
+    Err(BundleError::Http(
+        url.into(),
+        "remote bundles require the `http` feature".into(),
+    ))
+}
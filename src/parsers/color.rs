@@ -0,0 +1,152 @@
+// parsers/color.rs
+//
+// `color` parameters: a color written as `#rrggbb` (or `#rgb`, `#rrggbbaa`,
+// `#rgba`), `rgb(255, 128, 0)`, `rgba(255, 128, 0, 0.5)`, or one of the
+// common CSS names below ("crimson"). They become a `Color` value with
+// components from 0 to 1, like Godot's.
+
+use std::sync::LazyLock;
+
+/// CSS names and their `0xRRGGBB` value
+const NAMED_COLORS: &[(&str, u32)] = &[
+    ("black", 0x000000),
+    ("white", 0xffffff),
+    ("gray", 0x808080),
+    ("grey", 0x808080),
+    ("silver", 0xc0c0c0),
+    ("red", 0xff0000),
+    ("crimson", 0xdc143c),
+    ("maroon", 0x800000),
+    ("tomato", 0xff6347),
+    ("coral", 0xff7f50),
+    ("salmon", 0xfa8072),
+    ("orange", 0xffa500),
+    ("gold", 0xffd700),
+    ("yellow", 0xffff00),
+    ("khaki", 0xf0e68c),
+    ("olive", 0x808000),
+    ("lime", 0x00ff00),
+    ("green", 0x008000),
+    ("forestgreen", 0x228b22),
+    ("teal", 0x008080),
+    ("cyan", 0x00ffff),
+    ("aqua", 0x00ffff),
+    ("turquoise", 0x40e0d0),
+    ("skyblue", 0x87ceeb),
+    ("blue", 0x0000ff),
+    ("navy", 0x000080),
+    ("indigo", 0x4b0082),
+    ("purple", 0x800080),
+    ("violet", 0xee82ee),
+    ("magenta", 0xff00ff),
+    ("fuchsia", 0xff00ff),
+    ("pink", 0xffc0cb),
+    ("hotpink", 0xff69b4),
+    ("brown", 0xa52a2a),
+    ("chocolate", 0xd2691e),
+    ("tan", 0xd2b48c),
+    ("beige", 0xf5f5dc),
+    ("ivory", 0xfffff0),
+];
+
+/// What a `color` parameter captures
+pub(crate) static COLOR_PATTERN: LazyLock<String> = LazyLock::new(|| {
+    let mut names: Vec<&str> = NAMED_COLORS.iter().map(|(name, _)| *name).collect();
+    // longest first, so "forestgreen" isn't read as a name starting it
+    names.sort_by_key(|name| std::cmp::Reverse(name.len()));
+    format!(
+        r"((?i:#(?:[0-9a-f]{{8}}|[0-9a-f]{{6}}|[0-9a-f]{{3,4}})\b|rgba?\([^()\n]*\)|(?:{})\b))",
+        names.join("|")
+    )
+});
+
+/// `(r, g, b, a)` of `text`, each from 0 to 1
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let grammar = "Tint:\n  - \"Tints {tint: color}\"\n";
+/// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Tint".into(), grammar).unwrap());
+/// let tint = |text| pipe.validate(text).unwrap()[0].get_path("tint").cloned();
+///
+/// let orange = 128.0 / 255.0;
+/// assert_eq!(tint("Tints #ff8000"), Some(GodotValue::Color(1.0, orange, 0.0, 1.0)));
+/// assert_eq!(tint("Tints #f00"), Some(GodotValue::Color(1.0, 0.0, 0.0, 1.0)));
+/// assert_eq!(tint("Tints rgba(255, 128, 0, 0.5)"), Some(GodotValue::Color(1.0, orange, 0.0, 0.5)));
+/// assert_eq!(tint("Tints white"), Some(GodotValue::Color(1.0, 1.0, 1.0, 1.0)));
+///
+/// let doc = pipe.run_markdown("Tints rgb(300, 0, 0)");
+/// assert_eq!(doc.nodes[0].diagnostics[0].code, "invalid-parameter");
+/// ```
+pub(crate) fn parse_color(text: &str) -> Result<(f64, f64, f64, f64), String> {
+    let text = text.trim();
+    let lower = text.to_lowercase();
+    if let Some(hex) = lower.strip_prefix('#') {
+        return parse_hex(hex).ok_or_else(|| format!("Invalid hex color '{}'", text));
+    }
+    if let Some(args) = lower
+        .strip_prefix("rgba(")
+        .or_else(|| lower.strip_prefix("rgb("))
+        .and_then(|rest| rest.strip_suffix(')'))
+    {
+        return parse_rgb(args).ok_or_else(|| {
+            format!(
+                "Expected rgb(r, g, b) with components up to 255, and an alpha up to 1: {}",
+                text
+            )
+        });
+    }
+    NAMED_COLORS
+        .iter()
+        .find(|(name, _)| *name == lower)
+        .map(|(_, rgb)| {
+            let channel = |shift: u32| ((rgb >> shift) & 0xff) as f64 / 255.0;
+            (channel(16), channel(8), channel(0), 1.0)
+        })
+        .ok_or_else(|| format!("Unknown color '{}'", text))
+}
+
+/// `#rrggbb` (alpha included when it isn't opaque), as documents write colors
+pub(crate) fn to_hex(r: f64, g: f64, b: f64, a: f64) -> String {
+    let byte = |c: f64| (c.clamp(0.0, 1.0) * 255.0).round() as u8;
+    let rgb = format!("#{:02x}{:02x}{:02x}", byte(r), byte(g), byte(b));
+    match byte(a) {
+        255 => rgb,
+        alpha => format!("{}{:02x}", rgb, alpha),
+    }
+}
+
+fn parse_hex(hex: &str) -> Option<(f64, f64, f64, f64)> {
+    if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    // `#rgb` and `#rgba` repeat each digit
+    let digits: String = match hex.len() {
+        3 | 4 => hex.chars().flat_map(|c| [c, c]).collect(),
+        6 | 8 => hex.to_string(),
+        _ => return None,
+    };
+    let channel = |i: usize| {
+        digits
+            .get(i * 2..i * 2 + 2)
+            .map_or(Some(255), |pair| u8::from_str_radix(pair, 16).ok())
+            .map(|byte| byte as f64 / 255.0)
+    };
+    Some((channel(0)?, channel(1)?, channel(2)?, channel(3)?))
+}
+
+fn parse_rgb(args: &str) -> Option<(f64, f64, f64, f64)> {
+    let parts: Vec<f64> = args
+        .split(',')
+        .map(|part| part.trim().parse::<f64>().ok())
+        .collect::<Option<_>>()?;
+    let channel = |c: f64| (0.0..=255.0).contains(&c).then_some(c / 255.0);
+    let (rgb, alpha) = match parts[..] {
+        [r, g, b] => ([r, g, b], 1.0),
+        [r, g, b, a] => ([r, g, b], a),
+        _ => return None,
+    };
+    if !(0.0..=1.0).contains(&alpha) {
+        return None;
+    }
+    Some((channel(rgb[0])?, channel(rgb[1])?, channel(rgb[2])?, alpha))
+}
@@ -0,0 +1,144 @@
+// parsers/continuation.rs
+//
+// Effects written as prose, one sentence continuing another:
+//
+//     Deals 5 fire damage.
+//
+//     Additionally, burns the target for 3 turns.
+//
+// A statement starting with a connective ("Also", "Additionally,", "In addition,")
+// becomes the last child of the statement before it, without its connective,
+// so the grammar parses it like a bullet written under that statement and the
+// previous statement's value gets it as a child. No need to restructure the
+// text into lists. A continuation with nothing before it is left as written,
+// with a warning.
+
+use std::collections::HashMap;
+
+use crate::diagnostics::Diagnostic;
+use crate::{DokeNode, DokeNodeState, DokeParser, GodotValue};
+
+/// Connectives recognized by default
+const CONNECTIVES: &[&str] = &[
+    "additionally",
+    "also",
+    "furthermore",
+    "in addition",
+    "moreover",
+    "plus",
+];
+
+/// Moves statements starting with a connective under the statement before them.
+/// Add it before the parsers reading those statements.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::{ContinuationParser, SentenceParser}};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: int} {element} damage"
+/// Burn:
+///   - "Burns the target for {turns: int} turns"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(ContinuationParser::new()).map(parser);
+///
+/// let values = pipe
+///     .validate("Deals 5 fire damage.\n\nAdditionally, burns the target for 3 turns.")
+///     .unwrap();
+/// assert_eq!(values.len(), 1);
+/// assert_eq!(values[0].get_path("children/0/turns"), Some(&GodotValue::Int(3)));
+/// ```
+#[derive(Debug)]
+pub struct ContinuationParser {
+    /// Lowercase, longest first
+    connectives: Vec<String>,
+}
+
+impl Default for ContinuationParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ContinuationParser {
+    pub fn new() -> Self {
+        Self::with_connectives(CONNECTIVES.iter().copied())
+    }
+
+    /// Recognize these connectives instead of the default ones ("also", "additionally"...)
+    pub fn with_connectives<I, S>(connectives: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let mut connectives: Vec<String> = connectives
+            .into_iter()
+            .map(|c| c.into().to_lowercase())
+            .collect();
+        // "in addition" before "in"
+        connectives.sort_by_key(|c| std::cmp::Reverse(c.len()));
+        Self { connectives }
+    }
+
+    /// The statement without its leading connective, when it starts with one
+    fn strip(&self, statement: &str) -> Option<String> {
+        let trimmed = statement.trim_start();
+        let lower = trimmed.to_lowercase();
+        self.connectives.iter().find_map(|connective| {
+            let rest = lower.strip_prefix(connective.as_str())?;
+            // a whole word, "Plush toy" isn't a continuation
+            if !rest.starts_with([' ', ',', '\t']) {
+                return None;
+            }
+            let rest = trimmed
+                .get(connective.len()..)?
+                .trim_start()
+                .trim_start_matches(',')
+                .trim_start();
+            let mut chars = rest.chars();
+            let first = chars.next()?;
+            Some(first.to_uppercase().chain(chars).collect())
+        })
+    }
+
+    /// Move the continuations of `nodes` under the node before them, at every depth
+    fn attach(&self, nodes: &mut Vec<DokeNode>) {
+        let mut kept: Vec<DokeNode> = Vec::with_capacity(nodes.len());
+        for mut node in nodes.drain(..) {
+            self.attach(&mut node.children);
+            let continued = match node.state {
+                DokeNodeState::Unresolved => self.strip(&node.statement),
+                _ => None,
+            };
+            match (continued, kept.last_mut()) {
+                (Some(statement), Some(previous)) => {
+                    node.statement = statement.into();
+                    previous.children.push(node);
+                }
+                (Some(_), None) => {
+                    node.diagnostics.push(Diagnostic::warning(
+                        "continuation-without-statement",
+                        "this statement continues another one, but nothing comes before it",
+                    ));
+                    kept.push(node);
+                }
+                (None, _) => kept.push(node),
+            }
+        }
+        *nodes = kept;
+    }
+}
+
+impl DokeParser for ContinuationParser {
+    /// Continuations among the node's children, for a node processed on its own
+    fn process(&self, node: &mut DokeNode, _frontmatter: &HashMap<String, GodotValue>) {
+        self.attach(&mut node.children);
+    }
+
+    fn process_document(
+        &self,
+        nodes: &mut Vec<DokeNode>,
+        _frontmatter: &HashMap<String, GodotValue>,
+    ) {
+        self.attach(nodes);
+    }
+}
@@ -0,0 +1,275 @@
+// curve.rs
+//
+// Progression data written as a header and keyed bullets:
+//
+//     Experience:
+//     - Level 1: 100 XP
+//     - Level 2: 250 XP
+//
+// The header resolves to a `Curve` resource whose points are sorted by key,
+// ready to be imported as a Godot Curve.
+
+use regex::Regex;
+use std::collections::HashMap;
+
+use crate::diagnostics::Diagnostic;
+use crate::parsers::list_header;
+use crate::{DokeNode, DokeNodeState, DokeOut, DokeParser, GodotValue};
+
+/// Recognizes "Header:" statements whose children all look like
+/// "- Level 1: 100 XP", "- 1: 100" or "- Wave 3: 12 enemies".
+///
+/// The optional word before the key is kept as `key_label`, the optional
+/// word after the value as `unit`. Duplicate keys are errors.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::CurveParser};
+///
+/// let pipe = DokePipe::new().add(CurveParser::new());
+/// let values = pipe.validate("Experience:\n- Level 2: 250 XP\n- Level 1: 100 XP").unwrap();
+/// assert_eq!(values[0].get_path("unit"), Some(&GodotValue::String("XP".into())));
+/// assert_eq!(values[0].get_path("points/0/1"), Some(&GodotValue::Float(100.0)));
+///
+/// let doc = pipe.run_markdown("Experience:\n- Level 1: 100 XP\n- Level 1: 250 XP");
+/// assert_eq!(doc.nodes[0].children[1].diagnostics[0].code, "curve-duplicate-key");
+/// ```
+#[derive(Debug)]
+pub struct CurveParser {
+    point_regex: Regex,
+    headers: Option<Vec<String>>,
+}
+
+impl Default for CurveParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A parsed "- Level 1: 100 XP" bullet
+struct Point {
+    label: Option<String>,
+    x: f64,
+    y: f64,
+    unit: Option<String>,
+}
+
+impl CurveParser {
+    pub fn new() -> Self {
+        Self {
+            point_regex: Regex::new(
+                r"^\s*(?:([A-Za-z][\w ]*?)\s+)?([-+]?[0-9]*\.?[0-9]+)\s*:\s*([-+]?[0-9]*\.?[0-9]+)\s*([A-Za-z%][\w %]*?)?\s*\.?\s*$",
+            )
+            .unwrap(),
+            headers: None,
+        }
+    }
+
+    /// Only treat these headers (e.g. "Experience") as curves.
+    /// By default any header with keyed children is one.
+    pub fn with_headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.headers = Some(headers.into_iter().map(|h| h.into()).collect());
+        self
+    }
+
+    fn parse_point(&self, statement: &str) -> Option<Point> {
+        let caps = self.point_regex.captures(statement)?;
+        Some(Point {
+            label: caps.get(1).map(|m| m.as_str().to_string()),
+            x: caps[2].parse().ok()?,
+            y: caps[3].parse().ok()?,
+            unit: caps.get(4).map(|m| m.as_str().to_string()),
+        })
+    }
+
+    fn process_curve(&self, node: &mut DokeNode, name: String, points: Vec<Point>) {
+        let curve = Curve {
+            name: name.clone(),
+            key_label: points.iter().find_map(|p| p.label.clone()),
+            unit: points.iter().find_map(|p| p.unit.clone()),
+            points: Vec::new(),
+        };
+
+        let mut seen: Vec<f64> = Vec::new();
+        for (child, point) in node.children.iter_mut().zip(&points) {
+            if seen.contains(&point.x) {
+                child.diagnostics.push(
+                    Diagnostic::error(
+                        "curve-duplicate-key",
+                        format!("'{}' defines key {} more than once", name, point.x),
+                    )
+                    .with_span(child.span.clone()),
+                );
+            } else if seen.last().is_some_and(|last| *last > point.x) {
+                child.diagnostics.push(
+                    Diagnostic::warning(
+                        "curve-unsorted-key",
+                        format!("key {} of '{}' is out of order", point.x, name),
+                    )
+                    .with_span(child.span.clone()),
+                );
+            }
+            seen.push(point.x);
+
+            if point.label.is_some() && point.label != curve.key_label
+                || point.unit.is_some() && point.unit != curve.unit
+            {
+                child.diagnostics.push(
+                    Diagnostic::warning(
+                        "curve-mixed-units",
+                        format!(
+                            "'{}' doesn't use the same label or unit as '{}'",
+                            child.statement, name
+                        ),
+                    )
+                    .with_span(child.span.clone()),
+                );
+            }
+
+            child.state = DokeNodeState::Resolved(Box::new(CurvePoint {
+                x: point.x,
+                y: point.y,
+            }));
+        }
+
+        node.parse_data
+            .insert("abstract_type".into(), GodotValue::String("Curve".into()));
+        node.state = DokeNodeState::Resolved(Box::new(curve));
+    }
+}
+
+impl DokeParser for CurveParser {
+    #[allow(clippy::only_used_in_recursion)]
+    fn process(&self, node: &mut DokeNode, frontmatter: &HashMap<String, GodotValue>) {
+        if matches!(node.state, DokeNodeState::Unresolved)
+            && !node.children.is_empty()
+            && let Some(name) = list_header(node, self.headers.as_deref())
+        {
+            let points: Option<Vec<_>> = node
+                .children
+                .iter()
+                .map(|child| self.parse_point(&child.statement))
+                .collect();
+            // Only claim the node when every child is a point
+            if let Some(points) = points {
+                let name = name.to_string();
+                self.process_curve(node, name, points);
+                return;
+            }
+        }
+
+        for child in &mut node.children {
+            self.process(child, frontmatter);
+        }
+    }
+}
+
+// ----------------- Output -----------------
+
+#[derive(Debug)]
+struct Curve {
+    name: String,
+    key_label: Option<String>,
+    unit: Option<String>,
+    points: Vec<(f64, f64)>,
+}
+
+impl DokeOut for Curve {
+    fn kind(&self) -> &'static str {
+        "Curve"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        let mut points = self.points.clone();
+        points.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+        let xs = points.iter().map(|p| p.0);
+        let ys = points.iter().map(|p| p.1);
+        let mut fields = HashMap::from([
+            ("name".into(), GodotValue::String(self.name.clone())),
+            (
+                "points".into(),
+                GodotValue::Array(
+                    points
+                        .iter()
+                        .map(|(x, y)| {
+                            GodotValue::Array(vec![GodotValue::Float(*x), GodotValue::Float(*y)])
+                        })
+                        .collect(),
+                ),
+            ),
+            (
+                "min_domain".into(),
+                GodotValue::Float(xs.clone().fold(f64::INFINITY, f64::min)),
+            ),
+            (
+                "max_domain".into(),
+                GodotValue::Float(xs.fold(f64::NEG_INFINITY, f64::max)),
+            ),
+            (
+                "min_value".into(),
+                GodotValue::Float(ys.clone().fold(f64::INFINITY, f64::min)),
+            ),
+            (
+                "max_value".into(),
+                GodotValue::Float(ys.fold(f64::NEG_INFINITY, f64::max)),
+            ),
+        ]);
+        if let Some(label) = &self.key_label {
+            fields.insert("key_label".into(), GodotValue::String(label.clone()));
+        }
+        if let Some(unit) = &self.unit {
+            fields.insert("unit".into(), GodotValue::String(unit.clone()));
+        }
+
+        GodotValue::Resource {
+            type_name: "Curve".into(),
+            abstract_type_name: "Curve".into(),
+            fields,
+        }
+    }
+
+    fn get_asbtract_type(&self) -> Option<String> {
+        Some("Curve".into())
+    }
+
+    fn use_child(
+        &mut self,
+        child: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let GodotValue::Array(pair) = &child else {
+            return Err(Box::new(std::io::Error::other(format!(
+                "'{}' is not a curve point",
+                child
+            ))));
+        };
+        match pair[..] {
+            [GodotValue::Float(x), GodotValue::Float(y)] => {
+                self.points.push((x, y));
+                Ok(())
+            }
+            _ => Err(Box::new(std::io::Error::other(format!(
+                "'{}' is not a curve point",
+                child
+            )))),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct CurvePoint {
+    x: f64,
+    y: f64,
+}
+
+impl DokeOut for CurvePoint {
+    fn kind(&self) -> &'static str {
+        "CurvePoint"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Array(vec![GodotValue::Float(self.x), GodotValue::Float(self.y)])
+    }
+}
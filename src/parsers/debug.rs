@@ -13,6 +13,7 @@ impl DebugPrinter {
             DokeNodeState::Hypothesis(_) => "💡",
             DokeNodeState::Resolved(_) => "✅",
             DokeNodeState::Error(_) => "❌",
+            DokeNodeState::Skipped(_) => "➖",
         }
     }
 
@@ -21,7 +22,7 @@ impl DebugPrinter {
         println!(
             "{}{}{} {}",
             padding,
-            if constituent_name != "" {
+            if !constituent_name.is_empty() {
                 format!("{}:", constituent_name)
             } else {
                 "".to_string()
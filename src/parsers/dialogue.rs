@@ -0,0 +1,495 @@
+// dialogue.rs
+//
+// Dialogue written as speaker lines and choice bullets:
+//
+//     GUARD: Halt! Who goes there? {#start}
+//     - [A friend] -> #friend
+//     - [Let me through] -> #bribe if player has 10 gold
+//
+//     GUARD: Welcome, friend. {#friend} -> END
+//
+// All the lines of a document are grouped into one `DialogueGraph` resource
+// holding the lines as nodes and the jumps between them as edges.
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostics::Diagnostic;
+use crate::semantic::BlockKind;
+use crate::{DokeNode, DokeNodeState, DokeOut, DokeParser, Extensions, GodotValue};
+
+/// Jump target ending the conversation
+const END: &str = "END";
+
+/// Parses "SPEAKER: text" lines and their "- [Choice] -> #id" bullets
+/// into a dialogue graph.
+///
+/// Lines get an id from a trailing `{#id}` anchor, or `line_<n>` otherwise.
+/// A line without choices continues to the next line unless it jumps
+/// somewhere with `-> #id` or ends the conversation with `-> END`.
+/// Choice conditions (`if ...`) are left as unresolved `condition` constituents,
+/// so add the condition grammar after this parser.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::DialogueParser};
+///
+/// let pipe = DokePipe::new().add(DialogueParser::new());
+/// let values = pipe.validate("\
+/// GUARD: Halt! Who goes there?
+/// - [A friend] -> #friend
+/// - [Nobody] -> END
+///
+/// GUARD: Welcome, friend. {#friend} -> END").unwrap();
+/// let string = |s: &str| Some(GodotValue::String(s.into()));
+/// assert_eq!(values[0].get_path("start").cloned(), string("line_1"));
+/// assert_eq!(values[0].get_path("nodes/0/choices/0/target").cloned(), string("friend"));
+/// assert_eq!(values[0].get_path("nodes/0/choices/1/target"), Some(&GodotValue::Nil));
+/// assert_eq!(values[0].get_path("edges/0/to").cloned(), string("friend"));
+///
+/// let doc = pipe.run_markdown("GUARD: Halt!\n- [A friend] -> #frend");
+/// let choice = &doc.nodes[0].children[0].children[0];
+/// assert_eq!(choice.diagnostics[0].code, "dialogue-missing-target");
+/// ```
+#[derive(Debug)]
+pub struct DialogueParser {
+    line_regex: Regex,
+    tail_regex: Regex,
+    choice_regex: Regex,
+    speakers: Option<Vec<String>>,
+}
+
+impl Default for DialogueParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A "SPEAKER: text {#id} -> #target" statement, split up
+struct Line {
+    speaker: String,
+    text: String,
+    anchor: Option<String>,
+    jump: Option<String>,
+}
+
+/// A "[text] -> #target if condition" bullet, split up
+struct Choice {
+    text: String,
+    target: String,
+    condition: Option<String>,
+}
+
+impl DialogueParser {
+    pub fn new() -> Self {
+        Self {
+            line_regex: Regex::new(r"^\s*([A-Z][A-Za-z0-9_]*)\s*:\s+(.+?)\s*$").unwrap(),
+            tail_regex: Regex::new(r"^(.*?)\s*(?:\{#([\w-]+)\})?\s*(?:->\s*(?:#([\w-]+)|(END)))?$")
+                .unwrap(),
+            choice_regex: Regex::new(
+                r"^\s*\[([^\]]+)\]\s*->\s*(?:#([\w-]+)|(END))(?:\s+if\s+(.+?))?\s*\.?\s*$",
+            )
+            .unwrap(),
+            speakers: None,
+        }
+    }
+
+    /// Only accept lines from these speakers. By default any capitalized
+    /// single word before a colon is a speaker.
+    pub fn with_speakers<I, S>(mut self, speakers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.speakers = Some(speakers.into_iter().map(|s| s.into()).collect());
+        self
+    }
+
+    fn parse_line(&self, node: &DokeNode) -> Option<Line> {
+        if !matches!(node.state, DokeNodeState::Unresolved) {
+            return None;
+        }
+        let caps = self.line_regex.captures(&node.statement)?;
+        let speaker = caps[1].to_string();
+        if let Some(speakers) = &self.speakers
+            && !speakers.contains(&speaker)
+        {
+            return None;
+        }
+
+        let tail = self.tail_regex.captures(&caps[2])?;
+        let text = tail[1].to_string();
+        if text.is_empty() {
+            return None;
+        }
+        Some(Line {
+            speaker,
+            text,
+            anchor: tail.get(2).map(|m| m.as_str().to_string()),
+            jump: tail
+                .get(3)
+                .or_else(|| tail.get(4))
+                .map(|m| m.as_str().to_string()),
+        })
+    }
+
+    fn parse_choice(&self, statement: &str) -> Option<Choice> {
+        let caps = self.choice_regex.captures(statement)?;
+        Some(Choice {
+            text: caps[1].trim().to_string(),
+            target: caps.get(2).or_else(|| caps.get(3))?.as_str().to_string(),
+            condition: caps.get(4).map(|m| m.as_str().to_string()),
+        })
+    }
+
+    /// Resolve a line and its choice bullets.
+    /// `ids` is every line id of the dialogue, to check jump targets against.
+    fn resolve_line(
+        &self,
+        node: &mut DokeNode,
+        line: Line,
+        id: String,
+        next: Option<String>,
+        ids: &HashSet<String>,
+    ) {
+        let missing_target = |target: &str| {
+            Diagnostic::error(
+                "dialogue-missing-target",
+                format!("'#{}' doesn't match any dialogue line", target),
+            )
+        };
+
+        if let Some(target) = &line.jump
+            && target != END
+            && !ids.contains(target)
+        {
+            node.diagnostics
+                .push(missing_target(target).with_span(node.span.clone()));
+        }
+
+        for child in &mut node.children {
+            let Some(choice) = self.parse_choice(&child.statement) else {
+                // Other children (actions, animations...) are left to other parsers
+                continue;
+            };
+            if choice.target != END && !ids.contains(&choice.target) {
+                child
+                    .diagnostics
+                    .push(missing_target(&choice.target).with_span(child.span.clone()));
+            }
+            if let Some(condition) = &choice.condition {
+                child.constituents.insert(
+                    "condition".into(),
+                    DokeNode {
+                        statement: condition.as_str().into(),
+                        state: DokeNodeState::Unresolved,
+                        children: Vec::new(),
+                        parse_data: HashMap::new(),
+                        extensions: Extensions::new(),
+                        constituents: HashMap::new(),
+                        span: child.span.clone(),
+                        diagnostics: Vec::new(),
+                        block: BlockKind::Other,
+                    },
+                );
+            }
+            child.state = DokeNodeState::Resolved(Box::new(DialogueChoice {
+                text: choice.text,
+                target: (choice.target != END).then_some(choice.target),
+                condition: GodotValue::Nil,
+            }));
+        }
+
+        let next = match line.jump {
+            Some(target) if target == END => None,
+            Some(target) => Some(target),
+            None => next,
+        };
+        node.parse_data
+            .insert("dialogue_id".into(), GodotValue::String(id.clone()));
+        node.state = DokeNodeState::Resolved(Box::new(DialogueLine {
+            id,
+            speaker: line.speaker,
+            text: line.text,
+            next,
+            choices: Vec::new(),
+            actions: Vec::new(),
+        }));
+    }
+}
+
+impl DokeParser for DialogueParser {
+    /// Resolve a single line on its own, with no implicit next line.
+    fn process(&self, node: &mut DokeNode, _frontmatter: &HashMap<String, GodotValue>) {
+        if let Some(line) = self.parse_line(node) {
+            let id = line.anchor.clone().unwrap_or_else(|| "line_1".into());
+            let ids = HashSet::from([id.clone()]);
+            self.resolve_line(node, line, id, None, &ids);
+        }
+    }
+
+    fn process_document(
+        &self,
+        nodes: &mut Vec<DokeNode>,
+        frontmatter: &HashMap<String, GodotValue>,
+    ) {
+        let lines: Vec<(usize, Line)> = nodes
+            .iter()
+            .enumerate()
+            .filter_map(|(i, node)| self.parse_line(node).map(|line| (i, line)))
+            .collect();
+        let Some(&(first, _)) = lines.first() else {
+            return;
+        };
+
+        let line_ids: Vec<String> = lines
+            .iter()
+            .enumerate()
+            .map(|(n, (_, line))| {
+                line.anchor
+                    .clone()
+                    .unwrap_or_else(|| format!("line_{}", n + 1))
+            })
+            .collect();
+        let ids: HashSet<String> = line_ids.iter().cloned().collect();
+
+        let mut seen = HashSet::new();
+        for ((i, _), id) in lines.iter().zip(&line_ids) {
+            if !seen.insert(id) {
+                let node = &mut nodes[*i];
+                node.diagnostics.push(
+                    Diagnostic::error(
+                        "dialogue-duplicate-id",
+                        format!("'#{}' is used by more than one dialogue line", id),
+                    )
+                    .with_span(node.span.clone()),
+                );
+            }
+        }
+
+        let line_indices: Vec<usize> = lines.iter().map(|(i, _)| *i).collect();
+        for (n, (i, line)) in lines.into_iter().enumerate() {
+            let has_choices = nodes[i]
+                .children
+                .iter()
+                .any(|child| self.parse_choice(&child.statement).is_some());
+            let next = if has_choices {
+                None
+            } else {
+                line_ids.get(n + 1).cloned()
+            };
+            self.resolve_line(&mut nodes[i], line, line_ids[n].clone(), next, &ids);
+        }
+
+        // Group the lines under a single graph node, where the first line was
+        let first_span = nodes[first].span.clone();
+        let mut line_nodes = Vec::new();
+        for i in line_indices.into_iter().rev() {
+            line_nodes.push(nodes.remove(i));
+        }
+        line_nodes.reverse();
+
+        let name = ["dialogue", "title"]
+            .iter()
+            .find_map(|key| match frontmatter.get(*key) {
+                Some(GodotValue::String(s)) => Some(s.clone()),
+                _ => None,
+            });
+        let span = line_nodes
+            .iter()
+            .map(|node| node.span.clone())
+            .reduce(|a, b| a.merge(&b))
+            .unwrap_or(first_span);
+
+        let graph = DokeNode {
+            statement: name.as_deref().unwrap_or("Dialogue").into(),
+            state: DokeNodeState::Resolved(Box::new(DialogueGraph {
+                name,
+                start: line_ids[0].clone(),
+                lines: Vec::new(),
+            })),
+            children: line_nodes,
+            parse_data: HashMap::from([(
+                "abstract_type".into(),
+                GodotValue::String("DialogueGraph".into()),
+            )]),
+            extensions: Extensions::new(),
+            constituents: HashMap::new(),
+            span,
+            diagnostics: Vec::new(),
+            block: BlockKind::Other,
+        };
+        nodes.insert(first, graph);
+    }
+}
+
+// ----------------- Output -----------------
+
+#[derive(Debug)]
+struct DialogueGraph {
+    name: Option<String>,
+    start: String,
+    lines: Vec<GodotValue>,
+}
+
+impl DokeOut for DialogueGraph {
+    fn kind(&self) -> &'static str {
+        "DialogueGraph"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        let mut edges = Vec::new();
+        for line in &self.lines {
+            let from = line.get_path("id").cloned().unwrap_or(GodotValue::Nil);
+            if let Some(GodotValue::Array(choices)) = line.get_path("choices") {
+                for choice in choices {
+                    edges.push(GodotValue::Dict(HashMap::from([
+                        ("from".into(), from.clone()),
+                        (
+                            "to".into(),
+                            choice
+                                .get_path("target")
+                                .cloned()
+                                .unwrap_or(GodotValue::Nil),
+                        ),
+                        (
+                            "text".into(),
+                            choice.get_path("text").cloned().unwrap_or(GodotValue::Nil),
+                        ),
+                        (
+                            "condition".into(),
+                            choice
+                                .get_path("condition")
+                                .cloned()
+                                .unwrap_or(GodotValue::Nil),
+                        ),
+                    ])));
+                }
+            }
+            if let Some(next @ GodotValue::String(_)) = line.get_path("next") {
+                edges.push(GodotValue::Dict(HashMap::from([
+                    ("from".into(), from.clone()),
+                    ("to".into(), next.clone()),
+                    ("text".into(), GodotValue::Nil),
+                    ("condition".into(), GodotValue::Nil),
+                ])));
+            }
+        }
+
+        let mut fields = HashMap::from([
+            ("start".into(), GodotValue::String(self.start.clone())),
+            ("nodes".into(), GodotValue::Array(self.lines.clone())),
+            ("edges".into(), GodotValue::Array(edges)),
+        ]);
+        if let Some(name) = &self.name {
+            fields.insert("name".into(), GodotValue::String(name.clone()));
+        }
+        GodotValue::Resource {
+            type_name: "DialogueGraph".into(),
+            abstract_type_name: "DialogueGraph".into(),
+            fields,
+        }
+    }
+
+    fn get_asbtract_type(&self) -> Option<String> {
+        Some("DialogueGraph".into())
+    }
+
+    fn use_child(
+        &mut self,
+        child: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.lines.push(child);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct DialogueLine {
+    id: String,
+    speaker: String,
+    text: String,
+    next: Option<String>,
+    choices: Vec<GodotValue>,
+    actions: Vec<GodotValue>,
+}
+
+impl DokeOut for DialogueLine {
+    fn kind(&self) -> &'static str {
+        "DialogueLine"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Resource {
+            type_name: "DialogueLine".into(),
+            abstract_type_name: "DialogueLine".into(),
+            fields: HashMap::from([
+                ("id".into(), GodotValue::String(self.id.clone())),
+                ("speaker".into(), GodotValue::String(self.speaker.clone())),
+                ("text".into(), GodotValue::String(self.text.clone())),
+                (
+                    "next".into(),
+                    self.next
+                        .clone()
+                        .map(GodotValue::String)
+                        .unwrap_or(GodotValue::Nil),
+                ),
+                ("choices".into(), GodotValue::Array(self.choices.clone())),
+                ("actions".into(), GodotValue::Array(self.actions.clone())),
+            ]),
+        }
+    }
+
+    fn use_child(
+        &mut self,
+        child: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match &child {
+            GodotValue::Resource { type_name, .. } if type_name == "DialogueChoice" => {
+                self.choices.push(child)
+            }
+            _ => self.actions.push(child),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct DialogueChoice {
+    text: String,
+    target: Option<String>,
+    condition: GodotValue,
+}
+
+impl DokeOut for DialogueChoice {
+    fn kind(&self) -> &'static str {
+        "DialogueChoice"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Resource {
+            type_name: "DialogueChoice".into(),
+            abstract_type_name: "DialogueChoice".into(),
+            fields: HashMap::from([
+                ("text".into(), GodotValue::String(self.text.clone())),
+                (
+                    "target".into(),
+                    self.target
+                        .clone()
+                        .map(GodotValue::String)
+                        .unwrap_or(GodotValue::Nil),
+                ),
+                ("condition".into(), self.condition.clone()),
+            ]),
+        }
+    }
+
+    fn use_constituent(
+        &mut self,
+        name: &str,
+        value: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if name == "condition" {
+            self.condition = value;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,495 @@
+// expression.rs
+//
+// Natural-ish boolean expressions, for the `condition` parameter type:
+// "the target is burning and below 50% HP", "level >= 3 or player has the key",
+// and arithmetic ones for the `formula` type: "2 * STR + level / 3".
+// The grammar lives in grammar.lalrpop.
+
+use lalrpop_util::lalrpop_mod;
+use std::collections::HashMap;
+use std::fmt;
+
+use crate::GodotValue;
+
+/// Functions formulas can call, on a single variable
+pub const FUNCTIONS: &[&str] = &["len"];
+
+lalrpop_mod!(
+    #[allow(clippy::all)]
+    grammar,
+    "/parsers/grammar.rs"
+);
+
+/// A parsed expression. Boolean and numeric nodes share this type,
+/// `parse_condition` makes sure they are used in the right places.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    Number(f64),
+    /// "50%", left to the evaluator to apply to the compared stat
+    Percent(f64),
+    /// "gold", "target HP": the last word is the stat, the others its owner
+    Var {
+        subject: Option<String>,
+        name: String,
+    },
+    /// "len(modifiers)", one of `FUNCTIONS`
+    Call {
+        function: String,
+        argument: Box<Expr>,
+    },
+    Unary {
+        op: &'static str,
+        operand: Box<Expr>,
+    },
+    Binary {
+        op: &'static str,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+    /// "the target is burning", or a bare "burning"
+    Is {
+        subject: Option<String>,
+        state: String,
+    },
+    /// "player has the key"
+    Has {
+        subject: Option<String>,
+        item: String,
+    },
+    /// "below 50% HP", about whatever the previous statement was about.
+    /// Only exists between parsing and `parse_condition`.
+    Elided {
+        op: &'static str,
+        right: Box<Expr>,
+        stat: Option<String>,
+    },
+}
+
+/// What the previous statement of a condition was about,
+/// to complete the elided ones after it.
+#[derive(Default)]
+struct Context {
+    owner: Option<String>,
+    compared: Option<Expr>,
+}
+
+/// Parse a condition, checking it is boolean, with numeric comparison operands.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Gate:
+///   - "Only when {when: condition}"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+/// let op = |statement: &str, path: &str| {
+///     let values = pipe.validate(statement).unwrap();
+///     values[0].get_path(&format!("when/{}", path)).cloned()
+/// };
+/// let string = |s: &str| Some(GodotValue::String(s.into()));
+///
+/// // `and` binds tighter than `or`
+/// assert_eq!(op("Only when level >= 3 or gold > 10 and not cursed", "op"), string("or"));
+/// assert_eq!(op("Only when level >= 3 or gold > 10 and not cursed", "operands/1/op"), string("and"));
+/// // unless parentheses say otherwise
+/// assert_eq!(op("Only when (level >= 3 or gold > 10) and not cursed", "op"), string("and"));
+/// assert_eq!(op("Only when (level >= 3 or gold > 10) and not cursed", "operands/0/op"), string("or"));
+///
+/// let doc = pipe.run_markdown("Only when level >= and");
+/// let diagnostic = &doc.nodes[0].diagnostics[0];
+/// assert_eq!(diagnostic.code, "invalid-parameter");
+/// assert!(diagnostic.message.contains("Invalid condition 'level >= and'"));
+/// ```
+pub fn parse_condition(text: &str) -> Result<Expr, String> {
+    let expr = grammar::ConditionParser::new()
+        .parse(text)
+        .map_err(|e| format!("Invalid condition '{}': {}", text, e))?;
+    expr.check_condition(&mut Context::default())
+}
+
+/// Parse an arithmetic formula, checking it doesn't contain conditions.
+pub fn parse_formula(text: &str) -> Result<Expr, String> {
+    let expr = grammar::FormulaParser::new()
+        .parse(text)
+        .map_err(|e| format!("Invalid formula '{}': {}", text, e))?;
+    expr.check_number()
+}
+
+/// A `Formula` resource holding both the expression tree and its
+/// reverse polish notation, whichever is easier to evaluate from GDScript.
+///
+/// RPN tokens are floats for numbers, operator strings (`+`, `-`, `*`, `/`, `neg`),
+/// function names (`len`) and variable names, which are also listed in `variables`.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Scale:
+///   - "Scales with {amount: formula}"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+/// let rpn = |statement: &str| {
+///     let values = pipe.validate(statement).unwrap();
+///     values[0].get_path("amount/rpn").cloned()
+/// };
+/// let tokens = |tokens: &[&str]| {
+///     let tokens = tokens.iter().map(|token| match token.parse() {
+///         Ok(number) => GodotValue::Float(number),
+///         Err(_) => GodotValue::String(token.to_string()),
+///     });
+///     Some(GodotValue::Array(tokens.collect()))
+/// };
+/// assert_eq!(rpn("Scales with 2 + 3 * STR"), tokens(&["2", "3", "STR", "*", "+"]));
+/// assert_eq!(rpn("Scales with (2 + 3) * STR"), tokens(&["2", "3", "+", "STR", "*"]));
+///
+/// let doc = pipe.run_markdown("Scales with 2 +");
+/// assert!(doc.nodes[0].diagnostics[0].message.contains("Invalid formula '2 +'"));
+/// ```
+pub fn formula_to_godot(source: &str, formula: &Expr) -> GodotValue {
+    let mut rpn = Vec::new();
+    let mut variables = Vec::new();
+    formula.push_rpn(&mut rpn, &mut variables);
+
+    GodotValue::Resource {
+        type_name: "Formula".into(),
+        abstract_type_name: "Formula".into(),
+        fields: HashMap::from([
+            (
+                "source".into(),
+                GodotValue::String(source.trim().to_string()),
+            ),
+            ("tree".into(), formula.to_godot()),
+            ("rpn".into(), GodotValue::Array(rpn)),
+            (
+                "variables".into(),
+                GodotValue::Array(variables.into_iter().map(GodotValue::String).collect()),
+            ),
+        ]),
+    }
+}
+
+impl Expr {
+    fn binary(op: &'static str, left: Expr, right: Expr) -> Self {
+        Expr::Binary {
+            op,
+            left: Box::new(left),
+            right: Box::new(right),
+        }
+    }
+
+    fn unary(op: &'static str, operand: Expr) -> Self {
+        Expr::Unary {
+            op,
+            operand: Box::new(operand),
+        }
+    }
+
+    fn negated(negate: bool, expr: Expr) -> Self {
+        if negate {
+            Expr::unary("not", expr)
+        } else {
+            expr
+        }
+    }
+
+    fn var(mut words: Vec<String>) -> Self {
+        let name = words.pop().unwrap_or_default();
+        Expr::Var {
+            subject: (!words.is_empty()).then(|| words.join(" ")),
+            name,
+        }
+    }
+
+    /// "the target is below 50% HP" compares the target's HP,
+    /// "gold is below 10" compares the gold itself.
+    fn compare_quantity(
+        subject: Option<Vec<String>>,
+        op: &'static str,
+        (right, stat): (Expr, Option<String>),
+    ) -> Self {
+        let left = match (subject, stat) {
+            (subject, Some(name)) => Expr::Var {
+                subject: subject.map(|s| s.join(" ")),
+                name,
+            },
+            (Some(words), None) => Expr::var(words),
+            (None, None) => Expr::Var {
+                subject: None,
+                name: String::new(),
+            },
+        };
+        Expr::binary(op, left, right)
+    }
+
+    fn check_condition(self, context: &mut Context) -> Result<Expr, String> {
+        match self {
+            // a bare "burning" or "target stunned" is a flag
+            Expr::Var { subject, name } => {
+                context.owner = subject.clone();
+                Ok(Expr::Is {
+                    subject,
+                    state: name,
+                })
+            }
+            Expr::Is { ref subject, .. } | Expr::Has { ref subject, .. } => {
+                context.owner = subject.clone();
+                context.compared = None;
+                Ok(self)
+            }
+            Expr::Elided { op, right, stat } => {
+                let left = match (stat, &context.compared) {
+                    (Some(name), _) => Expr::Var {
+                        subject: context.owner.clone(),
+                        name,
+                    },
+                    (None, Some(compared)) => compared.clone(),
+                    (None, None) => {
+                        return Err(format!(
+                            "'{} {}' doesn't say what it compares",
+                            op_word(op),
+                            right
+                        ));
+                    }
+                };
+                Expr::binary(op, left, *right).check_condition(context)
+            }
+            Expr::Unary { op: "not", operand } => {
+                Ok(Expr::unary("not", operand.check_condition(context)?))
+            }
+            Expr::Binary {
+                op: op @ ("and" | "or"),
+                left,
+                right,
+            } => {
+                let left = left.check_condition(context)?;
+                Ok(Expr::binary(op, left, right.check_condition(context)?))
+            }
+            Expr::Binary { op, left, right } if is_comparison(op) => {
+                let left = left.check_number()?;
+                if let Expr::Var { subject, .. } = &left {
+                    context.owner = subject.clone();
+                    context.compared = Some(left.clone());
+                }
+                Ok(Expr::binary(op, left, right.check_number()?))
+            }
+            other => Err(format!("'{}' is a number, not a condition", other)),
+        }
+    }
+
+    fn check_number(self) -> Result<Expr, String> {
+        match self {
+            Expr::Number(_) | Expr::Percent(_) | Expr::Var { .. } => Ok(self),
+            Expr::Call { ref function, .. } if !FUNCTIONS.contains(&function.as_str()) => {
+                Err(format!("unknown function '{}'", function))
+            }
+            Expr::Call { .. } => Ok(self),
+            Expr::Unary { op: "neg", operand } => Ok(Expr::unary("neg", operand.check_number()?)),
+            Expr::Binary { op, left, right } if is_arithmetic(op) => Ok(Expr::binary(
+                op,
+                left.check_number()?,
+                right.check_number()?,
+            )),
+            other => Err(format!("'{}' is a condition, not a number", other)),
+        }
+    }
+
+    /// Nested `Condition` resources, with `Expression` resources for numbers.
+    pub fn to_godot(&self) -> GodotValue {
+        let string = |s: &str| GodotValue::String(s.to_string());
+        let optional = |s: &Option<String>| s.as_deref().map(string).unwrap_or(GodotValue::Nil);
+
+        let (type_name, fields) = match self {
+            Expr::Number(n) => (
+                "Expression",
+                vec![("op", string("const")), ("value", GodotValue::Float(*n))],
+            ),
+            Expr::Percent(p) => (
+                "Expression",
+                vec![("op", string("percent")), ("value", GodotValue::Float(*p))],
+            ),
+            Expr::Var { subject, name } => (
+                "Expression",
+                vec![
+                    ("op", string("var")),
+                    ("subject", optional(subject)),
+                    ("name", string(name)),
+                ],
+            ),
+            Expr::Call { function, argument } => (
+                "Expression",
+                vec![
+                    ("op", string("call")),
+                    ("function", string(function)),
+                    ("argument", argument.to_godot()),
+                ],
+            ),
+            Expr::Unary { op, operand } => (
+                if *op == "not" {
+                    "Condition"
+                } else {
+                    "Expression"
+                },
+                vec![("op", string(op)), ("operand", operand.to_godot())],
+            ),
+            Expr::Binary {
+                op: op @ ("and" | "or"),
+                ..
+            } => {
+                // a and b and c -> and [a, b, c]
+                let mut operands = Vec::new();
+                self.collect_operands(op, &mut operands);
+                (
+                    "Condition",
+                    vec![
+                        ("op", string(op)),
+                        ("operands", GodotValue::Array(operands)),
+                    ],
+                )
+            }
+            Expr::Binary { op, left, right } => (
+                if is_comparison(op) {
+                    "Condition"
+                } else {
+                    "Expression"
+                },
+                vec![
+                    ("op", string(op)),
+                    ("left", left.to_godot()),
+                    ("right", right.to_godot()),
+                ],
+            ),
+            Expr::Is { subject, state } => (
+                "Condition",
+                vec![
+                    ("op", string("is")),
+                    ("subject", optional(subject)),
+                    ("state", string(state)),
+                ],
+            ),
+            Expr::Has { subject, item } => (
+                "Condition",
+                vec![
+                    ("op", string("has")),
+                    ("subject", optional(subject)),
+                    ("item", string(item)),
+                ],
+            ),
+            Expr::Elided { op, right, stat } => (
+                "Condition",
+                vec![
+                    ("op", string(op)),
+                    ("stat", optional(stat)),
+                    ("right", right.to_godot()),
+                ],
+            ),
+        };
+
+        GodotValue::Resource {
+            type_name: type_name.into(),
+            abstract_type_name: type_name.into(),
+            fields: fields
+                .into_iter()
+                .map(|(k, v)| (k.to_string(), v))
+                .collect::<HashMap<_, _>>(),
+        }
+    }
+
+    fn push_rpn(&self, rpn: &mut Vec<GodotValue>, variables: &mut Vec<String>) {
+        match self {
+            Expr::Number(n) => rpn.push(GodotValue::Float(*n)),
+            Expr::Percent(p) => rpn.push(GodotValue::Float(p / 100.0)),
+            Expr::Var { subject, name } => {
+                let path = match subject {
+                    Some(subject) => format!("{}.{}", subject, name),
+                    None => name.clone(),
+                };
+                if !variables.contains(&path) {
+                    variables.push(path.clone());
+                }
+                rpn.push(GodotValue::String(path));
+            }
+            Expr::Call { function, argument } => {
+                argument.push_rpn(rpn, variables);
+                rpn.push(GodotValue::String(function.clone()));
+            }
+            Expr::Unary { op, operand } => {
+                operand.push_rpn(rpn, variables);
+                rpn.push(GodotValue::String(op.to_string()));
+            }
+            Expr::Binary { op, left, right } => {
+                left.push_rpn(rpn, variables);
+                right.push_rpn(rpn, variables);
+                rpn.push(GodotValue::String(op.to_string()));
+            }
+            // conditions don't make it through `parse_formula`
+            Expr::Is { .. } | Expr::Has { .. } | Expr::Elided { .. } => {}
+        }
+    }
+
+    fn collect_operands(&self, chain_op: &str, operands: &mut Vec<GodotValue>) {
+        match self {
+            Expr::Binary { op, left, right } if *op == chain_op => {
+                left.collect_operands(chain_op, operands);
+                right.collect_operands(chain_op, operands);
+            }
+            other => operands.push(other.to_godot()),
+        }
+    }
+}
+
+impl fmt::Display for Expr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Expr::Number(n) => write!(f, "{}", n),
+            Expr::Percent(p) => write!(f, "{}%", p),
+            Expr::Var {
+                subject: Some(subject),
+                name,
+            } => write!(f, "{} {}", subject, name),
+            Expr::Var {
+                subject: None,
+                name,
+            } => write!(f, "{}", name),
+            Expr::Call { function, argument } => write!(f, "{}({})", function, argument),
+            Expr::Unary { op: "neg", operand } => write!(f, "-{}", operand),
+            Expr::Unary { op, operand } => write!(f, "{} {}", op, operand),
+            Expr::Binary { op, left, right } => write!(f, "({} {} {})", left, op, right),
+            Expr::Is {
+                subject: Some(subject),
+                state,
+            } => write!(f, "{} is {}", subject, state),
+            Expr::Is {
+                subject: None,
+                state,
+            } => write!(f, "{}", state),
+            Expr::Has { subject, item } => {
+                write!(f, "{} has {}", subject.as_deref().unwrap_or("it"), item)
+            }
+            Expr::Elided { op, right, stat } => {
+                write!(f, "{} {}", op_word(op), right)?;
+                if let Some(stat) = stat {
+                    write!(f, " {}", stat)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn is_comparison(op: &str) -> bool {
+    matches!(op, "<" | "<=" | ">" | ">=" | "==" | "!=")
+}
+
+fn is_arithmetic(op: &str) -> bool {
+    matches!(op, "+" | "-" | "*" | "/")
+}
+
+fn op_word(op: &str) -> &'static str {
+    match op {
+        "<" => "below",
+        ">" => "above",
+        "<=" => "at most",
+        ">=" => "at least",
+        _ => "equal to",
+    }
+}
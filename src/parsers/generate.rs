@@ -0,0 +1,251 @@
+// generate.rs
+//
+// Random sentences from a grammar, to fuzz grammars, write placeholder content
+// and check round trips (generate -> parse -> compare).
+//
+// A phrase of the section is picked, and each parameter gets a random value of
+// its type: basic types are sampled, other types are sentences of the section
+// named after them. Optional parameters are left out half of the time.
+
+use std::collections::HashMap;
+
+use rand::Rng;
+use rand::seq::IndexedRandom;
+
+use super::bounds::Bounds;
+use super::param_types::is_basic_type;
+use super::sentence::{
+    DISPLAY_TEMPLATE_KEY, LITERALS_KEY, PhraseConfig, ReturnSpec, SentenceParseError,
+    SentenceParser, perform_format_string,
+};
+use crate::{GodotValue, numeric};
+
+/// Past this depth, phrases without nested sentences are picked when there are some
+const SHALLOW_DEPTH: usize = 8;
+const MAX_DEPTH: usize = 100;
+
+const WORDS: [&str; 8] = [
+    "sword", "fire", "goblin", "shield", "potion", "ice", "arrow", "dragon",
+];
+const STATS: [&str; 4] = ["level", "health", "STR", "DEX"];
+
+impl SentenceParser {
+    /// A random sentence matching a phrase of `section`.
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser};
+    /// use rand::SeedableRng;
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} {element: Element} damage"
+    /// Element:
+    ///   - "fire"
+    ///   - "ice"
+    /// "#).unwrap();
+    /// let mut rng = rand::rngs::StdRng::seed_from_u64(7);
+    /// let sentence = parser.generate("Damage", &mut rng).unwrap();
+    /// assert!(sentence.starts_with("Deals "));
+    /// assert!(DokePipe::new().add(parser).validate(&sentence).is_ok());
+    /// ```
+    pub fn generate<R: Rng + ?Sized>(
+        &self,
+        section: &str,
+        rng: &mut R,
+    ) -> Result<String, SentenceParseError> {
+        let phrase = self.pick_phrase(section, rng, 0)?;
+        self.generate_phrase(phrase, rng, 0)
+            .map(|(sentence, _)| sentence)
+    }
+
+    /// A random sentence matching `phrase`, and the value parsing it gives
+    pub(crate) fn generate_phrase<R: Rng + ?Sized>(
+        &self,
+        phrase: &PhraseConfig,
+        rng: &mut R,
+        depth: usize,
+    ) -> Result<(String, GodotValue), SentenceParseError> {
+        let mut texts = HashMap::new();
+        let mut values = HashMap::new();
+        let mut literals = HashMap::new();
+        for definition in &phrase.parameters {
+            if definition.optional && rng.random_bool(0.5) {
+                continue;
+            }
+            let text = match definition.param_type.to_lowercase().as_str() {
+                "price" => Some(phrase.currency.spell(rng.random_range(1..=2000))),
+                "input_action" => match &phrase.input_actions {
+                    Some(actions) if !actions.is_empty() => actions.choose(rng).cloned(),
+                    _ => Some("jump".to_string()),
+                },
+                "stat" => match &phrase.stats {
+                    Some(stats) if !stats.is_empty() => stats.choose(rng).cloned(),
+                    _ => random_value("stat", rng),
+                },
+                _ => match definition.bounds {
+                    Some(bounds) => random_in(&bounds, &definition.param_type, rng),
+                    None => random_value(&definition.param_type, rng),
+                },
+            };
+            let (text, value) = match text {
+                Some(text) => {
+                    let (value, _) = phrase
+                        .parse_parameter(&text, &definition.param_type)
+                        .expect("random values are valid");
+                    if numeric::literal_differs(&text, &value) {
+                        literals.insert(definition.name.clone(), GodotValue::String(text.clone()));
+                    }
+                    (text, value)
+                }
+                None => {
+                    let section = &definition.param_type;
+                    if depth > MAX_DEPTH {
+                        return Err(SentenceParseError::MaxRecursionDepthExceeded(
+                            section.to_string(),
+                        ));
+                    }
+                    let nested = self.pick_phrase(section, rng, depth + 1)?;
+                    self.generate_phrase(nested, rng, depth + 1)?
+                }
+            };
+            texts.insert(definition.name.clone(), text);
+            values.insert(definition.name.clone(), value);
+        }
+
+        // what `process_with_depth` and validation make of the sentence
+        let value = match &phrase.return_spec {
+            ReturnSpec::Type(type_name) => {
+                let mut fields = values;
+                fields.insert(
+                    "doke_tr_key".into(),
+                    GodotValue::String(phrase.make_tr_key()),
+                );
+                fields.insert(
+                    DISPLAY_TEMPLATE_KEY.into(),
+                    GodotValue::String(phrase.tr_template()),
+                );
+                if !literals.is_empty() {
+                    fields.insert(LITERALS_KEY.into(), GodotValue::Dict(literals));
+                }
+                GodotValue::Resource {
+                    type_name: type_name.clone(),
+                    abstract_type_name: self.abstract_type.clone(),
+                    fields,
+                }
+            }
+            ReturnSpec::Literal(value) => value.clone(),
+            ReturnSpec::Format(format) => GodotValue::String(perform_format_string(
+                format,
+                &values,
+                &HashMap::new(),
+                None,
+            )),
+        };
+        Ok((phrase.spell(&texts), value))
+    }
+
+    /// A random phrase of `section`, one without nested sentences when deep enough
+    fn pick_phrase<R: Rng + ?Sized>(
+        &self,
+        section: &str,
+        rng: &mut R,
+        depth: usize,
+    ) -> Result<&PhraseConfig, SentenceParseError> {
+        let phrases: Vec<&PhraseConfig> = self
+            .phrases
+            .iter()
+            .filter(|p| p.section == section)
+            .collect();
+        let shallow: Vec<&PhraseConfig> = phrases
+            .iter()
+            .copied()
+            .filter(|p| p.parameters.iter().all(|d| is_basic_type(&d.param_type)))
+            .collect();
+        let candidates = match depth >= SHALLOW_DEPTH && !shallow.is_empty() {
+            true => &shallow,
+            false => &phrases,
+        };
+        candidates
+            .choose(rng)
+            .copied()
+            .ok_or_else(|| SentenceParseError::UnknownSection(section.to_string()))
+    }
+}
+
+/// Text of a random number within `bounds`, in tenths for floats
+fn random_in<R: Rng + ?Sized>(bounds: &Bounds, param_type: &str, rng: &mut R) -> Option<String> {
+    let scale = match param_type.eq_ignore_ascii_case("float") {
+        true => 10.0,
+        false => 1.0,
+    };
+    // a hundred units past the open end
+    let span = 100 * scale as i64;
+    let high = bounds.max.map(|max| match bounds.inclusive {
+        true => (max * scale).floor() as i64,
+        false => (max * scale).ceil() as i64 - 1,
+    });
+    let low = match (bounds.min, high) {
+        (Some(min), _) => (min * scale).ceil() as i64,
+        (None, Some(high)) => high.min(0),
+        (None, None) => 0,
+    };
+    let high = high.unwrap_or(low + span);
+    let value = rng.random_range(low..=high.max(low));
+    Some(match scale == 1.0 {
+        true => value.to_string(),
+        false => format!("{:.1}", value as f64 / scale),
+    })
+}
+
+/// Text of a random value of a basic type
+fn random_value<R: Rng + ?Sized>(param_type: &str, rng: &mut R) -> Option<String> {
+    let date = |rng: &mut R| {
+        format!(
+            "{}-{:02}-{:02}",
+            rng.random_range(1990..=2040),
+            rng.random_range(1..=12),
+            rng.random_range(1..=28)
+        )
+    };
+    let stat = |rng: &mut R| *STATS.choose(rng).unwrap();
+    Some(match param_type.to_lowercase().as_str() {
+        "int" => rng.random_range(0..=100).to_string(),
+        "float" => format!("{:.1}", rng.random_range(0.0..100.0)),
+        "bool" => rng.random_bool(0.5).to_string(),
+        "string" => WORDS.choose(rng).unwrap().to_string(),
+        "date" => date(rng),
+        "datetime" => format!(
+            "{}T{:02}:{:02}:00",
+            date(rng),
+            rng.random_range(0..24),
+            rng.random_range(0..60)
+        ),
+        "condition" => format!(
+            "{} {} {}",
+            stat(rng),
+            [">=", "<", "=="].choose(rng).unwrap(),
+            rng.random_range(1..=20)
+        ),
+        "formula" => format!(
+            "{} {} {}",
+            stat(rng),
+            ["+", "*"].choose(rng).unwrap(),
+            rng.random_range(1..=10)
+        ),
+        "ref" => format!("{}_{}", WORDS.choose(rng).unwrap(), rng.random_range(1..=9)),
+        "vec2" => format!("({}, {})", rng.random_range(0..50), rng.random_range(0..50)),
+        "rect" => {
+            let (x, y) = (rng.random_range(0..50), rng.random_range(0..50));
+            format!(
+                "({}, {}) to ({}, {})",
+                x,
+                y,
+                x + rng.random_range(1..20),
+                y + rng.random_range(1..20)
+            )
+        }
+        "color" => format!("#{:06x}", rng.random_range(0..=0xffffffu32)),
+        "gdexpr" => format!("`{} > {}`", stat(rng), rng.random_range(1..=20)),
+        "stat" => stat(rng).to_string(),
+        _ => return None,
+    })
+}
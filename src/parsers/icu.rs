@@ -0,0 +1,120 @@
+// parsers/icu.rs
+//
+// The translation entries of a grammar as ICU MessageFormat strings, for
+// localization stacks that use it instead of gettext. Messages are keyed by
+// translation key, like PO entries:
+//
+//     Damage:
+//       phrases:
+//         - "Deals {damage: int} damage to {target: Target}"
+//       plural: [damage]
+//
+//     "DAMAGE_PUAEXM5": "{damage, plural, one {Deals {damage, number} damage to {target}}
+//                        other {Deals {damage, number} damage to {target}}}"
+//
+// Parameters listed in a section's `plural` option wrap the message in a
+// skeleton for translators to fill: `plural` for numbers, `select` for the
+// others (a translator may add `feminine {...}` cases). Whole sentences go in
+// each case, as ICU recommends.
+
+use std::fs;
+use std::path::Path;
+
+use super::sentence::{PARAMETER, PhraseConfig, SentenceParseError, SentenceParser};
+use crate::export::json::json_string;
+
+impl SentenceParser {
+    /// `(translation key, ICU message)` of every phrase
+    /// ```
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Heal:
+    ///   phrases:
+    ///     - "Heals {amount: int} HP, it's free"
+    ///   plural: [amount]
+    /// "#).unwrap();
+    /// let (_, message) = &parser.icu_messages()[0];
+    /// assert_eq!(
+    ///     message,
+    ///     "{amount, plural, one {Heals {amount, number} HP, it''s free} \
+    ///      other {Heals {amount, number} HP, it''s free}}"
+    /// );
+    /// ```
+    pub fn icu_messages(&self) -> Vec<(String, String)> {
+        self.phrases
+            .iter()
+            .map(|phrase| (phrase.make_tr_key(), icu_message(phrase)))
+            .collect()
+    }
+
+    /// Write the messages as a JSON object keyed by translation key, the
+    /// format FormatJS and most ICU libraries load
+    pub fn write_icu_messages(&self, path: &Path) -> Result<(), SentenceParseError> {
+        let entries: Vec<String> = self
+            .icu_messages()
+            .iter()
+            .map(|(key, message)| format!("  {}: {}", json_string(key), json_string(message)))
+            .collect();
+        fs::write(path, format!("{{\n{}\n}}\n", entries.join(",\n")))?;
+        Ok(())
+    }
+}
+
+/// The phrase's template with ICU arguments, in the skeletons of its plural parameters
+fn icu_message(phrase: &PhraseConfig) -> String {
+    let template = phrase.tr_template();
+    let inflected: Vec<_> = phrase
+        .parameters
+        .iter()
+        .filter(|param| phrase.plural.contains(&param.name))
+        .collect();
+
+    let in_plural = inflected.iter().any(|param| is_number(&param.param_type));
+    let mut body = String::new();
+    let mut last = 0;
+    for cap in PARAMETER.captures_iter(&template) {
+        let m = cap.get(0).unwrap();
+        body.push_str(&escape(&template[last..m.start()], in_plural));
+        let name = &cap[1];
+        match phrase.parameters.iter().find(|param| param.name == name) {
+            Some(param) if is_number(&param.param_type) => {
+                body.push_str(&format!("{{{}, number}}", name))
+            }
+            _ => body.push_str(&format!("{{{}}}", name)),
+        }
+        last = m.end();
+    }
+    body.push_str(&escape(&template[last..], in_plural));
+
+    // the first parameter is the outermost skeleton
+    inflected
+        .iter()
+        .rev()
+        .fold(body, |inner, param| match is_number(&param.param_type) {
+            true => format!(
+                "{{{}, plural, one {{{}}} other {{{}}}}}",
+                param.name, inner, inner
+            ),
+            false => format!("{{{}, select, other {{{}}}}}", param.name, inner),
+        })
+}
+
+fn is_number(param_type: &str) -> bool {
+    matches!(param_type.to_lowercase().as_str(), "int" | "float")
+}
+
+/// Literal text of a message: apostrophes doubled, syntax characters quoted
+/// (`#` only means something inside plural cases)
+fn escape(text: &str, in_plural: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\'' => out.push_str("''"),
+            '{' | '}' => out.push_str(&format!("'{}'", c)),
+            '#' if in_plural => out.push_str("'#'"),
+            c => out.push(c),
+        }
+    }
+    out
+}
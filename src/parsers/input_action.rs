@@ -0,0 +1,89 @@
+// parsers/input_action.rs
+//
+// `input_action` parameters: the name of an input action ("Press {key: input_action}
+// to jump"), kept as a String. Tutorial and UI copy often names actions, and a
+// renamed action silently breaks it, so names are checked against the actions
+// the project declares, when it declares some:
+//
+//     input_actions: [jump, attack, ui_accept]    # in the dokeconfig
+//     input_actions: project.godot                # or Godot's InputMap
+//
+// A document may add its own with the same frontmatter key. An unknown action
+// is a warning: the value is still produced.
+
+use std::collections::HashMap;
+
+use crate::GodotValue;
+
+/// Frontmatter and dokeconfig key listing the known actions
+pub const INPUT_ACTIONS_KEY: &str = "input_actions";
+
+/// What an `input_action` parameter captures: an identifier like `ui_accept`
+pub(crate) const INPUT_ACTION_PATTERN: &str = r"([A-Za-z_][\w.]*)";
+
+/// The actions of the `[input]` section of a Godot `project.godot`
+/// ```
+/// use doke::parsers::godot_input_actions;
+///
+/// let project = r#"
+/// [input]
+///
+/// jump={
+/// "deadzone": 0.5,
+/// "events": [Object(InputEventKey,"keycode":32)]
+/// }
+/// attack={
+/// "deadzone": 0.5,
+/// "events": []
+/// }
+///
+/// [rendering]
+/// quality=1
+/// "#;
+/// assert_eq!(godot_input_actions(project), ["jump", "attack"]);
+/// ```
+pub fn godot_input_actions(project: &str) -> Vec<String> {
+    let mut in_input = false;
+    let mut actions = Vec::new();
+    for line in project.lines() {
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            in_input = section == "input";
+        } else if in_input && let Some((key, _)) = line.split_once('=') {
+            // values span lines, their inner lines start with quotes or brackets
+            if !key.is_empty() && !key.starts_with([' ', '\t', '"', '}', ']']) {
+                actions.push(key.to_string());
+            }
+        }
+    }
+    actions
+}
+
+/// A warning when `action` isn't among the declared actions, those of the
+/// grammar and those of the document's frontmatter. Without any, every action is known.
+pub(crate) fn check(
+    action: &str,
+    known: Option<&[String]>,
+    frontmatter: &HashMap<String, GodotValue>,
+) -> Option<String> {
+    let declared: Vec<&str> = match frontmatter.get(INPUT_ACTIONS_KEY) {
+        Some(GodotValue::Array(items)) => items
+            .iter()
+            .filter_map(|item| match item {
+                GodotValue::String(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect(),
+        Some(GodotValue::String(name)) => vec![name.as_str()],
+        _ => Vec::new(),
+    };
+    if known.is_none() && declared.is_empty() {
+        return None;
+    }
+    let is_known = known
+        .into_iter()
+        .flatten()
+        .map(String::as_str)
+        .chain(declared)
+        .any(|name| name == action);
+    (!is_known).then(|| format!("unknown input action '{}'", action))
+}
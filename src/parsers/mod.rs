@@ -1,24 +1,74 @@
+mod bounds;
+#[cfg(feature = "bundles")]
+mod bundle;
+pub(crate) mod color;
+mod continuation;
+mod curve;
 mod debug;
-mod sentence;
+mod dialogue;
+pub(crate) mod expression;
+#[cfg(feature = "generate")]
+mod generate;
+mod icu;
+mod input_action;
+pub(crate) mod param_types;
+mod price;
+pub(crate) mod sentence;
+mod stat;
+mod state_machine;
+mod translation;
 mod typed_sentences;
+mod variables;
+mod weighted_table;
+pub use bounds::{Bounds, OUT_OF_RANGE_CODE, STRING_CONSTRAINT_CODE, TextConstraints};
+#[cfg(feature = "bundles")]
+pub use bundle::BundleError;
+pub use continuation::ContinuationParser;
+pub use curve::CurveParser;
 pub use debug::DebugPrinter;
+pub use dialogue::DialogueParser;
+pub use input_action::{INPUT_ACTIONS_KEY, godot_input_actions};
+pub use price::{Currency, PRICE_TYPE};
 use regex::Regex;
-pub use sentence::SentenceParser;
+pub use sentence::{
+    ANTECEDENT_PREFIX, CANONICAL_KEY, DISPLAY_TEMPLATE_KEY, LITERALS_KEY, PARAM_RAW_PREFIX,
+    ParameterDefinition, REFERENCE_SUFFIX, SentenceParseError, SentenceParser,
+};
+pub use stat::STATS_KEY;
+pub use state_machine::StateMachineParser;
 use std::collections::HashMap;
-pub use typed_sentences::TypedSentencesParser;
+use std::sync::LazyLock;
+pub use translation::{PoOptions, TrKeyFormat, TranslationEntry};
+pub use typed_sentences::{
+    BASE_LAYER, BundleSource, GrammarLayer, LoadOptions, PhraseOrigin, TypedSentencesError,
+    TypedSentencesParser,
+};
+pub use variables::VariablesParser;
+pub use weighted_table::WeightedTableParser;
 
 use crate::{
     GodotValue,
     semantic::{DokeNode, DokeParser},
 };
 
+/// `{key}` of a frontmatter template
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([\w .\-]+)\}").unwrap());
+
+/// Replaces `{key}` placeholders with frontmatter values, and `{3 tiles}` with the
+/// amount scaled by the frontmatter's `units:` or `tile_size` (`_rate`, `_scale`)
+/// ```
+/// use doke::{DokePipe, parsers::FrontmatterTemplateParser};
+///
+/// let doc = DokePipe::new()
+///     .add(FrontmatterTemplateParser)
+///     .run_markdown("---\ntile_size: 16\nunits:\n  tick: 0.5\n---\nDashes {3 tiles} every {3 ticks}");
+/// assert_eq!(doc.nodes[0].statement, "Dashes 48 every 1.5");
+/// ```
 #[derive(Debug)]
 pub struct FrontmatterTemplateParser;
 
 impl DokeParser for FrontmatterTemplateParser {
     fn process(&self, node: &mut DokeNode, frontmatter: &HashMap<String, GodotValue>) {
-        let re = Regex::new(r"\{([a-zA-Z0-9_ ]+)\}").unwrap();
-
         // Normalize frontmatter keys: lowercase + replace spaces with '_'
         let normalized_map: HashMap<String, &GodotValue> = frontmatter
             .iter()
@@ -26,13 +76,16 @@ impl DokeParser for FrontmatterTemplateParser {
             .collect();
 
         // Replace placeholders
-        let new_statement = re.replace_all(&node.statement, |caps: &regex::Captures| {
+        let new_statement = PLACEHOLDER.replace_all(&node.statement, |caps: &regex::Captures| {
             let key_raw = &caps[1];
             let key = key_raw.to_lowercase().replace(' ', "_"); // normalize placeholder
 
-            if let Some(value) = normalized_map.get(&key) {
+            if let Some(scaled) = scale_units(key_raw, &normalized_map) {
+                scaled
+            } else if let Some(value) = normalized_map.get(&key) {
                 match value {
                     GodotValue::Int(i) => i.to_string(),
+                    GodotValue::UInt(u) => u.to_string(),
                     GodotValue::Float(f) => f.to_string(),
                     GodotValue::String(s) => s.clone(),
                     GodotValue::Bool(b) => b.to_string(),
@@ -43,7 +96,7 @@ impl DokeParser for FrontmatterTemplateParser {
             }
         });
 
-        node.statement = new_statement.to_string();
+        node.statement = new_statement.into();
 
         // Recursively process children
         for child in &mut node.children {
@@ -51,3 +104,55 @@ impl DokeParser for FrontmatterTemplateParser {
         }
     }
 }
+
+/// Expand a `{3 tiles}` template with the scale the frontmatter declares for the unit,
+/// either in a `units:` map (`units: {tile: 16}`) or as `tile_size`, `tile_rate` or `tile_scale`.
+fn scale_units(template: &str, frontmatter: &HashMap<String, &GodotValue>) -> Option<String> {
+    let (amount, unit) = template.trim().split_once(' ')?;
+    let unit = unit.trim().to_lowercase();
+    let amount: f64 = amount.parse().ok()?;
+
+    // "tiles" -> "tile", "boxes" -> "box"
+    let singular = [unit.strip_suffix("es"), unit.strip_suffix('s')];
+    let mut candidates = std::iter::once(unit.as_str()).chain(singular.into_iter().flatten());
+
+    let as_number = |value: &GodotValue| match value {
+        GodotValue::Int(i) => Some(*i as f64),
+        GodotValue::UInt(u) => Some(*u as f64),
+        GodotValue::Float(f) => Some(*f),
+        _ => None,
+    };
+    let scale = candidates.find_map(|name| {
+        let declared = match frontmatter.get("units") {
+            Some(GodotValue::Dict(units)) => units.get(name).and_then(as_number),
+            _ => None,
+        };
+        declared.or_else(|| {
+            ["size", "rate", "scale"].iter().find_map(|suffix| {
+                frontmatter
+                    .get(&format!("{}_{}", name, suffix))
+                    .and_then(|v| as_number(v))
+            })
+        })
+    })?;
+
+    let value = amount * scale;
+    if value.fract() == 0.0 && value.abs() < i64::MAX as f64 {
+        Some((value as i64).to_string())
+    } else {
+        Some(value.to_string())
+    }
+}
+
+/// The name of a "Header:" statement introducing a list of entries,
+/// if it is one of `headers` (or any header when `headers` is None).
+pub(crate) fn list_header<'a>(node: &'a DokeNode, headers: Option<&[String]>) -> Option<&'a str> {
+    let name = node.statement.trim().strip_suffix(':')?.trim();
+    if name.is_empty() {
+        return None;
+    }
+    match headers {
+        Some(headers) if !headers.iter().any(|h| h.eq_ignore_ascii_case(name)) => None,
+        _ => Some(name),
+    }
+}
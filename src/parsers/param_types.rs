@@ -0,0 +1,492 @@
+// param_types.rs
+//
+// Built-in parameter types of the sentence parser: the regex each type
+// captures with, and how the captured text is coerced into a GodotValue.
+// Types that aren't built in are parsed recursively as constituents.
+// `price` is coerced with the currency of its phrase, and `stat` checked against its
+// stats, see `PhraseConfig::parse_parameter`.
+//
+// Capture patterns must contain exactly one capturing group: parameters
+// are read back by group index, so any inner group has to be `(?:...)`.
+
+use std::collections::HashMap;
+
+use crate::GodotValue;
+use crate::numeric;
+use crate::parsers::color::{COLOR_PATTERN, parse_color};
+use crate::parsers::expression;
+use crate::parsers::input_action::INPUT_ACTION_PATTERN;
+use crate::parsers::price::PRICE_PATTERN;
+use crate::parsers::stat::STAT_PATTERN;
+
+/// Type of the resources `ref` parameters make, holding the `id` they point to
+pub const EXT_REF_TYPE: &str = "ExtRef";
+
+/// Type of the resources `gdexpr` parameters make, holding the expression's `source`
+pub const GD_EXPRESSION_TYPE: &str = "GDExpression";
+
+pub fn is_basic_type(param_type: &str) -> bool {
+    matches!(
+        param_type.to_lowercase().as_str(),
+        "int"
+            | "float"
+            | "bool"
+            | "string"
+            | "date"
+            | "datetime"
+            | "condition"
+            | "formula"
+            | "ref"
+            | "gdexpr"
+            | "price"
+            | "vec2"
+            | "rect"
+            | "color"
+            | "input_action"
+            | "stat"
+    )
+}
+
+/// The capture group a parameter of this type matches with
+pub fn capture_pattern(param_type: &str) -> String {
+    match param_type.to_lowercase().as_str() {
+        "int" => r"([-+]?(?:0[bB][01]+|0[oO][0-7]+|0[xX][0-9a-fA-F]+|\d+))".to_string(),
+        "float" => r"([-+]?(?:\d+\.\d*|\.\d+|\d+)(?:[eE][-+]?\d+)?)".to_string(),
+        "bool" => r"(true|false|yes|no|1|0)".to_string(),
+        "date" => format!("((?:{}|{}))", ISO_DATE, WORDY_DATE),
+        "datetime" => format!(
+            "((?:{}[T ]{}(?:Z|[-+]\\d{{2}}:?\\d{{2}})?|(?:{}|{})(?:,?\\s+(?:at\\s+)?{})?))",
+            ISO_DATE, CLOCK, ISO_DATE, WORDY_DATE, CLOCK
+        ),
+        // an id, optionally as a `[[wikilink]]`
+        "ref" => r"(?:\[\[)?([\w\-./]+)(?:\]\])?".to_string(),
+        // a GDScript expression in `backticks` or {braces}, delimiters included
+        "gdexpr" => r"(`[^`\n]+`|\{[^{}\n]+\})".to_string(),
+        "price" => PRICE_PATTERN.to_string(),
+        // `(3, 5)` or `3,5`
+        "vec2" => format!("({}|{})", point(), pair()),
+        // `(0, 0) to (10, 10)`, two corners
+        "rect" => format!(r"({}\s+to\s+{})", point(), point()),
+        // `#rrggbb`, `rgb(...)` or a name
+        "color" => COLOR_PATTERN.clone(),
+        "input_action" => INPUT_ACTION_PATTERN.to_string(),
+        "stat" => STAT_PATTERN.to_string(),
+        _ => r"(.+?)".to_string(), // non-greedy default
+    }
+}
+
+/// A value of this type to write in generated documents
+pub fn example_value(param_type: &str) -> Option<&'static str> {
+    Some(match param_type.to_lowercase().as_str() {
+        "int" => "1",
+        "float" => "1.5",
+        "bool" => "true",
+        "string" => "something",
+        "date" => "2024-01-01",
+        "datetime" => "2024-01-01T12:00:00",
+        "condition" => "level >= 1",
+        "formula" => "level * 2",
+        "ref" => "other_item",
+        "gdexpr" => "`health > 0`",
+        "vec2" => "(3, 5)",
+        "rect" => "(0, 0) to (10, 10)",
+        "color" => "#ff8800",
+        "input_action" => "jump",
+        "stat" => "STR",
+        _ => return None,
+    })
+}
+
+// Returns the value and an optional precision warning
+pub fn parse_basic_parameter(
+    value: &str,
+    param_type: &str,
+) -> std::result::Result<(GodotValue, Option<String>), String> {
+    match param_type.to_lowercase().as_str() {
+        // supports hex/octal/binary prefixes, and u64 values past i64::MAX
+        "int" => numeric::parse_int(value).map(|v| (v, None)),
+        "float" => numeric::parse_float(value).map(|parsed| (parsed.value, parsed.warning)),
+        "bool" => match value.to_lowercase().as_str() {
+            "true" | "yes" | "1" => Ok((GodotValue::Bool(true), None)),
+            "false" | "no" | "0" => Ok((GodotValue::Bool(false), None)),
+            _ => Err(format!("Invalid boolean value: {}", value)),
+        },
+        // input actions and stats are checked against the declared ones by the phrase
+        "string" | "input_action" | "stat" => Ok((GodotValue::String(value.to_string()), None)),
+        "date" => parse_date(value).map(|d| (d.to_godot(false), None)),
+        "datetime" => parse_datetime(value).map(|d| (d.to_godot(true), None)),
+        "condition" => expression::parse_condition(value).map(|c| (c.to_godot(), None)),
+        "formula" => expression::parse_formula(value)
+            .map(|f| (expression::formula_to_godot(value, &f), None)),
+        "ref" => Ok((
+            GodotValue::Resource {
+                type_name: EXT_REF_TYPE.into(),
+                abstract_type_name: EXT_REF_TYPE.into(),
+                fields: HashMap::from([("id".to_string(), GodotValue::String(value.into()))]),
+            },
+            None,
+        )),
+        "gdexpr" => gd_expression(value).map(|source| {
+            let fields = HashMap::from([("source".to_string(), GodotValue::String(source.into()))]);
+            let expression = GodotValue::Resource {
+                type_name: GD_EXPRESSION_TYPE.into(),
+                abstract_type_name: GD_EXPRESSION_TYPE.into(),
+                fields,
+            };
+            (expression, None)
+        }),
+        "vec2" => parse_vector2(value).map(|(x, y)| (GodotValue::Vector2(x, y), None)),
+        "rect" => parse_rect(value).map(|rect| (rect, None)),
+        "color" => parse_color(value).map(|(r, g, b, a)| (GodotValue::Color(r, g, b, a), None)),
+        _ => Err(format!("Unknown basic type: {}", param_type)),
+    }
+}
+
+/// The expression between the delimiters, kept verbatim for the engine to parse
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let grammar = "Trigger:\n  - \"Triggers when {cond: gdexpr}\"\n";
+/// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Trigger".into(), grammar).unwrap());
+/// let source = |text| pipe.validate(text).unwrap()[0].get_path("cond/source").cloned();
+///
+/// let expected = Some(GodotValue::String("health < max_health * 0.3".into()));
+/// assert_eq!(source("Triggers when `health < max_health * 0.3`"), expected);
+/// assert_eq!(source("Triggers when {health < max_health * 0.3}"), expected);
+/// // without delimiters, the statement isn't read as one
+/// assert!(pipe.validate("Triggers when health < 3").is_err());
+/// ```
+fn gd_expression(value: &str) -> Result<&str, String> {
+    value
+        .strip_prefix('`')
+        .and_then(|rest| rest.strip_suffix('`'))
+        .or_else(|| value.strip_prefix('{')?.strip_suffix('}'))
+        .ok_or_else(|| {
+            format!(
+                "Expected an expression in `backticks` or {{braces}}: {}",
+                value
+            )
+        })
+}
+
+// ----------------- Dates -----------------
+
+const ISO_DATE: &str = r"\d{4}-\d{2}-\d{2}";
+// "March 3rd, 2024", "Mar. 3 2024", "3rd of March 2024"
+const WORDY_DATE: &str = r"(?:[A-Za-z]+\.?\s+\d{1,2}(?:st|nd|rd|th)?,?\s+\d{4}|\d{1,2}(?:st|nd|rd|th)?\s+(?:of\s+)?[A-Za-z]+\.?,?\s+\d{4})";
+const CLOCK: &str = r"\d{1,2}:\d{2}(?::\d{2}(?:\.\d+)?)?(?:\s*[aApP][mM])?";
+
+const MONTHS: [&str; 12] = [
+    "january",
+    "february",
+    "march",
+    "april",
+    "may",
+    "june",
+    "july",
+    "august",
+    "september",
+    "october",
+    "november",
+    "december",
+];
+
+/// Calendar components, laid out like Godot's `Time` datetime dictionaries
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+struct DateTime {
+    year: i64,
+    month: i64,
+    day: i64,
+    hour: i64,
+    minute: i64,
+    second: i64,
+    /// Offset from UTC in minutes, when the text declared one
+    bias: Option<i64>,
+}
+
+impl DateTime {
+    fn to_godot(self, with_time: bool) -> GodotValue {
+        let mut dict = HashMap::from([
+            ("year".to_string(), GodotValue::Int(self.year)),
+            ("month".to_string(), GodotValue::Int(self.month)),
+            ("day".to_string(), GodotValue::Int(self.day)),
+        ]);
+        if with_time {
+            dict.insert("hour".into(), GodotValue::Int(self.hour));
+            dict.insert("minute".into(), GodotValue::Int(self.minute));
+            dict.insert("second".into(), GodotValue::Int(self.second));
+            if let Some(bias) = self.bias {
+                dict.insert("bias".into(), GodotValue::Int(bias));
+            }
+        }
+        GodotValue::Dict(dict)
+    }
+}
+
+/// An ISO or written out date, checked against the calendar
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Event".into(), r#"
+/// Release:
+///   - "Releases on {day: date}"
+/// Raid:
+///   - "Starts {start: datetime}"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+///
+/// let values = pipe.validate("Releases on March 3rd, 2024").unwrap();
+/// assert_eq!(values[0].get_path("day/month"), Some(&GodotValue::Int(3)));
+///
+/// let values = pipe.validate("Starts 2024-03-03T14:30:00+02:00").unwrap();
+/// assert_eq!(values[0].get_path("start/hour"), Some(&GodotValue::Int(14)));
+/// assert_eq!(values[0].get_path("start/bias"), Some(&GodotValue::Int(120)));
+///
+/// // words separated by non-breaking spaces
+/// let values = pipe.validate("Starts June\u{2003}1\u{2003}2024").unwrap();
+/// assert_eq!(values[0].get_path("start/month"), Some(&GodotValue::Int(6)));
+///
+/// let doc = pipe.run_markdown("Releases on 2023-02-30");
+/// assert!(doc.nodes[0].diagnostics[0].message.contains("Invalid date: 2023-02-30"));
+/// ```
+fn parse_date(text: &str) -> Result<DateTime, String> {
+    let text = text.trim();
+    let (year, month, day) = if let Some((y, m, d)) = split_iso_date(text) {
+        (y, m, d)
+    } else {
+        parse_wordy_date(text).ok_or_else(|| format!("Invalid date: {}", text))?
+    };
+
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(format!("Invalid date: {}", text));
+    }
+
+    Ok(DateTime {
+        year,
+        month,
+        day,
+        ..Default::default()
+    })
+}
+
+fn parse_datetime(text: &str) -> Result<DateTime, String> {
+    let text = text.trim();
+
+    // ISO: 2024-03-03T14:30:00+02:00
+    if let (Some(day), Some(rest)) = (text.get(..10), text.get(10..))
+        && !rest.is_empty()
+        && split_iso_date(day).is_some()
+    {
+        let mut date = parse_date(day)?;
+        let rest = rest.trim_start_matches(['T', ' ']);
+        let (clock, bias) = split_timezone(rest)?;
+        (date.hour, date.minute, date.second) = parse_clock(clock)?;
+        date.bias = bias;
+        return Ok(date);
+    }
+
+    // "March 3rd, 2024 at 14:30"
+    let lower = text.to_lowercase();
+    let split = lower.rfind(" at ").map(|i| (i, i + 4)).or_else(|| {
+        lower
+            .rfind(' ')
+            .filter(|&i| lower[i..].contains(':'))
+            .map(|i| (i, i + 1))
+    });
+    // lowercasing can change the length of the text
+    let split = split.and_then(|(date_end, clock_start)| {
+        Some((text.get(..date_end)?, text.get(clock_start..)?))
+    });
+    match split {
+        Some((day, clock)) => {
+            let mut date = parse_date(day.trim_end_matches(','))?;
+            (date.hour, date.minute, date.second) = parse_clock(clock)?;
+            Ok(date)
+        }
+        None => parse_date(text),
+    }
+}
+
+fn split_iso_date(text: &str) -> Option<(i64, i64, i64)> {
+    let mut parts = text.splitn(3, '-');
+    let year = parts.next()?;
+    let month = parts.next()?;
+    let day = parts.next()?;
+    if year.len() != 4 || month.len() != 2 || day.len() != 2 {
+        return None;
+    }
+    Some((year.parse().ok()?, month.parse().ok()?, day.parse().ok()?))
+}
+
+fn parse_wordy_date(text: &str) -> Option<(i64, i64, i64)> {
+    let words: Vec<&str> = text
+        .split(|c: char| c.is_whitespace() || c == ',')
+        .filter(|w| !w.is_empty() && !w.eq_ignore_ascii_case("of"))
+        .collect();
+    let [first, second, year] = words[..] else {
+        return None;
+    };
+    let year = year.parse().ok()?;
+    // "March 3rd 2024" or "3rd March 2024"
+    match (month_number(first), month_number(second)) {
+        (Some(month), None) => Some((year, month, day_number(second)?)),
+        (None, Some(month)) => Some((year, month, day_number(first)?)),
+        _ => None,
+    }
+}
+
+fn month_number(word: &str) -> Option<i64> {
+    let word = word.trim_end_matches('.').to_lowercase();
+    if word.len() < 3 {
+        return None;
+    }
+    MONTHS
+        .iter()
+        .position(|month| month.starts_with(&word))
+        .map(|i| i as i64 + 1)
+}
+
+fn day_number(word: &str) -> Option<i64> {
+    word.trim_end_matches(|c: char| c.is_ascii_alphabetic())
+        .parse()
+        .ok()
+}
+
+fn split_timezone(text: &str) -> Result<(&str, Option<i64>), String> {
+    if let Some(clock) = text.strip_suffix(['Z', 'z']) {
+        return Ok((clock, Some(0)));
+    }
+    if let Some(i) = text.rfind(['+', '-']) {
+        let (clock, zone) = text.split_at(i);
+        let sign = if zone.starts_with('-') { -1 } else { 1 };
+        let digits: String = zone[1..].chars().filter(|c| c.is_ascii_digit()).collect();
+        if digits.len() != 4 {
+            return Err(format!("Invalid timezone offset: {}", zone));
+        }
+        let hours: i64 = digits[..2].parse().map_err(|_| zone.to_string())?;
+        let minutes: i64 = digits[2..].parse().map_err(|_| zone.to_string())?;
+        return Ok((clock, Some(sign * (hours * 60 + minutes))));
+    }
+    Ok((text, None))
+}
+
+fn parse_clock(text: &str) -> Result<(i64, i64, i64), String> {
+    let lower = text.trim().to_lowercase();
+    let (clock, meridiem) = match lower
+        .strip_suffix("am")
+        .or_else(|| lower.strip_suffix("pm"))
+    {
+        Some(clock) => (clock.trim(), Some(lower.ends_with("pm"))),
+        None => (lower.as_str(), None),
+    };
+
+    let invalid = || format!("Invalid time: {}", text.trim());
+    let mut parts = clock.split(':');
+    let mut hour: i64 = parts
+        .next()
+        .ok_or_else(invalid)?
+        .parse()
+        .map_err(|_| invalid())?;
+    let minute: i64 = parts
+        .next()
+        .ok_or_else(invalid)?
+        .parse()
+        .map_err(|_| invalid())?;
+    let second: i64 = match parts.next() {
+        // fractional seconds are dropped, Godot datetime dicts hold whole seconds
+        Some(s) => s
+            .split('.')
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| invalid())?,
+        None => 0,
+    };
+
+    if let Some(pm) = meridiem {
+        if !(1..=12).contains(&hour) {
+            return Err(invalid());
+        }
+        hour = match (hour, pm) {
+            (12, false) => 0,
+            (12, true) => 12,
+            (h, true) => h + 12,
+            (h, false) => h,
+        };
+    }
+    if hour > 23 || minute > 59 || second > 60 {
+        return Err(invalid());
+    }
+    Ok((hour, minute, second))
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if (year % 4 == 0 && year % 100 != 0) || year % 400 == 0 => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// ----------------- Coordinates -----------------
+
+const COORDINATE: &str = r"[-+]?(?:\d+\.?\d*|\.\d+)";
+
+/// Two coordinates separated by a comma
+fn pair() -> String {
+    format!(r"{}\s*,\s*{}", COORDINATE, COORDINATE)
+}
+
+/// A pair in parentheses
+fn point() -> String {
+    format!(r"\(\s*{}\s*\)", pair())
+}
+
+fn parse_vector2(text: &str) -> Result<(f64, f64), String> {
+    let inner = text.trim();
+    let inner = inner
+        .strip_prefix('(')
+        .and_then(|rest| rest.strip_suffix(')'))
+        .unwrap_or(inner);
+    let (x, y) = inner
+        .split_once(',')
+        .ok_or_else(|| format!("Expected a position like (3, 5): {}", text))?;
+    let coordinate = |c: &str| {
+        c.trim()
+            .parse::<f64>()
+            .map_err(|_| format!("Invalid coordinate '{}'", c.trim()))
+    };
+    Ok((coordinate(x)?, coordinate(y)?))
+}
+
+/// The rectangle between two corners, whichever order they're given in
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let grammar = "Effect:\n  - \"Spawns at {at: vec2}\": Spawn\n  - \"Covers {area: rect}\": Zone\n";
+/// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap());
+/// let value = |text, field| pipe.validate(text).unwrap()[0].get_path(field).cloned();
+///
+/// assert_eq!(value("Spawns at (3, 5)", "at"), Some(GodotValue::Vector2(3.0, 5.0)));
+/// assert_eq!(value("Spawns at 3,5", "at"), Some(GodotValue::Vector2(3.0, 5.0)));
+/// assert_eq!(
+///     value("Covers (10, 10) to (0, 4)", "area"),
+///     Some(GodotValue::Rect2(0.0, 4.0, 10.0, 6.0))
+/// );
+/// assert!(pipe.validate("Covers (1, 2) to 3").is_err());
+/// ```
+fn parse_rect(text: &str) -> Result<GodotValue, String> {
+    let invalid = || format!("Expected a region like (0, 0) to (10, 10): {}", text);
+    let end = text.find(')').ok_or_else(invalid)?;
+    let (from, rest) = text.split_at(end + 1);
+    let to = rest.trim_start().strip_prefix("to").ok_or_else(invalid)?;
+    let (x1, y1) = parse_vector2(from)?;
+    let (x2, y2) = parse_vector2(to)?;
+    Ok(GodotValue::Rect2(
+        x1.min(x2),
+        y1.min(y2),
+        (x2 - x1).abs(),
+        (y2 - y1).abs(),
+    ))
+}
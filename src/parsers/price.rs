@@ -0,0 +1,221 @@
+// parsers/price.rs
+//
+// `price` parameters: amounts of money written in denominations, like
+// "3 gold 50 silver", "120g" or "2 gold and 5 copper", read with the currency
+// of the grammar, a top-level `currency:` key of the dokedef:
+//
+//     currency:
+//       gold: {value: 100, symbols: [g, gp]}
+//       silver: {value: 10, symbols: [s]}
+//       copper: 1
+//
+// Grammars without one use gold, silver and copper, worth 100, 10 and 1.
+// A price becomes a `Price` resource with its `value` in the smallest
+// denomination, and a field per denomination breaking it down the way a shop
+// shows it ("3 gold 550 copper" is 8 gold, 5 silver and 0 copper).
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use yaml_rust2::Yaml;
+
+use super::sentence::SentenceParseError;
+use crate::GodotValue;
+
+/// Type of the resources `price` parameters make
+pub const PRICE_TYPE: &str = "Price";
+
+/// What a `price` parameter captures: amounts each followed by a denomination
+pub(crate) const PRICE_PATTERN: &str = r"(\d+(?:\.\d+)?\s*(?:[^\W\d_]+|[$€£¥¢])(?:(?:\s*,\s*|\s+and\s+|\s+)\d+(?:\.\d+)?\s*(?:[^\W\d_]+|[$€£¥¢]))*)";
+
+static AMOUNT: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(\d+(?:\.\d+)?)\s*([^\W\d_]+|[$€£¥¢])").unwrap());
+
+#[derive(Debug, Clone, PartialEq)]
+struct Denomination {
+    name: String,
+    /// Worth in the smallest denomination
+    value: i64,
+    /// Short forms, like `g` for gold
+    symbols: Vec<String>,
+}
+
+/// The denominations `price` parameters are written in
+/// ```
+/// use doke::parsers::Currency;
+///
+/// let currency = Currency::new()
+///     .with_denomination("crown", 20, &["cr"])
+///     .with_denomination("penny", 1, &["p"]);
+/// assert_eq!(currency.parse("2 crowns and 5 pennies"), Ok(45));
+/// assert_eq!(currency.parse("3cr"), Ok(60));
+/// assert_eq!(currency.spell(45), "2 crown 5 penny");
+/// assert!(currency.parse("4 gold").is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Currency {
+    /// Most valuable first
+    denominations: Vec<Denomination>,
+}
+
+impl Default for Currency {
+    fn default() -> Self {
+        Self::new()
+            .with_denomination("gold", 100, &["g", "gp"])
+            .with_denomination("silver", 10, &["s", "sp"])
+            .with_denomination("copper", 1, &["c", "cp"])
+    }
+}
+
+impl Currency {
+    /// A currency without denominations, see `with_denomination`
+    pub fn new() -> Self {
+        Self {
+            denominations: Vec::new(),
+        }
+    }
+
+    /// Add a denomination worth `value` of the smallest one, written by its name
+    /// (singular or plural) or one of `symbols`
+    pub fn with_denomination(mut self, name: &str, value: i64, symbols: &[&str]) -> Self {
+        self.denominations.push(Denomination {
+            name: name.to_string(),
+            value,
+            symbols: symbols.iter().map(|s| s.to_string()).collect(),
+        });
+        self.denominations
+            .sort_by_key(|denomination| std::cmp::Reverse(denomination.value));
+        self
+    }
+
+    /// Accepts `{name: value}` pairs, or `{name: {value, symbols}}`
+    pub(crate) fn from_yaml(yaml: &Yaml) -> Result<Self, SentenceParseError> {
+        let invalid = |message: &str| SentenceParseError::InvalidPattern(message.to_string());
+        let Yaml::Hash(map) = yaml else {
+            return Err(invalid("currency must map denominations to their value"));
+        };
+        let mut currency = Self::new();
+        for (name, spec) in map {
+            let name = name
+                .as_str()
+                .ok_or_else(|| invalid("denomination names must be strings"))?;
+            let (value, symbols) = match spec {
+                Yaml::Integer(value) => (*value, Vec::new()),
+                Yaml::Hash(_) => {
+                    let value = spec["value"]
+                        .as_i64()
+                        .ok_or_else(|| invalid("a denomination needs an integer `value`"))?;
+                    let symbols = match &spec["symbols"] {
+                        Yaml::Array(items) => items.iter().filter_map(Yaml::as_str).collect(),
+                        Yaml::String(symbol) => vec![symbol.as_str()],
+                        _ => Vec::new(),
+                    };
+                    (value, symbols)
+                }
+                _ => {
+                    return Err(invalid(
+                        "a denomination must be a value or {value, symbols}",
+                    ));
+                }
+            };
+            if value <= 0 {
+                return Err(invalid("denominations must be worth more than 0"));
+            }
+            currency = currency.with_denomination(name, value, &symbols);
+        }
+        if currency.denominations.is_empty() {
+            return Err(invalid("currency needs at least one denomination"));
+        }
+        Ok(currency)
+    }
+
+    /// The amount `text` is worth in the smallest denomination
+    pub fn parse(&self, text: &str) -> Result<i64, String> {
+        let mut total: i64 = 0;
+        let mut amounts = 0;
+        for cap in AMOUNT.captures_iter(text) {
+            let denomination = self
+                .find(&cap[2])
+                .ok_or_else(|| format!("Unknown denomination '{}'", &cap[2]))?;
+            let amount: f64 = cap[1]
+                .parse()
+                .map_err(|_| format!("Invalid amount '{}'", &cap[1]))?;
+            let worth = amount * denomination.value as f64;
+            if worth.fract() != 0.0 {
+                return Err(format!(
+                    "{} {} isn't a whole amount of the smallest denomination",
+                    &cap[1], denomination.name
+                ));
+            }
+            total = Some(worth)
+                .filter(|worth| *worth < i64::MAX as f64)
+                .and_then(|worth| total.checked_add(worth as i64))
+                .ok_or_else(|| format!("Price too large: {}", text))?;
+            amounts += 1;
+        }
+        match amounts {
+            0 => Err(format!(
+                "Expected a price like '3 gold 50 silver': {}",
+                text
+            )),
+            _ => Ok(total),
+        }
+    }
+
+    /// `value` in each denomination, most valuable first, paid with as few coins as possible
+    pub fn breakdown(&self, value: i64) -> Vec<(&str, i64)> {
+        let mut rest = value;
+        self.denominations
+            .iter()
+            .map(|denomination| {
+                let count = rest / denomination.value;
+                rest %= denomination.value;
+                (denomination.name.as_str(), count)
+            })
+            .collect()
+    }
+
+    /// `value` written with its denominations, for generated documents
+    pub fn spell(&self, value: i64) -> String {
+        let parts: Vec<String> = self
+            .breakdown(value)
+            .into_iter()
+            .filter(|(_, count)| *count != 0)
+            .map(|(name, count)| format!("{} {}", count, name))
+            .collect();
+        match (parts.is_empty(), self.denominations.last()) {
+            (true, Some(smallest)) => format!("0 {}", smallest.name),
+            _ => parts.join(" "),
+        }
+    }
+
+    /// The `Price` resource of `value`
+    pub(crate) fn to_godot(&self, value: i64) -> GodotValue {
+        let mut fields: HashMap<String, GodotValue> = self
+            .breakdown(value)
+            .into_iter()
+            .map(|(name, count)| (name.to_string(), GodotValue::Int(count)))
+            .collect();
+        fields.insert("value".into(), GodotValue::Int(value));
+        GodotValue::Resource {
+            type_name: PRICE_TYPE.into(),
+            abstract_type_name: PRICE_TYPE.into(),
+            fields,
+        }
+    }
+
+    fn find(&self, word: &str) -> Option<&Denomination> {
+        let word = word.to_lowercase();
+        self.denominations.iter().find(|denomination| {
+            let name = denomination.name.to_lowercase();
+            word == name
+                || word.strip_suffix('s') == Some(name.as_str())
+                || word.strip_suffix("ies").map(|stem| format!("{}y", stem)) == Some(name.clone())
+                || denomination
+                    .symbols
+                    .iter()
+                    .any(|symbol| symbol.to_lowercase() == word)
+        })
+    }
+}
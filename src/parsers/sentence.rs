@@ -5,15 +5,40 @@
 // strict-case matching, whitespace-robust literals,
 // phrase specificity, and recursive constituent parsing.
 
+#[cfg(feature = "translate")]
 use polib::po_file::POParseError;
-use regex::Regex;
+use regex::{Regex, RegexBuilder};
 use std::collections::HashMap;
+use std::ops::Range;
 use std::path::PathBuf;
-use yaml_rust2::yaml::Hash;
+use std::sync::{LazyLock, OnceLock};
+use std::time::Instant;
+use unicode_normalization::UnicodeNormalization;
 
 use crate::base_parser::Position;
-use crate::utility::{camel_to_const_case, hash_value, u64_to_base32, update_po_file};
-use crate::{DokeNode, DokeNodeState, DokeOut, DokeParser, GodotValue, Hypo};
+use crate::describe::{Describer, Descriptions};
+use crate::diagnostics::Diagnostic;
+use crate::hierarchy::TypeHierarchy;
+use crate::limits;
+use crate::locale::{self, Locale};
+use crate::normalization;
+use crate::numeric;
+use crate::parsers::bounds::{
+    self, Bounds, DeclaredType, OUT_OF_RANGE_CODE, STRING_CONSTRAINT_CODE, TextConstraints,
+};
+use crate::parsers::color::to_hex;
+use crate::parsers::input_action;
+use crate::parsers::param_types::{capture_pattern, is_basic_type, parse_basic_parameter};
+use crate::parsers::price::Currency;
+use crate::parsers::stat;
+use crate::parsers::translation::{TrKeyFormat, check_tr_keys};
+use crate::rich_text::DISPLAY_TEXT_KEY;
+use crate::semantic::BlockKind;
+#[cfg(feature = "translate")]
+use crate::utility::update_po_file;
+use crate::{
+    DokeNode, DokeNodeState, DokeOut, DokeParser, Extensions, GodotValue, Hypo, Statement,
+};
 use thiserror::Error;
 use yaml_rust2::{Yaml, YamlLoader};
 
@@ -36,16 +61,97 @@ pub enum SentenceParseError {
     NoMatch(String),
     #[error("Max recursion depth exceeded : {0}")]
     MaxRecursionDepthExceeded(String),
+    #[error("No phrase in section {0}")]
+    UnknownSection(String),
+    #[cfg(feature = "translate")]
     #[error("Could not read translation file : {0}")]
     TranslationWriteError(#[from] POParseError),
+    #[error("Translation key {0} is made by two phrases: \"{1}\" and \"{2}\"")]
+    TrKeyCollision(String, String, String),
+    #[error("Could not write messages file : {0}")]
+    MessagesWriteError(#[from] std::io::Error),
 }
 
 // ----------------- Config structures -----------------
 
-#[derive(Debug, Clone)]
+/// `parse_data` key holding the statement as its matched phrase spells it, used by `DokePipe::format`
+pub const CANONICAL_KEY: &str = "doke_canonical";
+
+/// Prefix of the `parse_data` keys holding each parameter as the author wrote it
+/// (`param_raw:damage`), before coercion: a `Dict` with its `text`, and the `start`
+/// and `end` of its span
+/// ```
+/// use doke::parsers::{PARAM_RAW_PREFIX, SentenceParser};
+/// use doke::{DokePipe, GodotValue};
+///
+/// let grammar = "Effect:\n  - \"Deals {damage: float} damage\"\n";
+/// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap());
+/// let doc = pipe.run_markdown("Deals 1.50 damage\n");
+///
+/// let raw = &doc.nodes[0].parse_data[&format!("{}damage", PARAM_RAW_PREFIX)];
+/// assert_eq!(raw.get_path("text"), Some(&GodotValue::String("1.50".into())));
+/// assert_eq!(raw.get_path("start"), Some(&GodotValue::Int(6)));
+/// assert_eq!(raw.get_path("end"), Some(&GodotValue::Int(10)));
+/// ```
+pub const PARAM_RAW_PREFIX: &str = "param_raw:";
+
+/// Result field (and `parse_data` key, for `Format` returns) holding the phrase with
+/// `{param}` placeholders, the English entry of its translation key in the PO file:
+/// `tr(doke_tr_key).format(values)` displays the statement
+pub const DISPLAY_TEMPLATE_KEY: &str = "display_template";
+
+/// Result field holding, by parameter, the numbers written differently from how they
+/// print (`0x1F`, `1.50`), for writers to re-emit them as the author wrote them
+pub const LITERALS_KEY: &str = "doke_literals";
+
+/// Prefix of the `parse_data` keys a statement gives its children for each pronoun
+/// of its section's `antecedents` (`antecedent:it`), holding the parameter it binds to
+pub const ANTECEDENT_PREFIX: &str = "antecedent:";
+
+/// Suffix of the result field a parameter written as a bound pronoun gets instead of
+/// its value (`target_ref: "target"`), naming the parent's parameter it refers to
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Reaction:
+///   phrases:
+///     - "When {target: Unit} is hit"
+///   antecedents: {it: target, the target: target}
+/// Burn:
+///   - "Burns {target: Unit} for {turns: int} turns"
+/// Unit:
+///   - "an ally"
+/// "#).unwrap();
+/// let values = DokePipe::new()
+///     .map(parser)
+///     .validate("- When an ally is hit\n  - Burns it for 3 turns")
+///     .unwrap();
+/// assert_eq!(
+///     values[0].get_path("children/0/target_ref"),
+///     Some(&GodotValue::String("target".into()))
+/// );
+/// assert_eq!(values[0].get_path("children/0/target"), None);
+/// ```
+pub const REFERENCE_SUFFIX: &str = "_ref";
+
+pub(crate) static PARAMETER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\s*([^}:\s]+)\s*(?::[^}]*)?\}").unwrap());
+
+/// `{key}` of a format string
+pub(crate) static FORMAT_KEY: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{([^}]+)\}").unwrap());
+
+#[derive(Debug, Clone, PartialEq)]
 pub struct ParameterDefinition {
     pub name: String,
     pub param_type: String,
+    /// The phrase also matches without it
+    pub optional: bool,
+    /// Range of the values, `int(0..999)`
+    pub bounds: Option<Bounds>,
+    /// Length and pattern of the values, `string(max=32)`
+    pub text: Option<TextConstraints>,
 }
 
 #[derive(Debug, Clone)]
@@ -62,17 +168,294 @@ pub struct PhraseConfig {
     pub parameters: Vec<ParameterDefinition>,
     pub return_spec: ReturnSpec,
     pub section: String,
+    /// Shorthands replaced before matching this phrase
+    pub synonyms: Synonyms,
+    /// `regex` ignoring case, compiled the first time a pipeline folds case
+    folded_regex: OnceLock<Regex>,
+    /// The pattern's literal text and parameters, to write statements back canonically
+    spelling: Vec<Spelling>,
+    /// The dokedef file the phrase was written in, when loaded from files
+    pub source: Option<PathBuf>,
+    /// The line of `source` the phrase is written on, from 1
+    pub line: Option<usize>,
+    /// Description templates of the phrase's section, see `Describer`
+    pub descriptions: Descriptions,
+    /// Parameters translations vary with, see `icu_messages`
+    pub plural: Vec<String>,
+    /// Pronouns of the statements under this phrase's, lowercase, and the parameter
+    /// of this phrase they refer to
+    pub antecedents: Vec<(String, String)>,
+    /// The type this phrase's section specializes, see `hierarchy`
+    pub is_a: Option<String>,
+    /// Capabilities of this phrase's section, see `hierarchy`
+    pub capabilities: Vec<String>,
+    /// How `make_tr_key` makes the phrase's key
+    pub tr_keys: TrKeyFormat,
+    /// Denominations of `price` parameters, the grammar's `currency`
+    pub currency: Currency,
+    /// Actions `input_action` parameters may name, any when `None`
+    pub input_actions: Option<Vec<String>>,
+    /// Stats `stat` parameters may name, any when `None`
+    pub stats: Option<Vec<String>>,
+}
+
+#[derive(Debug, Clone)]
+enum Spelling {
+    Literal(String),
+    /// Index in `parameters`
+    Parameter(usize),
 }
 
 impl PhraseConfig {
+    fn new(
+        pattern: String,
+        return_spec: ReturnSpec,
+        section: &str,
+        param_re: &Regex,
+    ) -> std::result::Result<Self, Box<dyn std::error::Error>> {
+        // grammars and documents may not compose accents the same way
+        let pattern: String = pattern.nfc().collect();
+        let (regex, parameters, spelling) = build_regex_for_phrase(&pattern, param_re)?;
+        Ok(Self {
+            pattern,
+            regex,
+            parameters,
+            return_spec,
+            section: section.to_string(),
+            synonyms: Synonyms::default(),
+            folded_regex: OnceLock::new(),
+            spelling,
+            source: None,
+            line: None,
+            descriptions: Descriptions::default(),
+            plural: Vec::new(),
+            antecedents: Vec::new(),
+            is_a: None,
+            capabilities: Vec::new(),
+            tr_keys: TrKeyFormat::default(),
+            currency: Currency::default(),
+            input_actions: None,
+            stats: None,
+        })
+    }
+
+    /// Coerce the text of a basic parameter, prices with the phrase's currency
+    /// and stats checked against its known stats.
+    /// Returns the value and an optional precision warning.
+    pub(crate) fn parse_parameter(
+        &self,
+        value: &str,
+        param_type: &str,
+    ) -> std::result::Result<(GodotValue, Option<String>), String> {
+        match param_type.to_lowercase().as_str() {
+            "price" => self
+                .currency
+                .parse(value)
+                .map(|price| (self.currency.to_godot(price), None)),
+            "stat" => stat::check(value, self.stats.as_deref())
+                .map(|_| (GodotValue::String(value.to_string()), None)),
+            _ => parse_basic_parameter(value, param_type),
+        }
+    }
+
+    /// Write a statement the way this phrase spells it, with single spaces
+    /// and `values` for the parameters. Missing optional parameters are left out.
+    pub(crate) fn spell(&self, values: &HashMap<String, String>) -> String {
+        let mut out = String::new();
+        for part in &self.spelling {
+            match part {
+                Spelling::Literal(text) => out.push_str(text),
+                Spelling::Parameter(i) => match values.get(&self.parameters[*i].name) {
+                    Some(value) => out.push_str(value),
+                    None => out.truncate(out.trim_end().len()),
+                },
+            }
+        }
+        out.trim().to_string()
+    }
+
+    /// The phrase regex, ignoring case if `fold` is set
+    fn regex(&self, fold: bool) -> &Regex {
+        if !fold {
+            return &self.regex;
+        }
+        self.folded_regex.get_or_init(|| {
+            RegexBuilder::new(self.regex.as_str())
+                .case_insensitive(true)
+                .build()
+                .expect("the phrase regex already compiled")
+        })
+    }
+
     // A traduction key, Deterministic in the phrase pattern.
-    // Currently uses the section name the rule was in and a hash of the rule string
-    fn make_tr_key(&self) -> String {
-        let hash: String = u64_to_base32(hash_value(&self.pattern))
-            .chars()
-            .take(7)
+    // Uses the section name the rule was in and a hash of the rule string, see `TrKeyFormat`
+    pub(crate) fn make_tr_key(&self) -> String {
+        self.tr_keys.key(&self.section, &self.pattern)
+    }
+
+    /// The pattern with `{name}` placeholders, see `DISPLAY_TEMPLATE_KEY`
+    pub(crate) fn tr_template(&self) -> String {
+        PARAMETER.replace_all(&self.pattern, "{$1}").into_owned()
+    }
+}
+
+/// Words replaced by their canonical form before matching ("hp" => "health").
+///
+/// The author's text is left untouched: only the copy matched against phrases
+/// is rewritten. Aliases are matched as whole words, ignoring case.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// synonyms: {damage: [dmg, dmgs]}
+/// Damage:
+///   phrases:
+///     - "Deals {damage: int} damage to enemies"
+///   synonyms: {foes: enemies}
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+/// let values = pipe.validate("Deals 5 DMG to foes").unwrap();
+/// assert_eq!(values[0].get_path("damage"), Some(&GodotValue::Int(5)));
+///
+/// let doc = pipe.run_markdown("Deals 5 DMG to foes");
+/// assert_eq!(doc.nodes[0].statement, "Deals 5 DMG to foes");
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Synonyms {
+    canonical: HashMap<String, String>,
+    regex: Option<Regex>,
+}
+
+impl Synonyms {
+    pub fn new(aliases: HashMap<String, String>) -> Result<Self> {
+        let canonical: HashMap<String, String> = aliases
+            .into_iter()
+            .map(|(alias, canonical)| (alias.to_lowercase(), canonical))
             .collect();
-        format!("{}_{}", camel_to_const_case(&self.section), hash)
+        if canonical.is_empty() {
+            return Ok(Self::default());
+        }
+
+        // Longest aliases first, so "max hp" wins over "hp"
+        let mut alternatives: Vec<&String> = canonical.keys().collect();
+        alternatives.sort_by_key(|alias| std::cmp::Reverse(alias.len()));
+        let pattern = format!(
+            r"(?i)\b(?:{})\b",
+            alternatives
+                .iter()
+                .map(|alias| regex::escape(alias))
+                .collect::<Vec<_>>()
+                .join("|")
+        );
+        let regex = Regex::new(&pattern)
+            .map_err(|e| SentenceParseError::RegexError(pattern.clone(), e.to_string()))?;
+
+        Ok(Self {
+            canonical,
+            regex: Some(regex),
+        })
+    }
+
+    /// Accepts `{alias: canonical}` pairs, or `{canonical: [aliases]}` lists.
+    fn from_yaml(yaml: &Yaml) -> Result<Self> {
+        let Yaml::Hash(map) = yaml else {
+            return Err(SentenceParseError::InvalidPattern(
+                "synonyms must be a mapping".into(),
+            ));
+        };
+        let mut aliases = HashMap::new();
+        for (k, v) in map {
+            let key = k.as_str().ok_or(SentenceParseError::InvalidPattern(
+                "synonym keys must be strings".into(),
+            ))?;
+            match v {
+                Yaml::String(canonical) => {
+                    aliases.insert(key.to_string(), canonical.clone());
+                }
+                Yaml::Array(list) => {
+                    for alias in list.iter().filter_map(|a| a.as_str()) {
+                        aliases.insert(alias.to_string(), key.to_string());
+                    }
+                }
+                _ => {
+                    return Err(SentenceParseError::InvalidPattern(format!(
+                        "synonym '{}' must map to a word or a list of words",
+                        key
+                    )));
+                }
+            }
+        }
+        Self::new(aliases)
+    }
+
+    /// These synonyms, overridden by `other`'s
+    fn merged(&self, other: &Synonyms) -> Result<Self> {
+        let mut aliases = self.canonical.clone();
+        aliases.extend(other.canonical.clone());
+        Self::new(aliases)
+    }
+
+    pub fn apply<'a>(&self, text: &'a str) -> std::borrow::Cow<'a, str> {
+        self.apply_mapped(text).0
+    }
+
+    /// Like `apply`, with where the words were replaced
+    fn apply_mapped<'a>(&self, text: &'a str) -> (std::borrow::Cow<'a, str>, Rewrites) {
+        let mut rewrites = Rewrites::default();
+        let Some(regex) = &self.regex else {
+            return (std::borrow::Cow::Borrowed(text), rewrites);
+        };
+        let mut out = String::new();
+        let mut last = 0;
+        for found in regex.find_iter(text) {
+            // `(?i)` folds more characters than `to_lowercase` (the long s, Kelvin sign)
+            let Some(canonical) = self.canonical.get(&found.as_str().to_lowercase()) else {
+                continue;
+            };
+            out.push_str(&text[last..found.start()]);
+            let start = out.len();
+            out.push_str(canonical);
+            rewrites.0.push((start..out.len(), found.range()));
+            last = found.end();
+        }
+        if rewrites.0.is_empty() {
+            return (std::borrow::Cow::Borrowed(text), rewrites);
+        }
+        out.push_str(&text[last..]);
+        (std::borrow::Cow::Owned(out), rewrites)
+    }
+}
+
+/// Words `Synonyms` replaced, (range in the rewritten text, range in the original)
+#[derive(Debug, Default)]
+struct Rewrites(Vec<(Range<usize>, Range<usize>)>);
+
+impl Rewrites {
+    /// `range` of the rewritten text in the original, a replaced word standing for
+    /// the alias it replaced
+    fn original(&self, range: &Range<usize>) -> Range<usize> {
+        let (mut start, mut end) = (None, None);
+        let mut shift = 0isize;
+        for (new, old) in &self.0 {
+            if start.is_none() && range.start < new.end {
+                start = Some(if range.start < new.start {
+                    range.start.saturating_add_signed(-shift)
+                } else {
+                    old.start
+                });
+            }
+            if end.is_none() && range.end <= new.end {
+                end = Some(if range.end <= new.start {
+                    range.end.saturating_add_signed(-shift)
+                } else {
+                    old.end
+                });
+            }
+            shift += new.len() as isize - old.len() as isize;
+        }
+        let start = start.unwrap_or_else(|| range.start.saturating_add_signed(-shift));
+        let end = end.unwrap_or_else(|| range.end.saturating_add_signed(-shift));
+        start..end.max(start)
     }
 }
 
@@ -88,21 +471,107 @@ pub struct SentenceParser {
 
 impl SentenceParser {
     pub fn get_en_translation(&self) -> HashMap<String, String> {
-        let mut trads = HashMap::new();
-        let re = Regex::new(r"\{([^}:]+)(?:\s*:\s*[^}]*)?\}").unwrap();
-
-        for phrase in &self.phrases {
-            let cleaned_pattern = re.replace_all(&phrase.pattern, "{$1}");
-            trads.insert(phrase.make_tr_key(), cleaned_pattern.to_string());
-        }
-        trads
+        self.phrases
+            .iter()
+            .map(|phrase| (phrase.make_tr_key(), phrase.tr_template()))
+            .collect()
     }
 
+    #[cfg(feature = "translate")]
     pub fn make_or_update_po_file(&self, path: PathBuf, project_id_version: String) -> Result<()> {
         update_po_file(&path, self.get_en_translation(), project_id_version)?;
         Ok(())
     }
 
+    /// Describes the resources of this grammar with its sections' `description` templates
+    pub fn describer(&self) -> Describer {
+        Describer::new(&self.phrases)
+    }
+
+    /// Warn about `input_action` parameters naming other actions than these,
+    /// see `godot_input_actions`
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Tip".into(), r#"
+    /// Press:
+    ///   - "Press {action: input_action} to jump"
+    /// "#).unwrap().with_input_actions(["jump", "attack"]);
+    /// let pipe = DokePipe::new().add(parser);
+    ///
+    /// let doc = pipe.run_markdown("Press jmup to jump");
+    /// assert_eq!(doc.nodes[0].diagnostics[0].code, "unknown-input-action");
+    ///
+    /// let doc = pipe.run_markdown("---\ninput_actions: [jmup]\n---\nPress jmup to jump");
+    /// assert!(doc.nodes[0].diagnostics.is_empty());
+    /// ```
+    pub fn with_input_actions<I, S>(mut self, actions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let actions: Vec<String> = actions.into_iter().map(|s| s.into()).collect();
+        for phrase in &mut self.phrases {
+            phrase.input_actions = Some(actions.clone());
+        }
+        self
+    }
+
+    /// Reject `stat` parameters naming other stats than these
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Buff:
+    ///   - "Raises {stat: stat} by {amount: int}"
+    /// "#).unwrap().with_stats(["STR", "AGI", "crit_chance"]);
+    /// let pipe = DokePipe::new().add(parser);
+    ///
+    /// assert!(pipe.validate("Raises AGI by 2").is_ok());
+    /// let doc = pipe.run_markdown("Raises agi by 2");
+    /// assert!(doc.nodes[0].diagnostics[0].message.contains("did you mean 'AGI'?"));
+    /// ```
+    pub fn with_stats<I, S>(mut self, stats: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let stats: Vec<String> = stats.into_iter().map(|s| s.into()).collect();
+        for phrase in &mut self.phrases {
+            phrase.stats = Some(stats.clone());
+        }
+        self
+    }
+
+    /// The types sections declare they are with `is_a`, and their `capabilities`
+    pub fn hierarchy(&self) -> TypeHierarchy {
+        let mut hierarchy = TypeHierarchy::from_pairs(self.phrases.iter().filter_map(|phrase| {
+            phrase
+                .is_a
+                .as_ref()
+                .map(|parent| (phrase.section.clone(), parent.clone()))
+        }));
+        for phrase in &self.phrases {
+            for capability in &phrase.capabilities {
+                hierarchy.add_capability(phrase.section.clone(), capability.clone());
+            }
+        }
+        hierarchy
+    }
+
+    /// A grammar from a dokedef document. Sections are lists of phrases, or mappings
+    /// from words to the values they stand for
+    /// ```
+    /// use doke::{DokePipe, GodotValue, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Loot:
+    ///   - "Drops a {rarity: Rarity} item"
+    /// Rarity: {common: 0, rare: 1, epic: 2}
+    /// "#).unwrap();
+    /// let values = DokePipe::new().add(parser).validate("Drops a rare item").unwrap();
+    /// assert_eq!(values[0].get_path("rarity"), Some(&GodotValue::Int(1)));
+    /// ```
     pub fn from_yaml(
         abstract_type: String,
         config: &str,
@@ -111,57 +580,118 @@ impl SentenceParser {
         let mut phrases = Vec::new();
         let type_patterns = HashMap::new();
         let param_re = Regex::new(r"\{([^}:]+)(?::([^}]+))?\}")?;
+        let synonyms_key = Yaml::String("synonyms".into());
+        let phrases_key = Yaml::String("phrases".into());
+        let description_key = Yaml::String("description".into());
+        let plural_key = Yaml::String("plural".into());
+        let antecedents_key = Yaml::String("antecedents".into());
+        let is_a_key = Yaml::String("is_a".into());
+        let capabilities_key = Yaml::String("capabilities".into());
+        let currency_key = Yaml::String("currency".into());
 
         // Process ALL documents
         for doc in docs {
             if let Yaml::Hash(top_hash) = doc {
+                // `synonyms:` applies to every section of the document
+                let global_synonyms = match top_hash.get(&synonyms_key) {
+                    Some(y) => Synonyms::from_yaml(y)?,
+                    None => Synonyms::default(),
+                };
+                // so does `currency:`, for `price` parameters
+                let currency = match top_hash.get(&currency_key) {
+                    Some(y) => Currency::from_yaml(y)?,
+                    None => Currency::default(),
+                };
+
                 for (k, v) in top_hash {
                     let section_name = match k {
-                        Yaml::String(s) => s.clone(),
+                        Yaml::String(s) if s != "synonyms" && s != "currency" => s.clone(),
                         _ => continue,
                     };
-
-                    if let Some(items) = v.as_vec() {
-                        for item in items {
-                            match item {
-                                Yaml::String(phrase_str) => {
-                                    let (regex, params) =
-                                        build_regex_for_phrase(phrase_str, &param_re)?;
-                                    phrases.push(PhraseConfig {
-                                        pattern: phrase_str.clone(),
-                                        regex,
-                                        parameters: params,
-                                        return_spec: ReturnSpec::Type(section_name.clone()),
-                                        section: section_name.clone(),
-                                    });
-                                }
-                                Yaml::Hash(map) => {
-                                    for (mk, mv) in map {
-                                        let phrase_text = mk
-                                            .as_str()
-                                            .ok_or("Phrase key must be string")?
-                                            .to_string();
-                                        let return_spec =
-                                            parse_rhs_to_return_spec(mv, &section_name)?;
-                                        let (regex, params) =
-                                            build_regex_for_phrase(&phrase_text, &param_re)?;
-                                        phrases.push(PhraseConfig {
-                                            pattern: phrase_text,
-                                            regex,
-                                            parameters: params,
-                                            return_spec,
-                                            section: section_name.clone(),
-                                        });
-                                    }
-                                }
-                                _ => {}
+                    let mut section_phrases = Vec::new();
+                    let mut synonyms = global_synonyms.clone();
+                    let mut descriptions = Descriptions::default();
+                    let mut plural = Vec::new();
+                    let mut antecedents = Vec::new();
+                    let mut is_a = None;
+                    let mut capabilities = Vec::new();
+
+                    match &v {
+                        Yaml::Array(items) => parse_phrase_list(
+                            items,
+                            &section_name,
+                            &param_re,
+                            &mut section_phrases,
+                        )?,
+                        // Section with options: `Damage: {phrases: [...], synonyms: {...}}`
+                        Yaml::Hash(options) if options.contains_key(&phrases_key) => {
+                            if let Some(items) = options[&phrases_key].as_vec() {
+                                parse_phrase_list(
+                                    items,
+                                    &section_name,
+                                    &param_re,
+                                    &mut section_phrases,
+                                )?;
+                            }
+                            if let Some(y) = options.get(&synonyms_key) {
+                                synonyms = synonyms.merged(&Synonyms::from_yaml(y)?)?;
+                            }
+                            if let Some(y) = options.get(&description_key) {
+                                descriptions = Descriptions::from_yaml(y)?;
+                            }
+                            if let Some(y) = options.get(&plural_key) {
+                                plural = plural_parameters(y)?;
+                            }
+                            if let Some(y) = options.get(&antecedents_key) {
+                                antecedents = antecedent_parameters(y, &section_phrases)?;
+                            }
+                            if let Some(y) = options.get(&is_a_key) {
+                                is_a = Some(y.as_str().map(str::to_string).ok_or_else(|| {
+                                    SentenceParseError::InvalidPattern(format!(
+                                        "is_a of {} must be a type name",
+                                        section_name
+                                    ))
+                                })?);
+                            }
+                            if let Some(y) = options.get(&capabilities_key) {
+                                capabilities = capability_names(y, &section_name)?;
                             }
                         }
+                        // Value mapping: `Rarity: {common: 0, rare: 1}` matches the words
+                        // and emits the mapped values
+                        Yaml::Hash(mapping) => {
+                            for (mk, mv) in mapping {
+                                let phrase_text = match mk {
+                                    Yaml::String(s) => s.clone(),
+                                    Yaml::Integer(i) => i.to_string(),
+                                    _ => return Err("Mapped phrase key must be a string".into()),
+                                };
+                                section_phrases.push(PhraseConfig::new(
+                                    phrase_text,
+                                    ReturnSpec::Literal(yaml_to_godot_value(mv)),
+                                    &section_name,
+                                    &param_re,
+                                )?);
+                            }
+                        }
+                        _ => {}
+                    }
+
+                    for phrase in &mut section_phrases {
+                        phrase.synonyms = synonyms.clone();
+                        phrase.descriptions = descriptions.clone();
+                        phrase.plural = plural.clone();
+                        phrase.antecedents = antecedents.clone();
+                        phrase.is_a = is_a.clone();
+                        phrase.capabilities = capabilities.clone();
+                        phrase.currency = currency.clone();
                     }
+                    phrases.extend(section_phrases);
                 }
             }
         }
 
+        check_tr_keys(&phrases)?;
         Ok(Self {
             phrases,
             type_patterns,
@@ -170,6 +700,101 @@ impl SentenceParser {
         })
     }
 }
+
+/// `plural: [amount, target]`, see `icu_messages`
+fn plural_parameters(yaml: &Yaml) -> Result<Vec<String>> {
+    yaml.as_vec()
+        .and_then(|names| {
+            names
+                .iter()
+                .map(|name| name.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| {
+            SentenceParseError::InvalidPattern("plural must list parameter names".into())
+        })
+}
+
+/// `capabilities: [Stackable, Triggerable]`
+fn capability_names(yaml: &Yaml, section_name: &str) -> Result<Vec<String>> {
+    yaml.as_vec()
+        .and_then(|names| {
+            names
+                .iter()
+                .map(|name| name.as_str().map(str::to_string))
+                .collect()
+        })
+        .ok_or_else(|| {
+            SentenceParseError::InvalidPattern(format!(
+                "capabilities of {} must list capability names",
+                section_name
+            ))
+        })
+}
+
+/// `antecedents: {it: target, the target: target}`, pronouns and the parameter of
+/// the section's phrases they refer to
+fn antecedent_parameters(yaml: &Yaml, phrases: &[PhraseConfig]) -> Result<Vec<(String, String)>> {
+    let invalid = || {
+        SentenceParseError::InvalidPattern(
+            "antecedents must map pronouns to parameter names".into(),
+        )
+    };
+    let mut antecedents = Vec::new();
+    for (pronoun, parameter) in yaml.as_hash().ok_or_else(invalid)? {
+        let (Some(pronoun), Some(parameter)) = (pronoun.as_str(), parameter.as_str()) else {
+            return Err(invalid());
+        };
+        let known = phrases
+            .iter()
+            .any(|phrase| phrase.parameters.iter().any(|p| p.name == parameter));
+        if !known {
+            return Err(SentenceParseError::InvalidPattern(format!(
+                "antecedent '{}' refers to '{}', which no phrase of the section has",
+                pronoun, parameter
+            )));
+        }
+        antecedents.push((pronoun.trim().to_lowercase(), parameter.to_string()));
+    }
+    Ok(antecedents)
+}
+
+/// Parse a section's list of phrases: plain strings returning the section type,
+/// or `phrase: return spec` pairs.
+fn parse_phrase_list(
+    items: &[Yaml],
+    section_name: &str,
+    param_re: &Regex,
+    phrases: &mut Vec<PhraseConfig>,
+) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    for item in items {
+        match item {
+            Yaml::String(phrase_str) => {
+                phrases.push(PhraseConfig::new(
+                    phrase_str.clone(),
+                    ReturnSpec::Type(section_name.to_string()),
+                    section_name,
+                    param_re,
+                )?);
+            }
+            Yaml::Hash(map) => {
+                for (mk, mv) in map {
+                    let phrase_text = mk.as_str().ok_or("Phrase key must be string")?.to_string();
+                    let return_spec = parse_rhs_to_return_spec(mv, section_name)?;
+                    phrases.push(PhraseConfig::new(
+                        phrase_text,
+                        return_spec,
+                        section_name,
+                        param_re,
+                    )?);
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
 // ----------------- Processing -----------------
 
 impl SentenceParser {
@@ -180,47 +805,91 @@ impl SentenceParser {
         depth: usize,
     ) {
         if depth > 100 {
-            node.state = DokeNodeState::Error(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                "Max recursion",
-            )));
+            node.state = DokeNodeState::Error(Box::new(std::io::Error::other("Max recursion")));
             return;
         }
 
         if !matches!(node.state, DokeNodeState::Unresolved) {
             return;
         }
-        // trim whitespace and trailing .
-        let statement = node.statement.trim().trim_end_matches(|c| ".:".contains(c));
+        // trim whitespace and the trailing characters the pipeline ignores
+        let statement = normalization::matching_text(node);
+        let fold = normalization::folds_case(node);
+        let deadline = limits::deadline(node);
         let phrases_to_check: Vec<&PhraseConfig> = self.phrases.iter().collect();
-        let mut matches: Vec<(&PhraseConfig, HashMap<String, String>)> = Vec::new();
+        let mut matches: Vec<(&PhraseConfig, RawParams, HashMap<String, Position>)> = Vec::new();
 
         for phrase in phrases_to_check {
-            if let Ok(raw) = match_phrase_exact(statement, phrase) {
-                matches.push((phrase, raw));
+            if deadline.is_some_and(|deadline| Instant::now() > deadline) {
+                limits::time_out(node);
+                return;
+            }
+            let (text, rewrites) = phrase.synonyms.apply_mapped(statement);
+            if let Ok(ranges) = match_phrase_exact(&text, phrase, fold) {
+                let raw = ranges
+                    .iter()
+                    .map(|(name, range)| (name.clone(), text[range.clone()].to_string()))
+                    .collect();
+                // where the parameters are in the statement, before synonyms moved them
+                let written = ranges
+                    .into_iter()
+                    .map(|(name, range)| (name, rewrites.original(&range)))
+                    .collect();
+                let spans = parameter_spans(node, statement, &written);
+                matches.push((phrase, raw, spans));
             }
         }
 
         if matches.is_empty() {
             node.state = DokeNodeState::Hypothesis(vec![Box::new(ErrorHypo {
                 error: crate::parsers::sentence::SentenceParseError::NoMatch(statement.to_string()),
-                statement: statement.to_string(),
+                statement: node.statement.slice_of(statement),
             })]);
             return;
         }
 
-        matches.sort_by_key(|(p, _)| phrase_specificity(p));
-        let (best_phrase, raw_params) = matches.pop().unwrap();
-        let (parsed_params, constituent_nodes) = self.parse_parameters(
-            &best_phrase.parameters,
-            &raw_params,
-            frontmatter,
-            &node.span,
+        matches.sort_by_key(|(p, _, _)| phrase_specificity(p));
+        let (best_phrase, raw_params, spans) = matches.pop().unwrap();
+        let (parsed_params, literals) =
+            self.parse_parameters(best_phrase, &raw_params, &spans, frontmatter, node, depth);
+        for (pronoun, parameter) in &best_phrase.antecedents {
+            if raw_params.contains_key(parameter) {
+                for child in &mut node.children {
+                    child.parse_data.insert(
+                        format!("{}{}", ANTECEDENT_PREFIX, pronoun),
+                        GodotValue::String(parameter.clone()),
+                    );
+                }
+            }
+        }
+
+        // Parameters that matched a phrase themselves are spelled canonically too
+        let mut spelled = raw_params.clone();
+        for (name, value) in spelled.iter_mut() {
+            if let Some(GodotValue::String(canonical)) = node
+                .constituents
+                .get(name)
+                .and_then(|c| c.parse_data.get(CANONICAL_KEY))
+            {
+                *value = canonical.clone();
+            }
+        }
+        node.parse_data.insert(
+            CANONICAL_KEY.into(),
+            GodotValue::String(best_phrase.spell(&spelled)),
         );
 
-        // attach constituents
-        node.constituents.extend(constituent_nodes);
         let tr_key: String = best_phrase.make_tr_key();
+        if !matches!(best_phrase.return_spec, ReturnSpec::Literal(_)) {
+            node.parse_data.insert(
+                DISPLAY_TEMPLATE_KEY.into(),
+                GodotValue::String(best_phrase.tr_template()),
+            );
+        }
+        let display_text = match node.parse_data.get(DISPLAY_TEXT_KEY) {
+            Some(GodotValue::String(text)) => Some(text.clone()),
+            _ => None,
+        };
         let result = match &best_phrase.return_spec {
             ReturnSpec::Type(t) => SentenceResult::new_type(
                 t.clone(),
@@ -228,12 +897,17 @@ impl SentenceParser {
                 tr_key,
                 Some(self.abstract_type.clone()),
                 self.children_map.clone(),
-            ),
+            )
+            .with_literals(literals)
+            .with_display_text(display_text)
+            .with_display_template(best_phrase.tr_template()),
             ReturnSpec::Literal(lv) => {
                 SentenceResult::new_literal(lv.clone(), parsed_params, tr_key)
             }
             ReturnSpec::Format(fmt) => {
-                let final_str = perform_format_string(fmt, &parsed_params, frontmatter);
+                let locale = locale::of(node, frontmatter);
+                let final_str =
+                    perform_format_string(fmt, &parsed_params, frontmatter, locale.as_ref());
                 SentenceResult::new_literal(GodotValue::String(final_str), parsed_params, tr_key)
             }
         };
@@ -241,39 +915,169 @@ impl SentenceParser {
         node.state = DokeNodeState::Resolved(Box::new(result));
     }
 
+    /// Coerce the raw captures of basic types, and parse the other ones as constituents of `node`.
+    /// Returns the parsed values, and the literal text of numbers that wouldn't print back verbatim.
+    /// Parameters missing from `spans` are located at the whole statement.
+    /// ```
+    /// use doke::DokePipe;
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// let grammar = "Effect:\n  - \"Deals {damage: int} damage to {target: Target}\"\nTarget: {allies: 1, enemies: 2}\n";
+    /// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap());
+    /// let text = "Deals 99999999999999999999 damage to enemies\n";
+    /// let node = &pipe.run_markdown(text).nodes[0];
+    ///
+    /// let overflow = node.diagnostics[0].span.clone().unwrap();
+    /// assert_eq!(&text[overflow.start..overflow.end], "99999999999999999999");
+    /// let target = &node.constituents["target"].span;
+    /// assert_eq!(&text[target.start..target.end], "enemies");
+    ///
+    /// // spans go back through synonyms to the words the author wrote
+    /// let grammar = "synonyms: {foes: enemies}\nEffect:\n  - \"Strikes {target: Target} for {damage: int} damage\"\nTarget: {allies: 1, enemies: 2}\n";
+    /// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap());
+    /// let text = "Strikes foes for 99999999999999999999 damage\n";
+    /// let node = &pipe.run_markdown(text).nodes[0];
+    ///
+    /// let overflow = node.diagnostics[0].span.clone().unwrap();
+    /// assert_eq!(&text[overflow.start..overflow.end], "99999999999999999999");
+    /// let target = &node.constituents["target"];
+    /// assert_eq!(&text[target.span.start..target.span.end], "foes");
+    /// assert_eq!(target.statement, "enemies");
+    /// ```
     fn parse_parameters(
         &self,
-        param_defs: &[ParameterDefinition],
+        phrase: &PhraseConfig,
         raw_params: &HashMap<String, String>,
+        spans: &HashMap<String, Position>,
         frontmatter: &HashMap<String, GodotValue>,
-        span: &Position,
-    ) -> (HashMap<String, GodotValue>, HashMap<String, DokeNode>) {
+        node: &mut DokeNode,
+        depth: usize,
+    ) -> (HashMap<String, GodotValue>, HashMap<String, String>) {
         let mut parsed_params = HashMap::new();
-        let mut constituent_nodes = HashMap::new();
-
-        for param_def in param_defs {
-            match raw_params.get(&param_def.name) {
-                Some(raw_val) => {
-                    if is_basic_type(&param_def.param_type) {
-                        if let Ok(v) = parse_basic_parameter(raw_val, &param_def.param_type) {
+        let mut literals = HashMap::new();
+
+        for param_def in &phrase.parameters {
+            if let Some(raw_val) = raw_params.get(&param_def.name) {
+                let span = spans.get(&param_def.name).unwrap_or(&node.span).clone();
+                // the text at `span`, without the synonyms' replacements
+                let written = spans.get(&param_def.name).and_then(|_| {
+                    node.statement
+                        .get(span.start - node.span.start..span.end - node.span.start)
+                });
+                node.parse_data.insert(
+                    format!("{}{}", PARAM_RAW_PREFIX, param_def.name),
+                    raw_param(written.unwrap_or(raw_val), &span),
+                );
+                // a pronoun the parent statement binds, "it" for its target
+                let antecedent = format!("{}{}", ANTECEDENT_PREFIX, raw_val.trim().to_lowercase());
+                if let Some(GodotValue::String(parameter)) = node.parse_data.get(&antecedent) {
+                    parsed_params.insert(
+                        format!("{}{}", param_def.name, REFERENCE_SUFFIX),
+                        GodotValue::String(parameter.clone()),
+                    );
+                    continue;
+                }
+                if is_basic_type(&param_def.param_type) {
+                    match phrase.parse_parameter(raw_val, &param_def.param_type) {
+                        Ok((v, warning)) => {
+                            if let Some(warning) = warning {
+                                node.diagnostics.push(
+                                    Diagnostic::warning(
+                                        "numeric-precision",
+                                        format!("{}: {}", param_def.name, warning),
+                                    )
+                                    .with_span(span.clone()),
+                                );
+                            }
+                            let unknown_action =
+                                match param_def.param_type.eq_ignore_ascii_case("input_action") {
+                                    true => input_action::check(
+                                        raw_val,
+                                        phrase.input_actions.as_deref(),
+                                        frontmatter,
+                                    ),
+                                    false => None,
+                                };
+                            if let Some(warning) = unknown_action {
+                                node.diagnostics.push(
+                                    Diagnostic::warning(
+                                        "unknown-input-action",
+                                        format!("{}: {}", param_def.name, warning),
+                                    )
+                                    .with_span(span.clone()),
+                                );
+                            }
+                            if let Some(bounds) =
+                                param_def.bounds.filter(|bounds| bounds.excludes(&v))
+                            {
+                                node.diagnostics.push(
+                                    Diagnostic::error(
+                                        OUT_OF_RANGE_CODE,
+                                        format!(
+                                            "{}: {} is outside {}",
+                                            param_def.name,
+                                            raw_val.trim(),
+                                            bounds
+                                        ),
+                                    )
+                                    .with_span(span),
+                                );
+                                continue;
+                            }
+                            let text_problem = match (&param_def.text, &v) {
+                                (Some(text), GodotValue::String(s)) => text.check(s).err(),
+                                _ => None,
+                            };
+                            if let Some(problem) = text_problem {
+                                node.diagnostics.push(
+                                    Diagnostic::error(
+                                        STRING_CONSTRAINT_CODE,
+                                        format!("{}: {}", param_def.name, problem),
+                                    )
+                                    .with_span(span),
+                                );
+                                continue;
+                            }
+                            if numeric::literal_differs(raw_val, &v) {
+                                literals.insert(param_def.name.clone(), raw_val.clone());
+                            }
                             parsed_params.insert(param_def.name.clone(), v);
                         }
-                    } else {
-                        let mut child =
-                            create_constituent_node(raw_val, &param_def.param_type, span);
-                        child.parse_data.insert(
-                            "sentence_type".to_string(),
-                            GodotValue::String(param_def.param_type.clone()),
-                        );
-                        self.process_with_depth(&mut child, frontmatter, 0);
-                        constituent_nodes.insert(param_def.name.clone(), child);
+                        Err(e) => node.diagnostics.push(
+                            Diagnostic::error(
+                                "invalid-parameter",
+                                format!("{}: {}", param_def.name, e),
+                            )
+                            .with_span(span),
+                        ),
+                    }
+                } else {
+                    // shares the statement's text when it's what was matched
+                    let text = match written.filter(|written| written == raw_val) {
+                        Some(written) => node.statement.slice_of(written),
+                        None => raw_val.as_str().into(),
+                    };
+                    let mut child = create_constituent_node(text, &param_def.param_type, &span);
+                    child.parse_data.insert(
+                        "sentence_type".to_string(),
+                        GodotValue::String(param_def.param_type.clone()),
+                    );
+                    for key in [
+                        normalization::TRAILING_KEY,
+                        normalization::CASE_FOLD_KEY,
+                        limits::TIMEOUT_KEY,
+                    ] {
+                        if let Some(value) = node.parse_data.get(key) {
+                            child.parse_data.insert(key.into(), value.clone());
+                        }
                     }
+                    self.process_with_depth(&mut child, frontmatter, depth + 1);
+                    node.constituents.insert(param_def.name.clone(), child);
                 }
-                None => {}
             }
         }
 
-        (parsed_params, constituent_nodes)
+        (parsed_params, literals)
     }
 }
 
@@ -282,19 +1086,24 @@ impl DokeParser for SentenceParser {
     fn process(&self, node: &mut DokeNode, frontmatter: &HashMap<String, GodotValue>) {
         self.process_with_depth(node, frontmatter, 0);
     }
+
+    fn provides(&self) -> &[&'static str] {
+        &[CANONICAL_KEY, DISPLAY_TEMPLATE_KEY]
+    }
 }
 
 // ----------------- Helpers -----------------
 
-fn yaml_to_godot_value(y: &Yaml) -> GodotValue {
+pub(crate) fn yaml_to_godot_value(y: &Yaml) -> GodotValue {
     match y {
         Yaml::String(s) => GodotValue::String(s.clone()),
         Yaml::Integer(i) => GodotValue::Int(*i),
         Yaml::Real(r) => {
             // yaml_rust2 stores reals as strings like "3.14"
-            r.parse::<f64>()
-                .map(GodotValue::Float)
-                .unwrap_or(GodotValue::Float(0.0))
+            // keep unparsable ones as text rather than silently turning them into 0.0
+            numeric::parse_yaml_real(r)
+                .map(|parsed| parsed.value)
+                .unwrap_or_else(|_| GodotValue::String(r.clone()))
         }
         Yaml::Boolean(b) => GodotValue::Bool(*b),
         Yaml::Array(arr) => GodotValue::Array(arr.iter().map(yaml_to_godot_value).collect()),
@@ -311,81 +1120,50 @@ fn yaml_to_godot_value(y: &Yaml) -> GodotValue {
     }
 }
 
-fn is_basic_type(param_type: &str) -> bool {
-    matches!(
-        param_type.to_lowercase().as_str(),
-        "int" | "float" | "bool" | "string"
-    )
-}
-
-fn parse_basic_parameter(value: &str, param_type: &str) -> std::result::Result<GodotValue, String> {
-    match param_type.to_lowercase().as_str() {
-        "int" => {
-            // support hex/octal/binary prefixes
-            if value.starts_with("0b") || value.starts_with("0B") {
-                i64::from_str_radix(&value[2..], 2)
-                    .map(GodotValue::Int)
-                    .map_err(|e| e.to_string())
-            } else if value.starts_with("0o") || value.starts_with("0O") {
-                i64::from_str_radix(&value[2..], 8)
-                    .map(GodotValue::Int)
-                    .map_err(|e| e.to_string())
-            } else if value.starts_with("0x") || value.starts_with("0X") {
-                i64::from_str_radix(&value[2..], 16)
-                    .map(GodotValue::Int)
-                    .map_err(|e| e.to_string())
-            } else {
-                value
-                    .parse::<i64>()
-                    .map(GodotValue::Int)
-                    .map_err(|e| e.to_string())
-            }
-        }
-        "float" => value
-            .parse::<f64>()
-            .map(GodotValue::Float)
-            .map_err(|e| e.to_string()),
-        "bool" => match value.to_lowercase().as_str() {
-            "true" | "yes" | "1" => Ok(GodotValue::Bool(true)),
-            "false" | "no" | "0" => Ok(GodotValue::Bool(false)),
-            _ => Err(format!("Invalid boolean value: {}", value)),
-        },
-        "string" => Ok(GodotValue::String(value.to_string())),
-        _ => Err(format!("Unknown basic type: {}", param_type)),
-    }
+/// `{text, start, end}`, see `PARAM_RAW_PREFIX`
+fn raw_param(text: &str, span: &Position) -> GodotValue {
+    GodotValue::Dict(HashMap::from([
+        ("text".to_string(), GodotValue::String(text.to_string())),
+        ("start".to_string(), GodotValue::Int(span.start as i64)),
+        ("end".to_string(), GodotValue::Int(span.end as i64)),
+    ]))
 }
 
-fn create_constituent_node(value: &str, _param_type: &str, span: &Position) -> DokeNode {
+fn create_constituent_node(value: Statement, _param_type: &str, span: &Position) -> DokeNode {
     DokeNode {
-        statement: value.to_string(),
+        statement: value,
         state: DokeNodeState::Unresolved,
         children: Vec::new(),
         parse_data: HashMap::new(),
+        extensions: Extensions::new(),
         constituents: HashMap::new(),
         span: span.clone(),
+        diagnostics: Vec::new(),
+        block: BlockKind::Other,
     }
 }
 
-fn perform_format_string(
+pub(crate) fn perform_format_string(
     fmt: &str,
     params: &HashMap<String, GodotValue>,
     front: &HashMap<String, GodotValue>,
+    locale: Option<&Locale>,
 ) -> String {
     // replace occurrences of {name} with:
     //  1) params[name] if present
     //  2) front[name] if present
     //  3) keep {name} as-is otherwise
-    let re = Regex::new(r"\{([^}]+)\}").unwrap();
     let mut out = String::new();
     let mut last = 0;
-    for cap in re.captures_iter(fmt) {
+    for cap in FORMAT_KEY.captures_iter(fmt) {
         let m = cap.get(0).unwrap();
         let key = cap.get(1).unwrap().as_str();
         out.push_str(&fmt[last..m.start()]);
-        if let Some(v) = params.get(key) {
-            out.push_str(&godot_value_to_string(v));
-        } else if let Some(v) = front.get(key) {
-            out.push_str(&godot_value_to_string(v));
+        if let Some(v) = params.get(key).or_else(|| front.get(key)) {
+            match locale {
+                Some(locale) => out.push_str(&localized_value_string(v, locale)),
+                None => out.push_str(&godot_value_to_string(v)),
+            }
         } else {
             // keep placeholder as-is
             out.push_str(m.as_str());
@@ -396,15 +1174,33 @@ fn perform_format_string(
     out
 }
 
+/// Numbers with the locale's separators, arrays as a list ("A, B and C")
+fn localized_value_string(v: &GodotValue, locale: &Locale) -> String {
+    match v {
+        GodotValue::Int(i) => locale.format_integer(i),
+        GodotValue::UInt(u) => locale.format_integer(u),
+        GodotValue::Float(f) => locale.format_number(*f),
+        GodotValue::Array(a) => {
+            let parts: Vec<String> = a
+                .iter()
+                .map(|item| localized_value_string(item, locale))
+                .collect();
+            locale.join(&parts)
+        }
+        other => godot_value_to_string(other),
+    }
+}
+
 fn godot_value_to_string(v: &GodotValue) -> String {
     match v {
         GodotValue::Nil => "".to_string(),
         GodotValue::Bool(b) => b.to_string(),
         GodotValue::Int(i) => i.to_string(),
+        GodotValue::UInt(u) => u.to_string(),
         GodotValue::Float(f) => f.to_string(),
         GodotValue::String(s) => s.clone(),
         GodotValue::Array(a) => {
-            let parts: Vec<String> = a.iter().map(|gv| godot_value_to_string(gv)).collect();
+            let parts: Vec<String> = a.iter().map(godot_value_to_string).collect();
             format!("[{}]", parts.join(", "))
         }
         GodotValue::Dict(m) => {
@@ -425,16 +1221,26 @@ fn godot_value_to_string(v: &GodotValue) -> String {
             }
             format!("Resource({})", parts.join(","))
         }
+        // as written in documents
+        GodotValue::Vector2(x, y) => format!("({}, {})", x, y),
+        GodotValue::Rect2(x, y, w, h) => {
+            format!("({}, {}) to ({}, {})", x, y, x + w, y + h)
+        }
+        GodotValue::Color(r, g, b, a) => to_hex(*r, *g, *b, *a),
     }
 }
 
 // Build a regex for a phrase pattern, turning literal whitespace into \s+,
 // and capturing parameter groups according to their types.
+/// A phrase's regex, its parameters in capture order, and its spelling
+type CompiledPhrase = (Regex, Vec<ParameterDefinition>, Vec<Spelling>);
+
 fn build_regex_for_phrase(
     phrase: &str,
     param_re: &Regex,
-) -> std::result::Result<(Regex, Vec<ParameterDefinition>), Box<dyn std::error::Error>> {
+) -> std::result::Result<CompiledPhrase, Box<dyn std::error::Error>> {
     let mut parameters: Vec<ParameterDefinition> = Vec::new();
+    let mut spelling = Vec::new();
     let mut regex_pattern = String::new();
     regex_pattern.push('^');
 
@@ -446,25 +1252,25 @@ fn build_regex_for_phrase(
         if m.start() > last_end {
             let text = &phrase[last_end..m.start()];
             push_literal(&mut regex_pattern, text);
+            spelling.push(Spelling::Literal(collapse_whitespace(text)));
         }
 
         let mut name = cap.get(1).unwrap().as_str().trim().to_string();
-        let param_type = cap
-            .get(2)
-            .map(|m| m.as_str().trim().to_string())
-            .unwrap_or_else(|| "string".to_string());
+        let DeclaredType {
+            param_type,
+            bounds,
+            text,
+        } = match cap.get(2) {
+            Some(m) => bounds::split_type(m.as_str().trim())?,
+            None => bounds::split_type("string")?,
+        };
 
         let optional = name.ends_with(":?");
         if optional {
             name = name[..name.len() - 2].to_string(); // remove :?
         }
         // add capture group by type
-        let capture_group = match param_type.to_lowercase().as_str() {
-            "int" => r"([-+]?(?:0[bB][01]+|0[oO][0-7]+|0[xX][0-9a-fA-F]+|\d+))".to_string(),
-            "float" => r"([-+]?(?:\d+\.\d*|\.\d+|\d+)(?:[eE][-+]?\d+)?)".to_string(),
-            "bool" => r"(true|false|yes|no|1|0)".to_string(),
-            _ => r"(.+?)".to_string(), // non-greedy default
-        };
+        let capture_group = capture_pattern(&param_type);
 
         let group_regex = if optional {
             // whitespace + capture_group is optional
@@ -475,7 +1281,14 @@ fn build_regex_for_phrase(
 
         regex_pattern.push_str(&group_regex);
 
-        parameters.push(ParameterDefinition { name, param_type });
+        spelling.push(Spelling::Parameter(parameters.len()));
+        parameters.push(ParameterDefinition {
+            name,
+            param_type,
+            optional,
+            bounds,
+            text,
+        });
 
         last_end = m.end();
     }
@@ -484,12 +1297,31 @@ fn build_regex_for_phrase(
     if last_end < phrase.len() {
         let text = &phrase[last_end..];
         push_literal(&mut regex_pattern, text);
+        spelling.push(Spelling::Literal(collapse_whitespace(text)));
     }
 
     regex_pattern.push('$');
 
     let regex = Regex::new(&regex_pattern).map_err(|e| format!("{}", e))?;
-    Ok((regex, parameters))
+    Ok((regex, parameters, spelling))
+}
+
+// replace contiguous whitespace by a single space
+fn collapse_whitespace(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for (i, word) in s.split_whitespace().enumerate() {
+        if i > 0 {
+            out.push(' ');
+        }
+        out.push_str(word);
+    }
+    if s.starts_with(char::is_whitespace) {
+        out.insert(0, ' ');
+    }
+    if s.ends_with(char::is_whitespace) && !out.ends_with(' ') {
+        out.push(' ');
+    }
+    out
 }
 
 // Split trailing whitespace from a literal chunk.
@@ -527,31 +1359,67 @@ fn push_literal(buf: &mut String, s: &str) {
     }
 }
 
-// match a phrase exactly using its compiled regex and return raw param strings
+/// Parameter name → text as written
+type RawParams = HashMap<String, String>;
+
+// match a phrase exactly using its compiled regex and return where each param is, trimmed
 fn match_phrase_exact(
     statement: &str,
     phrase: &PhraseConfig,
-) -> std::result::Result<HashMap<String, String>, SentenceParseError> {
+    fold: bool,
+) -> std::result::Result<HashMap<String, Range<usize>>, SentenceParseError> {
     let caps = phrase
-        .regex
+        .regex(fold)
         .captures(statement)
         .ok_or(SentenceParseError::NoMatch(phrase.pattern.clone()))?;
-    let mut out: HashMap<String, String> = HashMap::new();
+    let mut out: HashMap<String, Range<usize>> = HashMap::new();
     for (i, param_def) in phrase.parameters.iter().enumerate() {
         if let Some(m) = caps.get(i + 1) {
-            out.insert(param_def.name.clone(), m.as_str().trim().to_string());
+            let text = m.as_str();
+            let start = m.start() + text.len() - text.trim_start().len();
+            out.insert(param_def.name.clone(), start..start + text.trim().len());
         }
     }
     Ok(out)
 }
 
+// spans in the source of params at `ranges` of `statement`, the text of `node` being matched
+// (before synonyms).
+// Empty once the statement was rewritten after being read (normalization, directives),
+// its offsets no longer being the source's
+fn parameter_spans(
+    node: &DokeNode,
+    statement: &str,
+    ranges: &HashMap<String, Range<usize>>,
+) -> HashMap<String, Position> {
+    if node.statement.len() != node.span.end.saturating_sub(node.span.start) {
+        return HashMap::new();
+    }
+    let offset = node.span.start + (statement.as_ptr() as usize - node.statement.as_ptr() as usize);
+    ranges
+        .iter()
+        .map(|(name, range)| {
+            let span = Position {
+                start: offset + range.start,
+                end: offset + range.end,
+            };
+            (name.clone(), span)
+        })
+        .collect()
+}
+
 // compute specificity: more literal chars and fewer params => higher specificity
-fn phrase_specificity(p: &PhraseConfig) -> (usize, usize) {
+pub(crate) fn phrase_specificity(p: &PhraseConfig) -> (usize, usize) {
     let mut literal = p.pattern.len();
     let mut params = 0usize;
     for pd in &p.parameters {
         params += 1;
-        literal = literal.saturating_sub(pd.name.len() + pd.param_type.len() + 4);
+        let bounds = pd.bounds.map_or(0, |bounds| bounds.to_string().len() + 2);
+        let text = pd
+            .text
+            .as_ref()
+            .map_or(0, |text| text.to_string().len() + 2);
+        literal = literal.saturating_sub(pd.name.len() + pd.param_type.len() + bounds + text + 4);
     }
     (literal, usize::MAX - params)
 }
@@ -581,8 +1449,8 @@ fn parse_rhs_to_return_spec(
             }
             // plain scalar might be int/bool/float (literal), or a type name
             // try parse int
-            if let Ok(i) = s_trim.parse::<i64>() {
-                return Ok(ReturnSpec::Literal(GodotValue::Int(i)));
+            if let Ok(i) = numeric::parse_int(s_trim) {
+                return Ok(ReturnSpec::Literal(i));
             }
             if let Ok(f) = s_trim.parse::<f64>() {
                 return Ok(ReturnSpec::Literal(GodotValue::Float(f)));
@@ -599,10 +1467,9 @@ fn parse_rhs_to_return_spec(
         }
         // if RHS is numeric/bool in YAML itself, parse directly
         Yaml::Integer(i) => Ok(ReturnSpec::Literal(GodotValue::Int(*i))),
-        Yaml::Real(r) => {
-            let f = r.parse::<f64>().unwrap_or(0.0);
-            Ok(ReturnSpec::Literal(GodotValue::Float(f)))
-        }
+        Yaml::Real(r) => numeric::parse_yaml_real(r)
+            .map(|parsed| ReturnSpec::Literal(parsed.value))
+            .map_err(SentenceParseError::InvalidPattern),
         Yaml::Boolean(b) => Ok(ReturnSpec::Literal(GodotValue::Bool(*b))),
         other => Err(SentenceParseError::InvalidPattern(format!(
             "Unsupported RHS: {:?}",
@@ -622,6 +1489,12 @@ struct SentenceResult {
     abstract_type: Option<String>,
     /// stores which children goes where
     children_map: HashMap<String, String>,
+    /// author's text for numbers that don't print back verbatim ("0x1F", "1.50")
+    literals: HashMap<String, String>,
+    /// BBCode rendering of the statement, see `DokePipe::with_rich_text`
+    display_text: Option<String>,
+    /// see `DISPLAY_TEMPLATE_KEY`
+    display_template: Option<String>,
 }
 
 impl SentenceResult {
@@ -639,8 +1512,26 @@ impl SentenceResult {
             tr_key,
             abstract_type,
             children_map,
+            literals: HashMap::new(),
+            display_text: None,
+            display_template: None,
         }
     }
+
+    fn with_literals(mut self, literals: HashMap<String, String>) -> Self {
+        self.literals = literals;
+        self
+    }
+
+    fn with_display_text(mut self, display_text: Option<String>) -> Self {
+        self.display_text = display_text;
+        self
+    }
+
+    fn with_display_template(mut self, display_template: String) -> Self {
+        self.display_template = Some(display_template);
+        self
+    }
     fn new_literal(val: GodotValue, params: HashMap<String, GodotValue>, tr_key: String) -> Self {
         Self {
             output_type: "".to_string(),
@@ -649,6 +1540,9 @@ impl SentenceResult {
             tr_key,
             abstract_type: None,
             children_map: HashMap::new(),
+            literals: HashMap::new(),
+            display_text: None,
+            display_template: None,
         }
     }
 }
@@ -667,6 +1561,30 @@ impl DokeOut for SentenceResult {
                 "doke_tr_key".into(),
                 GodotValue::String(self.tr_key.clone()),
             );
+            if !self.literals.is_empty() {
+                // lets writers re-emit numbers exactly as the author wrote them
+                fields.insert(
+                    LITERALS_KEY.into(),
+                    GodotValue::Dict(
+                        self.literals
+                            .iter()
+                            .map(|(k, v)| (k.clone(), GodotValue::String(v.clone())))
+                            .collect(),
+                    ),
+                );
+            }
+            if let Some(display_template) = &self.display_template {
+                fields.insert(
+                    DISPLAY_TEMPLATE_KEY.into(),
+                    GodotValue::String(display_template.clone()),
+                );
+            }
+            if let Some(display_text) = &self.display_text {
+                fields.insert(
+                    DISPLAY_TEXT_KEY.into(),
+                    GodotValue::String(display_text.clone()),
+                );
+            }
             let abstract_type = self.abstract_type.clone().unwrap_or("Resource".into());
             GodotValue::Resource {
                 type_name: self.output_type.clone(),
@@ -677,21 +1595,20 @@ impl DokeOut for SentenceResult {
     }
 
     fn get_asbtract_type(&self) -> Option<String> {
-        return self.abstract_type.clone();
+        self.abstract_type.clone()
     }
 
     fn use_child(
         &mut self,
         child: GodotValue,
-    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
         match self.parameters.entry("children".into()) {
             std::collections::hash_map::Entry::Occupied(mut e) => {
                 if let GodotValue::Array(a) = e.get_mut() {
                     a.push(child);
                     Ok(())
                 } else {
-                    Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::Other,
+                    Err(Box::new(std::io::Error::other(
                         "children field is not an array",
                     )))
                 }
@@ -707,7 +1624,7 @@ impl DokeOut for SentenceResult {
         &mut self,
         name: &str,
         value: GodotValue,
-    ) -> std::result::Result<(), Box<dyn std::error::Error>> {
+    ) -> std::result::Result<(), Box<dyn std::error::Error + Send + Sync>> {
         self.parameters.insert(name.to_string(), value);
         Ok(())
     }
@@ -718,7 +1635,7 @@ impl DokeOut for SentenceResult {
 #[derive(Debug)]
 struct ErrorHypo {
     error: SentenceParseError,
-    statement: String,
+    statement: Statement,
 }
 
 impl Hypo for ErrorHypo {
@@ -730,7 +1647,7 @@ impl Hypo for ErrorHypo {
     }
     fn promote(
         self: Box<Self>,
-    ) -> std::result::Result<Box<dyn DokeOut>, Box<dyn std::error::Error>> {
+    ) -> std::result::Result<Box<dyn DokeOut>, Box<dyn std::error::Error + Send + Sync>> {
         Err(Box::new(self.error))
     }
 }
@@ -0,0 +1,34 @@
+// parsers/stat.rs
+//
+// `stat` parameters: the name of a character stat ("Raises {stat: stat} by
+// {amount: int}"), kept as a String. The dokeconfig declares the project's stats,
+//
+//     stats: [STR, AGI, crit_chance]
+//
+// and naming another one is an error of the statement, so an "ATK" written
+// for "attack" fails the content build instead of the game at runtime.
+// Without declared stats any name is accepted.
+
+/// Dokeconfig key listing the known stats
+pub const STATS_KEY: &str = "stats";
+
+/// What a `stat` parameter captures: an identifier like `crit_chance`
+pub(crate) const STAT_PATTERN: &str = r"([A-Za-z_]\w*)";
+
+/// Fails when `stat` isn't one of `known`, pointing at a stat written with other case
+pub(crate) fn check(stat: &str, known: Option<&[String]>) -> Result<(), String> {
+    let Some(known) = known else {
+        return Ok(());
+    };
+    if known.iter().any(|name| name == stat) {
+        return Ok(());
+    }
+    match known.iter().find(|name| name.eq_ignore_ascii_case(stat)) {
+        Some(name) => Err(format!("Unknown stat '{}', did you mean '{}'?", stat, name)),
+        None => Err(format!(
+            "Unknown stat '{}', expected one of {}",
+            stat,
+            known.join(", ")
+        )),
+    }
+}
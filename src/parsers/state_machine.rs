@@ -0,0 +1,367 @@
+// state_machine.rs
+//
+// Quests and AI states written as headings and transitions:
+//
+//     ## State: Patrol
+//     On player spotted -> Chase
+//
+//     ## State: Chase
+//     On player lost -> Patrol
+//     On player caught -> Attack
+//
+// All the states of a document are grouped into one `StateMachine` resource.
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostics::Diagnostic;
+use crate::semantic::BlockKind;
+use crate::{DokeNode, DokeNodeState, DokeOut, DokeParser, Extensions, GodotValue};
+
+/// Compiles "State: Name" headers and the "On event -> Target" statements
+/// under them into a state machine.
+///
+/// Every statement up to the next state header belongs to that state, a heading
+/// at the same level or above ends the last one.
+/// Statements that aren't transitions are left to other parsers, and end up
+/// in the state's `actions`. The first state is the initial one.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::StateMachineParser};
+///
+/// let pipe = DokePipe::new().add(StateMachineParser::new());
+/// let values = pipe.validate("\
+/// ## State: Patrol
+/// On player spotted -> Chase
+///
+/// ## State: Chase
+/// On player lost -> Patrol").unwrap();
+/// let string = |s: &str| Some(GodotValue::String(s.into()));
+/// assert_eq!(values[0].get_path("initial").cloned(), string("Patrol"));
+/// assert_eq!(values[0].get_path("transitions/0/from").cloned(), string("Patrol"));
+/// assert_eq!(values[0].get_path("transitions/0/to").cloned(), string("Chase"));
+///
+/// let doc = pipe.run_markdown("## State: Patrol\nOn player spotted -> Chsae");
+/// let transition = &doc.nodes[0].children[0].children[0];
+/// assert_eq!(transition.diagnostics[0].code, "state-missing-target");
+///
+/// let doc = pipe.run_markdown("\
+/// ## State: Patrol
+/// ### Notes
+/// Keep it slow
+///
+/// ## Rewards
+/// 50 gold");
+/// assert_eq!(doc.nodes.len(), 3);
+/// assert_eq!(doc.nodes[0].children[0].children.len(), 2);
+/// assert!(doc.nodes[1].statement.ends_with("Rewards"));
+/// assert_eq!(doc.nodes[2].statement, "50 gold");
+/// ```
+#[derive(Debug)]
+pub struct StateMachineParser {
+    header_regex: Regex,
+    transition_regex: Regex,
+}
+
+impl Default for StateMachineParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StateMachineParser {
+    pub fn new() -> Self {
+        Self::with_keywords(["State"])
+    }
+
+    /// Use other header keywords than "State", e.g. "Step" for quests.
+    pub fn with_keywords<I, S>(keywords: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        let keywords: Vec<String> = keywords
+            .into_iter()
+            .map(|k| regex::escape(&k.into()))
+            .collect();
+        Self {
+            header_regex: Regex::new(&format!(
+                r"(?i)^\s*(?:#+\s*)?(?:{})\s*:\s*(.+?)\s*$",
+                keywords.join("|")
+            ))
+            .unwrap(),
+            transition_regex: Regex::new(r"(?i)^\s*(?:on\s+(.+?)\s*)?->\s*(.+?)\s*\.?\s*$")
+                .unwrap(),
+        }
+    }
+
+    fn state_name(&self, node: &DokeNode) -> Option<String> {
+        if !matches!(node.state, DokeNodeState::Unresolved) {
+            return None;
+        }
+        let caps = self.header_regex.captures(&node.statement)?;
+        Some(caps[1].to_string())
+    }
+
+    /// Resolve the transitions among a state's statements
+    fn resolve_transitions(&self, node: &mut DokeNode, states: &HashSet<String>) {
+        for child in &mut node.children {
+            if !matches!(child.state, DokeNodeState::Unresolved) {
+                continue;
+            }
+            let Some(caps) = self.transition_regex.captures(&child.statement) else {
+                continue;
+            };
+            let target = caps[2].to_string();
+            if !states.contains(&target) {
+                child.diagnostics.push(
+                    Diagnostic::error(
+                        "state-missing-target",
+                        format!("'{}' is not a state of this document", target),
+                    )
+                    .with_span(child.span.clone()),
+                );
+            }
+            child.state = DokeNodeState::Resolved(Box::new(Transition {
+                event: caps.get(1).map(|m| m.as_str().to_string()),
+                target,
+            }));
+        }
+    }
+}
+
+impl DokeParser for StateMachineParser {
+    /// States need the whole document, a single node is left as is.
+    fn process(&self, _node: &mut DokeNode, _frontmatter: &HashMap<String, GodotValue>) {}
+
+    fn process_document(
+        &self,
+        nodes: &mut Vec<DokeNode>,
+        frontmatter: &HashMap<String, GodotValue>,
+    ) {
+        let Some(first) = nodes
+            .iter()
+            .position(|node| self.state_name(node).is_some())
+        else {
+            return;
+        };
+
+        // Every statement up to the next header belongs to the current state,
+        // a heading that isn't deeper than the state's ends it
+        let mut state_nodes: Vec<DokeNode> = Vec::new();
+        let mut rest: Vec<DokeNode> = Vec::new();
+        let mut in_state = false;
+        for node in nodes.drain(first..) {
+            if self.state_name(&node).is_some() {
+                in_state = true;
+                state_nodes.push(node);
+                continue;
+            }
+            if let Some(depth) = heading_depth(&node)
+                && state_nodes
+                    .last()
+                    .and_then(heading_depth)
+                    .is_none_or(|state| depth <= state)
+            {
+                in_state = false;
+            }
+            match state_nodes.last_mut() {
+                Some(state) if in_state => state.children.push(node),
+                _ => rest.push(node),
+            }
+        }
+
+        let names: Vec<String> = state_nodes
+            .iter()
+            .filter_map(|node| self.state_name(node))
+            .collect();
+        let states: HashSet<String> = names.iter().cloned().collect();
+
+        let mut seen = HashSet::new();
+        for (node, name) in state_nodes.iter_mut().zip(&names) {
+            if !seen.insert(name) {
+                node.diagnostics.push(
+                    Diagnostic::error(
+                        "state-duplicate",
+                        format!("state '{}' is defined more than once", name),
+                    )
+                    .with_span(node.span.clone()),
+                );
+            }
+            self.resolve_transitions(node, &states);
+            node.state = DokeNodeState::Resolved(Box::new(State {
+                name: name.clone(),
+                transitions: Vec::new(),
+                actions: Vec::new(),
+            }));
+        }
+
+        let name = ["state_machine", "quest", "title"].iter().find_map(|key| {
+            match frontmatter.get(*key) {
+                Some(GodotValue::String(s)) => Some(s.clone()),
+                _ => None,
+            }
+        });
+        let span = state_nodes
+            .iter()
+            .map(|node| node.span.clone())
+            .chain(
+                state_nodes
+                    .iter()
+                    .flat_map(|node| node.children.iter().map(|c| c.span.clone())),
+            )
+            .reduce(|a, b| a.merge(&b))
+            .expect("there is at least one state");
+
+        nodes.push(DokeNode {
+            statement: name.as_deref().unwrap_or("State machine").into(),
+            state: DokeNodeState::Resolved(Box::new(StateMachine {
+                name,
+                initial: names[0].clone(),
+                states: Vec::new(),
+            })),
+            children: state_nodes,
+            parse_data: HashMap::from([(
+                "abstract_type".into(),
+                GodotValue::String("StateMachine".into()),
+            )]),
+            extensions: Extensions::new(),
+            constituents: HashMap::new(),
+            span,
+            diagnostics: Vec::new(),
+            block: BlockKind::Other,
+        });
+        nodes.extend(rest);
+    }
+}
+
+fn heading_depth(node: &DokeNode) -> Option<u8> {
+    match node.block {
+        BlockKind::Heading { depth } => Some(depth),
+        _ => None,
+    }
+}
+
+// ----------------- Output -----------------
+
+#[derive(Debug)]
+struct StateMachine {
+    name: Option<String>,
+    initial: String,
+    states: Vec<GodotValue>,
+}
+
+impl DokeOut for StateMachine {
+    fn kind(&self) -> &'static str {
+        "StateMachine"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        let mut transitions = Vec::new();
+        for state in &self.states {
+            let from = state.get_path("name").cloned().unwrap_or(GodotValue::Nil);
+            if let Some(GodotValue::Array(state_transitions)) = state.get_path("transitions") {
+                for transition in state_transitions {
+                    let mut transition = transition.clone();
+                    let _ = transition.set_path("from", from.clone());
+                    transitions.push(transition);
+                }
+            }
+        }
+
+        let mut fields = HashMap::from([
+            ("initial".into(), GodotValue::String(self.initial.clone())),
+            ("states".into(), GodotValue::Array(self.states.clone())),
+            ("transitions".into(), GodotValue::Array(transitions)),
+        ]);
+        if let Some(name) = &self.name {
+            fields.insert("name".into(), GodotValue::String(name.clone()));
+        }
+        GodotValue::Resource {
+            type_name: "StateMachine".into(),
+            abstract_type_name: "StateMachine".into(),
+            fields,
+        }
+    }
+
+    fn get_asbtract_type(&self) -> Option<String> {
+        Some("StateMachine".into())
+    }
+
+    fn use_child(
+        &mut self,
+        child: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.states.push(child);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct State {
+    name: String,
+    transitions: Vec<GodotValue>,
+    actions: Vec<GodotValue>,
+}
+
+impl DokeOut for State {
+    fn kind(&self) -> &'static str {
+        "State"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Resource {
+            type_name: "State".into(),
+            abstract_type_name: "State".into(),
+            fields: HashMap::from([
+                ("name".into(), GodotValue::String(self.name.clone())),
+                (
+                    "transitions".into(),
+                    GodotValue::Array(self.transitions.clone()),
+                ),
+                ("actions".into(), GodotValue::Array(self.actions.clone())),
+            ]),
+        }
+    }
+
+    fn use_child(
+        &mut self,
+        child: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        match &child {
+            GodotValue::Resource { type_name, .. } if type_name == "Transition" => {
+                self.transitions.push(child)
+            }
+            _ => self.actions.push(child),
+        }
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct Transition {
+    event: Option<String>,
+    target: String,
+}
+
+impl DokeOut for Transition {
+    fn kind(&self) -> &'static str {
+        "Transition"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Resource {
+            type_name: "Transition".into(),
+            abstract_type_name: "Transition".into(),
+            fields: HashMap::from([
+                (
+                    "event".into(),
+                    self.event
+                        .clone()
+                        .map(GodotValue::String)
+                        .unwrap_or(GodotValue::Nil),
+                ),
+                ("to".into(), GodotValue::String(self.target.clone())),
+            ]),
+        }
+    }
+}
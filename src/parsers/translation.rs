@@ -0,0 +1,311 @@
+// parsers/translation.rs
+//
+// PO catalogs of a grammar, see `SentenceParser::make_or_update_po_file_with`:
+// an entry per phrase keyed by its translation key, with the English text and
+// `{param}` placeholders. `PoOptions` give translators the context they need:
+// the section (and dokedef file) as `msgctxt`, and an example sentence.
+//
+//     #. en: Deals {damage} damage to {target}
+//     #. Deals 1 damage to enemies
+//     msgctxt "DamageEffect (weapons.dokedef.yaml)"
+//     msgid "DAMAGE_EFFECT_PUAEXM5"
+//     msgstr "Deals {damage} damage to {target}"
+//
+// Existing translations are kept across runs, entries whose English text changed
+// are marked fuzzy (see `update_po_entries`).
+//
+// Godot finds entries with a context through `tr(key, context)` only.
+//
+// Writing catalogs needs the `translate` feature, the entries themselves don't.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use super::param_types::{example_value, is_basic_type};
+use super::sentence::{ParameterDefinition, PhraseConfig, SentenceParseError, SentenceParser};
+use crate::utility::{PoEntry, camel_to_const_case, str_hash, u64_to_base32};
+#[cfg(feature = "translate")]
+use crate::utility::{update_po_entries, write_pot_file};
+
+/// Nested phrases deeper than this are left as `{param}` in examples
+const EXAMPLE_DEPTH: usize = 4;
+
+/// How translation keys are made: `{section}` is the phrase's section in CONST_CASE,
+/// `{hash}` the start of the base32 `str_hash` of its pattern
+/// ```
+/// use doke::parsers::{SentenceParser, TrKeyFormat};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// "#).unwrap();
+/// assert_eq!(parser.translation_entries()[0].key, "DAMAGE_OFAQS2N");
+///
+/// let format = TrKeyFormat::default().with_hash_len(10).with_template("fx.{section}.{hash}");
+/// let parser = parser.with_tr_key_format(format).unwrap();
+/// assert_eq!(parser.translation_entries()[0].key, "fx.DAMAGE.OFAQS2NCDV");
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TrKeyFormat {
+    hash_len: usize,
+    template: String,
+}
+
+impl Default for TrKeyFormat {
+    fn default() -> Self {
+        Self {
+            hash_len: 7,
+            template: "{section}_{hash}".into(),
+        }
+    }
+}
+
+impl TrKeyFormat {
+    /// Characters of the hash kept, 13 at most (the whole hash)
+    pub fn with_hash_len(mut self, len: usize) -> Self {
+        self.hash_len = len.clamp(1, 13);
+        self
+    }
+
+    pub fn with_template(mut self, template: &str) -> Self {
+        self.template = template.to_string();
+        self
+    }
+
+    pub(crate) fn key(&self, section: &str, pattern: &str) -> String {
+        let hash: String = u64_to_base32(str_hash(pattern))
+            .chars()
+            .take(self.hash_len)
+            .collect();
+        self.template
+            .replace("{section}", &camel_to_const_case(section))
+            .replace("{hash}", &hash)
+    }
+}
+
+/// Fails when two different phrases get the same translation key
+pub(crate) fn check_tr_keys<'a>(
+    phrases: impl IntoIterator<Item = &'a PhraseConfig>,
+) -> Result<(), SentenceParseError> {
+    let mut patterns: HashMap<String, &str> = HashMap::new();
+    for phrase in phrases {
+        let key = phrase.make_tr_key();
+        match patterns.get(&key) {
+            Some(pattern) if *pattern != phrase.pattern => {
+                return Err(SentenceParseError::TrKeyCollision(
+                    key,
+                    pattern.to_string(),
+                    phrase.pattern.clone(),
+                ));
+            }
+            _ => {
+                patterns.insert(key, &phrase.pattern);
+            }
+        }
+    }
+    Ok(())
+}
+
+/// What `make_or_update_po_file_with` writes besides the English text
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct PoOptions {
+    context: bool,
+    source_file: bool,
+    examples: bool,
+}
+
+impl PoOptions {
+    /// Write the phrase's section as `msgctxt`
+    pub fn with_context(mut self, enabled: bool) -> Self {
+        self.context = enabled;
+        self
+    }
+
+    /// Add the dokedef file the phrase comes from to the context
+    pub fn with_source_file(mut self, enabled: bool) -> Self {
+        self.source_file = enabled;
+        self
+    }
+
+    /// Write an example sentence as an extracted comment (`#.`)
+    pub fn with_examples(mut self, enabled: bool) -> Self {
+        self.examples = enabled;
+        self
+    }
+}
+
+/// A phrase as localization tooling sees it, see `SentenceParser::translation_entries`
+#[derive(Debug, Clone, PartialEq)]
+pub struct TranslationEntry {
+    /// Translation key, `doke_tr_key` of the results
+    pub key: String,
+    /// The English text with `{param}` placeholders
+    pub template: String,
+    /// Parameters in pattern order, with their types
+    pub parameters: Vec<ParameterDefinition>,
+    pub section: String,
+    /// The dokedef file the phrase was written in, when loaded from files
+    pub source: Option<PathBuf>,
+    /// The line of `source` the phrase is written on, from 1
+    pub line: Option<usize>,
+}
+
+impl SentenceParser {
+    /// Make the translation keys of the grammar with `format`, failing if two phrases
+    /// would share one
+    pub fn with_tr_key_format(mut self, format: TrKeyFormat) -> Result<Self, SentenceParseError> {
+        for phrase in &mut self.phrases {
+            phrase.tr_keys = format.clone();
+        }
+        check_tr_keys(&self.phrases)?;
+        Ok(self)
+    }
+
+    /// The keys `previous` made that this grammar's format changes, old key to new,
+    /// for `utility::rename_po_entries`
+    /// ```
+    /// use doke::parsers::{SentenceParser, TrKeyFormat};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let parser = parser.with_tr_key_format(TrKeyFormat::default().with_hash_len(10)).unwrap();
+    /// let renames = parser.tr_key_migration(&TrKeyFormat::default());
+    /// assert_eq!(renames["DAMAGE_OFAQS2N"], "DAMAGE_OFAQS2NCDV");
+    /// ```
+    pub fn tr_key_migration(&self, previous: &TrKeyFormat) -> HashMap<String, String> {
+        self.phrases
+            .iter()
+            .map(|phrase| {
+                let old = previous.key(&phrase.section, &phrase.pattern);
+                (old, phrase.make_tr_key())
+            })
+            .filter(|(old, new)| old != new)
+            .collect()
+    }
+
+    /// Every phrase with its translation key, to build localization tooling on
+    /// ```
+    /// use doke::parsers::SentenceParser;
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let entry = &parser.translation_entries()[0];
+    /// assert_eq!(entry.template, "Deals {damage} damage");
+    /// assert_eq!(entry.parameters[0].param_type, "int");
+    /// assert_eq!(entry.section, "Damage");
+    /// ```
+    pub fn translation_entries(&self) -> Vec<TranslationEntry> {
+        self.phrases
+            .iter()
+            .map(|phrase| TranslationEntry {
+                key: phrase.make_tr_key(),
+                template: phrase.tr_template(),
+                parameters: phrase.parameters.clone(),
+                section: phrase.section.clone(),
+                source: phrase.source.clone(),
+                line: phrase.line,
+            })
+            .collect()
+    }
+
+    /// The catalog entries of every phrase
+    /// ```
+    /// use doke::parsers::{PoOptions, SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage to {target: Target}"
+    /// Target:
+    ///   - "enemies": 0
+    /// "#).unwrap();
+    /// let options = PoOptions::default().with_context(true).with_examples(true);
+    /// let entry = &parser.po_entries(options)[0];
+    /// assert_eq!(entry.msgstr, "Deals {damage} damage to {target}");
+    /// assert_eq!(entry.context.as_deref(), Some("Damage"));
+    /// assert_eq!(entry.comments.as_deref(), Some("Deals 1 damage to enemies"));
+    /// ```
+    pub fn po_entries(&self, options: PoOptions) -> Vec<PoEntry> {
+        self.phrases
+            .iter()
+            .map(|phrase| PoEntry {
+                msgid: phrase.make_tr_key(),
+                msgstr: phrase.tr_template(),
+                context: options
+                    .context
+                    .then(|| context(phrase, options.source_file)),
+                comments: options.examples.then(|| self.example(phrase, 0)),
+            })
+            .collect()
+    }
+
+    /// Like `make_or_update_po_file`, writing what `options` asks for
+    #[cfg(feature = "translate")]
+    pub fn make_or_update_po_file_with(
+        &self,
+        path: PathBuf,
+        project_id_version: String,
+        options: PoOptions,
+    ) -> Result<(), SentenceParseError> {
+        update_po_entries(&path, self.po_entries(options), project_id_version)?;
+        Ok(())
+    }
+
+    /// A PO template of the grammar, with empty translations, see `write_pot_file`
+    #[cfg(feature = "translate")]
+    pub fn make_pot_file(
+        &self,
+        path: PathBuf,
+        project_id_version: String,
+        options: PoOptions,
+    ) -> Result<(), SentenceParseError> {
+        write_pot_file(&path, self.po_entries(options), project_id_version)?;
+        Ok(())
+    }
+
+    /// A sentence of `phrase` with plausible values, nested phrases written
+    /// with the first phrase of their section
+    fn example(&self, phrase: &PhraseConfig, depth: usize) -> String {
+        let values: HashMap<String, String> = phrase
+            .parameters
+            .iter()
+            .map(|param| {
+                let value = match is_basic_type(&param.param_type) {
+                    true if param.param_type.eq_ignore_ascii_case("price") => {
+                        phrase.currency.spell(350)
+                    }
+                    true => example_value(&param.param_type)
+                        .unwrap_or("text")
+                        .to_string(),
+                    false => self
+                        .phrases
+                        .iter()
+                        .find(|p| p.section == param.param_type)
+                        .filter(|_| depth < EXAMPLE_DEPTH)
+                        .map_or_else(
+                            || format!("{{{}}}", param.name),
+                            |nested| self.example(nested, depth + 1),
+                        ),
+                };
+                (param.name.clone(), value)
+            })
+            .collect();
+        phrase.spell(&values)
+    }
+}
+
+/// `Section`, or `Section (file.dokedef.yaml)`
+fn context(phrase: &PhraseConfig, source_file: bool) -> String {
+    let file = phrase
+        .source
+        .as_ref()
+        .filter(|_| source_file)
+        .and_then(|path| path.file_name());
+    match file {
+        Some(file) => format!("{} ({})", phrase.section, file.to_string_lossy()),
+        None => phrase.section.clone(),
+    }
+}
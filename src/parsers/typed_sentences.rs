@@ -3,12 +3,22 @@ use std::collections::HashMap;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+#[cfg(feature = "fs")]
 use glob::glob;
 use hashlink::LinkedHashMap;
+use sha2::{Digest, Sha256};
 use thiserror::Error;
 use yaml_rust2::Yaml;
 
-use crate::parsers::sentence::SentenceParser;
+use crate::describe::Describer;
+use crate::diagnostics::Diagnostic;
+use crate::hierarchy::TypeHierarchy;
+use crate::limits;
+use crate::parsers::input_action::{INPUT_ACTIONS_KEY, godot_input_actions};
+use crate::parsers::sentence::{CANONICAL_KEY, DISPLAY_TEMPLATE_KEY, ReturnSpec, SentenceParser};
+use crate::parsers::stat::STATS_KEY;
+use crate::parsers::translation::{TrKeyFormat, check_tr_keys};
+use crate::semantic::UNORDERED_CHILDREN_KEY;
 use crate::{DokeNode, DokeNodeState, DokeParser, GodotValue};
 
 #[derive(Debug, Error)]
@@ -27,47 +37,341 @@ pub enum TypedSentencesError {
 
     #[error("Glob pattern error: {0}")]
     GlobError(String),
+
+    #[error("Grammar bundle error: {0}")]
+    BundleError(String),
+
+    #[error("Path '{0}' is outside of the allowed grammar roots")]
+    PathEscape(String),
+}
+
+/// Options controlling where grammar files may be loaded from.
+///
+/// Configs can come from untrusted mod content, so every file a `parser:`
+/// reference resolves to is canonicalized and must live under one of the
+/// allowed roots. With no explicit root, the config's own directory is used.
+#[derive(Debug, Clone, Default)]
+pub struct LoadOptions {
+    pub allowed_roots: Vec<PathBuf>,
+}
+
+impl LoadOptions {
+    pub fn allow_root(mut self, root: impl Into<PathBuf>) -> Self {
+        self.allowed_roots.push(root.into());
+        self
+    }
+
+    fn resolved_roots(&self, base_dir: &Path) -> Result<Vec<PathBuf>, TypedSentencesError> {
+        let roots = if self.allowed_roots.is_empty() {
+            vec![base_dir.to_path_buf()]
+        } else {
+            self.allowed_roots.clone()
+        };
+        roots
+            .iter()
+            .map(|root| {
+                fs::canonicalize(root).map_err(|e| {
+                    TypedSentencesError::FileError(format!(
+                        "Invalid grammar root {}: {}",
+                        root.display(),
+                        e
+                    ))
+                })
+            })
+            .collect()
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct ParserReference {
     pub pattern: String,
     pub base_dir: PathBuf,
+    /// When set, `pattern` is matched against the files inside this bundle
+    /// instead of the filesystem.
+    pub bundle: Option<BundleSource>,
+}
+
+/// A packaged grammar (`.zip`, `.tar` or `.tar.gz`) shared between projects.
+///
+/// ```yaml
+/// parser:
+///   bundle: "grammars/core-1.2.0.tar.gz"   # or an https:// registry URL
+///   sha256: "9f86d08..."
+///   pattern: "**/*Effect.dokedef.yaml"
+/// ```
+#[derive(Debug, Clone)]
+pub struct BundleSource {
+    /// Path relative to the config directory, or an `http(s)://` URL.
+    pub location: String,
+    /// Hex encoded sha256 of the archive. Mandatory for remote bundles.
+    pub sha256: Option<String>,
 }
 
+impl BundleSource {
+    pub fn is_remote(&self) -> bool {
+        self.location.starts_with("http://") || self.location.starts_with("https://")
+    }
+}
+
+/// Diagnostic code of statements with too few or too many children of a field
+pub const CHILDREN_COUNT_CODE: &str = "children-count";
+
+/// Diagnostic code of children out of the order of an `ordered` rule's `children`
+pub const CHILDREN_ORDER_CODE: &str = "children-order";
+
 // src/parsers/typed_sentences.rs
 #[derive(Debug, Clone)]
 pub enum ChildSpec {
     Simple(Vec<String>), // Old syntax: children: [ItemEffect, DamageEffect]
-    Structured(HashMap<String, Vec<String>>), // New syntax: children: {damage_effects: [DamageEffect], other_effects: [ItemEffect]}
+    Structured(Vec<(String, ChildField)>), // New syntax: children: {damage_effects: [DamageEffect], other_effects: [ItemEffect]}
+}
+
+/// A field of structured children: `damage_effects: [DamageEffect]`, or with the
+/// number of children it takes, `damage_effects: {types: [DamageEffect], min: 1, max: 3}`
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChildField {
+    pub types: Vec<String>,
+    pub min: usize,
+    pub max: Option<usize>,
 }
 
 impl ChildSpec {
-    fn allowed(&self, child_abstract_type: &str) -> bool {
+    /// Whether `child_abstract_type` is one of the listed types, or a subtype of one
+    fn allowed(&self, child_abstract_type: &str, hierarchy: &TypeHierarchy) -> bool {
+        let listed = |types: &Vec<String>| {
+            types
+                .iter()
+                .any(|ty| hierarchy.is_a(child_abstract_type, ty))
+        };
+        match self {
+            ChildSpec::Simple(items) => listed(items),
+            ChildSpec::Structured(fields) => fields.iter().any(|(_, field)| listed(&field.types)),
+        }
+    }
+
+    /// The names and types of the listed fields, or of the listed types, in order
+    fn groups(&self) -> Vec<(&str, &[String])> {
         match self {
-            ChildSpec::Simple(items) => items.contains(&child_abstract_type.to_string()),
-            ChildSpec::Structured(hash_map) => hash_map
-                .values()
-                .any(|child_types| child_types.contains(&child_abstract_type.to_string())),
+            ChildSpec::Simple(items) => items
+                .iter()
+                .map(|ty| (ty.as_str(), std::slice::from_ref(ty)))
+                .collect(),
+            ChildSpec::Structured(fields) => fields
+                .iter()
+                .map(|(name, field)| (name.as_str(), field.types.as_slice()))
+                .collect(),
+        }
+    }
+
+    /// An error for each child of `node` listed before the children it should follow,
+    /// with the index of the child
+    /// ```
+    /// # #[cfg(feature = "fs")] {
+    /// use std::fs;
+    /// use doke::DokePipe;
+    /// use doke::parsers::TypedSentencesParser;
+    ///
+    /// let dir = std::env::temp_dir().join("doke_children_order");
+    /// fs::create_dir_all(&dir).unwrap();
+    /// fs::write(dir.join("Quest.dokedef.yaml"), "Quest:\n  - \"Quest {name: string}\"\n").unwrap();
+    /// fs::write(dir.join("Step.dokedef.yaml"), "Step:\n  - \"Go to {place: string}\"\n").unwrap();
+    /// fs::write(dir.join("Reward.dokedef.yaml"), "Reward:\n  - \"Gives {gold: int} gold\"\n").unwrap();
+    /// let config = "rules:
+    ///   - for: Quest
+    ///     parser: Quest.dokedef.yaml
+    ///     ordered: true
+    ///     children:
+    ///       steps: {types: [Step], min: 1, max: 2}
+    ///       rewards: [Reward]
+    ///   - for: Step
+    ///     parser: Step.dokedef.yaml
+    ///   - for: Reward
+    ///     parser: Reward.dokedef.yaml
+    /// ";
+    /// let load = |config: &str| DokePipe::new().add(TypedSentencesParser::from_config(config, &dir).unwrap());
+    ///
+    /// let doc = load(config).run_markdown("- Quest the key\n  - Gives 5 gold\n  - Go to the cave\n");
+    /// let step = &doc.nodes[0].children[1];
+    /// assert_eq!(step.diagnostics[0].code, "children-order");
+    /// assert_eq!(step.diagnostics[0].message, "steps go before rewards");
+    ///
+    /// // unordered children are sorted, the text breaking ties between types
+    /// let unordered = load(&config.replace("ordered: true", "ordered: false"));
+    /// let quest = &unordered.validate("- Quest the key\n  - Go to b\n  - Go to a\n").unwrap()[0];
+    /// assert_eq!(quest.get_path("children/0/place"), Some(&doke::GodotValue::String("a".into())));
+    /// # }
+    /// ```
+    fn order_diagnostics(
+        &self,
+        node: &DokeNode,
+        hierarchy: &TypeHierarchy,
+    ) -> Vec<(usize, Diagnostic)> {
+        let groups = self.groups();
+        let mut latest: Option<usize> = None;
+        let mut diagnostics = Vec::new();
+        for (i, child) in node.children.iter().enumerate() {
+            let types = resolved_types(child);
+            let Some(group) = groups.iter().position(|(_, listed)| {
+                types
+                    .iter()
+                    .any(|ty| listed.iter().any(|l| hierarchy.is_a(ty, l)))
+            }) else {
+                continue;
+            };
+            match latest {
+                Some(previous) if group < previous => diagnostics.push((
+                    i,
+                    Diagnostic::error(
+                        CHILDREN_ORDER_CODE,
+                        format!("{} go before {}", groups[group].0, groups[previous].0),
+                    )
+                    .with_span(child.span.clone()),
+                )),
+                _ => latest = Some(group),
+            }
+        }
+        diagnostics
+    }
+
+    /// An error for each field of `node` with too few or too many children
+    /// ```
+    /// # #[cfg(feature = "fs")] {
+    /// use std::fs;
+    /// use doke::DokePipe;
+    /// use doke::parsers::TypedSentencesParser;
+    ///
+    /// let dir = std::env::temp_dir().join("doke_children_count");
+    /// fs::create_dir_all(&dir).unwrap();
+    /// fs::write(dir.join("Quest.dokedef.yaml"), "Quest:\n  - \"Quest {name: string}\"\n").unwrap();
+    /// fs::write(dir.join("Step.dokedef.yaml"), "Step:\n  - \"Go to {place: string}\"\n").unwrap();
+    /// fs::write(dir.join("Reward.dokedef.yaml"), "Reward:\n  - \"Gives {gold: int} gold\"\n").unwrap();
+    /// let config = "rules:
+    ///   - for: Quest
+    ///     parser: Quest.dokedef.yaml
+    ///     children:
+    ///       steps: {types: [Step], min: 1, max: 2}
+    ///       rewards: [Reward]
+    ///   - for: Step
+    ///     parser: Step.dokedef.yaml
+    ///   - for: Reward
+    ///     parser: Reward.dokedef.yaml
+    /// ";
+    /// let pipe = DokePipe::new().add(TypedSentencesParser::from_config(config, &dir).unwrap());
+    /// let problems = |text| {
+    ///     let doc = pipe.run_markdown(text);
+    ///     doc.collect_diagnostics().iter().map(|d| d.message.clone()).collect::<Vec<_>>()
+    /// };
+    ///
+    /// assert!(problems("- Quest the key\n  - Go to the cave\n  - Gives 5 gold\n").is_empty());
+    /// assert_eq!(
+    ///     problems("- Quest the key\n  - Gives 5 gold\n"),
+    ///     ["steps: at least 1 Step expected, found 0"]
+    /// );
+    /// assert_eq!(
+    ///     problems("- Quest the key\n  - Go to a\n  - Go to b\n  - Go to c\n"),
+    ///     ["steps: at most 2 Step expected, found 3"]
+    /// );
+    /// # }
+    /// ```
+    fn count_diagnostics(&self, node: &DokeNode, hierarchy: &TypeHierarchy) -> Vec<Diagnostic> {
+        let ChildSpec::Structured(fields) = self else {
+            return Vec::new();
+        };
+        let child_types: Vec<Vec<String>> = node.children.iter().map(resolved_types).collect();
+        let mut diagnostics = Vec::new();
+        for (name, field) in fields {
+            let count = child_types
+                .iter()
+                .filter(|types| {
+                    types
+                        .iter()
+                        .any(|ty| field.types.iter().any(|listed| hierarchy.is_a(ty, listed)))
+                })
+                .count();
+            let expected = field.types.join(" or ");
+            let problem = match field.max {
+                _ if count < field.min => Some(format!("at least {}", field.min)),
+                Some(max) if count > max => Some(format!("at most {}", max)),
+                _ => None,
+            };
+            if let Some(problem) = problem {
+                diagnostics.push(
+                    Diagnostic::error(
+                        CHILDREN_COUNT_CODE,
+                        format!(
+                            "{}: {} {} expected, found {}",
+                            name, problem, expected, count
+                        ),
+                    )
+                    .with_span(node.span.clone()),
+                );
+            }
         }
+        diagnostics
     }
 }
 
+/// The abstract type a child resolved to and its resource type, none when unresolved
+fn resolved_types(child: &DokeNode) -> Vec<String> {
+    let DokeNodeState::Resolved(value) = &child.state else {
+        return Vec::new();
+    };
+    let mut types = Vec::new();
+    if let Some(GodotValue::String(abstract_type)) = child.parse_data.get("abstract_type") {
+        types.push(abstract_type.clone());
+    }
+    if let GodotValue::Resource { type_name, .. } = value.to_godot() {
+        types.push(type_name);
+    }
+    types
+}
+
 #[derive(Debug, Clone)]
 pub struct TypeRule {
     pub target_type: String,
     pub parser_ref: ParserReference,
     pub priority: i32,
     pub children: ChildSpec, // Changed from allowed_children
+    /// `ordered: true` checks children follow the order of `children`, `ordered: false`
+    /// sorts them by type then text. Kept as written when `None`.
+    pub ordered: Option<bool>,
     pub sentence_parser: SentenceParser,
+    /// Name of the grammar layer this rule was loaded in
+    pub layer: String,
+}
+
+/// Name of the layer rules loaded from a config file start in.
+pub const BASE_LAYER: &str = "base";
+
+/// A named set of rules (base game, DLC, a mod...) layered on top of the
+/// previous ones. Later layers take precedence over earlier ones.
+#[derive(Debug, Clone)]
+pub struct GrammarLayer {
+    pub name: String,
+    pub enabled: bool,
+}
+
+/// Where a phrase of the grammar comes from.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PhraseOrigin {
+    pub layer: String,
+    pub target_type: String,
+    pub section: String,
+    pub pattern: String,
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TypedSentencesParser {
     rules: Vec<TypeRule>,
+    layers: Vec<GrammarLayer>,
+    /// `frontmatter_statements` of the config
+    frontmatter_statements: Vec<(String, Option<String>)>,
+    /// Is-a relationships of the rules' types, see `hierarchy`
+    hierarchy: TypeHierarchy,
 }
 
 impl TypedSentencesParser {
+    #[cfg(feature = "fs")]
     pub fn from_config_file(config_path: &Path) -> Result<Self, TypedSentencesError> {
         let config_content = fs::read_to_string(config_path)
             .map_err(|e| TypedSentencesError::FileError(e.to_string()))?;
@@ -77,7 +381,67 @@ impl TypedSentencesParser {
         Self::from_config(&config_content, &base_dir)
     }
 
+    /// Like `from_config_file`, loading grammar files from the roots of `options` only
+    /// ```
+    /// use std::fs;
+    /// use doke::parsers::{LoadOptions, TypedSentencesParser};
+    ///
+    /// let dir = std::env::temp_dir().join("doke_grammar_roots");
+    /// fs::create_dir_all(dir.join("game")).unwrap();
+    /// fs::create_dir_all(dir.join("elsewhere")).unwrap();
+    /// let grammar = "Damage:\n  - \"Deals {damage: int} damage\"\n";
+    /// fs::write(dir.join("game/Effect.dokedef.yaml"), grammar).unwrap();
+    /// fs::write(dir.join("elsewhere/Stolen.dokedef.yaml"), grammar).unwrap();
+    ///
+    /// let options = LoadOptions::default().allow_root(dir.join("game"));
+    /// let config = dir.join("game/Item.dokeconfig.yaml");
+    /// fs::write(&config, "rules:\n  - for: Effect\n    parser: \"*.dokedef.yaml\"\n").unwrap();
+    /// assert!(TypedSentencesParser::from_config_file_with_options(&config, &options).is_ok());
+    ///
+    /// let escape = "rules:\n  - for: Effect\n    parser: \"../elsewhere/*.dokedef.yaml\"\n";
+    /// fs::write(&config, escape).unwrap();
+    /// let error = TypedSentencesParser::from_config_file_with_options(&config, &options).unwrap_err();
+    /// assert!(error.to_string().contains("outside of the allowed grammar roots"));
+    /// ```
+    #[cfg(feature = "fs")]
+    pub fn from_config_file_with_options(
+        config_path: &Path,
+        options: &LoadOptions,
+    ) -> Result<Self, TypedSentencesError> {
+        let config_content = fs::read_to_string(config_path)
+            .map_err(|e| TypedSentencesError::FileError(e.to_string()))?;
+
+        let base_dir = config_path.parent().unwrap_or(Path::new(".")).to_path_buf();
+
+        Self::from_config_with_options(&config_content, &base_dir, options)
+    }
+
+    /// Read a dokeconfig, its `parser:` patterns relative to `base_dir`. Without the `fs`
+    /// feature, they can't be loaded:
+    /// ```
+    /// use doke::parsers::TypedSentencesParser;
+    ///
+    /// let dir = std::env::temp_dir().join("doke_config_features");
+    /// std::fs::create_dir_all(&dir).unwrap();
+    /// let grammar = "Damage:\n  - \"Deals {damage: int} damage\"\n";
+    /// std::fs::write(dir.join("Effect.dokedef.yaml"), grammar).unwrap();
+    ///
+    /// let config = "rules:\n  - for: Effect\n    parser: \"*.dokedef.yaml\"\n";
+    /// let parser = TypedSentencesParser::from_config(config, &dir);
+    /// #[cfg(feature = "fs")]
+    /// assert!(parser.is_ok());
+    /// #[cfg(not(feature = "fs"))]
+    /// assert!(parser.unwrap_err().to_string().contains("requires the `fs` feature"));
+    /// ```
     pub fn from_config(config: &str, base_dir: &Path) -> Result<Self, TypedSentencesError> {
+        Self::from_config_with_options(config, base_dir, &LoadOptions::default())
+    }
+
+    pub fn from_config_with_options(
+        config: &str,
+        base_dir: &Path,
+        options: &LoadOptions,
+    ) -> Result<Self, TypedSentencesError> {
         let docs = yaml_rust2::YamlLoader::load_from_str(config)
             .map_err(|e| TypedSentencesError::YamlParseError(e.to_string()))?;
 
@@ -86,14 +450,20 @@ impl TypedSentencesParser {
             .ok_or(TypedSentencesError::YamlParseError("Empty YAML".into()))?;
 
         let mut rules = Vec::new();
+        let tr_keys = Self::parse_tr_keys(&doc["tr_keys"])?;
+        let roots = options.resolved_roots(base_dir)?;
+        let input_actions = Self::parse_input_actions(&doc[INPUT_ACTIONS_KEY], base_dir, &roots)?;
+        let stats = Self::parse_stats(&doc[STATS_KEY])?;
+        let frontmatter_statements =
+            Self::parse_frontmatter_statements(&doc["frontmatter_statements"])?;
 
-        if let Yaml::Hash(root) = doc {
-            if let Some(Yaml::Array(rules_array)) = root.get(&Yaml::String("rules".into())) {
-                for rule_config in rules_array {
-                    if let Yaml::Hash(rule_hash) = rule_config {
-                        let rule = Self::parse_rule(rule_hash, base_dir)?;
-                        rules.push(rule);
-                    }
+        if let Yaml::Hash(root) = doc
+            && let Some(Yaml::Array(rules_array)) = root.get(&Yaml::String("rules".into()))
+        {
+            for rule_config in rules_array {
+                if let Yaml::Hash(rule_hash) = rule_config {
+                    let rule = Self::parse_rule(rule_hash, base_dir)?;
+                    rules.push(rule);
                 }
             }
         }
@@ -101,24 +471,337 @@ impl TypedSentencesParser {
         // Load the actual sentence parsers from the referenced files
         let mut loaded_rules = Vec::new();
         for rule in rules {
-            let sentence_parser =
-                Self::load_parser_from_reference(&rule.parser_ref, rule.target_type.clone())?;
+            let mut sentence_parser = Self::load_parser_from_reference(
+                &rule.parser_ref,
+                rule.target_type.clone(),
+                &roots,
+            )?
+            .with_tr_key_format(tr_keys.clone())
+            .map_err(|e| TypedSentencesError::InvalidRule(e.to_string()))?;
+            if let Some(actions) = &input_actions {
+                sentence_parser = sentence_parser.with_input_actions(actions.iter().cloned());
+            }
+            if let Some(stats) = &stats {
+                sentence_parser = sentence_parser.with_stats(stats.iter().cloned());
+            }
 
             loaded_rules.push(TypeRule {
                 sentence_parser,
                 target_type: rule.target_type.clone(),
                 priority: rule.priority,
-                children: ChildSpec::Simple(vec![]),
+                children: rule.children,
+                ordered: rule.ordered,
                 parser_ref: rule.parser_ref,
+                layer: BASE_LAYER.to_string(),
             });
         }
 
-        // Sort by priority (highest first)
-        loaded_rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+        // rules of different types may share section names
+        check_tr_keys(
+            loaded_rules
+                .iter()
+                .flat_map(|rule| &rule.sentence_parser.phrases),
+        )
+        .map_err(|e| TypedSentencesError::InvalidRule(e.to_string()))?;
 
-        Ok(Self {
+        let mut parser = Self {
             rules: loaded_rules,
-        })
+            layers: vec![GrammarLayer {
+                name: BASE_LAYER.to_string(),
+                enabled: true,
+            }],
+            frontmatter_statements,
+            hierarchy: TypeHierarchy::new(),
+        };
+        parser.sort_rules();
+        parser.rebuild_hierarchy();
+        Ok(parser)
+    }
+
+    /// `tr_keys: {length: 10, format: "{section}_{hash}"}`, see `TrKeyFormat`
+    fn parse_tr_keys(yaml: &Yaml) -> Result<TrKeyFormat, TypedSentencesError> {
+        let mut format = TrKeyFormat::default();
+        if yaml.is_badvalue() {
+            return Ok(format);
+        }
+        if let Some(length) = yaml["length"].as_i64() {
+            format = format.with_hash_len(length.max(0) as usize);
+        }
+        if let Some(template) = yaml["format"].as_str() {
+            format = format.with_template(template);
+        }
+        match yaml {
+            Yaml::Hash(_) => Ok(format),
+            _ => Err(TypedSentencesError::InvalidRule(
+                "tr_keys must be a map with 'length' and 'format'".into(),
+            )),
+        }
+    }
+
+    /// `input_actions: [jump, attack]`, or the path of a `project.godot` to read them from
+    fn parse_input_actions(
+        yaml: &Yaml,
+        base_dir: &Path,
+        roots: &[PathBuf],
+    ) -> Result<Option<Vec<String>>, TypedSentencesError> {
+        match yaml {
+            Yaml::BadValue => Ok(None),
+            Yaml::Array(items) => Ok(Some(
+                items
+                    .iter()
+                    .filter_map(Yaml::as_str)
+                    .map(str::to_string)
+                    .collect(),
+            )),
+            Yaml::String(path) => {
+                let project = read_project_file(&base_dir.join(path), roots)?;
+                Ok(Some(godot_input_actions(&project)))
+            }
+            _ => Err(TypedSentencesError::InvalidRule(
+                "input_actions must be a list of actions or the path of a project.godot".into(),
+            )),
+        }
+    }
+
+    /// `stats: [STR, AGI, crit_chance]`
+    fn parse_stats(yaml: &Yaml) -> Result<Option<Vec<String>>, TypedSentencesError> {
+        match yaml {
+            Yaml::BadValue => Ok(None),
+            Yaml::Array(items) => items
+                .iter()
+                .map(|item| match item {
+                    Yaml::String(name) => Ok(name.clone()),
+                    _ => Err(TypedSentencesError::InvalidRule(
+                        "stats must be a list of stat names".into(),
+                    )),
+                })
+                .collect::<Result<_, _>>()
+                .map(Some),
+            _ => Err(TypedSentencesError::InvalidRule(
+                "stats must be a list of stat names".into(),
+            )),
+        }
+    }
+
+    /// `frontmatter_statements: {on_equip: Effect}`, or a list of keys whose
+    /// statements may build any type
+    fn parse_frontmatter_statements(
+        yaml: &Yaml,
+    ) -> Result<Vec<(String, Option<String>)>, TypedSentencesError> {
+        let invalid = || {
+            TypedSentencesError::InvalidRule(
+                "frontmatter_statements must map keys to types, or list keys".into(),
+            )
+        };
+        match yaml {
+            Yaml::BadValue => Ok(Vec::new()),
+            Yaml::Array(keys) => keys
+                .iter()
+                .map(|key| {
+                    key.as_str()
+                        .map(|key| (key.to_string(), None))
+                        .ok_or_else(invalid)
+                })
+                .collect(),
+            Yaml::Hash(keys) => keys
+                .iter()
+                .map(|(key, ty)| match (key.as_str(), ty) {
+                    (Some(key), Yaml::String(ty)) => Ok((key.to_string(), Some(ty.clone()))),
+                    (Some(key), Yaml::Null) => Ok((key.to_string(), None)),
+                    _ => Err(invalid()),
+                })
+                .collect(),
+            _ => Err(invalid()),
+        }
+    }
+
+    /// Layer the rules of `other` (a mod, a DLC...) on top of this grammar.
+    /// They take precedence over every previously added layer.
+    /// ```
+    /// # #[cfg(feature = "fs")] {
+    /// use std::fs;
+    /// use doke::{DokePipe, GodotValue, parsers::TypedSentencesParser};
+    ///
+    /// let load = |name: &str, grammar: &str| {
+    ///     let dir = std::env::temp_dir().join("doke_layers").join(name);
+    ///     fs::create_dir_all(&dir).unwrap();
+    ///     fs::write(dir.join("Effect.dokedef.yaml"), grammar).unwrap();
+    ///     let config = "rules:\n  - for: Effect\n    parser: \"*.dokedef.yaml\"\n";
+    ///     TypedSentencesParser::from_config(config, &dir).unwrap()
+    /// };
+    /// let base = load("base", "Damage:\n  - \"Deals {damage: int} damage\"\n");
+    /// let fire_mod = load(
+    ///     "fire_mod",
+    ///     "FireDamage:\n  - \"Deals {damage: int} damage\"\nBurn:\n  - \"Burns for {turns: int} turns\"\n",
+    /// );
+    /// let type_of = |grammar: &TypedSentencesParser, text: &str| {
+    ///     let pipe = DokePipe::new().add(grammar.clone());
+    ///     match pipe.validate(text).unwrap().remove(0) {
+    ///         GodotValue::Resource { type_name, .. } => type_name,
+    ///         other => panic!("not a resource: {:?}", other),
+    ///     }
+    /// };
+    ///
+    /// // the mod redefines the base phrase, and wins
+    /// let mut grammar = base.with_layer("fire_mod", fire_mod);
+    /// assert_eq!(grammar.layer_of_phrase("Burns for {turns: int} turns"), Some("fire_mod"));
+    /// assert_eq!(grammar.layer_of_phrase("Deals {damage: int} damage"), Some("fire_mod"));
+    /// assert_eq!(type_of(&grammar, "Deals 5 damage"), "FireDamage");
+    ///
+    /// assert!(grammar.set_layer_enabled("fire_mod", false));
+    /// assert_eq!(grammar.layer_of_phrase("Burns for {turns: int} turns"), None);
+    /// assert_eq!(grammar.layer_of_phrase("Deals {damage: int} damage"), Some("base"));
+    /// assert_eq!(type_of(&grammar, "Deals 5 damage"), "Damage");
+    /// # }
+    /// ```
+    pub fn with_layer(mut self, name: &str, other: TypedSentencesParser) -> Self {
+        self.layers.retain(|layer| layer.name != name);
+        self.rules.retain(|rule| rule.layer != name);
+        self.layers.push(GrammarLayer {
+            name: name.to_string(),
+            enabled: true,
+        });
+        self.rules.extend(other.rules.into_iter().map(|mut rule| {
+            rule.layer = name.to_string();
+            rule
+        }));
+        self.frontmatter_statements
+            .extend(other.frontmatter_statements);
+        self.sort_rules();
+        self.rebuild_hierarchy();
+        self
+    }
+
+    /// Enable or disable a layer at runtime. Returns false if no such layer exists.
+    pub fn set_layer_enabled(&mut self, name: &str, enabled: bool) -> bool {
+        match self.layers.iter_mut().find(|layer| layer.name == name) {
+            Some(layer) => {
+                layer.enabled = enabled;
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Layers from lowest to highest precedence
+    pub fn layers(&self) -> &[GrammarLayer] {
+        &self.layers
+    }
+
+    /// Every phrase of the grammar with the layer it came from,
+    /// highest precedence first.
+    pub fn phrase_origins(&self) -> Vec<PhraseOrigin> {
+        self.rules
+            .iter()
+            .flat_map(|rule| {
+                rule.sentence_parser
+                    .phrases
+                    .iter()
+                    .map(|phrase| PhraseOrigin {
+                        layer: rule.layer.clone(),
+                        target_type: rule.target_type.clone(),
+                        section: phrase.section.clone(),
+                        pattern: phrase.pattern.clone(),
+                    })
+            })
+            .collect()
+    }
+
+    /// Describes the resources of the enabled rules, see `Describer`
+    pub fn describer(&self) -> Describer {
+        Describer::new(
+            self.enabled_rules()
+                .flat_map(|rule| &rule.sentence_parser.phrases),
+        )
+    }
+
+    /// Sha256 of the enabled phrases and what they make, changing whenever
+    /// a phrase is added, removed or edited, whatever their order.
+    pub fn fingerprint(&self) -> String {
+        let mut lines: Vec<String> = self
+            .enabled_rules()
+            .flat_map(|rule| {
+                rule.sentence_parser.phrases.iter().map(|phrase| {
+                    let makes = match &phrase.return_spec {
+                        ReturnSpec::Type(t) => format!("type {}", t),
+                        ReturnSpec::Literal(v) => format!("literal {}", v),
+                        ReturnSpec::Format(f) => format!("format {}", f),
+                    };
+                    format!(
+                        "{}\t{}\t{}\t{}",
+                        rule.target_type, phrase.section, phrase.pattern, makes
+                    )
+                })
+            })
+            .collect();
+        lines.sort();
+        format!("{:x}", Sha256::digest(lines.join("\n").as_bytes()))
+    }
+
+    /// The enabled layer that wins for a given phrase pattern, if any defines it.
+    pub fn layer_of_phrase(&self, pattern: &str) -> Option<&str> {
+        self.rules
+            .iter()
+            .filter(|rule| self.layer_enabled(&rule.layer))
+            .find(|rule| {
+                rule.sentence_parser
+                    .phrases
+                    .iter()
+                    .any(|phrase| phrase.pattern == pattern)
+            })
+            .map(|rule| rule.layer.as_str())
+    }
+
+    /// Rules of the enabled layers, highest precedence first
+    pub(crate) fn enabled_rules(&self) -> impl Iterator<Item = &TypeRule> {
+        self.rules
+            .iter()
+            .filter(|rule| self.layer_enabled(&rule.layer))
+    }
+
+    fn layer_rank(&self, name: &str) -> usize {
+        self.layers
+            .iter()
+            .position(|layer| layer.name == name)
+            .unwrap_or(0)
+    }
+
+    fn layer_enabled(&self, name: &str) -> bool {
+        self.layers
+            .iter()
+            .any(|layer| layer.name == name && layer.enabled)
+    }
+
+    // Sort by layer precedence, then priority (highest first)
+    fn sort_rules(&mut self) {
+        let mut rules = std::mem::take(&mut self.rules);
+        rules.sort_by_key(|rule| {
+            (
+                std::cmp::Reverse(self.layer_rank(&rule.layer)),
+                std::cmp::Reverse(rule.priority),
+            )
+        });
+        self.rules = rules;
+    }
+
+    /// Sections are their rule's type unless they say otherwise, and declarations
+    /// of higher layers win
+    fn rebuild_hierarchy(&mut self) {
+        let mut hierarchy = TypeHierarchy::new();
+        for rule in self.rules.iter().rev() {
+            for phrase in &rule.sentence_parser.phrases {
+                hierarchy.insert(phrase.section.clone(), rule.target_type.clone());
+            }
+            hierarchy.extend(&rule.sentence_parser.hierarchy());
+        }
+        self.hierarchy = hierarchy;
+    }
+
+    /// Is-a relationships between the types of the grammar: sections declaring a
+    /// type with `is_a`, the others being the type of their rule. Give it to
+    /// `ResourceBuilder::with_hierarchy` so fields accept subtypes.
+    pub fn hierarchy(&self) -> &TypeHierarchy {
+        &self.hierarchy
     }
 
     fn parse_rule(
@@ -127,8 +810,10 @@ impl TypedSentencesParser {
     ) -> Result<TypeRule, TypedSentencesError> {
         let mut target_type = None;
         let mut parser_pattern = None;
+        let mut bundle = None;
         let mut priority = 0;
         let mut children = ChildSpec::Simple(Vec::new());
+        let mut ordered = None;
 
         for (key, value) in rule_hash {
             if let Yaml::String(key_str) = key {
@@ -138,21 +823,29 @@ impl TypedSentencesParser {
                             target_type = Some(type_str.clone());
                         }
                     }
-                    "parser" => {
-                        if let Yaml::String(pattern) = value {
-                            parser_pattern = Some(pattern.clone());
+                    "parser" => match value {
+                        Yaml::String(pattern) => parser_pattern = Some(pattern.clone()),
+                        Yaml::Hash(_) => {
+                            let (pattern, source) = Self::parse_bundle_reference(value)?;
+                            parser_pattern = Some(pattern);
+                            bundle = Some(source);
                         }
-                    }
+                        _ => {}
+                    },
                     "priority" => {
                         if let Yaml::Integer(prio) = value {
                             priority = *prio as i32;
                         }
                     }
-                    "children" => {
-                        if let Ok(spec) = Self::parse_child_spec(value) {
-                            children = spec
+                    "children" => children = Self::parse_child_spec(value)?,
+                    "ordered" => match value {
+                        Yaml::Boolean(b) => ordered = Some(*b),
+                        _ => {
+                            return Err(TypedSentencesError::InvalidRule(
+                                "'ordered' must be true or false".into(),
+                            ));
                         }
-                    }
+                    },
                     _ => {}
                 }
             }
@@ -170,15 +863,18 @@ impl TypedSentencesParser {
             parser_ref: ParserReference {
                 pattern: parser_pattern,
                 base_dir: base_dir.to_path_buf(),
+                bundle,
             },
             priority,
             children,
+            ordered,
             sentence_parser: SentenceParser {
                 phrases: Vec::new(),
                 type_patterns: HashMap::new(),
                 abstract_type: "".into(),
                 children_map: HashMap::new(),
             }, // Temporary placeholder
+            layer: BASE_LAYER.to_string(),
         })
     }
 
@@ -195,19 +891,42 @@ impl TypedSentencesParser {
                 Ok(ChildSpec::Simple(child_types))
             }
             // New syntax: children: {damage_effects: [DamageEffect], other_effects: [ItemEffect]}
+            // or with counts: {damage_effects: {types: [DamageEffect], min: 1, max: 3}}
             Yaml::Hash(children_map) => {
-                let mut structured_children = HashMap::new();
+                let mut structured_children = Vec::new();
                 for (field_name, child_types) in children_map {
-                    if let Yaml::String(field_str) = field_name {
-                        if let Yaml::Array(types_array) = child_types {
-                            let mut types_vec = Vec::new();
-                            for child_type in types_array {
-                                if let Yaml::String(type_str) = child_type {
-                                    types_vec.push(type_str.clone());
-                                }
+                    let Yaml::String(field_str) = field_name else {
+                        continue;
+                    };
+                    let (types, min, max) = match child_types {
+                        Yaml::Hash(_) => (
+                            &child_types["types"],
+                            Self::parse_count(field_str, "min", &child_types["min"])?,
+                            Self::parse_count(field_str, "max", &child_types["max"])?,
+                        ),
+                        types => (types, None, None),
+                    };
+                    if let Yaml::Array(types_array) = types {
+                        let mut types_vec = Vec::new();
+                        for child_type in types_array {
+                            if let Yaml::String(type_str) = child_type {
+                                types_vec.push(type_str.clone());
                             }
-                            structured_children.insert(field_str.clone(), types_vec);
                         }
+                        let field = ChildField {
+                            types: types_vec,
+                            min: min.unwrap_or(0),
+                            max,
+                        };
+                        if field.max.is_some_and(|max| max < field.min) {
+                            return Err(TypedSentencesError::InvalidRule(format!(
+                                "children field {} takes at least {} and at most {}",
+                                field_str,
+                                field.min,
+                                max.unwrap_or_default()
+                            )));
+                        }
+                        structured_children.push((field_str.clone(), field));
                     }
                 }
                 Ok(ChildSpec::Structured(structured_children))
@@ -215,77 +934,81 @@ impl TypedSentencesParser {
             _ => Ok(ChildSpec::Simple(Vec::new())), // Empty if invalid
         }
     }
+
+    /// `min` or `max` of a children field, a count when present
+    fn parse_count(
+        field: &str,
+        key: &str,
+        yaml: &Yaml,
+    ) -> Result<Option<usize>, TypedSentencesError> {
+        match yaml {
+            Yaml::BadValue => Ok(None),
+            Yaml::Integer(count) if *count >= 0 => Ok(Some(*count as usize)),
+            _ => Err(TypedSentencesError::InvalidRule(format!(
+                "{} of children field {} must be a count",
+                key, field
+            ))),
+        }
+    }
+
+    fn parse_bundle_reference(yaml: &Yaml) -> Result<(String, BundleSource), TypedSentencesError> {
+        let location = yaml["bundle"]
+            .as_str()
+            .ok_or(TypedSentencesError::InvalidRule(
+                "A 'parser' map needs a 'bundle' location".into(),
+            ))?;
+        let pattern = yaml["pattern"].as_str().unwrap_or("**/*.dokedef.yaml");
+        let sha256 = yaml["sha256"].as_str().map(str::to_string);
+
+        Ok((
+            pattern.to_string(),
+            BundleSource {
+                location: location.to_string(),
+                sha256,
+            },
+        ))
+    }
+
     fn load_parser_from_reference(
         parser_ref: &ParserReference,
         abstract_type: String,
+        roots: &[PathBuf],
     ) -> Result<SentenceParser, TypedSentencesError> {
-        let mut config_content = String::new();
         let mut found_files = Vec::new();
 
-        let full_pattern = parser_ref
-            .base_dir
-            .join(&parser_ref.pattern)
-            .to_string_lossy()
-            .into_owned();
-
-        let glob_iter = glob(&full_pattern).map_err(|e| {
-            TypedSentencesError::GlobError(format!(
-                "Invalid glob pattern '{}': {}",
-                full_pattern, e
-            ))
-        })?;
-
-        for entry in glob_iter {
-            match entry {
-                Ok(path) => {
-                    if path.is_file() && is_dokedef_file(&path) {
-                        match fs::read_to_string(&path) {
-                            Ok(content) => {
-                                config_content.push_str(&content);
-                                config_content.push_str("\n---\n");
-                                found_files.push(path);
-                            }
-                            Err(e) => {
-                                println!("Warning: Could not read file {}: {}", path.display(), e);
-                            }
-                        }
-                    }
-                }
-                Err(e) => {
-                    println!(
-                        "Warning: Error accessing file in pattern {}: {}",
-                        full_pattern, e
-                    );
+        if let Some(bundle) = &parser_ref.bundle {
+            if !bundle.is_remote() {
+                ensure_contained(&parser_ref.base_dir.join(&bundle.location), roots)?;
+            }
+            for (path, content) in read_bundle_files(bundle, parser_ref)? {
+                if is_dokedef_file(&path) {
+                    found_files.push((path, content));
                 }
             }
+            if found_files.is_empty() {
+                return Err(TypedSentencesError::FileError(format!(
+                    "No .dokedef.yaml files found for pattern: {} in bundle {}",
+                    parser_ref.pattern, bundle.location
+                )));
+            }
+            return parser_from_files(abstract_type, found_files);
         }
 
-        if found_files.is_empty() {
-            return Err(TypedSentencesError::FileError(format!(
-                "No .dokedef.yaml files found for pattern: {} (searched: {})",
-                parser_ref.pattern, full_pattern
-            )));
-        }
-
-        println!(
-            "Loaded parser from {} files: {:?}",
-            found_files.len(),
-            found_files
-        );
-
-        SentenceParser::from_yaml(abstract_type, &config_content).map_err(|e| {
-            TypedSentencesError::InvalidRule(format!(
-                "Failed to parse YAML from {} files: {}",
-                found_files.len(),
-                e
-            ))
-        })
+        let found_files = read_pattern_files(parser_ref, roots)?;
+        parser_from_files(abstract_type, found_files)
     }
 
+    /// Whether the rules of the parent's type list `rule`'s type as a child
     fn rule_matches_parent(&self, rule: &TypeRule, parent_abstract_type: Option<&str>) -> bool {
-        parent_abstract_type.map_or(true, |parent_type| {
-            let child_spec = &rule.children;
-            child_spec.allowed(parent_type)
+        parent_abstract_type.is_none_or(|parent_type| {
+            self.rules
+                .iter()
+                .filter(|parent_rule| parent_rule.target_type == parent_type)
+                .any(|parent_rule| {
+                    parent_rule
+                        .children
+                        .allowed(&rule.target_type, &self.hierarchy)
+                })
         })
     }
 
@@ -305,6 +1028,10 @@ impl TypedSentencesParser {
                 "abstract_type".to_string(),
                 GodotValue::String(rule.target_type.clone()),
             );
+            node.parse_data.insert(
+                "grammar_layer".to_string(),
+                GodotValue::String(rule.layer.clone()),
+            );
             true
         } else {
             // If we didn't resolve it, restore the unresolved state
@@ -327,26 +1054,33 @@ impl TypedSentencesParser {
         }
 
         if let DokeNodeState::Unresolved = &node.state {
-            let mut candidate_rules: Vec<&TypeRule> = self
+            // rules are kept sorted by layer precedence then priority
+            let candidate_rules: Vec<&TypeRule> = self
                 .rules
                 .iter()
+                .filter(|rule| self.layer_enabled(&rule.layer))
                 .filter(|rule| self.rule_matches_parent(rule, parent_abstract_type))
                 .collect();
 
-            candidate_rules.sort_by(|a, b| b.priority.cmp(&a.priority));
-
             for rule in candidate_rules {
-                if self.try_process_with_rule(node, frontmatter, rule) {
+                if self.try_process_with_rule(node, frontmatter, rule) || limits::timed_out(node) {
                     break;
                 }
             }
 
-            if let DokeNodeState::Unresolved = &node.state {
-                let mut all_rules: Vec<&TypeRule> = self.rules.iter().collect();
-                all_rules.sort_by(|a, b| b.priority.cmp(&a.priority));
+            if let DokeNodeState::Unresolved = &node.state
+                && !limits::timed_out(node)
+            {
+                let all_rules: Vec<&TypeRule> = self
+                    .rules
+                    .iter()
+                    .filter(|rule| self.layer_enabled(&rule.layer))
+                    .collect();
 
                 for rule in all_rules {
-                    if self.try_process_with_rule(node, frontmatter, rule) {
+                    if self.try_process_with_rule(node, frontmatter, rule)
+                        || limits::timed_out(node)
+                    {
                         break;
                     }
                 }
@@ -372,8 +1106,50 @@ impl TypedSentencesParser {
         for constituent in node.constituents.values_mut() {
             self.process_node_recursive(constituent, frontmatter, current_abstract_type, depth + 1);
         }
+
+        self.check_children(node);
+    }
+
+    /// Report the fields of a resolved node's rule with too few or too many children,
+    /// and apply the rule's children order.
+    /// Mapped pipelines process nodes more than once, each error is reported once.
+    fn check_children(&self, node: &mut DokeNode) {
+        let (Some(GodotValue::String(abstract_type)), Some(GodotValue::String(layer))) = (
+            node.parse_data.get("abstract_type"),
+            node.parse_data.get("grammar_layer"),
+        ) else {
+            return;
+        };
+        let Some(rule) = self
+            .rules
+            .iter()
+            .find(|rule| &rule.target_type == abstract_type && &rule.layer == layer)
+        else {
+            return;
+        };
+        for diagnostic in rule.children.count_diagnostics(node, &self.hierarchy) {
+            if !node.diagnostics.contains(&diagnostic) {
+                node.diagnostics.push(diagnostic);
+            }
+        }
+        match rule.ordered {
+            Some(true) => {
+                for (i, diagnostic) in rule.children.order_diagnostics(node, &self.hierarchy) {
+                    let child = &mut node.children[i];
+                    if !child.diagnostics.contains(&diagnostic) {
+                        child.diagnostics.push(diagnostic);
+                    }
+                }
+            }
+            Some(false) => {
+                node.parse_data
+                    .insert(UNORDERED_CHILDREN_KEY.into(), GodotValue::Bool(true));
+            }
+            None => {}
+        }
     }
 
+    #[cfg(feature = "fs")]
     pub fn debug_glob_pattern(
         &self,
         pattern: &str,
@@ -399,6 +1175,166 @@ impl DokeParser for TypedSentencesParser {
     fn process(&self, node: &mut DokeNode, frontmatter: &HashMap<String, GodotValue>) {
         self.process_node_recursive(node, frontmatter, None, 0);
     }
+
+    fn provides(&self) -> &[&'static str] {
+        &[
+            "abstract_type",
+            "grammar_layer",
+            UNORDERED_CHILDREN_KEY,
+            CANONICAL_KEY,
+            DISPLAY_TEMPLATE_KEY,
+        ]
+    }
+
+    fn frontmatter_statements(&self) -> &[(String, Option<String>)] {
+        &self.frontmatter_statements
+    }
+}
+
+/// Canonicalize `path` and check that it lives under one of `roots`
+/// (which must already be canonical), so `..` segments or symlinks
+/// can't pull in files from elsewhere on disk.
+fn ensure_contained(path: &Path, roots: &[PathBuf]) -> Result<PathBuf, TypedSentencesError> {
+    let resolved = fs::canonicalize(path)
+        .map_err(|e| TypedSentencesError::FileError(format!("{}: {}", path.display(), e)))?;
+    if roots.iter().any(|root| resolved.starts_with(root)) {
+        Ok(resolved)
+    } else {
+        Err(TypedSentencesError::PathEscape(path.display().to_string()))
+    }
+}
+
+#[cfg(feature = "bundles")]
+fn read_bundle_files(
+    bundle: &BundleSource,
+    parser_ref: &ParserReference,
+) -> Result<Vec<(PathBuf, String)>, TypedSentencesError> {
+    bundle
+        .read_matching(&parser_ref.base_dir, &parser_ref.pattern)
+        .map_err(|e| TypedSentencesError::BundleError(e.to_string()))
+}
+
+#[cfg(not(feature = "bundles"))]
+fn read_bundle_files(
+    bundle: &BundleSource,
+    _parser_ref: &ParserReference,
+) -> Result<Vec<(PathBuf, String)>, TypedSentencesError> {
+    Err(TypedSentencesError::BundleError(format!(
+        "loading '{}' requires the `bundles` feature",
+        bundle.location
+    )))
+}
+
+/// The dokedef files matching `parser_ref`'s glob pattern, with their content
+#[cfg(feature = "fs")]
+fn read_pattern_files(
+    parser_ref: &ParserReference,
+    roots: &[PathBuf],
+) -> Result<Vec<(PathBuf, String)>, TypedSentencesError> {
+    let mut found_files = Vec::new();
+
+    let full_pattern = parser_ref
+        .base_dir
+        .join(&parser_ref.pattern)
+        .to_string_lossy()
+        .into_owned();
+
+    let glob_iter = glob(&full_pattern).map_err(|e| {
+        TypedSentencesError::GlobError(format!("Invalid glob pattern '{}': {}", full_pattern, e))
+    })?;
+
+    for entry in glob_iter {
+        match entry {
+            Ok(path) => {
+                if path.is_file() && is_dokedef_file(&path) {
+                    let path = ensure_contained(&path, roots)?;
+                    match fs::read_to_string(&path) {
+                        Ok(content) => found_files.push((path, content)),
+                        Err(e) => {
+                            eprintln!("Warning: Could not read file {}: {}", path.display(), e);
+                        }
+                    }
+                }
+            }
+            Err(e) => {
+                eprintln!(
+                    "Warning: Error accessing file in pattern {}: {}",
+                    full_pattern, e
+                );
+            }
+        }
+    }
+
+    if found_files.is_empty() {
+        return Err(TypedSentencesError::FileError(format!(
+            "No .dokedef.yaml files found for pattern: {} (searched: {})",
+            parser_ref.pattern, full_pattern
+        )));
+    }
+
+    eprintln!(
+        "Loaded parser from {} files: {:?}",
+        found_files.len(),
+        found_files.iter().map(|(path, _)| path).collect::<Vec<_>>()
+    );
+    Ok(found_files)
+}
+
+#[cfg(not(feature = "fs"))]
+fn read_pattern_files(
+    parser_ref: &ParserReference,
+    _roots: &[PathBuf],
+) -> Result<Vec<(PathBuf, String)>, TypedSentencesError> {
+    Err(TypedSentencesError::FileError(format!(
+        "loading '{}' from disk requires the `fs` feature",
+        parser_ref.pattern
+    )))
+}
+
+/// The content of a project file the config names, like its `project.godot`
+#[cfg(feature = "fs")]
+fn read_project_file(path: &Path, roots: &[PathBuf]) -> Result<String, TypedSentencesError> {
+    let path = ensure_contained(path, roots)?;
+    fs::read_to_string(&path)
+        .map_err(|e| TypedSentencesError::FileError(format!("{}: {}", path.display(), e)))
+}
+
+#[cfg(not(feature = "fs"))]
+fn read_project_file(path: &Path, _roots: &[PathBuf]) -> Result<String, TypedSentencesError> {
+    Err(TypedSentencesError::FileError(format!(
+        "reading '{}' from disk requires the `fs` feature",
+        path.display()
+    )))
+}
+
+/// One parser with the phrases of every dokedef file, each phrase knowing its file
+fn parser_from_files(
+    abstract_type: String,
+    files: Vec<(PathBuf, String)>,
+) -> Result<SentenceParser, TypedSentencesError> {
+    let mut parser =
+        SentenceParser::from_yaml(abstract_type.clone(), "").expect("an empty grammar is valid");
+    for (path, content) in files {
+        let file_parser =
+            SentenceParser::from_yaml(abstract_type.clone(), &content).map_err(|e| {
+                TypedSentencesError::InvalidRule(format!(
+                    "Failed to parse YAML from {}: {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        parser
+            .phrases
+            .extend(file_parser.phrases.into_iter().map(|mut phrase| {
+                phrase.source = Some(path.clone());
+                phrase.line = content
+                    .lines()
+                    .position(|line| line.contains(phrase.pattern.as_str()))
+                    .map(|index| index + 1);
+                phrase
+            }));
+    }
+    Ok(parser)
 }
 
 fn is_dokedef_file(path: &Path) -> bool {
@@ -0,0 +1,103 @@
+// parsers/variables.rs
+//
+// Values named once and reused by the statements after them:
+//
+//     Let base_damage = 12
+//
+//     - Deals {base_damage} + level damage
+//     - Deals {base_damage} * 2 on a critical hit
+//
+// Bindings are scoped to their document and read in document order, children
+// included. Their statements leave the document (statements written under them
+// take their place), and `{name}` placeholders of
+// the following statements are replaced by the value's text before any later
+// parser matches them, like `FrontmatterTemplateParser` does with frontmatter.
+// A binding may use earlier ones (`Let crit = {base_damage} * 2`) and a later
+// binding of the same name replaces it from there on.
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::{DokeNode, DokeNodeState, DokeParser, GodotValue};
+
+/// `Let name = value`
+static BINDING: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)^\s*let\s+([A-Za-z_]\w*)\s*=\s*(.*?)\s*$").unwrap());
+
+/// `{name}` of a bound variable
+static REFERENCE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([A-Za-z_]\w*)\}").unwrap());
+
+/// Binds `Let name = value` statements and replaces `{name}` in the statements after them.
+/// Add it before the parsers reading those statements.
+/// ```
+/// use doke::{DokePipe, parsers::{SentenceParser, VariablesParser}};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: formula} damage"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(VariablesParser).add(parser);
+///
+/// let doc = pipe.run_markdown("Let base_damage = 12\n\nDeals {base_damage} + level damage");
+/// assert_eq!(doc.nodes.len(), 1);
+/// assert_eq!(doc.nodes[0].statement.as_str(), "Deals 12 + level damage");
+/// ```
+#[derive(Debug)]
+pub struct VariablesParser;
+
+impl DokeParser for VariablesParser {
+    /// Bindings among the node's children, for a node processed on its own
+    fn process(&self, node: &mut DokeNode, _frontmatter: &HashMap<String, GodotValue>) {
+        remove_bindings(&mut node.children, &mut HashMap::new());
+    }
+
+    fn process_document(
+        &self,
+        nodes: &mut Vec<DokeNode>,
+        _frontmatter: &HashMap<String, GodotValue>,
+    ) {
+        remove_bindings(nodes, &mut HashMap::new());
+    }
+}
+
+/// Substitute the variables in `nodes` in order, taking out the `Let` statements
+/// and binding their variables for the statements after them
+fn remove_bindings(nodes: &mut Vec<DokeNode>, variables: &mut HashMap<String, String>) {
+    let mut kept = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        substitute(&mut node, variables);
+        let binding = match node.state {
+            DokeNodeState::Unresolved => BINDING.captures(&node.statement),
+            _ => None,
+        };
+        let is_binding = binding.is_some();
+        if let Some(cap) = binding {
+            variables.insert(cap[1].to_string(), cap[2].to_string());
+        }
+        remove_bindings(&mut node.children, variables);
+        match is_binding {
+            // statements written under a binding take its place
+            true => kept.append(&mut node.children),
+            false => kept.push(node),
+        }
+    }
+    *nodes = kept;
+}
+
+/// Replace the `{name}` of bound variables in an unresolved statement
+fn substitute(node: &mut DokeNode, variables: &HashMap<String, String>) {
+    if variables.is_empty() || !matches!(node.state, DokeNodeState::Unresolved) {
+        return;
+    }
+    let replaced = REFERENCE.replace_all(&node.statement, |cap: &regex::Captures| {
+        variables
+            .get(&cap[1])
+            .cloned()
+            .unwrap_or_else(|| cap[0].to_string())
+    });
+    if let std::borrow::Cow::Owned(text) = replaced {
+        node.statement = text.into();
+    }
+}
@@ -0,0 +1,279 @@
+// weighted_table.rs
+//
+// Probability tables written as a header and weighted bullets:
+//
+//     Drops:
+//     - 30%: Gold
+//     - 5%: Rare Sword
+//
+// The header resolves to a `WeightedTable` resource and every bullet to an
+// entry holding its item and its normalized chance.
+
+use regex::Regex;
+use std::collections::{HashMap, HashSet};
+
+use crate::diagnostics::Diagnostic;
+use crate::parsers::list_header;
+use crate::semantic::BlockKind;
+use crate::{DokeNode, DokeNodeState, DokeOut, DokeParser, Extensions, GodotValue};
+
+/// Recognizes "Header:" statements whose children all look like "- 30%: Item"
+/// (percentages) or "- 3: Item" (relative weights), and turns them into a
+/// normalized weighted table.
+///
+/// Percentages must not add up past 100%, what is left is the chance of
+/// getting nothing (`none_chance`). Relative weights are normalized by their total.
+/// ```
+/// use doke::{DokePipe, GodotValue, parsers::WeightedTableParser};
+///
+/// let pipe = DokePipe::new().add(WeightedTableParser::new());
+/// let values = pipe.validate("Drops:\n- 30%: Gold\n- 5%: Rare Sword").unwrap();
+/// assert_eq!(values[0].get_path("none_chance"), Some(&GodotValue::Float(0.65)));
+/// assert_eq!(values[0].get_path("entries/1/chance"), Some(&GodotValue::Float(0.05)));
+///
+/// let doc = pipe.run_markdown("Drops:\n- 80%: Gold\n- 40%: Rare Sword");
+/// assert_eq!(doc.nodes[0].diagnostics[0].code, "table-weight-overflow");
+/// ```
+#[derive(Debug)]
+pub struct WeightedTableParser {
+    entry_regex: Regex,
+    headers: Option<Vec<String>>,
+    item_constituents: bool,
+}
+
+impl Default for WeightedTableParser {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl WeightedTableParser {
+    pub fn new() -> Self {
+        Self {
+            entry_regex: Regex::new(r"^\s*([0-9]*\.?[0-9]+)\s*(%?)\s*:\s*(.+?)\s*\.?\s*$").unwrap(),
+            headers: None,
+            item_constituents: false,
+        }
+    }
+
+    /// Only treat these headers (e.g. "Drops", "Encounters") as tables.
+    /// By default any header with weighted children is one.
+    pub fn with_headers<I, S>(mut self, headers: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.headers = Some(headers.into_iter().map(|h| h.into()).collect());
+        self
+    }
+
+    /// Parse entry items as unresolved `item` constituents for later parsers,
+    /// instead of keeping them as plain strings.
+    pub fn with_item_constituents(mut self) -> Self {
+        self.item_constituents = true;
+        self
+    }
+
+    fn parse_entry(&self, statement: &str) -> Option<(f64, bool, String)> {
+        let caps = self.entry_regex.captures(statement)?;
+        let weight = caps[1].parse().ok()?;
+        Some((weight, !caps[2].is_empty(), caps[3].to_string()))
+    }
+
+    fn process_table(&self, node: &mut DokeNode, name: String, entries: Vec<(f64, bool, String)>) {
+        let percentages = entries.iter().filter(|(_, percent, _)| *percent).count();
+        let total: f64 = entries.iter().map(|(weight, _, _)| weight).sum();
+        let mut table = WeightedTable {
+            name: name.clone(),
+            entries: Vec::new(),
+            total_weight: total,
+            none_chance: 0.0,
+        };
+
+        if percentages != 0 && percentages != entries.len() {
+            node.diagnostics.push(
+                Diagnostic::error(
+                    "table-mixed-weights",
+                    format!("'{}' mixes percentages and relative weights", name),
+                )
+                .with_span(node.span.clone()),
+            );
+        } else if total <= 0.0 {
+            node.diagnostics.push(
+                Diagnostic::error(
+                    "table-empty-weights",
+                    format!("'{}' has no positive weight", name),
+                )
+                .with_span(node.span.clone()),
+            );
+        } else if percentages > 0 && total > 100.0 + f64::EPSILON * 100.0 {
+            node.diagnostics.push(
+                Diagnostic::error(
+                    "table-weight-overflow",
+                    format!("'{}' chances add up to {}%, more than 100%", name, total),
+                )
+                .with_span(node.span.clone()),
+            );
+        } else if percentages > 0 {
+            table.none_chance = ((100.0 - total) / 100.0).max(0.0);
+        }
+
+        let divisor = if percentages > 0 { 100.0 } else { total };
+        let mut seen = HashSet::new();
+        for (child, (weight, _, item)) in node.children.iter_mut().zip(entries) {
+            if weight == 0.0 {
+                child.diagnostics.push(
+                    Diagnostic::warning(
+                        "table-zero-weight",
+                        format!("'{}' can never be picked from '{}'", item, name),
+                    )
+                    .with_span(child.span.clone()),
+                );
+            }
+            if !seen.insert(item.to_lowercase()) {
+                child.diagnostics.push(
+                    Diagnostic::warning(
+                        "table-duplicate-entry",
+                        format!("'{}' appears more than once in '{}'", item, name),
+                    )
+                    .with_span(child.span.clone()),
+                );
+            }
+
+            let chance = if divisor > 0.0 { weight / divisor } else { 0.0 };
+            let entry = if self.item_constituents {
+                child.constituents.insert(
+                    "item".into(),
+                    DokeNode {
+                        statement: item.into(),
+                        state: DokeNodeState::Unresolved,
+                        children: Vec::new(),
+                        parse_data: HashMap::new(),
+                        extensions: Extensions::new(),
+                        constituents: HashMap::new(),
+                        span: child.span.clone(),
+                        diagnostics: Vec::new(),
+                        block: BlockKind::Other,
+                    },
+                );
+                WeightedEntry {
+                    item: GodotValue::Nil,
+                    weight,
+                    chance,
+                }
+            } else {
+                WeightedEntry {
+                    item: GodotValue::String(item),
+                    weight,
+                    chance,
+                }
+            };
+            child.state = DokeNodeState::Resolved(Box::new(entry));
+        }
+
+        node.parse_data.insert(
+            "abstract_type".into(),
+            GodotValue::String("WeightedTable".into()),
+        );
+        node.state = DokeNodeState::Resolved(Box::new(table));
+    }
+}
+
+impl DokeParser for WeightedTableParser {
+    #[allow(clippy::only_used_in_recursion)]
+    fn process(&self, node: &mut DokeNode, frontmatter: &HashMap<String, GodotValue>) {
+        if matches!(node.state, DokeNodeState::Unresolved)
+            && !node.children.is_empty()
+            && let Some(name) = list_header(node, self.headers.as_deref())
+        {
+            let entries: Option<Vec<_>> = node
+                .children
+                .iter()
+                .map(|child| self.parse_entry(&child.statement))
+                .collect();
+            // Only claim the node when every child is a weighted entry
+            if let Some(entries) = entries {
+                let name = name.to_string();
+                self.process_table(node, name, entries);
+                return;
+            }
+        }
+
+        for child in &mut node.children {
+            self.process(child, frontmatter);
+        }
+    }
+}
+
+// ----------------- Output -----------------
+
+#[derive(Debug)]
+struct WeightedTable {
+    name: String,
+    entries: Vec<GodotValue>,
+    total_weight: f64,
+    none_chance: f64,
+}
+
+impl DokeOut for WeightedTable {
+    fn kind(&self) -> &'static str {
+        "WeightedTable"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Resource {
+            type_name: "WeightedTable".into(),
+            abstract_type_name: "WeightedTable".into(),
+            fields: HashMap::from([
+                ("name".into(), GodotValue::String(self.name.clone())),
+                ("entries".into(), GodotValue::Array(self.entries.clone())),
+                ("total_weight".into(), GodotValue::Float(self.total_weight)),
+                ("none_chance".into(), GodotValue::Float(self.none_chance)),
+            ]),
+        }
+    }
+
+    fn get_asbtract_type(&self) -> Option<String> {
+        Some("WeightedTable".into())
+    }
+
+    fn use_child(
+        &mut self,
+        child: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.entries.push(child);
+        Ok(())
+    }
+}
+
+#[derive(Debug)]
+struct WeightedEntry {
+    item: GodotValue,
+    weight: f64,
+    chance: f64,
+}
+
+impl DokeOut for WeightedEntry {
+    fn kind(&self) -> &'static str {
+        "WeightedEntry"
+    }
+
+    fn to_godot(&self) -> GodotValue {
+        GodotValue::Dict(HashMap::from([
+            ("item".into(), self.item.clone()),
+            ("weight".into(), GodotValue::Float(self.weight)),
+            ("chance".into(), GodotValue::Float(self.chance)),
+        ]))
+    }
+
+    fn use_constituent(
+        &mut self,
+        name: &str,
+        value: GodotValue,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        if name == "item" {
+            self.item = value;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,111 @@
+// profile.rs
+//
+// Where a pipe spends its time on one document, see `DokePipe::run_profiled`:
+// wall time of each parser, and the statements that took longest to parse.
+//
+// Parser times come from the normal run. Statements are timed on a second
+// read of the document, giving each top-level statement (children included)
+// to each parser's `process` one at a time: parsers that regroup statements
+// in `process_document` are timed on statements they would have seen together.
+
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use crate::{DokeDocument, DokeNode, DokeParser, GodotValue, Position};
+
+/// Statements kept in `Profile::hot_spots`
+pub const HOT_SPOTS: usize = 10;
+
+/// A document and the time it took, from `DokePipe::run_profiled`
+#[derive(Debug)]
+pub struct Profile {
+    /// The document, as `run_markdown` returns it
+    pub document: DokeDocument,
+    /// Reading the input into statements, normalization included
+    pub read: Duration,
+    /// Each parser of the pipe, in pipe order
+    pub parsers: Vec<ParserTime>,
+    /// The slowest statements, slowest first
+    pub hot_spots: Vec<HotSpot>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ParserTime {
+    /// `DokeParser::name`
+    pub parser: &'static str,
+    pub time: Duration,
+}
+
+/// A statement that was slow to parse
+#[derive(Debug, Clone, PartialEq)]
+pub struct HotSpot {
+    pub statement: String,
+    pub span: Position,
+    /// Time every parser spent on it
+    pub time: Duration,
+    /// The parser that spent the most
+    pub parser: &'static str,
+}
+
+impl Profile {
+    /// Reading and parsing the document
+    pub fn total(&self) -> Duration {
+        self.read + self.parsers.iter().map(|p| p.time).sum::<Duration>()
+    }
+}
+
+impl fmt::Display for Profile {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "total {:>10.3?}", self.total())?;
+        writeln!(f, "read  {:>10.3?}", self.read)?;
+        for parser in &self.parsers {
+            writeln!(f, "      {:>10.3?}  {}", parser.time, parser.parser)?;
+        }
+        if !self.hot_spots.is_empty() {
+            writeln!(f, "slowest statements:")?;
+            for spot in &self.hot_spots {
+                writeln!(
+                    f,
+                    "      {:>10.3?}  {}  ({}, {})",
+                    spot.time, spot.statement, spot.parser, spot.span
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Time each top-level statement of `nodes` through `parsers`, keep the slowest
+pub(crate) fn hot_spots(
+    nodes: &mut [DokeNode],
+    parsers: &[Box<dyn DokeParser + Send + Sync + 'static>],
+    frontmatter: &HashMap<String, GodotValue>,
+) -> Vec<HotSpot> {
+    let mut spots: Vec<HotSpot> = nodes
+        .iter_mut()
+        .map(|node| {
+            let mut time = Duration::ZERO;
+            let mut slowest = (Duration::ZERO, "");
+            for parser in parsers {
+                let started = Instant::now();
+                parser.process(node, frontmatter);
+                let elapsed = started.elapsed();
+                time += elapsed;
+                if elapsed >= slowest.0 {
+                    slowest = (elapsed, parser.name());
+                }
+            }
+            HotSpot {
+                statement: node.statement.to_string(),
+                span: node.span.clone(),
+                time,
+                parser: slowest.1,
+            }
+        })
+        .collect();
+    spots.sort_by_key(|spot| Reverse(spot.time));
+    spots.truncate(HOT_SPOTS);
+    spots
+}
@@ -0,0 +1,113 @@
+// rich_text.rs
+//
+// Author formatting kept for display, see `DokePipe::with_rich_text`: statements
+// are matched without their emphasis markers, and their BBCode rendering (what
+// Godot's RichTextLabel reads) goes along in `parse_data`:
+//
+//     Deals **5** damage to *burning* enemies
+//     -> statement     Deals 5 damage to burning enemies
+//     -> display_text  Deals [b]5[/b] damage to [i]burning[/i] enemies
+//
+// Sentence results of a `Type` copy it to their `display_text` field.
+
+use markdown::mdast::Node;
+
+use crate::Position;
+
+/// `parse_data` key, and result field, holding the BBCode rendering of a statement
+pub const DISPLAY_TEXT_KEY: &str = "display_text";
+
+/// The text at `span` of `source` without emphasis markers, and as BBCode.
+/// `node` is the block the statement was read from, `span` may be a part of it
+/// (see `input::Segmentation`) cut outside any emphasis.
+pub(crate) fn render(node: &Node, source: &str, span: &Position) -> (String, String) {
+    let mut out = Rendering {
+        end: span.end,
+        ..Default::default()
+    };
+    let mut cursor = span.start;
+    out.inline(node, source, &mut cursor);
+    out.copy(source, cursor, span.end);
+    (out.plain, out.bbcode)
+}
+
+/// RichTextLabel would read brackets as tags
+fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '[' => out.push_str("[lb]"),
+            ']' => out.push_str("[rb]"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+#[derive(Default)]
+struct Rendering {
+    plain: String,
+    bbcode: String,
+    /// End of the rendered span
+    end: usize,
+}
+
+impl Rendering {
+    /// Source text between `start` and `end`, to both renderings
+    fn copy(&mut self, source: &str, start: usize, end: usize) {
+        let text = source.get(start..end).unwrap_or_default();
+        self.plain.push_str(text);
+        self.bbcode.push_str(&escape(text));
+    }
+
+    /// The children of `node`, from `cursor` on
+    fn inline(&mut self, node: &Node, source: &str, cursor: &mut usize) {
+        for child in node.children().into_iter().flatten() {
+            let Some(position) = child.position() else {
+                continue;
+            };
+            let (start, end) = (position.start.offset, position.end.offset);
+            // in another part of the paragraph
+            if start < *cursor || end > self.end {
+                continue;
+            }
+            let tag = match child {
+                Node::Emphasis(_) => "i",
+                Node::Strong(_) => "b",
+                Node::Delete(_) => "s",
+                Node::InlineCode(code) => {
+                    // matched with its backticks
+                    self.copy(source, *cursor, start);
+                    self.plain
+                        .push_str(source.get(start..end).unwrap_or_default());
+                    let code = format!("[code]{}[/code]", escape(&code.value));
+                    self.bbcode.push_str(&code);
+                    *cursor = end;
+                    continue;
+                }
+                _ => {
+                    self.inline(child, source, cursor);
+                    continue;
+                }
+            };
+            self.copy(source, *cursor, start);
+            self.bbcode.push_str(&format!("[{}]", tag));
+            // skip the markers around the content
+            let content = child.children().into_iter().flatten();
+            *cursor = content
+                .filter_map(|c| c.position())
+                .map(|p| p.start.offset)
+                .next()
+                .unwrap_or(end);
+            self.inline(child, source, cursor);
+            let content_end = child
+                .children()
+                .and_then(|c| c.last())
+                .and_then(|c| c.position())
+                .map_or(*cursor, |p| p.end.offset);
+            self.copy(source, *cursor, content_end);
+            self.bbcode.push_str(&format!("[/{}]", tag));
+            *cursor = end;
+        }
+    }
+}
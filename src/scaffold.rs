@@ -0,0 +1,129 @@
+// scaffold.rs
+//
+// Skeleton documents for `doke new`: one example statement per field of a
+// builder config, written with the grammar's own phrases so the result
+// parses and builds as is.
+
+use std::collections::{BTreeSet, HashMap};
+
+use crate::file_builder::{FieldType, ResourceBuilder};
+use crate::parsers::TypedSentencesParser;
+use crate::parsers::param_types::example_value;
+use crate::parsers::sentence::{FORMAT_KEY, PhraseConfig, ReturnSpec};
+
+/// How deep example parameters are expanded with other phrases
+const MAX_EXAMPLE_DEPTH: usize = 4;
+
+/// Write a markdown skeleton for the resource `builder` builds.
+///
+/// The frontmatter has the document `title` and every key the grammar's
+/// format strings read. Each field gets a comment naming it and its type,
+/// followed by an example statement. Fields are marked by comments rather
+/// than headings, since headings would be statements the grammar can't read.
+/// Optional fields have their example commented out.
+pub fn new_document(
+    builder: &ResourceBuilder,
+    grammar: &TypedSentencesParser,
+    title: &str,
+) -> String {
+    let phrases: Vec<(&str, &PhraseConfig)> = grammar
+        .enabled_rules()
+        .flat_map(|rule| {
+            rule.sentence_parser
+                .phrases
+                .iter()
+                .map(move |phrase| (rule.target_type.as_str(), phrase))
+        })
+        .collect();
+
+    let mut out = String::from("---\n");
+    out.push_str(&format!("title: {}\n", title));
+    for key in frontmatter_keys(&phrases) {
+        if key != "title" {
+            out.push_str(&format!("{}: \n", key));
+        }
+    }
+    out.push_str("---\n");
+
+    for field in &builder.config().children {
+        let (ty, count) = match &field.ty {
+            FieldType::Single(ty) => (ty, ""),
+            FieldType::Array(ty) => (ty, ", one or more"),
+        };
+        let optional = if field.optional { " (optional)" } else { "" };
+        out.push_str(&format!(
+            "\n<!-- {}{}: {}{} -->\n",
+            field.name, optional, ty, count
+        ));
+
+        match example(&phrases, ty, 0) {
+            Some(statement) if field.optional => out.push_str(&format!("<!-- {} -->\n", statement)),
+            Some(statement) => out.push_str(&format!("{}\n", statement)),
+            None => out.push_str(&format!(
+                "<!-- no phrase of the grammar produces {} -->\n",
+                ty
+            )),
+        }
+    }
+    out
+}
+
+/// "my_sword" → "My Sword"
+pub fn title_from_file_stem(stem: &str) -> String {
+    stem.split(['_', '-', ' '])
+        .filter(|word| !word.is_empty())
+        .map(|word| {
+            let mut chars = word.chars();
+            match chars.next() {
+                Some(first) => first.to_uppercase().chain(chars).collect(),
+                None => String::new(),
+            }
+        })
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+/// The first phrase producing `ty`, spelled with example parameters
+fn example(phrases: &[(&str, &PhraseConfig)], ty: &str, depth: usize) -> Option<String> {
+    if depth > MAX_EXAMPLE_DEPTH {
+        return None;
+    }
+    let phrase = phrases
+        .iter()
+        .find(|(_, phrase)| {
+            phrase.section == ty || matches!(&phrase.return_spec, ReturnSpec::Type(t) if t == ty)
+        })
+        .or_else(|| phrases.iter().find(|(target, _)| *target == ty))
+        .map(|(_, phrase)| *phrase)?;
+
+    let mut values = HashMap::new();
+    for param in &phrase.parameters {
+        let value = match param.param_type.eq_ignore_ascii_case("price") {
+            true => Some(phrase.currency.spell(350)),
+            false => example_value(&param.param_type).map(|example| match &param.bounds {
+                Some(bounds) => bounds.example(example),
+                None => example.to_string(),
+            }),
+        }
+        .or_else(|| example(phrases, &param.param_type, depth + 1))
+        .unwrap_or_else(|| format!("<{}>", param.name));
+        values.insert(param.name.clone(), value);
+    }
+    Some(phrase.spell(&values))
+}
+
+/// Frontmatter keys read by format strings, other than the phrase's own parameters
+fn frontmatter_keys(phrases: &[(&str, &PhraseConfig)]) -> BTreeSet<String> {
+    let mut keys = BTreeSet::new();
+    for (_, phrase) in phrases {
+        if let ReturnSpec::Format(fmt) = &phrase.return_spec {
+            for cap in FORMAT_KEY.captures_iter(fmt) {
+                let key = cap[1].trim();
+                if !phrase.parameters.iter().any(|p| p.name == key) {
+                    keys.insert(key.to_string());
+                }
+            }
+        }
+    }
+    keys
+}
@@ -1,11 +1,12 @@
-use markdown::mdast::Node;
-use std::any::Any;
 use std::collections::HashMap;
 use std::error::Error;
 use std::fmt::{self, Debug};
 use thiserror::Error;
 
+use crate::Statement;
 use crate::base_parser::Position;
+use crate::diagnostics::Diagnostic;
+use crate::extensions::Extensions;
 
 // ----------------- GodotValue -----------------
 
@@ -14,6 +15,8 @@ pub enum GodotValue {
     Nil,
     Bool(bool),
     Int(i64),
+    /// Unsigned values that don't fit in an `Int`
+    UInt(u64),
     Float(f64),
     String(String),
     Array(Vec<GodotValue>),
@@ -23,6 +26,12 @@ pub enum GodotValue {
         abstract_type_name: String,
         fields: HashMap<String, GodotValue>,
     },
+    /// `Vector2(x, y)`
+    Vector2(f64, f64),
+    /// `Rect2(x, y, width, height)`
+    Rect2(f64, f64, f64, f64),
+    /// `Color(r, g, b, a)`, components from 0 to 1
+    Color(f64, f64, f64, f64),
 }
 
 impl fmt::Display for GodotValue {
@@ -31,6 +40,7 @@ impl fmt::Display for GodotValue {
             GodotValue::Nil => write!(f, "Nil"),
             GodotValue::Bool(b) => write!(f, "{}", b),
             GodotValue::Int(i) => write!(f, "{}", i),
+            GodotValue::UInt(u) => write!(f, "{}", u),
             GodotValue::Float(fl) => write!(f, "{}", fl),
             GodotValue::String(s) => write!(f, "\"{}\"", s),
             GodotValue::Array(arr) => {
@@ -38,10 +48,12 @@ impl fmt::Display for GodotValue {
                 write!(f, "[{}]", elements.join(", "))
             }
             GodotValue::Dict(dict) => {
-                let entries: Vec<String> = dict
+                // sorted, so the same value always prints the same
+                let mut entries: Vec<String> = dict
                     .iter()
                     .map(|(k, v)| format!("\"{}\": {}", k, v))
                     .collect();
+                entries.sort();
                 write!(f, "{{{}}}", entries.join(", "))
             }
             GodotValue::Resource {
@@ -49,43 +61,312 @@ impl fmt::Display for GodotValue {
                 fields,
                 abstract_type_name: _,
             } => {
-                let entries: Vec<String> = fields
+                let mut entries: Vec<String> = fields
                     .iter()
                     .map(|(k, v)| format!("\"{}\": {}", k, v))
                     .collect();
+                entries.sort();
                 write!(f, "{} {{ {} }}", type_name, entries.join(", "))
             }
+            GodotValue::Vector2(x, y) => write!(f, "Vector2({}, {})", x, y),
+            GodotValue::Rect2(x, y, w, h) => write!(f, "Rect2({}, {}, {}, {})", x, y, w, h),
+            GodotValue::Color(r, g, b, a) => write!(f, "Color({}, {}, {}, {})", r, g, b, a),
+        }
+    }
+}
+
+/// How `GodotValue::merge` resolves keys present on both sides.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeStrategy {
+    /// Values from the other side win. Dicts and resources are merged
+    /// recursively, arrays are replaced.
+    Override,
+    /// Like `Override`, but arrays are concatenated.
+    Append,
+    /// Existing values win, the other side only fills in missing keys.
+    KeepExisting,
+}
+
+impl GodotValue {
+    /// The components of a `Vector2` (`x`, `y`), a `Rect2` (and `width`, `height`)
+    /// or a `Color` (`r`, `g`, `b`, `a`), for formats without these types
+    pub fn components(&self) -> Option<HashMap<String, GodotValue>> {
+        let components: &[(&str, f64)] = match self {
+            GodotValue::Vector2(x, y) => &[("x", *x), ("y", *y)],
+            GodotValue::Rect2(x, y, w, h) => &[("x", *x), ("y", *y), ("width", *w), ("height", *h)],
+            GodotValue::Color(r, g, b, a) => &[("r", *r), ("g", *g), ("b", *b), ("a", *a)],
+            _ => return None,
+        };
+        Some(
+            components
+                .iter()
+                .map(|(name, value)| (name.to_string(), GodotValue::Float(*value)))
+                .collect(),
+        )
+    }
+
+    /// Get a nested value by a `/` separated path of dict keys,
+    /// resource fields and array indices.
+    /// ```
+    /// use doke::GodotValue;
+    /// use std::collections::HashMap;
+    ///
+    /// let stats = GodotValue::Dict(HashMap::from([("defense".to_string(), GodotValue::Int(3))]));
+    /// let item = GodotValue::Dict(HashMap::from([("stats".to_string(), stats)]));
+    /// assert_eq!(item.get_path("stats/defense"), Some(&GodotValue::Int(3)));
+    /// ```
+    pub fn get_path(&self, path: &str) -> Option<&GodotValue> {
+        path_segments(path).try_fold(self, |value, segment| value.get_segment(segment))
+    }
+
+    /// Like `get_path`, to change the value in place.
+    /// ```
+    /// use doke::GodotValue;
+    ///
+    /// let mut rewards = GodotValue::Array(vec![GodotValue::Int(10)]);
+    /// if let Some(GodotValue::Int(gold)) = rewards.get_path_mut("0") {
+    ///     *gold *= 2;
+    /// }
+    /// assert_eq!(rewards.get_path("0"), Some(&GodotValue::Int(20)));
+    /// assert!(rewards.get_path_mut("1").is_none());
+    /// ```
+    pub fn get_path_mut(&mut self, path: &str) -> Option<&mut GodotValue> {
+        path_segments(path).try_fold(self, |value, segment| value.get_segment_mut(segment))
+    }
+
+    /// Set a nested value, creating intermediate dicts for missing keys.
+    /// ```
+    /// use doke::GodotValue;
+    /// use std::collections::HashMap;
+    ///
+    /// let mut item = GodotValue::Dict(HashMap::new());
+    /// item.set_path("stats/defense", GodotValue::Int(3)).unwrap();
+    /// assert_eq!(item.get_path("stats/defense"), Some(&GodotValue::Int(3)));
+    ///
+    /// item.set_path("tags", GodotValue::Array(vec![GodotValue::String("blade".into())])).unwrap();
+    /// item.set_path("tags/0", GodotValue::String("axe".into())).unwrap();
+    /// assert_eq!(item.get_path("tags/0"), Some(&GodotValue::String("axe".into())));
+    ///
+    /// let error = item.set_path("tags/1", GodotValue::Nil).unwrap_err();
+    /// assert!(error.to_string().contains("'1' is not a valid index for an array of 1"));
+    /// let error = item.set_path("stats/defense/base", GodotValue::Int(1)).unwrap_err();
+    /// assert!(error.to_string().contains("can't index into a"));
+    /// ```
+    pub fn set_path(&mut self, path: &str, value: GodotValue) -> Result<(), GodotValueError> {
+        let segments: Vec<&str> = path_segments(path).collect();
+        let Some((last, parents)) = segments.split_last() else {
+            *self = value;
+            return Ok(());
+        };
+
+        let mut current = self;
+        for segment in parents {
+            current = match current {
+                GodotValue::Dict(map) | GodotValue::Resource { fields: map, .. } => map
+                    .entry(segment.to_string())
+                    .or_insert_with(|| GodotValue::Dict(HashMap::new())),
+                GodotValue::Array(items) => {
+                    let index = parse_index(segment, items.len(), path)?;
+                    &mut items[index]
+                }
+                other => {
+                    return Err(GodotValueError::InvalidPath(
+                        path.to_string(),
+                        format!("can't index into a {}", other.kind()),
+                    ));
+                }
+            };
+        }
+
+        match current {
+            GodotValue::Dict(map) | GodotValue::Resource { fields: map, .. } => {
+                map.insert(last.to_string(), value);
+                Ok(())
+            }
+            GodotValue::Array(items) => {
+                let index = parse_index(last, items.len(), path)?;
+                items[index] = value;
+                Ok(())
+            }
+            other => Err(GodotValueError::InvalidPath(
+                path.to_string(),
+                format!("can't index into a {}", other.kind()),
+            )),
+        }
+    }
+
+    /// Merge `other` into this value, e.g. to apply a balance patch or a mod override.
+    /// ```
+    /// use doke::{GodotValue, MergeStrategy};
+    ///
+    /// let sword = |type_name: &str, fields: Vec<(&str, GodotValue)>| GodotValue::Resource {
+    ///     type_name: type_name.into(),
+    ///     abstract_type_name: "Item".into(),
+    ///     fields: fields.into_iter().map(|(k, v)| (k.to_string(), v)).collect(),
+    /// };
+    /// let tags = |tags: &[&str]| GodotValue::Array(tags.iter().map(|t| GodotValue::String(t.to_string())).collect());
+    /// let base = sword("Sword", vec![("damage", GodotValue::Int(5)), ("tags", tags(&["blade"]))]);
+    /// let patch = || sword("FireSword", vec![("tags", tags(&["fire"])), ("burn", GodotValue::Int(2))]);
+    ///
+    /// let mut overridden = base.clone();
+    /// overridden.merge(patch(), MergeStrategy::Override);
+    /// assert_eq!(overridden.get_path("tags"), Some(&tags(&["fire"])));
+    /// assert_eq!(overridden.get_path("damage"), Some(&GodotValue::Int(5)));
+    ///
+    /// let mut appended = base.clone();
+    /// appended.merge(patch(), MergeStrategy::Append);
+    /// assert_eq!(appended.get_path("tags"), Some(&tags(&["blade", "fire"])));
+    ///
+    /// let mut kept = base.clone();
+    /// kept.merge(patch(), MergeStrategy::KeepExisting);
+    /// assert_eq!(kept.get_path("tags"), Some(&tags(&["blade"])));
+    /// assert_eq!(kept.get_path("burn"), Some(&GodotValue::Int(2)));
+    /// assert!(matches!(&kept, GodotValue::Resource { type_name, .. } if type_name == "Sword"));
+    /// assert!(matches!(&appended, GodotValue::Resource { type_name, .. } if type_name == "FireSword"));
+    /// ```
+    pub fn merge(&mut self, other: GodotValue, strategy: MergeStrategy) {
+        match (self, other) {
+            (
+                GodotValue::Dict(mine) | GodotValue::Resource { fields: mine, .. },
+                GodotValue::Dict(theirs),
+            ) => merge_maps(mine, theirs, strategy),
+            (
+                GodotValue::Resource {
+                    type_name,
+                    abstract_type_name,
+                    fields: mine,
+                },
+                GodotValue::Resource {
+                    type_name: other_type,
+                    abstract_type_name: other_abstract,
+                    fields: theirs,
+                },
+            ) => {
+                if strategy != MergeStrategy::KeepExisting {
+                    *type_name = other_type;
+                    *abstract_type_name = other_abstract;
+                }
+                merge_maps(mine, theirs, strategy);
+            }
+            (GodotValue::Array(mine), GodotValue::Array(theirs))
+                if strategy == MergeStrategy::Append =>
+            {
+                mine.extend(theirs);
+            }
+            (_, _) if strategy == MergeStrategy::KeepExisting => {}
+            (this, other) => *this = other,
+        }
+    }
+
+    fn get_segment(&self, segment: &str) -> Option<&GodotValue> {
+        match self {
+            GodotValue::Dict(map) | GodotValue::Resource { fields: map, .. } => map.get(segment),
+            GodotValue::Array(items) => segment.parse::<usize>().ok().and_then(|i| items.get(i)),
+            _ => None,
+        }
+    }
+
+    fn get_segment_mut(&mut self, segment: &str) -> Option<&mut GodotValue> {
+        match self {
+            GodotValue::Dict(map) | GodotValue::Resource { fields: map, .. } => {
+                map.get_mut(segment)
+            }
+            GodotValue::Array(items) => {
+                segment.parse::<usize>().ok().and_then(|i| items.get_mut(i))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn path_segments(path: &str) -> impl Iterator<Item = &str> {
+    path.split('/').filter(|segment| !segment.is_empty())
+}
+
+fn parse_index(segment: &str, len: usize, path: &str) -> Result<usize, GodotValueError> {
+    match segment.parse::<usize>() {
+        Ok(i) if i < len => Ok(i),
+        _ => Err(GodotValueError::InvalidPath(
+            path.to_string(),
+            format!("'{}' is not a valid index for an array of {}", segment, len),
+        )),
+    }
+}
+
+fn merge_maps(
+    mine: &mut HashMap<String, GodotValue>,
+    theirs: HashMap<String, GodotValue>,
+    strategy: MergeStrategy,
+) {
+    for (key, value) in theirs {
+        match mine.get_mut(&key) {
+            Some(existing) => existing.merge(value, strategy),
+            None => {
+                mine.insert(key, value);
+            }
         }
     }
 }
 
 // ----------------- Traits -----------------
 
-pub trait Hypo: std::fmt::Debug {
+pub trait Hypo: std::fmt::Debug + Send {
     fn kind(&self) -> &'static str;
     fn confidence(&self) -> f32 {
         1.0
     }
-    fn promote(self: Box<Self>) -> Result<Box<dyn DokeOut>, Box<dyn Error>>;
+    fn promote(self: Box<Self>) -> Result<Box<dyn DokeOut>, Box<dyn Error + Send + Sync>>;
 }
 
 /// Trait for things that can convert to_godot and potentially use_child
-pub trait DokeOut: std::fmt::Debug {
+pub trait DokeOut: std::fmt::Debug + Send {
     fn kind(&self) -> &'static str;
     fn to_godot(&self) -> GodotValue;
     fn get_asbtract_type(&self) -> Option<String> {
         None
     }
-    fn use_child(&mut self, _child: GodotValue) -> Result<(), Box<dyn Error>> {
+    fn use_child(&mut self, _child: GodotValue) -> Result<(), Box<dyn Error + Send + Sync>> {
         Ok(())
     }
-    fn use_constituent(&mut self, _name: &str, _value: GodotValue) -> Result<(), Box<dyn Error>> {
+    fn use_constituent(
+        &mut self,
+        _name: &str,
+        _value: GodotValue,
+    ) -> Result<(), Box<dyn Error + Send + Sync>> {
         Ok(())
     }
 }
 
 // ----------------- DokeNode -----------------
 
+/// The markdown block a statement was written as, so parsers don't have to guess it
+/// from the text. AsciiDoc sections, listings and list items map to the same kinds.
+/// ```
+/// use doke::{DokePipe, semantic::BlockKind};
+///
+/// let doc = DokePipe::new().run_markdown("## Effects\n\n- Deals 5 damage\n\n```lua\nreturn 1\n```");
+/// assert_eq!(doc.nodes[0].block, BlockKind::Heading { depth: 2 });
+/// assert_eq!(doc.nodes[0].children[0].block, BlockKind::ListItem);
+/// assert_eq!(doc.nodes[1].block, BlockKind::Code { language: Some("lua".into()) });
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum BlockKind {
+    Paragraph,
+    /// `#` is depth 1
+    Heading {
+        depth: u8,
+    },
+    /// A fenced or indented code block, with the language of its fence
+    Code {
+        language: Option<String>,
+    },
+    /// The first paragraph of a list item, the others are its children
+    ListItem,
+    /// Not written as a block: constituents, nodes parsers made, plain text and spreadsheet rows
+    #[default]
+    Other,
+}
+
 /// The semantic tree that parsers operate on.
 /// It is made from statements parsed by the Doke base parser
 ///
@@ -98,7 +379,7 @@ pub struct DokeNode {
     ///
     /// Parsers can edit this to move some data to parse_data
     /// while preventing other parsers to have to deal with the syntax.
-    pub statement: String,
+    pub statement: Statement,
     /// The state of the node : UnResolved, Resolved(result),
     /// Hypothesis(potential results), or Error(err)
     pub state: DokeNodeState,
@@ -106,12 +387,20 @@ pub struct DokeNode {
     pub children: Vec<DokeNode>,
     /// A bucket of Godot-Compatible data that parsers can populate and read from.
     pub parse_data: HashMap<String, GodotValue>,
+    /// Typed data of the parsers, for themselves. Never exported, unlike `parse_data`.
+    pub extensions: Extensions,
     /// The constituent parts of the statement, if it takes some and a parser broke it like that.
     pub constituents: HashMap<String, DokeNode>,
-    /// The position of the original statement in the source string
-    /// For constituents as of now, it is the position of the whole statement.
+    /// The position of the original statement in the source string.
+    /// For constituents, the position of the parameter they were written as, or of the whole
+    /// statement when normalization rewrote it before parsing.
     /// Only used for error reporting
     pub span: Position,
+    /// Warnings and errors reported by parsers about this node.
+    /// Error diagnostics make validation fail even if the node resolved.
+    pub diagnostics: Vec<Diagnostic>,
+    /// The markdown block the statement was written as
+    pub block: BlockKind,
 }
 
 /// The state of an unparsed, parsed, maybe parsed, or definitely wrong statement.
@@ -136,7 +425,23 @@ pub enum DokeNodeState {
     /// A parser erroring on a node because it is not formed like what he parses
     /// Can choose to push a negative confidence Hypothesis that resolves to
     /// an Error.
-    Error(Box<dyn Error>),
+    Error(Box<dyn Error + Send + Sync>),
+    /// The statement is deliberately not semantic (a decorative separator, an author's
+    /// aside), and why. Validation leaves it out with its children, instead of failing.
+    /// Further parsers should ignore the node.
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser, semantic::DokeNodeState};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let pipe = DokePipe::new().add(parser);
+    /// let mut doc = pipe.run_markdown("- Deals 5 damage\n- Deals 3 damage <!-- doke-skip: too strong -->");
+    /// assert!(matches!(&doc.nodes[1].state, DokeNodeState::Skipped(reason) if reason == "too strong"));
+    /// assert_eq!(doc.validate_spanned().unwrap().len(), 1);
+    /// ```
+    Skipped(String),
 }
 
 // ----------------- Parsers -----------------
@@ -144,9 +449,57 @@ pub enum DokeNodeState {
 /// Updated trait: parsers now get a reference to frontmatter
 pub trait DokeParser: Debug + Send + Sync {
     fn process(&self, node: &mut DokeNode, frontmatter: &HashMap<String, GodotValue>);
+
+    /// Name of the parser in metrics, its type name by default
+    fn name(&self) -> &'static str {
+        let name = std::any::type_name::<Self>();
+        let path = name.split('<').next().unwrap_or(name);
+        path.rsplit("::").next().unwrap_or(path)
+    }
+
+    /// Process the top-level nodes of a document, in order.
+    ///
+    /// Parsers that need to see several statements at once (graphs, cross references)
+    /// can override this, and may regroup nodes. Defaults to `process` on each node.
+    fn process_document(
+        &self,
+        nodes: &mut Vec<DokeNode>,
+        frontmatter: &HashMap<String, GodotValue>,
+    ) {
+        for node in nodes.iter_mut() {
+            self.process(node, frontmatter);
+        }
+    }
+
+    /// `parse_data` keys this parser annotates nodes with, like `"abstract_type"`
+    fn provides(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// `parse_data` keys this parser reads, that a parser before it must provide,
+    /// see `DokePipe::verify`
+    fn requires(&self) -> &[&'static str] {
+        &[]
+    }
+
+    /// Frontmatter keys whose values are statements, with the type they must build,
+    /// see `DokePipe::with_frontmatter_statements`
+    fn frontmatter_statements(&self) -> &[(String, Option<String>)] {
+        &[]
+    }
 }
 // ----------------- Error Types -----------------
 
+/// A pipe whose parsers can't work together
+#[derive(Debug, Error)]
+pub enum PipeError {
+    #[error("{parser} requires the '{annotation}' annotation, which no parser before it provides")]
+    MissingPrerequisite {
+        parser: &'static str,
+        annotation: &'static str,
+    },
+}
+
 #[derive(Debug, Error)]
 pub enum DokeValidationError {
     #[error("Validation error at node: {0} : {1}")]
@@ -156,15 +509,17 @@ pub enum DokeValidationError {
     #[error("Invalid field type for '{0}' in resource '{1}': expected {2}, got {3}")]
     InvalidFieldType(String, String, String, String),
     #[error("(Promoted Err) {0} - position {1}")]
-    HypothesisPromotionFailed(#[source] Box<dyn Error>, Position),
+    HypothesisPromotionFailed(#[source] Box<dyn Error + Send + Sync>, Position),
     #[error("Unresolved node: {0}")]
     UnresolvedNode(String),
+    #[error("Not confident enough in node: {0} : {1} is under {2}")]
+    LowConfidence(String, f32, f32),
     #[error("Multiple errors occurred during validation: {0}")]
     MultipleErrors(#[from] DokeErrors),
     #[error("Failed to use child: {0}")]
-    ChildUsageFailed(#[source] Box<dyn Error>),
+    ChildUsageFailed(#[source] Box<dyn Error + Send + Sync>),
     #[error("Dynamic Error")]
-    DynamicError(#[from] Box<dyn std::error::Error>),
+    DynamicError(#[from] Box<dyn std::error::Error + Send + Sync>),
 }
 
 // Wrapper struct for multiple errors
@@ -179,7 +534,7 @@ impl From<Vec<DokeValidationError>> for DokeErrors {
 
 impl fmt::Display for DokeErrors {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        writeln!(f, "")?;
+        writeln!(f)?;
         for (i, error) in self.0.iter().enumerate() {
             writeln!(f, "  {}. {}", i + 1, error)?;
         }
@@ -188,18 +543,18 @@ impl fmt::Display for DokeErrors {
 }
 
 #[derive(Debug)]
-struct ErrorHypo<Er: Error> {
+struct ErrorHypo<Er: Error + Send + Sync> {
     error: Er,
 }
 
-impl<Er: Error + 'static> Hypo for ErrorHypo<Er> {
+impl<Er: Error + Send + Sync + 'static> Hypo for ErrorHypo<Er> {
     fn kind(&self) -> &'static str {
         "Error"
     }
     fn confidence(&self) -> f32 {
         -1.
     }
-    fn promote(self: Box<Self>) -> Result<Box<dyn DokeOut>, Box<dyn Error>> {
+    fn promote(self: Box<Self>) -> Result<Box<dyn DokeOut>, Box<dyn Error + Send + Sync>> {
         Err(Box::new(self.error))
     }
 }
@@ -208,6 +563,8 @@ impl<Er: Error + 'static> Hypo for ErrorHypo<Er> {
 pub enum GodotValueError {
     #[error("Tried to add a child to a {0}")]
     InvalidChild(String),
+    #[error("Invalid path '{0}': {1}")]
+    InvalidPath(String, String),
 }
 
 impl DokeOut for GodotValue {
@@ -216,6 +573,7 @@ impl DokeOut for GodotValue {
             GodotValue::Nil => "Null",
             GodotValue::Bool(_) => "Bool",
             GodotValue::Int(_) => "Int",
+            GodotValue::UInt(_) => "UInt",
             GodotValue::Float(_) => "Float",
             GodotValue::String(_) => "String",
             GodotValue::Array(_) => "Array",
@@ -225,18 +583,25 @@ impl DokeOut for GodotValue {
                 fields: _,
                 abstract_type_name: _,
             } => "Resource",
+            GodotValue::Vector2(..) => "Vector2",
+            GodotValue::Rect2(..) => "Rect2",
+            GodotValue::Color(..) => "Color",
         }
     }
     fn to_godot(&self) -> GodotValue {
         self.clone()
     }
-    fn use_child(&mut self, _child: GodotValue) -> Result<(), Box<dyn Error>> {
+    fn use_child(&mut self, _child: GodotValue) -> Result<(), Box<dyn Error + Send + Sync>> {
         match self {
             GodotValue::Nil
             | GodotValue::Bool(_)
             | GodotValue::Int(_)
+            | GodotValue::UInt(_)
             | GodotValue::Float(_)
-            | GodotValue::String(_) => Err(Box::new(GodotValueError::InvalidChild(
+            | GodotValue::String(_)
+            | GodotValue::Vector2(..)
+            | GodotValue::Rect2(..)
+            | GodotValue::Color(..) => Err(Box::new(GodotValueError::InvalidChild(
                 self.kind().to_owned(),
             ))),
             GodotValue::Array(v) => {
@@ -270,15 +635,38 @@ impl DokeOut for GodotValue {
     }
 }
 
+/// `parse_data` key of statements whose children are built sorted by type, then text,
+/// whatever order they're written in. Set by rules with `ordered: false`.
+pub const UNORDERED_CHILDREN_KEY: &str = "doke_unordered_children";
+
+/// The resource type of a value, or its kind
+fn sort_type(value: &GodotValue) -> &str {
+    match value {
+        GodotValue::Resource { type_name, .. } => type_name,
+        value => value.kind(),
+    }
+}
+
 // ----------------- DokeValidate Parser -----------------
 
 pub struct DokeValidate {
     errors: Vec<DokeValidationError>,
+    /// Hypotheses less confident aren't promoted, see `Strictness`
+    min_confidence: Option<f32>,
+}
+
+impl Default for DokeValidate {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl DokeValidate {
     pub fn new() -> Self {
-        Self { errors: Vec::new() }
+        Self {
+            errors: Vec::new(),
+            min_confidence: None,
+        }
     }
 
     pub fn validate_tree(
@@ -286,16 +674,14 @@ impl DokeValidate {
         frontmatter: &HashMap<String, GodotValue>,
     ) -> Result<Vec<GodotValue>, DokeValidationError> {
         let mut validator = Self::new();
-        let results: Vec<Result<GodotValue, DokeValidationError>> = root_nodes
-            .iter_mut()
-            .map(|n| validator.process_node(n, frontmatter))
-            .collect();
+        let results = Self::validate_roots(root_nodes, frontmatter, None);
 
-        // Flatten results
+        // Flatten results, skipped nodes have none
         let mut ok_values = Vec::new();
         for r in results {
             match r {
-                Ok(v) => ok_values.push(v),
+                Ok(Some(v)) => ok_values.push(v),
+                Ok(None) => {}
                 Err(e) => validator.errors.push(e),
             }
         }
@@ -311,30 +697,78 @@ impl DokeValidate {
         }
     }
 
+    /// The value of each root node, `None` when skipped, or why it has none. Hypotheses
+    /// less confident than `min_confidence` aren't promoted, unless they are errors
+    /// (negative confidence)
+    pub(crate) fn validate_roots(
+        root_nodes: &mut [DokeNode],
+        frontmatter: &HashMap<String, GodotValue>,
+        min_confidence: Option<f32>,
+    ) -> Vec<Result<Option<GodotValue>, DokeValidationError>> {
+        let mut validator = Self {
+            min_confidence,
+            ..Self::new()
+        };
+        root_nodes
+            .iter_mut()
+            .map(|n| validator.process_node(n, frontmatter))
+            .collect()
+    }
+
+    /// The value of `node`, `None` when it's skipped
+    #[allow(clippy::only_used_in_recursion)]
     fn process_node(
         &mut self,
         node: &mut DokeNode,
         frontmatter: &HashMap<String, GodotValue>,
-    ) -> Result<GodotValue, DokeValidationError> {
+    ) -> Result<Option<GodotValue>, DokeValidationError> {
+        if let DokeNodeState::Skipped(_) = node.state {
+            return Ok(None);
+        }
         let mut child_values = Vec::new();
+        let mut skipped_children = Vec::new();
         let mut constituent_values: HashMap<String, GodotValue> = HashMap::new();
-        for child in &mut node.children {
+        for (i, child) in node.children.iter_mut().enumerate() {
             match self.process_node(child, frontmatter) {
-                Ok(v) => child_values.push(v),
+                Ok(Some(v)) => child_values.push(v),
+                Ok(None) => skipped_children.push(i),
                 Err(e) => return Err(e),
             };
         }
         for (name, constituent) in &mut node.constituents {
             match self.process_node(constituent, frontmatter) {
-                Ok(v) => constituent_values.insert(name.into(), v),
+                Ok(Some(v)) => constituent_values.insert(name.into(), v),
+                Ok(None) => None,
                 Err(e) => return Err(e),
             };
         }
+        // so output diffs don't depend on authoring order
+        if let Some(GodotValue::Bool(true)) = node.parse_data.get(UNORDERED_CHILDREN_KEY) {
+            let texts = node
+                .children
+                .iter()
+                .enumerate()
+                .filter(|(i, _)| !skipped_children.contains(i))
+                .map(|(_, c)| c.statement.to_string());
+            let mut sorted: Vec<(GodotValue, String)> =
+                child_values.into_iter().zip(texts).collect();
+            sorted.sort_by(|(a, a_text), (b, b_text)| {
+                (sort_type(a), a_text).cmp(&(sort_type(b), b_text))
+            });
+            child_values = sorted.into_iter().map(|(value, _)| value).collect();
+        }
 
-        match &mut node.state {
-            DokeNodeState::Unresolved => {
-                Err(DokeValidationError::UnresolvedNode(node.statement.clone()))
-            }
+        if let Some(diagnostic) = node.diagnostics.iter().find(|d| d.is_error()) {
+            return Err(DokeValidationError::NodeError(
+                node.statement.to_string(),
+                diagnostic.to_string(),
+            ));
+        }
+
+        let value = match &mut node.state {
+            DokeNodeState::Unresolved => Err(DokeValidationError::UnresolvedNode(
+                node.statement.to_string(),
+            )),
             DokeNodeState::Hypothesis(hypotheses) => {
                 let best_index = hypotheses
                     .iter()
@@ -347,6 +781,16 @@ impl DokeValidate {
                     .map(|(i, _)| i);
 
                 if let Some(best_index) = best_index {
+                    let confidence = hypotheses[best_index].confidence();
+                    if let Some(min) = self.min_confidence
+                        && (0.0..min).contains(&confidence)
+                    {
+                        return Err(DokeValidationError::LowConfidence(
+                            node.statement.to_string(),
+                            confidence,
+                            min,
+                        ));
+                    }
                     let hypo = hypotheses.remove(best_index);
                     let mut resolved = hypo.promote().map_err(|e| {
                         DokeValidationError::HypothesisPromotionFailed(e, node.span.clone())
@@ -368,7 +812,9 @@ impl DokeValidate {
                         unreachable!()
                     }
                 } else {
-                    Err(DokeValidationError::UnresolvedNode(node.statement.clone()))
+                    Err(DokeValidationError::UnresolvedNode(
+                        node.statement.to_string(),
+                    ))
                 }
             }
             DokeNodeState::Resolved(resolved) => {
@@ -383,9 +829,11 @@ impl DokeValidate {
                 Ok(resolved.to_godot())
             }
             DokeNodeState::Error(e) => Err(DokeValidationError::NodeError(
-                node.statement.clone(),
+                node.statement.to_string(),
                 format!("{}", e),
             )),
-        }
+            DokeNodeState::Skipped(_) => unreachable!("skipped nodes have no value"),
+        };
+        value.map(Some)
     }
 }
@@ -0,0 +1,227 @@
+// serialize.rs
+//
+// `Serialize` for parse results, so tools outside Rust (a Python content pipeline,
+// a web editor) can read a whole document: frontmatter, the node tree with spans,
+// states and diagnostics, and the values statements resolved to.
+//
+//     {"frontmatter": {...}, "nodes": [{"statement": "Deals 5 damage",
+//       "span": {"start": 2, "end": 16}, "block": {"kind": "list_item"}, "state": {"state": "resolved",
+//       "kind": "Damage", "value": {...}}, "children": [...], ...}], ...}
+//
+// Values have the layout of `export::json`: resources are objects with their fields
+// plus `type` and `abstract_type`, vectors and colors objects of their components.
+// Keys are sorted so the output diffs well. Hypotheses and errors are trait objects,
+// they're written as their kind, confidence and message.
+
+use std::collections::{BTreeMap, HashMap};
+
+use serde::ser::{Serialize, SerializeMap, SerializeStruct, Serializer};
+
+use crate::diagnostics::{Diagnostic, Severity};
+use crate::semantic::{BlockKind, DokeNodeState};
+use crate::{DokeDocument, DokeNode, GodotValue, Position, Statement};
+
+impl DokeDocument {
+    /// The document as JSON: frontmatter, nodes with their spans, states and values,
+    /// and diagnostics. Values are written like `export::json::to_json`
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Damage:
+    ///   - "Deals {damage: int} damage"
+    /// "#).unwrap();
+    /// let doc = DokePipe::new().add(parser).run_markdown("- Deals 5 damage");
+    /// let json = doc.to_json();
+    /// assert!(json.contains(r#""statement":"Deals 5 damage""#));
+    /// assert!(json.contains(r#""damage":5"#));
+    /// ```
+    pub fn to_json(&self) -> String {
+        serde_json::to_string(self).expect("documents serialize to JSON")
+    }
+
+    /// Like `to_json`, indented
+    pub fn to_json_pretty(&self) -> String {
+        serde_json::to_string_pretty(self).expect("documents serialize to JSON")
+    }
+}
+
+impl Serialize for DokeDocument {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut document = serializer.serialize_struct("DokeDocument", 4)?;
+        document.serialize_field("frontmatter", &Sorted(&self.frontmatter))?;
+        document.serialize_field("nodes", &self.nodes)?;
+        document.serialize_field("diagnostics", &self.diagnostics)?;
+        document.serialize_field("suppressed", &self.suppressed)?;
+        document.end()
+    }
+}
+
+impl Serialize for DokeNode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut node = serializer.serialize_struct("DokeNode", 8)?;
+        node.serialize_field("statement", &self.statement)?;
+        node.serialize_field("span", &self.span)?;
+        node.serialize_field("block", &self.block)?;
+        node.serialize_field("state", &self.state)?;
+        node.serialize_field("parse_data", &Sorted(&self.parse_data))?;
+        let constituents: BTreeMap<_, _> = self.constituents.iter().collect();
+        node.serialize_field("constituents", &constituents)?;
+        node.serialize_field("diagnostics", &self.diagnostics)?;
+        node.serialize_field("children", &self.children)?;
+        node.end()
+    }
+}
+
+/// `{"state": "resolved", "kind": .., "value": ..}`, `{"state": "hypothesis", "hypotheses":
+/// [{"kind": .., "confidence": ..}]}`, `{"state": "error", "message": ..}`,
+/// `{"state": "skipped", "reason": ..}` or `{"state": "unresolved"}`
+impl Serialize for DokeNodeState {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_map(None)?;
+        match self {
+            DokeNodeState::Unresolved => state.serialize_entry("state", "unresolved")?,
+            DokeNodeState::Hypothesis(hypotheses) => {
+                state.serialize_entry("state", "hypothesis")?;
+                let hypotheses: Vec<_> = hypotheses
+                    .iter()
+                    .map(|h| HypothesisSummary {
+                        kind: h.kind(),
+                        confidence: h.confidence(),
+                    })
+                    .collect();
+                state.serialize_entry("hypotheses", &hypotheses)?;
+            }
+            DokeNodeState::Resolved(out) => {
+                state.serialize_entry("state", "resolved")?;
+                state.serialize_entry("kind", out.kind())?;
+                state.serialize_entry("value", &out.to_godot())?;
+            }
+            DokeNodeState::Error(error) => {
+                state.serialize_entry("state", "error")?;
+                state.serialize_entry("message", &error.to_string())?;
+            }
+            DokeNodeState::Skipped(reason) => {
+                state.serialize_entry("state", "skipped")?;
+                state.serialize_entry("reason", reason)?;
+            }
+        }
+        state.end()
+    }
+}
+
+/// `{"kind": "heading", "depth": 2}`, `{"kind": "code", "language": "lua"}`, `{"kind": "paragraph"}`...
+impl Serialize for BlockKind {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut block = serializer.serialize_map(None)?;
+        match self {
+            BlockKind::Paragraph => block.serialize_entry("kind", "paragraph")?,
+            BlockKind::Heading { depth } => {
+                block.serialize_entry("kind", "heading")?;
+                block.serialize_entry("depth", depth)?;
+            }
+            BlockKind::Code { language } => {
+                block.serialize_entry("kind", "code")?;
+                block.serialize_entry("language", language)?;
+            }
+            BlockKind::ListItem => block.serialize_entry("kind", "list_item")?,
+            BlockKind::Other => block.serialize_entry("kind", "other")?,
+        }
+        block.end()
+    }
+}
+
+struct HypothesisSummary {
+    kind: &'static str,
+    confidence: f32,
+}
+
+impl Serialize for HypothesisSummary {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut hypothesis = serializer.serialize_struct("Hypothesis", 2)?;
+        hypothesis.serialize_field("kind", self.kind)?;
+        hypothesis.serialize_field("confidence", &self.confidence)?;
+        hypothesis.end()
+    }
+}
+
+/// In the layout of `export::json::to_json`
+impl Serialize for GodotValue {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            GodotValue::Nil => serializer.serialize_unit(),
+            GodotValue::Bool(b) => serializer.serialize_bool(*b),
+            GodotValue::Int(i) => serializer.serialize_i64(*i),
+            GodotValue::UInt(u) => serializer.serialize_u64(*u),
+            // JSON has no NaN or infinities
+            GodotValue::Float(f) if !f.is_finite() => serializer.serialize_unit(),
+            GodotValue::Float(f) => serializer.serialize_f64(*f),
+            GodotValue::String(s) => serializer.serialize_str(s),
+            GodotValue::Array(items) => items.serialize(serializer),
+            GodotValue::Dict(fields) => Sorted(fields).serialize(serializer),
+            GodotValue::Resource {
+                type_name,
+                abstract_type_name,
+                fields,
+            } => {
+                let mut resource = serializer.serialize_map(Some(fields.len() + 2))?;
+                resource.serialize_entry("abstract_type", abstract_type_name)?;
+                resource.serialize_entry("type", type_name)?;
+                for (name, value) in Sorted(fields).entries() {
+                    resource.serialize_entry(name, value)?;
+                }
+                resource.end()
+            }
+            GodotValue::Vector2(..) | GodotValue::Rect2(..) | GodotValue::Color(..) => {
+                Sorted(&self.components().unwrap_or_default()).serialize(serializer)
+            }
+        }
+    }
+}
+
+impl Serialize for Position {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut position = serializer.serialize_struct("Position", 2)?;
+        position.serialize_field("start", &self.start)?;
+        position.serialize_field("end", &self.end)?;
+        position.end()
+    }
+}
+
+impl Serialize for Statement {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl Serialize for Severity {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+impl Serialize for Diagnostic {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut diagnostic = serializer.serialize_struct("Diagnostic", 4)?;
+        diagnostic.serialize_field("severity", &self.severity)?;
+        diagnostic.serialize_field("code", self.code)?;
+        diagnostic.serialize_field("message", &self.message)?;
+        diagnostic.serialize_field("span", &self.span)?;
+        diagnostic.end()
+    }
+}
+
+/// A map of values, keys sorted
+struct Sorted<'a>(&'a HashMap<String, GodotValue>);
+
+impl<'a> Sorted<'a> {
+    fn entries(&self) -> BTreeMap<&'a String, &'a GodotValue> {
+        self.0.iter().collect()
+    }
+}
+
+impl Serialize for Sorted<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_map(self.entries())
+    }
+}
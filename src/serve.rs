@@ -0,0 +1,263 @@
+// serve.rs
+//
+// `doke serve`: validation over HTTP, for web front-ends (a CMS used by remote
+// writers) that can't run doke locally. Every request is answered from one
+// shared pipe, on a pool of worker threads.
+//
+//     POST /validate   body: a document  -> values and diagnostics
+//     GET  /grammar                      -> every phrase of the grammar
+//     POST /complete   body: a statement -> phrases it may be the start of
+//     GET  /metrics                      -> Prometheus metrics, see `with_metrics`
+//
+// `Service::handle` doesn't know about HTTP, so services already running a web
+// framework can mount it on their own routes.
+
+use std::fmt::Write;
+use std::io::{self, Read};
+use std::sync::Arc;
+use std::thread;
+
+use crate::diagnostics::Diagnostic;
+use crate::export::json::{json_string, to_json};
+#[cfg(feature = "prometheus")]
+use crate::metrics::PrometheusSink;
+use crate::parsers::{PhraseOrigin, TypedSentencesParser};
+use crate::workspace::Workspace;
+use crate::{SharedPipe, extract_frontmatter};
+
+/// Documents larger than this are refused
+const MAX_BODY: usize = 1 << 20;
+
+/// An answer to a request
+#[derive(Debug, Clone, PartialEq)]
+pub struct Response {
+    pub status: u16,
+    /// JSON, but for `/metrics`
+    pub content_type: &'static str,
+    pub body: String,
+}
+
+impl Response {
+    fn json(body: String) -> Self {
+        Self {
+            status: 200,
+            content_type: "application/json",
+            body,
+        }
+    }
+
+    fn error(status: u16, message: &str) -> Self {
+        Self {
+            status,
+            ..Self::json(format!("{{\"error\":{}}}", json_string(message)))
+        }
+    }
+}
+
+/// Validation, grammar docs and completions for one grammar
+#[derive(Debug, Clone)]
+pub struct Service {
+    pipe: SharedPipe,
+    grammar: Arc<TypedSentencesParser>,
+    #[cfg(feature = "prometheus")]
+    metrics: Option<Arc<PrometheusSink>>,
+}
+
+impl Service {
+    /// `pipe` validates documents, `grammar` (usually one of its parsers)
+    /// answers `/grammar` and `/complete`
+    pub fn new(pipe: impl Into<SharedPipe>, grammar: TypedSentencesParser) -> Self {
+        Self {
+            pipe: pipe.into(),
+            grammar: Arc::new(grammar),
+            #[cfg(feature = "prometheus")]
+            metrics: None,
+        }
+    }
+
+    /// Answer `/metrics` with what `sink` collected, give it to the pipe too
+    /// (`DokePipe::with_metrics`) for parsing metrics
+    #[cfg(feature = "prometheus")]
+    pub fn with_metrics(mut self, sink: Arc<PrometheusSink>) -> Self {
+        self.metrics = Some(sink);
+        self
+    }
+
+    /// Answer a request, `path` may have a query string.
+    /// ```
+    /// use std::path::Path;
+    /// use doke::{DokePipe, parsers::TypedSentencesParser, serve::Service};
+    ///
+    /// let grammar = TypedSentencesParser::from_config("rules: []", Path::new(".")).unwrap();
+    /// let service = Service::new(DokePipe::new().add(grammar.clone()), grammar);
+    /// let response = service.handle("POST", "/validate", "Deals 5 damage");
+    /// assert_eq!(response.status, 200);
+    /// assert!(response.body.starts_with("{\"valid\":false,"));
+    /// assert_eq!(service.handle("GET", "/nothing", "").status, 404);
+    /// ```
+    pub fn handle(&self, method: &str, path: &str, body: &str) -> Response {
+        let path = path.split('?').next().unwrap_or_default();
+        match (method, path) {
+            ("POST", "/validate") => self.validate(body),
+            ("GET", "/grammar") => self.grammar(),
+            ("POST", "/complete") => self.complete(body),
+            #[cfg(feature = "prometheus")]
+            ("GET", "/metrics") if self.metrics.is_some() => Response {
+                status: 200,
+                content_type: "text/plain; version=0.0.4",
+                body: self.metrics.as_ref().unwrap().render(),
+            },
+            (_, "/validate" | "/grammar" | "/complete") => {
+                Response::error(405, "method not allowed")
+            }
+            _ => Response::error(404, "not found"),
+        }
+    }
+
+    /// Listen on `address` (`127.0.0.1:8080`) until the process ends
+    pub fn serve(self, address: &str) -> io::Result<()> {
+        let server = tiny_http::Server::http(address).map_err(io::Error::other)?;
+        let server = Arc::new(server);
+        let workers = thread::available_parallelism().map_or(4, |n| n.get());
+        let handles: Vec<_> = (0..workers)
+            .map(|_| {
+                let (server, service) = (server.clone(), self.clone());
+                thread::spawn(move || {
+                    for request in server.incoming_requests() {
+                        service.respond(request);
+                    }
+                })
+            })
+            .collect();
+        for handle in handles {
+            let _ = handle.join();
+        }
+        Ok(())
+    }
+
+    fn respond(&self, mut request: tiny_http::Request) {
+        let response = match request.method() {
+            // CORS preflight of browsers on another origin
+            tiny_http::Method::Options => Response {
+                status: 204,
+                ..Response::json(String::new())
+            },
+            method => {
+                let method = method.to_string();
+                let mut body = String::new();
+                let read = request
+                    .as_reader()
+                    .take(MAX_BODY as u64 + 1)
+                    .read_to_string(&mut body);
+                match read {
+                    Err(_) => Response::error(400, "the body isn't UTF-8"),
+                    Ok(len) if len > MAX_BODY => Response::error(413, "document too large"),
+                    Ok(_) => self.handle(&method, request.url(), &body),
+                }
+            }
+        };
+        let header = |name: &str, value: &str| {
+            tiny_http::Header::from_bytes(name.as_bytes(), value.as_bytes()).unwrap()
+        };
+        let http = tiny_http::Response::from_string(response.body.as_str())
+            .with_status_code(response.status)
+            .with_header(header("Content-Type", response.content_type))
+            .with_header(header("Access-Control-Allow-Origin", "*"))
+            .with_header(header("Access-Control-Allow-Methods", "GET, POST, OPTIONS"))
+            .with_header(header("Access-Control-Allow-Headers", "Content-Type"));
+        // the client may be gone already
+        let _ = request.respond(http);
+    }
+
+    /// `{"valid": bool, "values": [...], "diagnostics": [{severity, code, message, line}]}`
+    fn validate(&self, source: &str) -> Response {
+        let mut workspace = Workspace::new();
+        workspace.add_document("document", source.to_string(), &self.pipe);
+        let doc = &workspace.documents()[0];
+
+        let mut diagnostics: Vec<(usize, &Diagnostic)> = doc
+            .document
+            .collect_diagnostics()
+            .into_iter()
+            .map(|d| (d.span.as_ref().map_or(1, |span| doc.line(span)), d))
+            .collect();
+        let invalid;
+        let values = match &doc.values {
+            Ok(values) => values.iter().map(|(value, _)| to_json(value)).collect(),
+            Err(e) => {
+                invalid = Diagnostic::error("invalid-document", e.to_string());
+                diagnostics.push((1, &invalid));
+                Vec::new()
+            }
+        };
+
+        let mut body = format!(
+            "{{\"valid\":{},\"values\":[{}],\"diagnostics\":[",
+            doc.values.is_ok(),
+            values.join(",")
+        );
+        for (i, (line, diagnostic)) in diagnostics.iter().enumerate() {
+            if i > 0 {
+                body.push(',');
+            }
+            write!(
+                body,
+                "{{\"severity\":\"{}\",\"code\":{},\"message\":{},\"line\":{}}}",
+                diagnostic.severity,
+                json_string(diagnostic.code),
+                json_string(&diagnostic.message),
+                line
+            )
+            .unwrap();
+        }
+        body.push_str("]}");
+        Response::json(body)
+    }
+
+    /// `{"fingerprint": "...", "phrases": [{type, section, pattern, layer}]}`
+    fn grammar(&self) -> Response {
+        let phrases = phrases_json(&self.grammar.phrase_origins());
+        Response::json(format!(
+            "{{\"fingerprint\":{},\"phrases\":{}}}",
+            json_string(&self.grammar.fingerprint()),
+            phrases
+        ))
+    }
+
+    /// `{"phrases": [...]}`, those the statement being typed may become
+    fn complete(&self, text: &str) -> Response {
+        let (_, statement) = extract_frontmatter(text);
+        let phrases: Vec<PhraseOrigin> = self
+            .grammar
+            .phrase_origins()
+            .into_iter()
+            .filter(|origin| self.grammar.layer_of_phrase(&origin.pattern) == Some(&origin.layer))
+            .filter(|origin| completes(&origin.pattern, statement))
+            .collect();
+        Response::json(format!("{{\"phrases\":{}}}", phrases_json(&phrases)))
+    }
+}
+
+/// Whether `statement` may be the start of a sentence of `pattern`: one is a
+/// prefix of the other, up to the first parameter, ignoring case
+fn completes(pattern: &str, statement: &str) -> bool {
+    let head = pattern.split('{').next().unwrap_or_default().to_lowercase();
+    let statement = statement.trim_start().to_lowercase();
+    head.starts_with(&statement) || (!head.is_empty() && statement.starts_with(&head))
+}
+
+fn phrases_json(phrases: &[PhraseOrigin]) -> String {
+    let items: Vec<String> = phrases
+        .iter()
+        .map(|origin| {
+            format!(
+                "{{\"type\":{},\"section\":{},\"pattern\":{},\"layer\":{}}}",
+                json_string(&origin.target_type),
+                json_string(&origin.section),
+                json_string(&origin.pattern),
+                json_string(&origin.layer)
+            )
+        })
+        .collect();
+    format!("[{}]", items.join(","))
+}
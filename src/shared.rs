@@ -0,0 +1,66 @@
+// shared.rs
+//
+// One configured pipe shared by the worker threads of a validation service.
+// Every method of `DokePipe` takes `&self`, so sharing is an `Arc` away, this
+// just spares callers the wrapping and checks the pipe stays thread safe.
+
+use std::ops::Deref;
+use std::sync::Arc;
+
+use crate::{DokeDocument, DokePipe};
+
+// Parsers, input formats and options must all stay `Send + Sync`, and what they
+// produce `Send`, for documents parsed on worker threads
+const _: fn() = || {
+    fn thread_safe<T: Send + Sync>() {}
+    fn sendable<T: Send>() {}
+    thread_safe::<DokePipe>();
+    sendable::<DokeDocument>();
+};
+
+/// A cheaply cloned handle on a `DokePipe`, to validate from many threads.
+/// ```
+/// use doke::{DokePipe, parsers::SentenceParser};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser).shared();
+/// let workers: Vec<_> = (0..4)
+///     .map(|i| {
+///         let pipe = pipe.clone();
+///         std::thread::spawn(move || pipe.validate(&format!("Deals {} damage", i)).is_ok())
+///     })
+///     .collect();
+/// assert!(workers.into_iter().all(|w| w.join().unwrap()));
+/// ```
+#[derive(Debug, Clone)]
+pub struct SharedPipe(Arc<DokePipe>);
+
+impl DokePipe {
+    /// Freeze the pipe into a handle that can be cloned across threads
+    pub fn shared(self) -> SharedPipe {
+        SharedPipe(Arc::new(self))
+    }
+}
+
+impl From<DokePipe> for SharedPipe {
+    fn from(pipe: DokePipe) -> Self {
+        pipe.shared()
+    }
+}
+
+impl From<Arc<DokePipe>> for SharedPipe {
+    fn from(pipe: Arc<DokePipe>) -> Self {
+        SharedPipe(pipe)
+    }
+}
+
+impl Deref for SharedPipe {
+    type Target = DokePipe;
+
+    fn deref(&self) -> &DokePipe {
+        &self.0
+    }
+}
@@ -0,0 +1,231 @@
+// spreadsheet.rs
+//
+// Reading spreadsheet rows as documents, so numbers maintained in sheets go
+// through the same grammar, validation and builder as markdown documents.
+//
+// A mapping config says which column names the document of a row, which
+// columns go to the frontmatter, and the statements written for each row,
+// with `{Column}` standing for the cell:
+//
+//     id: Name
+//     frontmatter: {title: Name}
+//     statements:
+//       - "Deals {Damage} damage to {Target}"
+//       - "Costs {Mana} mana"
+//
+// A statement is left out of a row when one of its cells is empty, and rows
+// with the same id are statements of the same document.
+
+use std::path::Path;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use thiserror::Error;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::export::json::json_string;
+
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{([^{}]+)\}").unwrap());
+
+#[derive(Debug, Error)]
+pub enum SpreadsheetError {
+    #[error("IO error: {0}")]
+    Io(#[from] std::io::Error),
+
+    #[error("CSV error: {0}")]
+    Csv(#[from] csv::Error),
+
+    #[error("YAML error: {0}")]
+    Yaml(String),
+
+    #[error("Invalid mapping: {0}")]
+    Mapping(String),
+
+    #[error("The sheet has no column {0}")]
+    MissingColumn(String),
+}
+
+/// Which columns make the documents of a sheet
+#[derive(Debug, Clone, Default)]
+pub struct CsvMapping {
+    /// Column naming the document of each row
+    pub id: String,
+    /// (frontmatter key, column)
+    pub frontmatter: Vec<(String, String)>,
+    /// Statement templates, `{Column}` standing for the cell
+    pub statements: Vec<String>,
+}
+
+/// A document made of the rows of a sheet sharing an id
+#[derive(Debug, Clone, PartialEq)]
+pub struct SheetDocument {
+    pub id: String,
+    /// The rows written as markdown, to run through a `DokePipe`
+    pub source: String,
+    /// 1-based line in the sheet of each line of `source`
+    pub lines: Vec<usize>,
+}
+
+impl CsvMapping {
+    pub fn from_file(path: &Path) -> Result<Self, SpreadsheetError> {
+        Self::from_yaml(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn from_yaml(yaml: &str) -> Result<Self, SpreadsheetError> {
+        let docs =
+            YamlLoader::load_from_str(yaml).map_err(|e| SpreadsheetError::Yaml(e.to_string()))?;
+        let y = docs.into_iter().next().unwrap_or(Yaml::Null);
+        let id = y["id"]
+            .as_str()
+            .ok_or_else(|| SpreadsheetError::Mapping("missing 'id' column".into()))?
+            .to_string();
+        let frontmatter = match &y["frontmatter"] {
+            Yaml::Hash(h) => h
+                .iter()
+                .map(|(k, v)| match (k.as_str(), v.as_str()) {
+                    (Some(k), Some(v)) => Ok((k.to_string(), v.to_string())),
+                    _ => Err(SpreadsheetError::Mapping(
+                        "'frontmatter' maps keys to column names".into(),
+                    )),
+                })
+                .collect::<Result<_, _>>()?,
+            Yaml::BadValue | Yaml::Null => Vec::new(),
+            _ => {
+                return Err(SpreadsheetError::Mapping(
+                    "'frontmatter' must be a mapping".into(),
+                ));
+            }
+        };
+        let statements = match &y["statements"] {
+            Yaml::Array(a) => a
+                .iter()
+                .map(|s| {
+                    s.as_str().map(str::to_string).ok_or_else(|| {
+                        SpreadsheetError::Mapping("statements must be strings".into())
+                    })
+                })
+                .collect::<Result<_, _>>()?,
+            _ => {
+                return Err(SpreadsheetError::Mapping(
+                    "'statements' must be a list".into(),
+                ));
+            }
+        };
+        Ok(Self {
+            id,
+            frontmatter,
+            statements,
+        })
+    }
+
+    /// Every column the mapping reads
+    fn columns(&self) -> impl Iterator<Item = &str> {
+        std::iter::once(self.id.as_str())
+            .chain(self.frontmatter.iter().map(|(_, column)| column.as_str()))
+            .chain(self.statements.iter().flat_map(|s| {
+                PLACEHOLDER
+                    .captures_iter(s)
+                    .map(|c| c.get(1).unwrap().as_str().trim())
+            }))
+    }
+
+    /// The documents of a CSV sheet, in the order of their first row.
+    ///
+    /// The frontmatter comes from the first row of each document.
+    /// ```
+    /// use doke::spreadsheet::{CsvMapping, SpreadsheetError};
+    ///
+    /// let mapping = CsvMapping::from_yaml(
+    ///     "id: Name\nfrontmatter: {title: Name}\nstatements:\n  - \"Deals {Damage} damage\"\n  - \"Costs {Mana} mana\"\n",
+    /// )
+    /// .unwrap();
+    /// let sheet = "Name,Damage,Mana\nFire Sword,12,3\nFire Sword,4,\nIce Wand,,5\n";
+    /// let documents = mapping.read_csv(sheet).unwrap();
+    ///
+    /// assert_eq!(documents.len(), 2);
+    /// assert_eq!(
+    ///     documents[0].source,
+    ///     "---\ntitle: \"Fire Sword\"\n---\n\nDeals 12 damage\n\nCosts 3 mana\n\nDeals 4 damage\n\n"
+    /// );
+    /// assert_eq!(documents[0].lines[documents[0].lines.len() - 1], 3);
+    /// assert_eq!(documents[1].source, "---\ntitle: \"Ice Wand\"\n---\n\nCosts 5 mana\n\n");
+    ///
+    /// let missing = mapping.read_csv("Name,Damage\nFire Sword,12\n");
+    /// assert!(matches!(missing, Err(SpreadsheetError::MissingColumn(c)) if c == "Mana"));
+    /// ```
+    pub fn read_csv(&self, text: &str) -> Result<Vec<SheetDocument>, SpreadsheetError> {
+        let mut reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(text.as_bytes());
+        let headers: Vec<String> = reader
+            .headers()?
+            .iter()
+            .map(|h| h.trim().to_string())
+            .collect();
+        if let Some(missing) = self.columns().find(|c| !headers.iter().any(|h| h == c)) {
+            return Err(SpreadsheetError::MissingColumn(missing.to_string()));
+        }
+        let column = |name: &str| headers.iter().position(|h| h == name.trim());
+
+        let mut documents: Vec<SheetDocument> = Vec::new();
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |p| p.line() as usize);
+            let cell = |name: &str| {
+                column(name)
+                    .and_then(|i| record.get(i))
+                    .map(|c| c.trim().replace(['\n', '\r'], " "))
+                    .unwrap_or_default()
+            };
+            let id = cell(&self.id);
+            if id.is_empty() {
+                continue;
+            }
+
+            let index = match documents.iter().position(|d| d.id == id) {
+                Some(i) => i,
+                None => {
+                    let mut source = String::new();
+                    if !self.frontmatter.is_empty() {
+                        source.push_str("---\n");
+                        for (key, column) in &self.frontmatter {
+                            source.push_str(&format!("{}: {}\n", key, yaml_scalar(&cell(column))));
+                        }
+                        source.push_str("---\n\n");
+                    }
+                    let lines = vec![line; source.matches('\n').count()];
+                    documents.push(SheetDocument {
+                        id: id.clone(),
+                        source,
+                        lines,
+                    });
+                    documents.len() - 1
+                }
+            };
+            let document = &mut documents[index];
+            for template in &self.statements {
+                let mut complete = true;
+                let statement = PLACEHOLDER.replace_all(template, |c: &regex::Captures| {
+                    let value = cell(&c[1]);
+                    complete &= !value.is_empty();
+                    value
+                });
+                if complete {
+                    document.source.push_str(&statement);
+                    document.source.push_str("\n\n");
+                    document.lines.extend([line, line]);
+                }
+            }
+        }
+        Ok(documents)
+    }
+}
+
+/// Numbers and bools as they are, anything else quoted
+fn yaml_scalar(cell: &str) -> String {
+    if cell.parse::<f64>().is_ok() || cell == "true" || cell == "false" {
+        cell.to_string()
+    } else {
+        json_string(cell)
+    }
+}
@@ -0,0 +1,170 @@
+// statement.rs
+//
+// The text of a `DokeNode`. Statements read from a document are slices of one
+// shared copy of its body instead of a string each, so a large document isn't
+// copied again for its statements, their constituents and the hypotheses
+// parsers keep about them. Cloning a statement only bumps a reference count.
+//
+// Parsers that rewrite a statement assign a new one (`node.statement = text.into()`),
+// which then owns its text.
+
+use std::borrow::Borrow;
+use std::fmt;
+use std::hash::{Hash, Hasher};
+use std::ops::{Deref, Range};
+use std::sync::Arc;
+
+/// Text of a statement, derefs to `str`
+#[derive(Clone, Default)]
+pub struct Statement {
+    source: Arc<str>,
+    range: Range<usize>,
+}
+
+impl Statement {
+    /// A statement owning `text`
+    pub fn new(text: &str) -> Self {
+        Self {
+            source: Arc::from(text),
+            range: 0..text.len(),
+        }
+    }
+
+    /// The part of `source` in `range`, sharing it. Empty if `range` isn't
+    /// in `source` or doesn't fall on character boundaries.
+    pub fn shared(source: &Arc<str>, range: Range<usize>) -> Self {
+        match source.get(range.clone()) {
+            Some(_) => Self {
+                source: source.clone(),
+                range,
+            },
+            None => Self::default(),
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.source[self.range.clone()]
+    }
+
+    /// `part` as a statement sharing this one's text when `part` is a slice of
+    /// it (as `trim` or regex captures return), a copy otherwise
+    /// ```
+    /// use doke::Statement;
+    ///
+    /// let statement = Statement::new("  Deals 5 damage ");
+    /// let trimmed = statement.slice_of(statement.trim());
+    /// assert_eq!(trimmed, "Deals 5 damage");
+    /// assert!(trimmed.shares_text(&statement));
+    /// ```
+    pub fn slice_of(&self, part: &str) -> Self {
+        let text = self.as_str();
+        let offset = (part.as_ptr() as usize).wrapping_sub(text.as_ptr() as usize);
+        match offset <= text.len() && part.len() <= text.len() - offset {
+            true => {
+                let start = self.range.start + offset;
+                Self {
+                    source: self.source.clone(),
+                    range: start..start + part.len(),
+                }
+            }
+            false => Self::new(part),
+        }
+    }
+
+    /// Whether both statements are slices of the same text
+    pub fn shares_text(&self, other: &Statement) -> bool {
+        Arc::ptr_eq(&self.source, &other.source)
+    }
+}
+
+impl Deref for Statement {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for Statement {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl Borrow<str> for Statement {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl fmt::Debug for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl fmt::Display for Statement {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fmt::Display::fmt(self.as_str(), f)
+    }
+}
+
+impl PartialEq for Statement {
+    fn eq(&self, other: &Self) -> bool {
+        self.as_str() == other.as_str()
+    }
+}
+
+impl Eq for Statement {}
+
+impl Hash for Statement {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl PartialEq<str> for Statement {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for Statement {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl PartialEq<String> for Statement {
+    fn eq(&self, other: &String) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl From<&str> for Statement {
+    fn from(text: &str) -> Self {
+        Self::new(text)
+    }
+}
+
+impl From<String> for Statement {
+    fn from(text: String) -> Self {
+        let range = 0..text.len();
+        Self {
+            source: Arc::from(text),
+            range,
+        }
+    }
+}
+
+impl From<std::borrow::Cow<'_, str>> for Statement {
+    fn from(text: std::borrow::Cow<'_, str>) -> Self {
+        Self::new(&text)
+    }
+}
+
+impl From<Statement> for String {
+    fn from(statement: Statement) -> Self {
+        statement.as_str().to_string()
+    }
+}
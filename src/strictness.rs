@@ -0,0 +1,100 @@
+// strictness.rs
+//
+// How much of a document must parse for it to validate. Data files should fail on
+// the first statement the grammar doesn't know, but design notes mixing effects and
+// prose are still useful when most of their statements resolve:
+//
+//     let notes = DokePipe::new()
+//         .add(parser)
+//         .with_strictness(Strictness::default().with_min_resolved(0.95).with_min_confidence(0.5));
+//
+// Below the fraction, validation fails as usual. Above it, the values of the
+// statements that resolved are returned, and the others are left out with a
+// warning. A document can set its own knobs in its frontmatter:
+//
+//     ---
+//     min_resolved: 80%
+//     min_confidence: 0.3
+//     ---
+
+use std::collections::HashMap;
+
+use crate::GodotValue;
+
+/// Frontmatter key overriding `Strictness::min_resolved` for a document,
+/// a fraction (`0.95`) or a percentage (`95%`)
+pub const MIN_RESOLVED_KEY: &str = "min_resolved";
+
+/// Frontmatter key overriding `Strictness::min_confidence` for a document
+pub const MIN_CONFIDENCE_KEY: &str = "min_confidence";
+
+/// Diagnostic code of statements left out of a document's values
+pub const DROPPED_CODE: &str = "statement-dropped";
+
+/// What validating a document requires, every statement resolving by default
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Strictness {
+    /// Fraction of the top-level statements that must resolve, from 0 to 1
+    pub min_resolved: f64,
+    /// Hypotheses less confident aren't promoted, and their statement doesn't resolve
+    pub min_confidence: Option<f32>,
+}
+
+impl Default for Strictness {
+    fn default() -> Self {
+        Self {
+            min_resolved: 1.0,
+            min_confidence: None,
+        }
+    }
+}
+
+impl Strictness {
+    pub fn with_min_resolved(mut self, fraction: f64) -> Self {
+        self.min_resolved = fraction.clamp(0.0, 1.0);
+        self
+    }
+
+    pub fn with_min_confidence(mut self, confidence: f32) -> Self {
+        self.min_confidence = Some(confidence);
+        self
+    }
+
+    /// These settings, overridden by the document's frontmatter
+    pub fn for_document(&self, frontmatter: &HashMap<String, GodotValue>) -> Self {
+        let mut strictness = *self;
+        if let Some(fraction) = frontmatter.get(MIN_RESOLVED_KEY).and_then(fraction) {
+            strictness = strictness.with_min_resolved(fraction);
+        }
+        if let Some(confidence) = frontmatter.get(MIN_CONFIDENCE_KEY).and_then(number) {
+            strictness = strictness.with_min_confidence(confidence as f32);
+        }
+        strictness
+    }
+
+    /// Whether `resolved` statements out of `total` are enough
+    pub(crate) fn accepts(&self, resolved: usize, total: usize) -> bool {
+        total == 0 || resolved as f64 / total as f64 >= self.min_resolved
+    }
+}
+
+fn number(value: &GodotValue) -> Option<f64> {
+    match value {
+        GodotValue::Float(f) => Some(*f),
+        GodotValue::Int(i) => Some(*i as f64),
+        GodotValue::UInt(u) => Some(*u as f64),
+        _ => None,
+    }
+}
+
+/// `0.95`, `95%` or `"95%"`
+fn fraction(value: &GodotValue) -> Option<f64> {
+    match value {
+        GodotValue::String(s) => s
+            .trim()
+            .strip_suffix('%')
+            .and_then(|percent| percent.trim().parse::<f64>().ok())
+            .map(|percent| percent / 100.0),
+        value => number(value),
+    }
+}
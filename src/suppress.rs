@@ -0,0 +1,205 @@
+// suppress.rs
+//
+// `doke-allow` directives, silencing diagnostic codes where they are expected
+// (a prose section, a statement kept on purpose) instead of loosening checks
+// for the whole project:
+//
+//     ---
+//     doke_allow: [workspace-unresolved-ref]     # the whole document
+//     ---
+//     <!-- doke-allow: unresolved -->            # the statement below
+//     Some lore nobody should try to parse
+//
+//     - Deals 5 damage <!-- doke-allow: numeric-overflow -->   # this statement
+//
+// Directives are read before parsers run, and applied after: silenced
+// diagnostics move to `DokeDocument::suppressed`, and statements no parser
+// recognized are skipped when `unresolved` is allowed.
+//
+// `doke-skip` marks a statement as not meant to be parsed, whatever it says,
+// with an optional reason. It is left out of the values with its children:
+//
+//     <!-- doke-skip: designer note -->
+//     Deals 5 damage was too much in playtests
+
+use std::collections::HashSet;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::diagnostics::Diagnostic;
+use crate::semantic::DokeNodeState;
+use crate::{DokeDocument, DokeNode, GodotValue};
+
+/// Frontmatter and `parse_data` key holding the allowed codes
+pub const ALLOW_KEY: &str = "doke_allow";
+
+/// Code allowing statements that no parser recognizes
+pub const UNRESOLVED_CODE: &str = "unresolved";
+
+static DIRECTIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!--\s*doke-allow:\s*(.*?)\s*-->").unwrap());
+
+static SKIP_DIRECTIVE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"<!--\s*doke-skip(?::\s*(.*?))?\s*-->").unwrap());
+
+/// Codes of a directive, separated by commas or spaces
+fn codes(list: &str) -> impl Iterator<Item = GodotValue> + '_ {
+    list.split([',', ' '])
+        .filter(|code| !code.is_empty())
+        .map(|code| GodotValue::String(code.to_string()))
+}
+
+/// Record the directives written in statements or on the line before them,
+/// `body` being the text the spans point into
+pub(crate) fn read_directives(nodes: &mut [DokeNode], body: &str) {
+    for node in nodes {
+        let mut allowed = Vec::new();
+        let line = line_before(body, node.span.start);
+        if let Some(line) = line
+            && let Some(caps) = DIRECTIVE.captures(line)
+            && caps.get(0).is_some_and(|m| m.as_str() == line)
+        {
+            allowed.extend(codes(&caps[1]));
+        }
+        let skip = line
+            .and_then(|line| {
+                SKIP_DIRECTIVE
+                    .captures(line)
+                    .filter(|caps| &caps[0] == line)
+            })
+            .or_else(|| SKIP_DIRECTIVE.captures(&node.statement))
+            .map(|caps| match caps.get(1) {
+                Some(reason) if !reason.as_str().is_empty() => reason.as_str().to_string(),
+                _ => "doke-skip directive".to_string(),
+            });
+        if let Some(reason) = skip {
+            node.state = DokeNodeState::Skipped(reason);
+            node.statement = SKIP_DIRECTIVE
+                .replace_all(&node.statement, "")
+                .trim()
+                .into();
+        }
+        if DIRECTIVE.is_match(&node.statement) {
+            for caps in DIRECTIVE.captures_iter(&node.statement) {
+                allowed.extend(codes(&caps[1]));
+            }
+            node.statement = DIRECTIVE.replace_all(&node.statement, "").trim().into();
+        }
+        if !allowed.is_empty() {
+            node.parse_data
+                .insert(ALLOW_KEY.into(), GodotValue::Array(allowed));
+        }
+        read_directives(&mut node.children, body);
+    }
+}
+
+/// The line before the one at `offset`, when only a list marker or a quote
+/// separates them
+fn line_before(body: &str, offset: usize) -> Option<&str> {
+    let before = body.get(..offset)?;
+    let line_start = before.rfind('\n')?;
+    let marker = before[line_start + 1..].trim();
+    let is_marker = marker.is_empty()
+        || marker
+            .trim_end_matches(['.', ')'])
+            .chars()
+            .all(|c| c.is_ascii_digit())
+        || matches!(marker, "-" | "*" | "+" | ">");
+    if !is_marker {
+        return None;
+    }
+    let previous = before[..line_start].trim_end_matches('\r');
+    let line = previous.rsplit('\n').next()?.trim();
+    (!line.is_empty()).then_some(line)
+}
+
+/// Move silenced diagnostics to `doc.suppressed`, and skip the unresolved
+/// statements that are allowed
+/// ```
+/// use doke::DokePipe;
+/// use doke::parsers::SentenceParser;
+///
+/// let grammar = "Effect:\n  - \"Deals {damage: int} damage\"\n";
+/// let pipe = DokePipe::new().add(SentenceParser::from_yaml("Effect".into(), grammar).unwrap());
+///
+/// let lore = "Some lore nobody should parse\n\nDeals 5 damage\n";
+/// assert!(pipe.validate(lore).is_err());
+/// let allowed = format!("<!-- doke-allow: unresolved -->\n{}", lore);
+/// assert_eq!(pipe.validate(&allowed).unwrap().len(), 1);
+///
+/// let doc = pipe.run_markdown(
+///     "Deals 99999999999999999999999 damage <!-- doke-allow: invalid-parameter -->\n",
+/// );
+/// assert!(doc.collect_diagnostics().is_empty());
+/// assert_eq!(doc.suppressed[0].code, "invalid-parameter");
+/// ```
+pub(crate) fn apply(doc: &mut DokeDocument) {
+    let file = allowed(doc.frontmatter.get(ALLOW_KEY));
+    let (kept, silenced) = doc
+        .diagnostics
+        .drain(..)
+        .partition(|d| !file.contains(d.code));
+    doc.diagnostics = kept;
+    doc.suppressed.extend(silenced);
+    apply_nodes(&mut doc.nodes, &file, &mut doc.suppressed);
+}
+
+/// An allowed unresolved statement is skipped, and its children, which often
+/// aren't its own (a list right after a paragraph), follow it
+fn apply_nodes(nodes: &mut Vec<DokeNode>, inherited: &HashSet<String>, out: &mut Vec<Diagnostic>) {
+    let mut kept = Vec::with_capacity(nodes.len());
+    for mut node in nodes.drain(..) {
+        let mut allowed = allowed(node.parse_data.get(ALLOW_KEY));
+        allowed.extend(inherited.iter().cloned());
+        if unrecognized(&node) && allowed.contains(UNRESOLVED_CODE) {
+            out.push(
+                Diagnostic::warning(
+                    UNRESOLVED_CODE,
+                    format!("'{}' isn't recognized, left out", node.statement),
+                )
+                .with_span(node.span.clone()),
+            );
+            out.append(&mut node.diagnostics);
+            apply_nodes(&mut node.children, &allowed, out);
+            let mut children = std::mem::take(&mut node.children);
+            node.state = DokeNodeState::Skipped(format!("{} is allowed", UNRESOLVED_CODE));
+            kept.push(node);
+            kept.append(&mut children);
+            continue;
+        }
+        let (diagnostics, silenced) = node
+            .diagnostics
+            .drain(..)
+            .partition(|d| !allowed.contains(d.code));
+        node.diagnostics = diagnostics;
+        out.extend(silenced);
+        apply_nodes(&mut node.children, &allowed, out);
+        kept.push(node);
+    }
+    *nodes = kept;
+}
+
+/// No parser recognized the statement, some may have ruled it out
+pub(crate) fn unrecognized(node: &DokeNode) -> bool {
+    match &node.state {
+        DokeNodeState::Unresolved => true,
+        DokeNodeState::Hypothesis(hypotheses) => hypotheses.iter().all(|h| h.confidence() < 0.0),
+        _ => false,
+    }
+}
+
+/// Codes of a `doke_allow` value, a list or a single code
+pub(crate) fn allowed(value: Option<&GodotValue>) -> HashSet<String> {
+    match value {
+        Some(GodotValue::String(code)) => HashSet::from([code.clone()]),
+        Some(GodotValue::Array(codes)) => codes
+            .iter()
+            .filter_map(|code| match code {
+                GodotValue::String(code) => Some(code.clone()),
+                _ => None,
+            })
+            .collect(),
+        _ => HashSet::new(),
+    }
+}
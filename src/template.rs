@@ -0,0 +1,262 @@
+// template.rs
+//
+// Template documents: a family of similar documents written once, with the
+// parameters they vary in declared in the frontmatter,
+//
+//     ---
+//     template_params: [element, tier]
+//     id: "{element}_sword_{tier}"
+//     ---
+//     Deals {tier}0 {element} damage
+//
+// `instantiate` renders the concrete document for values of the parameters,
+// `{element}` and `{tier}` replaced everywhere, frontmatter included, and
+// `DokePipe::run_template` parses it directly. Other placeholders are left for
+// the parsers (frontmatter templates, variables).
+//
+// A `matrix:` expands one document into every combination of its axes,
+//
+//     matrix:
+//       element: [fire, ice, poison]
+//       tier: [1, 2, 3]
+//     matrix_id: "{element}_sword_{tier}"
+//
+// nine variants here, each rendered like a template instance. `matrix_id` names
+// them, workspaces add the values to the file's id otherwise (`sword_fire_1`).
+
+use std::collections::HashMap;
+use std::sync::LazyLock;
+
+use regex::Regex;
+use thiserror::Error;
+use yaml_rust2::{Yaml, YamlLoader};
+
+use crate::extract_frontmatter;
+
+/// Frontmatter key declaring the parameters of a template
+pub const TEMPLATE_PARAMS_KEY: &str = "template_params";
+
+/// Frontmatter key mapping each axis of a matrix to its values
+pub const MATRIX_KEY: &str = "matrix";
+
+/// Frontmatter key naming the variants of a matrix, `"{element}_sword_{tier}"`
+pub const MATRIX_ID_KEY: &str = "matrix_id";
+
+/// `{name}` of a parameter
+static PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{(\w+)\}").unwrap());
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("the document declares no template_params or matrix")]
+    NotATemplate,
+
+    #[error("invalid template_params: {0}")]
+    InvalidParams(String),
+
+    #[error("invalid matrix: {0}")]
+    InvalidMatrix(String),
+
+    #[error("the template takes {0} values ({1}), got {2}")]
+    Arguments(usize, String, usize),
+}
+
+/// The parameters a template declares, in order
+pub fn params(source: &str) -> Result<Vec<String>, TemplateError> {
+    let (Some(frontmatter), _) = extract_frontmatter(source) else {
+        return Err(TemplateError::NotATemplate);
+    };
+    let docs = YamlLoader::load_from_str(frontmatter)
+        .map_err(|e| TemplateError::InvalidParams(e.to_string()))?;
+    let declared = docs.first().map(|doc| &doc[TEMPLATE_PARAMS_KEY]);
+    match declared {
+        None | Some(Yaml::BadValue) => Err(TemplateError::NotATemplate),
+        Some(Yaml::Array(items)) => items
+            .iter()
+            .map(|item| match item {
+                Yaml::String(name) => Ok(name.clone()),
+                other => Err(TemplateError::InvalidParams(format!(
+                    "parameter names must be strings, got {:?}",
+                    other
+                ))),
+            })
+            .collect(),
+        Some(Yaml::String(name)) => Ok(vec![name.clone()]),
+        Some(_) => Err(TemplateError::InvalidParams(
+            "template_params must list parameter names".into(),
+        )),
+    }
+}
+
+/// The document `source` renders for `values`, given in the order of its parameters
+/// ```
+/// use doke::template;
+///
+/// let source = "---\ntemplate_params: [element, tier]\nid: \"{element}_sword_{tier}\"\n---\nDeals {tier}0 {element} damage\n";
+/// let document = template::instantiate(source, &["fire", "3"]).unwrap();
+/// assert_eq!(document, "---\nid: \"fire_sword_3\"\n---\nDeals 30 fire damage\n");
+///
+/// assert!(template::instantiate(source, &["fire"]).is_err());
+/// ```
+pub fn instantiate(source: &str, values: &[&str]) -> Result<String, TemplateError> {
+    let names = params(source)?;
+    if names.len() != values.len() {
+        return Err(TemplateError::Arguments(
+            names.len(),
+            names.join(", "),
+            values.len(),
+        ));
+    }
+    let values: HashMap<&str, &str> = names
+        .iter()
+        .map(String::as_str)
+        .zip(values.iter().copied())
+        .collect();
+    let (frontmatter, body) = extract_frontmatter(source);
+    let frontmatter = without_key(frontmatter.unwrap_or_default(), TEMPLATE_PARAMS_KEY);
+    let body = substitute(body, &values);
+    Ok(match frontmatter.trim().is_empty() {
+        true => body,
+        false => format!("---\n{}\n---\n{}", substitute(&frontmatter, &values), body),
+    })
+}
+
+/// A document of a matrix
+#[derive(Debug, Clone, PartialEq)]
+pub struct Variant {
+    /// The `matrix_id` pattern for these values, when the matrix has one
+    pub id: Option<String>,
+    /// Value of each axis, in the matrix's order
+    pub values: Vec<(String, String)>,
+    /// The rendered document
+    pub source: String,
+}
+
+/// Every variant of the matrix of `source`, the last axis changing fastest
+/// ```
+/// use doke::template;
+///
+/// let source = "---\nmatrix:\n  element: [fire, ice]\n  tier: [1, 2, 3]\nmatrix_id: \"{element}_sword_{tier}\"\n---\nDeals {tier}0 {element} damage\n";
+/// let variants = template::expand_matrix(source).unwrap();
+/// assert_eq!(variants.len(), 6);
+/// assert_eq!(variants[4].id.as_deref(), Some("ice_sword_2"));
+/// assert_eq!(variants[4].source, "---\nid: \"ice_sword_2\"\n---\nDeals 20 ice damage\n");
+/// ```
+pub fn expand_matrix(source: &str) -> Result<Vec<Variant>, TemplateError> {
+    let (Some(frontmatter), body) = extract_frontmatter(source) else {
+        return Err(TemplateError::NotATemplate);
+    };
+    let docs = YamlLoader::load_from_str(frontmatter)
+        .map_err(|e| TemplateError::InvalidMatrix(e.to_string()))?;
+    let Some(doc) = docs.first() else {
+        return Err(TemplateError::NotATemplate);
+    };
+    let axes = match &doc[MATRIX_KEY] {
+        Yaml::BadValue => return Err(TemplateError::NotATemplate),
+        Yaml::Hash(axes) => axes
+            .iter()
+            .map(|(name, values)| {
+                let name = scalar(name).ok_or_else(|| {
+                    TemplateError::InvalidMatrix("axis names must be strings".into())
+                })?;
+                let values: Vec<String> = match values {
+                    Yaml::Array(items) => items.iter().filter_map(scalar).collect(),
+                    _ => Vec::new(),
+                };
+                match values.is_empty() {
+                    true => Err(TemplateError::InvalidMatrix(format!(
+                        "axis '{}' must list its values",
+                        name
+                    ))),
+                    false => Ok((name, values)),
+                }
+            })
+            .collect::<Result<Vec<_>, _>>()?,
+        _ => {
+            return Err(TemplateError::InvalidMatrix(
+                "matrix must map each axis to its values".into(),
+            ));
+        }
+    };
+    let id_pattern = doc[MATRIX_ID_KEY].as_str();
+
+    let mut frontmatter = without_key(frontmatter, MATRIX_KEY);
+    frontmatter = without_key(&frontmatter, MATRIX_ID_KEY);
+    if id_pattern.is_some() {
+        frontmatter = without_key(&frontmatter, "id");
+    }
+
+    // combinations, like an odometer
+    let mut combinations: Vec<Vec<(String, String)>> = vec![Vec::new()];
+    for (name, values) in &axes {
+        combinations = combinations
+            .into_iter()
+            .flat_map(|combination| {
+                values.iter().map(move |value| {
+                    let mut combination = combination.clone();
+                    combination.push((name.clone(), value.clone()));
+                    combination
+                })
+            })
+            .collect();
+    }
+
+    Ok(combinations
+        .into_iter()
+        .map(|values| {
+            let map: HashMap<&str, &str> = values
+                .iter()
+                .map(|(name, value)| (name.as_str(), value.as_str()))
+                .collect();
+            let id = id_pattern.map(|pattern| substitute(pattern, &map));
+            let mut lines: Vec<String> = id.iter().map(|id| format!("id: {:?}", id)).collect();
+            if !frontmatter.trim().is_empty() {
+                lines.push(substitute(&frontmatter, &map));
+            }
+            let body = substitute(body, &map);
+            let source = match lines.is_empty() {
+                true => body,
+                false => format!("---\n{}\n---\n{}", lines.join("\n"), body),
+            };
+            Variant { id, values, source }
+        })
+        .collect())
+}
+
+/// A YAML string or number as text
+fn scalar(yaml: &Yaml) -> Option<String> {
+    match yaml {
+        Yaml::String(s) | Yaml::Real(s) => Some(s.clone()),
+        Yaml::Integer(i) => Some(i.to_string()),
+        Yaml::Boolean(b) => Some(b.to_string()),
+        _ => None,
+    }
+}
+
+/// `text` with the `{name}` of `values` replaced, other placeholders kept
+pub(crate) fn substitute(text: &str, values: &HashMap<&str, &str>) -> String {
+    PLACEHOLDER
+        .replace_all(text, |cap: &regex::Captures| match values.get(&cap[1]) {
+            Some(value) => value.to_string(),
+            None => cap[0].to_string(),
+        })
+        .into_owned()
+}
+
+/// Frontmatter text without the top-level `key` and its indented or listed lines
+pub(crate) fn without_key(frontmatter: &str, key: &str) -> String {
+    let mut kept = Vec::new();
+    let mut in_key = false;
+    for line in frontmatter.lines() {
+        let continues = line.starts_with([' ', '\t', '-']) || line.trim().is_empty();
+        if in_key && continues {
+            continue;
+        }
+        in_key = line
+            .strip_prefix(key)
+            .is_some_and(|rest| rest.trim_start().starts_with(':'));
+        if !in_key {
+            kept.push(line);
+        }
+    }
+    kept.join("\n")
+}
@@ -0,0 +1,131 @@
+// testing.rs
+//
+// Property tests for grammars: sentences generated for every phrase must parse
+// back to the value the phrase gives, which catches phrases shadowing each other,
+// greedy captures and optional groups swallowing text.
+//
+//     #[test]
+//     fn grammar_round_trips() {
+//         let parser = SentenceParser::from_yaml("Effect".into(), GRAMMAR).unwrap();
+//         doke::testing::assert_round_trips(&parser);
+//     }
+
+use proptest::prelude::*;
+use proptest::test_runner::{Config, TestError, TestRunner};
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use thiserror::Error;
+
+use crate::parsers::SentenceParser;
+use crate::{DokePipe, GodotValue};
+
+#[derive(Debug, Error)]
+pub enum RoundTripError {
+    #[error("Can't generate a sentence for '{phrase}': {error}")]
+    Generate { phrase: String, error: String },
+
+    #[error("'{sentence}' (from '{phrase}') parsed as {parsed}, expected {expected}")]
+    Mismatch {
+        phrase: String,
+        sentence: String,
+        expected: String,
+        parsed: String,
+    },
+}
+
+/// A sentence generated from a phrase, and the value it must parse to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Case {
+    /// The phrase's pattern
+    pub phrase: String,
+    pub sentence: String,
+    pub expected: GodotValue,
+}
+
+/// Cases for the phrase at `index` in `parser.phrases`, for `proptest!` tests
+pub fn cases(parser: &SentenceParser, index: usize) -> impl Strategy<Value = Case> + '_ {
+    any::<u64>().prop_map(move |seed| case(parser, index, seed).unwrap_or_else(|e| panic!("{}", e)))
+}
+
+/// Generate the case of `seed` for the phrase at `index`
+pub fn case(parser: &SentenceParser, index: usize, seed: u64) -> Result<Case, RoundTripError> {
+    let phrase = &parser.phrases[index];
+    let mut rng = StdRng::seed_from_u64(seed);
+    let (sentence, expected) =
+        parser
+            .generate_phrase(phrase, &mut rng, 0)
+            .map_err(|e| RoundTripError::Generate {
+                phrase: phrase.pattern.clone(),
+                error: e.to_string(),
+            })?;
+    Ok(Case {
+        phrase: phrase.pattern.clone(),
+        sentence,
+        expected,
+    })
+}
+
+/// Parse a case's sentence with `pipe` and compare with the expected value
+pub fn check(pipe: &DokePipe, case: &Case) -> Result<(), RoundTripError> {
+    let parsed = match pipe.validate(&case.sentence) {
+        Ok(values) if values.first() == Some(&case.expected) => return Ok(()),
+        Ok(values) => values
+            .first()
+            .map_or("nothing".to_string(), ToString::to_string),
+        Err(e) => e.to_string(),
+    };
+    Err(RoundTripError::Mismatch {
+        phrase: case.phrase.clone(),
+        sentence: case.sentence.clone(),
+        expected: case.expected.to_string(),
+        parsed,
+    })
+}
+
+/// Check `config.cases` generated sentences of every phrase of `parser`.
+///
+/// Returns the first failing case, shrunk by proptest. Failures aren't persisted,
+/// seeds say nothing once the grammar changed.
+/// ```
+/// use doke::{parsers::SentenceParser, testing};
+/// use proptest::test_runner::Config;
+///
+/// // the first number takes every digit but one
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Damage:
+///   - "Deals {low: int}{high: int} damage"
+/// "#).unwrap();
+/// assert!(testing::round_trips(&parser, Config::with_cases(32)).is_err());
+/// ```
+pub fn round_trips(parser: &SentenceParser, mut config: Config) -> Result<(), RoundTripError> {
+    config.failure_persistence = None;
+    let pipe = DokePipe::new().add(parser.clone());
+    for index in 0..parser.phrases.len() {
+        let mut runner = TestRunner::new(config.clone());
+        let result = runner.run(&any::<u64>(), |seed| {
+            case(parser, index, seed)
+                .and_then(|case| check(&pipe, &case))
+                .map_err(|e| TestCaseError::fail(e.to_string()))
+        });
+        match result {
+            Ok(()) => {}
+            Err(TestError::Fail(_, seed)) => {
+                return case(parser, index, seed).and_then(|case| check(&pipe, &case));
+            }
+            Err(TestError::Abort(reason)) => {
+                return Err(RoundTripError::Generate {
+                    phrase: parser.phrases[index].pattern.clone(),
+                    error: reason.to_string(),
+                });
+            }
+        }
+    }
+    Ok(())
+}
+
+/// `round_trips` with proptest's default config, panicking on failure
+pub fn assert_round_trips(parser: &SentenceParser) {
+    if let Err(e) = round_trips(parser, Config::default()) {
+        panic!("{}", e);
+    }
+}
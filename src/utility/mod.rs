@@ -1,17 +1,143 @@
+// utility
+//
+// Helpers shared by the parsers and exporters, public for code generators and
+// localization tooling built on doke: stable hashes, the name conversions of
+// translation keys and Godot scripts, and PO catalog updates.
+
+#[cfg(feature = "translate")]
 use polib::{
     catalog::Catalog,
-    message::Message,
+    message::{Message, MessageMutView, MessageView},
     metadata::CatalogMetadata,
     po_file::{self, POParseError},
 };
 use std::hash::{Hash, Hasher};
-use std::{collections::HashMap, hash::DefaultHasher, path::Path};
+#[cfg(feature = "translate")]
+use std::{collections::HashMap, path::Path};
+
+/// FNV-1a as a `Hasher`: unlike `DefaultHasher`, the same on every run, platform
+/// and Rust version. Integers are hashed little-endian.
+#[derive(Debug, Clone, Copy)]
+pub struct StableHasher(u64);
+
+impl Default for StableHasher {
+    fn default() -> Self {
+        Self(0xcbf29ce484222325)
+    }
+}
+
+impl Hasher for StableHasher {
+    fn write(&mut self, bytes: &[u8]) {
+        self.0 = bytes.iter().fold(self.0, |hash, byte| {
+            (hash ^ *byte as u64).wrapping_mul(0x100000001b3)
+        });
+    }
+
+    fn write_u16(&mut self, i: u16) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, i: u32) {
+        self.write(&i.to_le_bytes());
+    }
 
+    fn write_u64(&mut self, i: u64) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, i: u128) {
+        self.write(&i.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, i: usize) {
+        self.write_u64(i as u64);
+    }
+
+    fn finish(&self) -> u64 {
+        self.0
+    }
+}
+
+/// `value` hashed with `StableHasher`
 pub fn hash_value<T: Hash>(value: &T) -> u64 {
-    let mut hasher = DefaultHasher::new();
+    let mut hasher = StableHasher::default();
     value.hash(&mut hasher);
     hasher.finish()
 }
+
+/// SipHash-1-3 with zero keys of `text` followed by `0xFF`: what `DefaultHasher`
+/// gave for a `str` when translation keys were made with it, spelled out so
+/// those keys never change with the compiler
+pub fn str_hash(text: &str) -> u64 {
+    let mut bytes = text.as_bytes().to_vec();
+    bytes.push(0xFF);
+    let (mut v0, mut v1, mut v2, mut v3): (u64, u64, u64, u64) = (
+        0x736f6d6570736575,
+        0x646f72616e646f6d,
+        0x6c7967656e657261,
+        0x7465646279746573,
+    );
+    let round = |v0: &mut u64, v1: &mut u64, v2: &mut u64, v3: &mut u64| {
+        *v0 = v0.wrapping_add(*v1);
+        *v1 = v1.rotate_left(13) ^ *v0;
+        *v0 = v0.rotate_left(32);
+        *v2 = v2.wrapping_add(*v3);
+        *v3 = v3.rotate_left(16) ^ *v2;
+        *v0 = v0.wrapping_add(*v3);
+        *v3 = v3.rotate_left(21) ^ *v0;
+        *v2 = v2.wrapping_add(*v1);
+        *v1 = v1.rotate_left(17) ^ *v2;
+        *v2 = v2.rotate_left(32);
+    };
+    let chunks = bytes.chunks_exact(8);
+    let tail = chunks.remainder();
+    for chunk in chunks {
+        let m = u64::from_le_bytes(chunk.try_into().unwrap());
+        v3 ^= m;
+        round(&mut v0, &mut v1, &mut v2, &mut v3);
+        v0 ^= m;
+    }
+    let mut last = (bytes.len() as u64 & 0xFF) << 56;
+    for (i, byte) in tail.iter().enumerate() {
+        last |= (*byte as u64) << (8 * i);
+    }
+    v3 ^= last;
+    round(&mut v0, &mut v1, &mut v2, &mut v3);
+    v0 ^= last;
+    v2 ^= 0xFF;
+    for _ in 0..3 {
+        round(&mut v0, &mut v1, &mut v2, &mut v3);
+    }
+    v0 ^ v1 ^ v2 ^ v3
+}
+
+/// FNV-1a of `bytes`, see `StableHasher`
+pub fn stable_hash(bytes: &[u8]) -> u64 {
+    let mut hasher = StableHasher::default();
+    hasher.write(bytes);
+    hasher.finish()
+}
+
+/// A Godot 4 `uid://` derived from `key`, the same on every export.
+/// `salt` keeps projects sharing document ids from sharing uids.
+pub fn godot_uid(salt: &str, key: &str) -> String {
+    // Godot ids are positive i64, written in base 34 with `a`-`y` then `0`-`8`
+    let mut id = stable_hash(format!("{}\0{}", salt, key).as_bytes()) & 0x7FFF_FFFF_FFFF_FFFF;
+    let mut digits = Vec::new();
+    loop {
+        let c = (id % 34) as u8;
+        digits.push(if c < 25 { b'a' + c } else { b'0' + (c - 25) } as char);
+        id /= 34;
+        if id == 0 {
+            break;
+        }
+    }
+    format!("uid://{}", digits.iter().rev().collect::<String>())
+}
+
+/// `ItemModifier` -> `ITEM_MODIFIER`, the section part of translation keys.
+/// Its splitting rules are kept as they are so keys don't change, use
+/// `to_snake_case` and friends for other names.
 pub fn camel_to_const_case(input: &str) -> String {
     let mut result = String::new();
     let mut chars = input.chars().peekable();
@@ -27,10 +153,12 @@ pub fn camel_to_const_case(input: &str) -> String {
         if !result.is_empty() {
             if is_upper && prev_was_lower {
                 result.push('_');
-            } else if let Some(&next) = chars.peek() {
-                if !is_upper && prev_was_upper && next.is_uppercase() {
-                    result.push('_');
-                }
+            } else if let Some(&next) = chars.peek()
+                && !is_upper
+                && prev_was_upper
+                && next.is_uppercase()
+            {
+                result.push('_');
             }
         }
 
@@ -48,6 +176,7 @@ const BASE32_ALPHABET: [char; 32] = [
     'T', 'U', 'V', 'W', 'X', 'Y', 'Z', '2', '3', '4', '5', '6', '7',
 ];
 
+/// `num` in base32 (`A`-`Z` then `2`-`7`), most significant digit first
 pub fn u64_to_base32(mut num: u64) -> String {
     if num == 0 {
         return "A".to_string();
@@ -64,10 +193,139 @@ pub fn u64_to_base32(mut num: u64) -> String {
     result.chars().rev().collect()
 }
 
+/// The words of an identifier or a phrase, split where Godot's `to_snake_case`
+/// splits (`HTTPServer2D` -> `HTTP`, `Server`, `2D`) and on spaces, `_` and `-`
+pub fn words(input: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    for part in input.split(|c: char| c.is_whitespace() || c == '_' || c == '-') {
+        let chars: Vec<char> = part.chars().collect();
+        let mut word = String::new();
+        for (i, &c) in chars.iter().enumerate() {
+            if i > 0 {
+                let prev = chars[i - 1];
+                let next_lower = chars.get(i + 1).is_some_and(|n| n.is_lowercase());
+                let split = (prev.is_lowercase() && c.is_uppercase())
+                    || ((prev.is_uppercase() || prev.is_ascii_digit())
+                        && c.is_uppercase()
+                        && next_lower)
+                    || (prev.is_ascii_digit() && c.is_lowercase() && next_lower)
+                    || ((prev.is_uppercase() || prev.is_lowercase()) && c.is_ascii_digit());
+                if split {
+                    words.push(std::mem::take(&mut word));
+                }
+            }
+            word.push(c);
+        }
+        if !word.is_empty() {
+            words.push(word);
+        }
+    }
+    words
+}
+
+/// `ItemModifier` -> `item_modifier`, like Godot's `to_snake_case` (script file names)
+/// ```
+/// use doke::utility::{to_kebab_case, to_pascal_case, to_snake_case};
+///
+/// assert_eq!(to_snake_case("HTTPServer"), "http_server");
+/// assert_eq!(to_kebab_case("ItemModifier"), "item-modifier");
+/// assert_eq!(to_pascal_case("fire damage"), "FireDamage");
+/// ```
+pub fn to_snake_case(input: &str) -> String {
+    words(input).join("_").to_lowercase()
+}
+
+/// `ItemModifier` -> `item-modifier`
+pub fn to_kebab_case(input: &str) -> String {
+    words(input).join("-").to_lowercase()
+}
+
+/// `item_modifier` -> `ItemModifier`, each word capitalized
+pub fn to_pascal_case(input: &str) -> String {
+    words(input)
+        .iter()
+        .map(|word| {
+            let mut chars = word.chars();
+            chars.next().map_or_else(String::new, |first| {
+                first
+                    .to_uppercase()
+                    .chain(chars.flat_map(char::to_lowercase))
+                    .collect()
+            })
+        })
+        .collect()
+}
+
+/// An entry of a PO catalog, see `update_po_entries`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PoEntry {
+    pub msgid: String,
+    /// The English text
+    pub msgstr: String,
+    /// `msgctxt`, for translators and lookups with `tr(msgid, context)`
+    pub context: Option<String>,
+    /// Extracted comments (`#.`), for translators
+    pub comments: Option<String>,
+}
+
+#[cfg(feature = "translate")]
 pub fn update_po_file(
     po_path: &Path,
     translations: HashMap<String, String>,
     project_id_version: String,
+) -> Result<(), POParseError> {
+    let entries = translations
+        .into_iter()
+        .map(|(msgid, msgstr)| PoEntry {
+            msgid,
+            msgstr,
+            ..PoEntry::default()
+        })
+        .collect();
+    update_po_entries(po_path, entries, project_id_version)
+}
+
+/// Extracted comment line recording the English text an entry was made from
+pub const PO_SOURCE_COMMENT: &str = "en: ";
+
+/// Add `entries` to the PO file at `po_path`, created if missing.
+///
+/// Translations already in the file are kept: only new entries get the English
+/// text as `msgstr`. An entry whose English text changed since the last run is
+/// marked `fuzzy` for translators to review. An entry getting a context replaces
+/// the same msgid without one.
+/// ```
+/// use doke::utility::{PoEntry, update_po_entries};
+///
+/// let path = std::env::temp_dir().join("doke_update_po_entries.po");
+/// let _ = std::fs::remove_file(&path);
+/// let entry = |msgstr: &str| PoEntry {
+///     msgid: "DAMAGE".into(),
+///     msgstr: msgstr.into(),
+///     ..PoEntry::default()
+/// };
+/// update_po_entries(&path, vec![entry("Deals {damage} damage")], "game".into()).unwrap();
+/// // a translator's work
+/// let po = std::fs::read_to_string(&path).unwrap();
+/// std::fs::write(&path, po.replace("msgstr \"Deals", "msgstr \"Inflige")).unwrap();
+///
+/// update_po_entries(&path, vec![entry("Deals {damage} damage")], "game".into()).unwrap();
+/// let po = std::fs::read_to_string(&path).unwrap();
+/// assert!(po.contains("msgstr \"Inflige {damage} damage\""));
+/// assert!(!po.contains("fuzzy"));
+///
+/// update_po_entries(&path, vec![entry("Deals {damage} fire damage")], "game".into()).unwrap();
+/// let po = std::fs::read_to_string(&path).unwrap();
+/// assert!(po.contains("msgstr \"Inflige {damage} damage\""));
+/// assert!(po.contains("#, fuzzy"));
+/// assert!(po.contains("#. en: Deals {damage} fire damage"));
+/// assert_eq!(po.matches("msgid \"DAMAGE_PL\"").count(), 1);
+/// ```
+#[cfg(feature = "translate")]
+pub fn update_po_entries(
+    po_path: &Path,
+    entries: Vec<PoEntry>,
+    project_id_version: String,
 ) -> Result<(), POParseError> {
     // Load existing PO file or create new
     let mut catalog = if po_path.exists() {
@@ -79,17 +337,33 @@ pub fn update_po_file(
 
         Catalog::new(meta)
     };
-    for (msgid, msgentrad) in translations {
-        let m_singular = Message::build_singular()
-            .with_msgid(msgid.clone())
-            .with_msgstr(msgentrad.clone())
-            .done();
-        let m_plural = Message::build_plural()
-            .with_msgid(format!("{}_PL", msgid.clone()))
-            .with_msgstr(msgentrad.clone())
-            .done();
-        catalog.append_or_update(m_singular);
-        catalog.append_or_update(m_plural);
+    for entry in entries {
+        let plural_id = format!("{}_PL", entry.msgid);
+        if entry.context.is_some() {
+            catalog.delete_message(None, &entry.msgid, None);
+            catalog.delete_message(None, &plural_id, None);
+        }
+        let comments = po_comments(&entry);
+        let context = entry.context.as_deref();
+        // `_PL` is a key of its own: read back, a plural message without
+        // `msgid_plural` is a singular one
+        for msgid in [&entry.msgid, &plural_id] {
+            if let Some(mut message) = catalog.find_message_mut(context, msgid, None) {
+                let previous = po_source(message.comments()).map(str::to_string);
+                if previous.is_some_and(|previous| previous != entry.msgstr) {
+                    message.flags_mut().add_flag("fuzzy");
+                }
+                *message.comments_mut() = comments.clone();
+                continue;
+            }
+            let message = Message::build_singular()
+                .with_msgctxt(entry.context.clone().unwrap_or_default())
+                .with_comments(comments.clone())
+                .with_msgid(msgid.clone())
+                .with_msgstr(entry.msgstr.clone())
+                .done();
+            catalog.append_or_update(message);
+        }
     }
 
     // Save updated PO file
@@ -97,3 +371,85 @@ pub fn update_po_file(
 
     Ok(())
 }
+
+/// Rename the entries of the PO file at `po_path` from the keys of `renames` to
+/// their values, keeping translations, e.g. after a change of translation key
+/// format (see `SentenceParser::tr_key_migration`). Returns the entries renamed.
+#[cfg(feature = "translate")]
+pub fn rename_po_entries(
+    po_path: &Path,
+    renames: &HashMap<String, String>,
+) -> Result<usize, POParseError> {
+    let mut catalog = po_file::parse(po_path)?;
+    let renamed = |msgid: &str| match msgid.strip_suffix("_PL") {
+        Some(key) => renames.get(key).map(|new| format!("{}_PL", new)),
+        None => renames.get(msgid).cloned(),
+    };
+    let targets: Vec<(String, String, String)> = catalog
+        .messages()
+        .filter(|message| message.is_singular())
+        .filter_map(|message| {
+            let new = renamed(message.msgid())?;
+            Some((
+                message.msgctxt().to_string(),
+                message.msgid().to_string(),
+                new,
+            ))
+        })
+        .collect();
+    for (context, msgid, new) in &targets {
+        let context = Some(context.as_str()).filter(|c| !c.is_empty());
+        if let Some(mut message) = catalog.detach_message(context, msgid, None) {
+            message.set_msgid(new.clone());
+            catalog.append_or_update(message);
+        }
+    }
+    po_file::write(&catalog, po_path)?;
+    Ok(targets.len())
+}
+
+/// Write `entries` to a PO template (`.pot`) at `po_path`, replacing it: the
+/// English text goes in comments and every `msgstr` is empty, for translators
+/// to merge into their catalogs (`msgmerge`).
+#[cfg(feature = "translate")]
+pub fn write_pot_file(
+    po_path: &Path,
+    entries: Vec<PoEntry>,
+    project_id_version: String,
+) -> Result<(), POParseError> {
+    let mut meta = CatalogMetadata::new();
+    meta.project_id_version = project_id_version;
+    let mut catalog = Catalog::new(meta);
+    for entry in entries {
+        let plural_id = format!("{}_PL", entry.msgid);
+        let comments = po_comments(&entry);
+        for msgid in [entry.msgid.clone(), plural_id] {
+            let message = Message::build_singular()
+                .with_msgctxt(entry.context.clone().unwrap_or_default())
+                .with_comments(comments.clone())
+                .with_msgid(msgid)
+                .done();
+            catalog.append_or_update(message);
+        }
+    }
+    po_file::write(&catalog, po_path)?;
+    Ok(())
+}
+
+/// The English text, then the entry's own comments
+#[cfg(feature = "translate")]
+fn po_comments(entry: &PoEntry) -> String {
+    let source = format!("{}{}", PO_SOURCE_COMMENT, entry.msgstr);
+    match &entry.comments {
+        Some(comments) => format!("{}\n{}", source, comments),
+        None => source,
+    }
+}
+
+/// The English text recorded in an entry's comments
+#[cfg(feature = "translate")]
+fn po_source(comments: &str) -> Option<&str> {
+    comments
+        .lines()
+        .find_map(|line| line.strip_prefix(PO_SOURCE_COMMENT))
+}
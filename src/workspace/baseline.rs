@@ -0,0 +1,184 @@
+// workspace/baseline.rs
+//
+// Diagnostics a content base already had when strict checks were turned on.
+// Runs against a baseline only report new diagnostics, so legacy documents
+// can be fixed one at a time.
+//
+// Entries are keyed by file, code and a hash of the message, which names the
+// statement: lines moving around don't make known diagnostics new again.
+// The baseline is a text file, one entry per line, to review in diffs:
+//
+//     items/sword.md	workspace-invalid-document	5c1f0e8a9b2d4e61	Unresolved node: Frobnicates
+//
+// The message after the hash is only there for readers.
+
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use super::WorkspaceDiagnostic;
+use crate::utility::stable_hash;
+
+/// File name of the baseline in the checked directory, for `doke check`
+pub const BASELINE_FILE: &str = "doke.baseline";
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+struct Key {
+    /// Relative to the workspace root, with `/` separators
+    path: String,
+    code: String,
+    message_hash: u64,
+}
+
+/// Known diagnostics of a workspace, see `Baseline::new_diagnostics`
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Baseline {
+    /// How many times each diagnostic occurs
+    entries: HashMap<Key, usize>,
+    /// First line of each message, written for readers
+    messages: HashMap<Key, String>,
+}
+
+impl Baseline {
+    /// Record `diagnostics` of the workspace read from `root`
+    pub fn from_diagnostics(diagnostics: &[WorkspaceDiagnostic], root: &Path) -> Self {
+        let mut baseline = Self::default();
+        for diagnostic in diagnostics {
+            let key = key(diagnostic, root);
+            let summary = diagnostic
+                .diagnostic
+                .message
+                .lines()
+                .next()
+                .unwrap_or_default();
+            baseline
+                .messages
+                .entry(key.clone())
+                .or_insert_with(|| summary.to_string());
+            *baseline.entries.entry(key).or_default() += 1;
+        }
+        baseline
+    }
+
+    /// Read a baseline written by `to_text`, ignoring blank, `#` and malformed lines
+    pub fn parse(text: &str) -> Self {
+        let mut baseline = Self::default();
+        for line in text.lines() {
+            if line.trim().is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let mut columns = line.splitn(4, '\t');
+            let (Some(path), Some(code), Some(hash)) =
+                (columns.next(), columns.next(), columns.next())
+            else {
+                continue;
+            };
+            let Ok(message_hash) = u64::from_str_radix(hash, 16) else {
+                continue;
+            };
+            let key = Key {
+                path: path.to_string(),
+                code: code.to_string(),
+                message_hash,
+            };
+            if let Some(message) = columns.next() {
+                baseline
+                    .messages
+                    .entry(key.clone())
+                    .or_insert_with(|| message.to_string());
+            }
+            *baseline.entries.entry(key).or_default() += 1;
+        }
+        baseline
+    }
+
+    pub fn from_file(path: &Path) -> io::Result<Self> {
+        Ok(Self::parse(&fs::read_to_string(path)?))
+    }
+
+    /// One line per occurrence, sorted so that re-recording gives small diffs
+    pub fn to_text(&self) -> String {
+        let mut keys: Vec<&Key> = self.entries.keys().collect();
+        keys.sort();
+        let mut out = String::from("# Known diagnostics, `doke check` only fails on others\n");
+        for key in keys {
+            let message = self.messages.get(key).map_or("", String::as_str);
+            for _ in 0..self.entries[key] {
+                out.push_str(&format!(
+                    "{}\t{}\t{:016x}\t{}\n",
+                    key.path, key.code, key.message_hash, message
+                ));
+            }
+        }
+        out
+    }
+
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        fs::write(path, self.to_text())
+    }
+
+    /// Number of diagnostics recorded
+    pub fn len(&self) -> usize {
+        self.entries.values().sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// The diagnostics of the workspace read from `root` that aren't in the
+    /// baseline. A diagnostic recorded once only excuses one occurrence.
+    /// ```
+    /// use std::path::Path;
+    /// use doke::diagnostics::Diagnostic;
+    /// use doke::workspace::{Baseline, WorkspaceDiagnostic};
+    ///
+    /// let root = Path::new("content");
+    /// let unresolved = |line| WorkspaceDiagnostic {
+    ///     path: root.join("items/sword.md"),
+    ///     line,
+    ///     diagnostic: Diagnostic::error("workspace-invalid-document", "Unresolved node: Frobnicates"),
+    /// };
+    /// let baseline = Baseline::from_diagnostics(&[unresolved(5)], root);
+    /// assert_eq!(Baseline::parse(&baseline.to_text()), baseline);
+    ///
+    /// // moving the statement doesn't make it new, a second occurrence is
+    /// let new = baseline.new_diagnostics(vec![unresolved(9), unresolved(12)], root);
+    /// assert_eq!(new.len(), 1);
+    /// assert_eq!(new[0].line, 12);
+    /// ```
+    pub fn new_diagnostics(
+        &self,
+        diagnostics: Vec<WorkspaceDiagnostic>,
+        root: &Path,
+    ) -> Vec<WorkspaceDiagnostic> {
+        let mut known = self.entries.clone();
+        diagnostics
+            .into_iter()
+            .filter(|diagnostic| match known.get_mut(&key(diagnostic, root)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .collect()
+    }
+}
+
+fn key(diagnostic: &WorkspaceDiagnostic, root: &Path) -> Key {
+    let path = diagnostic
+        .path
+        .strip_prefix(root)
+        .unwrap_or(&diagnostic.path);
+    Key {
+        path: path
+            .components()
+            .map(|c| c.as_os_str().to_string_lossy())
+            .collect::<Vec<_>>()
+            .join("/"),
+        code: diagnostic.diagnostic.code.to_string(),
+        message_hash: stable_hash(diagnostic.diagnostic.message.as_bytes()),
+    }
+}
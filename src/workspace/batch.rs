@@ -0,0 +1,87 @@
+// workspace/batch.rs
+//
+// Workspaces read from async code, for build servers and web services running
+// on tokio. Files are read concurrently, and parsed on tokio's blocking pool so
+// the runtime's workers stay free.
+
+use std::io;
+use std::path::Path;
+use std::sync::Arc;
+
+use tokio::sync::{Semaphore, mpsc};
+
+use super::{Workspace, WorkspaceDocument, document_paths};
+use crate::SharedPipe;
+
+/// Documents read and parsed at the same time
+const CONCURRENT_DOCUMENTS: usize = 64;
+
+impl Workspace {
+    /// Read and parse the documents `from_dir` would, sending each one as soon as
+    /// it is done, in no particular order. Must be called from a tokio runtime.
+    /// ```no_run
+    /// # async fn run() -> std::io::Result<()> {
+    /// use doke::{DokePipe, workspace::Workspace};
+    ///
+    /// let pipe = DokePipe::new().shared();
+    /// let mut documents = Workspace::stream_dir("content".as_ref(), &pipe)?;
+    /// while let Some(document) = documents.recv().await {
+    ///     let document = document?;
+    ///     println!("{}: {}", document.path.display(), document.values.is_ok());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_dir(
+        root: &Path,
+        pipe: &SharedPipe,
+    ) -> io::Result<mpsc::Receiver<io::Result<WorkspaceDocument>>> {
+        let (sender, receiver) = mpsc::channel(CONCURRENT_DOCUMENTS);
+        let permits = Arc::new(Semaphore::new(CONCURRENT_DOCUMENTS));
+        for path in document_paths(root, pipe)? {
+            let (sender, permits, pipe) = (sender.clone(), permits.clone(), pipe.clone());
+            tokio::spawn(async move {
+                let Ok(_permit) = permits.acquire_owned().await else {
+                    return;
+                };
+                let document = match tokio::fs::read_to_string(&path).await {
+                    Ok(source) => tokio::task::spawn_blocking(move || {
+                        WorkspaceDocument::parse(path, source, &pipe)
+                    })
+                    .await
+                    .map_err(io::Error::other),
+                    Err(e) => Err(e),
+                };
+                // the receiver is gone when the caller stopped on an error
+                let _ = sender.send(document).await;
+            });
+        }
+        Ok(receiver)
+    }
+
+    /// `from_dir` from async code, see `stream_dir`
+    pub async fn from_dir_async(root: &Path, pipe: &SharedPipe) -> io::Result<Self> {
+        let mut documents = Self::stream_dir(root, pipe)?;
+        let mut workspace = Self {
+            root: Some(root.to_path_buf()),
+            ..Self::new()
+        };
+        while let Some(document) = documents.recv().await {
+            workspace.documents.push(document?);
+        }
+        // same order as `from_dir`, diagnostics follow it
+        workspace.documents.sort_by(|a, b| a.path.cmp(&b.path));
+
+        #[cfg(feature = "csv")]
+        let workspace = {
+            let (root, pipe) = (root.to_path_buf(), pipe.clone());
+            tokio::task::spawn_blocking(move || {
+                workspace.add_csv_dir(&root, &pipe)?;
+                Ok::<_, io::Error>(workspace)
+            })
+            .await
+            .map_err(io::Error::other)??
+        };
+        Ok(workspace)
+    }
+}
@@ -0,0 +1,142 @@
+// workspace/graph.rs
+//
+// Which documents reference which, to export referenced resources
+// before the ones pointing at them.
+
+use std::fmt::Write;
+
+/// Documents of a workspace and the documents they reference
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Document ids, in workspace order
+    ids: Vec<String>,
+    /// Referenced documents of each document, by index
+    edges: Vec<Vec<usize>>,
+}
+
+impl DependencyGraph {
+    pub(crate) fn new(ids: impl IntoIterator<Item = String>) -> Self {
+        let ids: Vec<String> = ids.into_iter().collect();
+        Self {
+            edges: vec![Vec::new(); ids.len()],
+            ids,
+        }
+    }
+
+    /// `from` references `to`, a document referencing itself needs nothing first
+    pub(crate) fn add_edge(&mut self, from: usize, to: usize) {
+        if from != to && !self.edges[from].contains(&to) {
+            self.edges[from].push(to);
+        }
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.ids.iter().map(String::as_str)
+    }
+
+    /// Ids of the documents `id` references
+    pub fn dependencies(&self, id: &str) -> impl Iterator<Item = &str> {
+        self.index(id)
+            .into_iter()
+            .flat_map(|i| self.edges[i].iter().map(|&j| self.ids[j].as_str()))
+    }
+
+    fn index(&self, id: &str) -> Option<usize> {
+        self.ids.iter().position(|i| i == id)
+    }
+
+    /// Document ids with every document after the ones it references,
+    /// otherwise in workspace order.
+    ///
+    /// Fails with the ids of a cycle, its first document repeated at the end.
+    /// ```
+    /// use doke::{DokePipe, parsers::SentenceParser, workspace::Workspace};
+    ///
+    /// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+    /// Upgrade:
+    ///   - "Upgrades into {next: ref}"
+    /// "#).unwrap();
+    /// let pipe = DokePipe::new().add(parser);
+    ///
+    /// let mut workspace = Workspace::new();
+    /// workspace.add_document("iron_sword.md", "Upgrades into steel_sword".into(), &pipe);
+    /// workspace.add_document("steel_sword.md", "Upgrades into mithril_sword".into(), &pipe);
+    /// workspace.add_document("mithril_sword.md", "---\nid: mithril_sword\n---\n".into(), &pipe);
+    /// let graph = workspace.dependency_graph();
+    /// assert_eq!(graph.dependencies("iron_sword").collect::<Vec<_>>(), ["steel_sword"]);
+    /// assert_eq!(graph.build_order(), Ok(vec!["mithril_sword", "steel_sword", "iron_sword"]));
+    ///
+    /// let mut cyclic = Workspace::new();
+    /// cyclic.add_document("a.md", "---\ninherits: b\n---\n".into(), &pipe);
+    /// cyclic.add_document("b.md", "---\ninherits: a\n---\n".into(), &pipe);
+    /// assert_eq!(cyclic.dependency_graph().build_order(), Err(vec!["a", "b", "a"]));
+    /// ```
+    pub fn build_order(&self) -> Result<Vec<&str>, Vec<&str>> {
+        let order = self.build_order_indices()?;
+        Ok(order.into_iter().map(|i| self.ids[i].as_str()).collect())
+    }
+
+    /// Like `build_order`, with the index of each document in the workspace
+    pub(crate) fn build_order_indices(&self) -> Result<Vec<usize>, Vec<&str>> {
+        #[derive(Clone, Copy, PartialEq)]
+        enum Mark {
+            New,
+            Visiting,
+            Done,
+        }
+
+        let mut marks = vec![Mark::New; self.ids.len()];
+        let mut order = Vec::with_capacity(self.ids.len());
+        for start in 0..self.ids.len() {
+            if marks[start] != Mark::New {
+                continue;
+            }
+            // iterative depth-first search, (document, next edge to follow)
+            let mut stack = vec![(start, 0)];
+            marks[start] = Mark::Visiting;
+            while let Some((node, edge)) = stack.last_mut() {
+                let node = *node;
+                match self.edges[node].get(*edge) {
+                    Some(&next) => {
+                        *edge += 1;
+                        match marks[next] {
+                            Mark::New => {
+                                marks[next] = Mark::Visiting;
+                                stack.push((next, 0));
+                            }
+                            Mark::Visiting => {
+                                let from = stack.iter().position(|(n, _)| *n == next).unwrap();
+                                let mut cycle: Vec<&str> = stack[from..]
+                                    .iter()
+                                    .map(|(n, _)| self.ids[*n].as_str())
+                                    .collect();
+                                cycle.push(&self.ids[next]);
+                                return Err(cycle);
+                            }
+                            Mark::Done => {}
+                        }
+                    }
+                    None => {
+                        marks[node] = Mark::Done;
+                        order.push(node);
+                        stack.pop();
+                    }
+                }
+            }
+        }
+        Ok(order)
+    }
+
+    /// The graph in Graphviz format, each document pointing at what it references
+    pub fn to_dot(&self) -> String {
+        let mut out = String::from("digraph dependencies {\n");
+        for (i, id) in self.ids.iter().enumerate() {
+            writeln!(out, "    {:?};", id).unwrap();
+            for &j in &self.edges[i] {
+                writeln!(out, "    {:?} -> {:?};", id, self.ids[j]).unwrap();
+            }
+        }
+        out.push_str("}\n");
+        out
+    }
+}
@@ -0,0 +1,828 @@
+// workspace/mod.rs
+//
+// A set of documents checked together: ids must be unique across the project,
+// and what one document refers to must exist somewhere.
+//
+// A document can be a variant of another one, `inherits: iron_sword` in its
+// frontmatter: it's built from the parent's fields, see
+// `ResourceBuilder::build_file_resource_inheriting`.
+//
+// A document with a `matrix` adds one document per variant, see `template`.
+
+mod baseline;
+#[cfg(feature = "tokio")]
+mod batch;
+mod graph;
+
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+#[cfg(feature = "fs")]
+use std::io;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+use crate::diagnostics::Diagnostic;
+use crate::duplicates;
+use crate::export::{ExportError, Manifest, ManifestEntry, TresExport, json};
+use crate::file_builder::ResourceBuilder;
+use crate::parsers::param_types::EXT_REF_TYPE;
+use crate::semantic::{DokeNodeState, DokeValidationError};
+#[cfg(feature = "csv")]
+use crate::spreadsheet::{CsvMapping, SpreadsheetError};
+use crate::suppress::{self, ALLOW_KEY};
+use crate::template::{self, MATRIX_KEY, TemplateError};
+use crate::{DokeDocument, DokeNode, DokePipe, GodotValue, Position, extract_frontmatter};
+
+pub use baseline::{BASELINE_FILE, Baseline};
+pub use graph::DependencyGraph;
+
+/// Frontmatter key naming the document a document inherits its fields from
+pub const INHERITS_KEY: &str = "inherits";
+
+/// A parsed and validated document of a workspace
+#[derive(Debug)]
+pub struct WorkspaceDocument {
+    pub path: PathBuf,
+    /// The frontmatter `id`, or the file stem
+    pub id: String,
+    /// Id of the document this one inherits from
+    pub inherits: Option<String>,
+    pub source: String,
+    pub document: DokeDocument,
+    /// Top level values with the span of their statement
+    pub values: Result<Vec<(GodotValue, Position)>, DokeValidationError>,
+    /// Line in `path` of each line of `source`, when `source` was generated from
+    /// another format (like the rows of a sheet), empty otherwise
+    pub source_lines: Vec<usize>,
+}
+
+impl WorkspaceDocument {
+    fn parse(path: PathBuf, source: String, pipe: &DokePipe) -> Self {
+        let mut document = pipe.run_markdown(&source);
+        let values = pipe.validate_document(&mut document);
+        let id = match document.frontmatter.get("id") {
+            Some(GodotValue::String(id)) => id.clone(),
+            _ => path
+                .file_stem()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        };
+        Self {
+            path,
+            id,
+            inherits: inherits(&document),
+            source,
+            document,
+            values,
+            source_lines: Vec::new(),
+        }
+    }
+
+    /// 1-based line of a statement span
+    pub fn line(&self, span: &Position) -> usize {
+        let (_, body) = extract_frontmatter(&self.source);
+        let offset = self.source.len() - body.len() + span.start;
+        let line = self.source[..offset.min(self.source.len())]
+            .matches('\n')
+            .count()
+            + 1;
+        self.source_lines.get(line - 1).copied().unwrap_or(line)
+    }
+}
+
+/// A diagnostic located in a workspace file
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorkspaceDiagnostic {
+    pub path: PathBuf,
+    pub line: usize,
+    pub diagnostic: Diagnostic,
+}
+
+impl fmt::Display for WorkspaceDiagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let Diagnostic {
+            severity,
+            code,
+            message,
+            ..
+        } = &self.diagnostic;
+        write!(
+            f,
+            "{}:{}: {}[{}]: {}",
+            self.path.display(),
+            self.line,
+            severity,
+            code,
+            message
+        )
+    }
+}
+
+/// Something a statement declares or uses, checked across documents
+#[derive(Debug, Clone, PartialEq)]
+enum Item {
+    /// An `id` field
+    Id(String),
+    /// An `ExtRef` to another document or resource id
+    Ref(String),
+    /// A variable read by a formula
+    Variable(String),
+}
+
+/// Documents under `root` with the extension of `pipe`'s input format, sorted
+#[cfg(feature = "fs")]
+fn document_paths(root: &Path, pipe: &DokePipe) -> io::Result<Vec<PathBuf>> {
+    let extension = pipe.extension();
+    let pattern = root.join("**").join(format!("*.{}", extension));
+    let overrides = format!(".override.{}", extension);
+    let mut paths: Vec<PathBuf> = glob::glob(&pattern.to_string_lossy())
+        .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+        .filter_map(Result::ok)
+        .filter(|path| !path.to_string_lossy().ends_with(&overrides))
+        .collect();
+    paths.sort();
+    Ok(paths)
+}
+
+/// Documents checked together, see `check`
+/// ```
+/// use doke::{DokePipe, parsers::SentenceParser, workspace::Workspace};
+///
+/// let parser = SentenceParser::from_yaml("Effect".into(), r#"
+/// Upgrade:
+///   - "Upgrades into {next: ref}"
+/// Damage:
+///   - "Deals {damage: int} damage"
+/// "#).unwrap();
+/// let pipe = DokePipe::new().add(parser);
+///
+/// let mut workspace = Workspace::new();
+/// workspace.add_document("iron_sword.md", "Deals 5 damage\n\nUpgrades into steel_sword".into(), &pipe);
+/// workspace.add_document("steel_sword.md", "Deals 8 damage\n\nUpgrades into mithril_swrod".into(), &pipe);
+///
+/// let diagnostics = workspace.check();
+/// assert_eq!(diagnostics.len(), 1);
+/// assert_eq!(diagnostics[0].path.to_str(), Some("steel_sword.md"));
+/// assert_eq!(diagnostics[0].line, 3);
+/// assert_eq!(diagnostics[0].diagnostic.code, "workspace-unresolved-ref");
+/// ```
+#[derive(Debug, Default)]
+pub struct Workspace {
+    documents: Vec<WorkspaceDocument>,
+    /// Stats formulas may read, not checked if `None`
+    stats: Option<HashSet<String>>,
+    /// Directory the documents were read from
+    root: Option<PathBuf>,
+    /// Report content written twice, see `with_duplicate_lint`
+    duplicate_lint: bool,
+}
+
+impl Workspace {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Every document under `root` with the extension of `pipe`'s input format
+    /// (`.md` by default), override files aside.
+    ///
+    /// With the `csv` feature, the rows of every `<name>.csv` next to a `<name>.csvmap.yaml`
+    /// mapping too.
+    #[cfg(feature = "fs")]
+    pub fn from_dir(root: &Path, pipe: &DokePipe) -> io::Result<Self> {
+        let mut workspace = Self {
+            root: Some(root.to_path_buf()),
+            ..Self::new()
+        };
+        for path in document_paths(root, pipe)? {
+            let source = fs::read_to_string(&path)?;
+            workspace.add_document(path, source, pipe);
+        }
+        #[cfg(feature = "csv")]
+        workspace.add_csv_dir(root, pipe)?;
+        Ok(workspace)
+    }
+
+    #[cfg(all(feature = "fs", feature = "csv"))]
+    fn add_csv_dir(&mut self, root: &Path, pipe: &DokePipe) -> io::Result<()> {
+        let pattern = root.join("**").join("*.csv");
+        let mut paths: Vec<PathBuf> = glob::glob(&pattern.to_string_lossy())
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?
+            .filter_map(Result::ok)
+            .collect();
+        paths.sort();
+        for path in paths {
+            let mapping_path = path.with_extension("csvmap.yaml");
+            if !mapping_path.exists() {
+                continue;
+            }
+            let invalid = |e: SpreadsheetError| {
+                io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    format!("{}: {}", path.display(), e),
+                )
+            };
+            let mapping = CsvMapping::from_file(&mapping_path).map_err(invalid)?;
+            let text = fs::read_to_string(&path)?;
+            self.add_csv(&path, &text, &mapping, pipe)
+                .map_err(invalid)?;
+        }
+        Ok(())
+    }
+
+    /// Stats formula variables must be one of, e.g. `["STR", "level"]`
+    pub fn with_stats<I, S>(mut self, stats: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.stats = Some(stats.into_iter().map(Into::into).collect());
+        self
+    }
+
+    /// Make `check` warn about statements repeating a sibling and documents building
+    /// the same values as another one under a different id, see `duplicates`
+    pub fn with_duplicate_lint(mut self, enabled: bool) -> Self {
+        self.duplicate_lint = enabled;
+        self
+    }
+
+    /// Parse and validate `source` with `pipe`, each variant of it when it has
+    /// a `matrix`, see `template::expand_matrix`
+    pub fn add_document(&mut self, path: impl Into<PathBuf>, source: String, pipe: &DokePipe) {
+        let path = path.into();
+        match template::expand_matrix(&source) {
+            Err(TemplateError::NotATemplate) => self
+                .documents
+                .push(WorkspaceDocument::parse(path, source, pipe)),
+            Err(e) => {
+                let mut document = WorkspaceDocument::parse(path, source, pipe);
+                document.values = Err(DokeValidationError::NodeError(
+                    MATRIX_KEY.into(),
+                    e.to_string(),
+                ));
+                self.documents.push(document);
+            }
+            Ok(variants) => {
+                for variant in variants {
+                    let mut document = WorkspaceDocument::parse(path.clone(), variant.source, pipe);
+                    // without a naming pattern nor an id of their own, the values tell variants apart
+                    if variant.id.is_none() && !document.document.frontmatter.contains_key("id") {
+                        for (_, value) in &variant.values {
+                            document.id = format!("{}_{}", document.id, value);
+                        }
+                    }
+                    self.documents.push(document);
+                }
+            }
+        }
+    }
+
+    /// Parse and validate every document of the CSV sheet `text` with `pipe`,
+    /// see `spreadsheet::CsvMapping`
+    #[cfg(feature = "csv")]
+    pub fn add_csv(
+        &mut self,
+        path: impl Into<PathBuf>,
+        text: &str,
+        mapping: &CsvMapping,
+        pipe: &DokePipe,
+    ) -> Result<(), SpreadsheetError> {
+        let path = path.into();
+        for sheet in mapping.read_csv(text)? {
+            let mut document = pipe.run_markdown(&sheet.source);
+            let values = pipe.validate_document(&mut document);
+            self.documents.push(WorkspaceDocument {
+                path: path.clone(),
+                id: sheet.id,
+                inherits: inherits(&document),
+                source: sheet.source,
+                document,
+                values,
+                source_lines: sheet.lines,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn documents(&self) -> &[WorkspaceDocument] {
+        &self.documents
+    }
+
+    /// The document with this id
+    pub fn document(&self, id: &str) -> Option<&WorkspaceDocument> {
+        self.documents.iter().find(|doc| doc.id == id)
+    }
+
+    /// Every violation of the workspace, in document order:
+    /// - documents that don't validate
+    /// - ids declared more than once, by documents or `id` fields
+    /// - references to ids that don't exist
+    /// - formula variables that aren't declared stats
+    /// - with `with_duplicate_lint`, statements repeating a sibling and documents
+    ///   identical to another one but for their id (warnings)
+    ///
+    /// Codes a document allows in its `doke_allow` frontmatter are left out,
+    /// see `suppressed`.
+    pub fn check(&self) -> Vec<WorkspaceDiagnostic> {
+        let allowed = self.allowed_codes();
+        self.violations()
+            .into_iter()
+            .filter(|d| !allowed[d.path.as_path()].contains(d.diagnostic.code))
+            .collect()
+    }
+
+    /// Diagnostics silenced by `doke-allow` directives, of statements and of `check`
+    pub fn suppressed(&self) -> Vec<WorkspaceDiagnostic> {
+        let mut out: Vec<WorkspaceDiagnostic> = self
+            .documents
+            .iter()
+            .flat_map(|doc| {
+                doc.document
+                    .suppressed
+                    .iter()
+                    .map(|diagnostic| WorkspaceDiagnostic {
+                        path: doc.path.clone(),
+                        line: diagnostic.span.as_ref().map_or(1, |span| doc.line(span)),
+                        diagnostic: diagnostic.clone(),
+                    })
+            })
+            .collect();
+        let allowed = self.allowed_codes();
+        out.extend(
+            self.violations()
+                .into_iter()
+                .filter(|d| allowed[d.path.as_path()].contains(d.diagnostic.code)),
+        );
+        out
+    }
+
+    /// Codes allowed by the frontmatter of the documents of each file
+    fn allowed_codes(&self) -> HashMap<&Path, HashSet<String>> {
+        let mut out: HashMap<&Path, HashSet<String>> = HashMap::new();
+        for doc in &self.documents {
+            out.entry(&doc.path)
+                .or_default()
+                .extend(suppress::allowed(doc.document.frontmatter.get(ALLOW_KEY)));
+        }
+        out
+    }
+
+    fn violations(&self) -> Vec<WorkspaceDiagnostic> {
+        let mut out = Vec::new();
+        let located = |doc: &WorkspaceDocument, line, diagnostic| WorkspaceDiagnostic {
+            path: doc.path.clone(),
+            line,
+            diagnostic,
+        };
+
+        // where each id is declared
+        let mut declared: HashMap<&str, Vec<(&WorkspaceDocument, usize)>> = HashMap::new();
+        let items = self.items();
+        for (doc, items) in self.documents.iter().zip(&items) {
+            declared.entry(&doc.id).or_default().push((doc, 1));
+            for (item, line) in items {
+                if let Item::Id(id) = item {
+                    declared.entry(id).or_default().push((doc, *line));
+                }
+            }
+        }
+
+        for (doc, items) in self.documents.iter().zip(&items) {
+            if let Err(e) = &doc.values {
+                out.push(located(
+                    doc,
+                    1,
+                    Diagnostic::error("workspace-invalid-document", e.to_string()),
+                ));
+            }
+            if let Some((first, _)) = declared[doc.id.as_str()].first()
+                && !std::ptr::eq(*first, doc)
+            {
+                out.push(located(
+                    doc,
+                    1,
+                    Diagnostic::error(
+                        "workspace-duplicate-id",
+                        format!(
+                            "id '{}' is already declared by {}",
+                            doc.id,
+                            first.path.display()
+                        ),
+                    ),
+                ));
+            }
+            if let Some(parent) = &doc.inherits
+                && self.document(parent).is_none()
+            {
+                out.push(located(
+                    doc,
+                    1,
+                    Diagnostic::error(
+                        "workspace-unknown-parent",
+                        format!("inherits from '{}', which isn't a document", parent),
+                    ),
+                ));
+            }
+            for (item, line) in items {
+                let diagnostic = match item {
+                    Item::Id(id) => match declared[id.as_str()].first() {
+                        Some((first, first_line))
+                            if !(std::ptr::eq(*first, doc) && first_line == line) =>
+                        {
+                            Diagnostic::error(
+                                "workspace-duplicate-id",
+                                format!(
+                                    "id '{}' is already declared at {}:{}",
+                                    id,
+                                    first.path.display(),
+                                    first_line
+                                ),
+                            )
+                        }
+                        _ => continue,
+                    },
+                    Item::Ref(id) if !declared.contains_key(id.as_str()) => Diagnostic::error(
+                        "workspace-unresolved-ref",
+                        format!("nothing in the workspace has the id '{}'", id),
+                    ),
+                    Item::Variable(name) => match &self.stats {
+                        Some(stats) if !stats.contains(stat_name(name)) => Diagnostic::error(
+                            "workspace-unknown-stat",
+                            format!("formula reads '{}', which isn't a declared stat", name),
+                        ),
+                        _ => continue,
+                    },
+                    Item::Ref(_) => continue,
+                };
+                out.push(located(doc, *line, diagnostic));
+            }
+        }
+
+        if self.duplicate_lint {
+            out.extend(self.duplicates());
+        }
+
+        if let Err(cycle) = self.dependency_graph().build_order() {
+            let first = self
+                .document(cycle[0])
+                .expect("cycles are made of documents");
+            out.push(located(
+                first,
+                1,
+                Diagnostic::error(
+                    "workspace-dependency-cycle",
+                    format!("documents reference each other: {}", cycle.join(" -> ")),
+                ),
+            ));
+        }
+        out
+    }
+
+    /// Warnings of the duplicate lint, see `duplicates`
+    fn duplicates(&self) -> Vec<WorkspaceDiagnostic> {
+        let mut out = Vec::new();
+        // documents by their content without their id, as JSON with sorted keys
+        let mut contents: HashMap<String, &WorkspaceDocument> = HashMap::new();
+        for doc in &self.documents {
+            for diagnostic in duplicates::duplicate_siblings(&doc.document.nodes) {
+                out.push(WorkspaceDiagnostic {
+                    path: doc.path.clone(),
+                    line: diagnostic.span.as_ref().map_or(1, |span| doc.line(span)),
+                    diagnostic,
+                });
+            }
+            let Ok(values) = &doc.values else {
+                continue;
+            };
+            let mut frontmatter = doc.document.frontmatter.clone();
+            frontmatter.remove("id");
+            if frontmatter.is_empty() && values.is_empty() {
+                continue;
+            }
+            let values = values.iter().map(|(value, _)| value.clone()).collect();
+            let content = json::to_json(&GodotValue::Array(vec![
+                GodotValue::Dict(frontmatter),
+                GodotValue::Array(values),
+            ]));
+            match contents.get(&content) {
+                Some(first) => out.push(WorkspaceDiagnostic {
+                    path: doc.path.clone(),
+                    line: 1,
+                    diagnostic: Diagnostic::warning(
+                        duplicates::DUPLICATE_RESOURCE_CODE,
+                        format!(
+                            "'{}' builds the same resource as '{}' ({}), copied and not edited?",
+                            doc.id,
+                            first.id,
+                            first.path.display()
+                        ),
+                    ),
+                }),
+                None => {
+                    contents.insert(content, doc);
+                }
+            }
+        }
+        out
+    }
+
+    /// Which documents each document references, resolving ids declared by `id` fields
+    /// to the document declaring them, and the documents they inherit from
+    pub fn dependency_graph(&self) -> DependencyGraph {
+        let items = self.items();
+        let owner = self.owners(&items);
+        let mut graph = DependencyGraph::new(self.documents.iter().map(|doc| doc.id.clone()));
+        for (i, items) in items.iter().enumerate() {
+            for (item, _) in items {
+                if let Item::Ref(id) = item
+                    && let Some(&target) = owner.get(id.as_str())
+                {
+                    graph.add_edge(i, target);
+                }
+            }
+        }
+        for (i, doc) in self.documents.iter().enumerate() {
+            if let Some(parent) = &doc.inherits
+                && let Some(target) = self.documents.iter().position(|d| &d.id == parent)
+            {
+                graph.add_edge(i, target);
+            }
+        }
+        graph
+    }
+
+    /// Build every document with `builder` and write it to `dir` as `<id>.tres`,
+    /// referenced documents first, then the list of written files as `manifest.json`.
+    ///
+    /// Fails without writing anything when two documents have the same id.
+    /// ```
+    /// use doke::export::{ExportError, TresExport};
+    /// use doke::file_builder::{Config, ResourceBuilder};
+    /// use doke::{DokePipe, parsers::FrontmatterTemplateParser, workspace::Workspace};
+    ///
+    /// let pipe = DokePipe::new().add(FrontmatterTemplateParser);
+    /// let mut workspace = Workspace::new();
+    /// workspace.add_document("weapons/sword.md", "---\ndamage: 5\n---\n".into(), &pipe);
+    /// workspace.add_document("armor/sword.md", "---\narmor: 2\n---\n".into(), &pipe);
+    /// let builder = ResourceBuilder::from_config(Config { root: "Item".into(), ..Default::default() }).unwrap();
+    ///
+    /// let dir = std::env::temp_dir().join("doke_duplicate_ids");
+    /// let error = workspace.export_tres(&builder, &TresExport::default(), &dir).unwrap_err();
+    /// assert!(matches!(&error, ExportError::DuplicateId(id, ..) if id == "sword"));
+    /// assert!(!dir.join("sword.tres").exists());
+    /// ```
+    pub fn export_tres(
+        &self,
+        builder: &ResourceBuilder,
+        export: &TresExport,
+        dir: &Path,
+    ) -> Result<Manifest, ExportError> {
+        self.export(builder, export, dir, |id, resource, documents| {
+            let tres = export.to_tres(id, resource, documents);
+            (
+                export.resource_path(id),
+                format!("{}.tres", id),
+                tres.into_bytes(),
+            )
+        })
+    }
+
+    /// Same as `export_tres`, writing `<id>.dokb` files in the binary format of `export::binary`
+    pub fn export_binary(
+        &self,
+        builder: &ResourceBuilder,
+        export: &TresExport,
+        dir: &Path,
+    ) -> Result<Manifest, ExportError> {
+        self.export(builder, export, dir, |id, resource, documents| {
+            let bytes = export.to_binary(resource, documents);
+            (export.binary_path(id), format!("{}.dokb", id), bytes)
+        })
+    }
+
+    /// `write` gives the `res://` path, file name and content of each document
+    fn export(
+        &self,
+        builder: &ResourceBuilder,
+        export: &TresExport,
+        dir: &Path,
+        write: impl Fn(&str, &GodotValue, &HashMap<String, String>) -> (String, String, Vec<u8>),
+    ) -> Result<Manifest, ExportError> {
+        let documents = self.ref_documents();
+        let built = self.build_migrated(builder)?;
+
+        fs::create_dir_all(dir)?;
+        let mut manifest = Manifest {
+            grammar_version: export.grammar_version.clone(),
+            resources: Vec::new(),
+        };
+        for (doc, resource) in built {
+            let (path, file_name, content) = write(&doc.id, &resource, &documents);
+            fs::write(dir.join(file_name), &content)?;
+
+            let source = match &self.root {
+                Some(root) => doc.path.strip_prefix(root).unwrap_or(&doc.path),
+                None => &doc.path,
+            };
+            manifest.resources.push(ManifestEntry {
+                id: doc.id.clone(),
+                path,
+                uid: export.uid(&doc.id),
+                source: source.to_path_buf(),
+                sha256: format!("{:x}", Sha256::digest(&content)),
+            });
+        }
+        fs::write(dir.join("manifest.json"), manifest.to_json())?;
+        Ok(manifest)
+    }
+
+    /// Build every document with `builder` and write them all to a SQLite database,
+    /// see `export::write_sqlite`.
+    #[cfg(feature = "sqlite")]
+    pub fn export_sqlite(&self, builder: &ResourceBuilder, path: &Path) -> Result<(), ExportError> {
+        let built = self.build_migrated(builder)?;
+        crate::export::write_sqlite(path, built.iter().map(|(doc, r)| (doc.id.as_str(), r)))
+    }
+
+    /// Every document built with `builder`, referenced documents first
+    fn build_all(
+        &self,
+        builder: &ResourceBuilder,
+    ) -> Result<Vec<(&WorkspaceDocument, GodotValue)>, ExportError> {
+        // each document is written to a file named after its id
+        let mut paths: HashMap<&str, &Path> = HashMap::new();
+        for doc in &self.documents {
+            if let Some(first) = paths.insert(&doc.id, &doc.path) {
+                return Err(ExportError::DuplicateId(
+                    doc.id.clone(),
+                    first.to_path_buf(),
+                    doc.path.clone(),
+                ));
+            }
+        }
+        let graph = self.dependency_graph();
+        let order = graph
+            .build_order_indices()
+            .map_err(|cycle| ExportError::Cycle(cycle.join(" -> ")))?;
+        let mut built: Vec<(&WorkspaceDocument, GodotValue)> = Vec::with_capacity(order.len());
+        for i in order {
+            let doc = &self.documents[i];
+            let values = match &doc.values {
+                Ok(values) => values.iter().map(|(v, _)| v.clone()).collect(),
+                Err(e) => return Err(ExportError::Invalid(doc.path.clone(), e.to_string())),
+            };
+            let parent = doc
+                .inherits
+                .as_ref()
+                .and_then(|parent| built.iter().find(|(d, _)| &d.id == parent));
+            let resource = match parent {
+                Some((_, parent)) => builder.build_file_resource_inheriting(values, parent),
+                None => builder.build_file_resource(values),
+            }
+            .map_err(|e| ExportError::Build(doc.path.clone(), e))?;
+            built.push((doc, resource));
+        }
+        Ok(built)
+    }
+
+    /// Every document built like `build_all`, for the schema version of `builder`
+    fn build_migrated(
+        &self,
+        builder: &ResourceBuilder,
+    ) -> Result<Vec<(&WorkspaceDocument, GodotValue)>, ExportError> {
+        let mut built = self.build_all(builder)?;
+        for (doc, resource) in built.iter_mut() {
+            builder
+                .migrate(resource)
+                .map_err(|e| ExportError::Build(doc.path.clone(), e))?;
+        }
+        Ok(built)
+    }
+
+    /// The document declaring each id `ExtRef`s can point at
+    fn ref_documents(&self) -> HashMap<String, String> {
+        let items = self.items();
+        self.owners(&items)
+            .into_iter()
+            .map(|(id, i)| (id.to_string(), self.documents[i].id.clone()))
+            .collect()
+    }
+
+    /// Index of the document declaring each id, first one wins
+    fn owners<'a>(&'a self, items: &'a [Vec<(Item, usize)>]) -> HashMap<&'a str, usize> {
+        let mut owner: HashMap<&str, usize> = HashMap::new();
+        for (i, doc) in self.documents.iter().enumerate() {
+            owner.entry(&doc.id).or_insert(i);
+        }
+        for (i, items) in items.iter().enumerate() {
+            for (item, _) in items {
+                if let Item::Id(id) = item {
+                    owner.entry(id).or_insert(i);
+                }
+            }
+        }
+        owner
+    }
+
+    /// Items of each document, with their line
+    fn items(&self) -> Vec<Vec<(Item, usize)>> {
+        self.documents
+            .iter()
+            .map(|doc| {
+                collect_items(&doc.document.nodes)
+                    .into_iter()
+                    .map(|(item, span)| (item, doc.line(&span)))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// The frontmatter `inherits` of a document
+fn inherits(document: &DokeDocument) -> Option<String> {
+    match document.frontmatter.get(INHERITS_KEY) {
+        Some(GodotValue::String(parent)) => Some(parent.clone()),
+        _ => None,
+    }
+}
+
+/// "target.HP" → "HP"
+fn stat_name(variable: &str) -> &str {
+    variable.rsplit('.').next().unwrap_or(variable)
+}
+
+/// Items of the resolved nodes, each on the deepest statement whose value holds it
+fn collect_items(nodes: &[DokeNode]) -> Vec<(Item, Position)> {
+    let mut out = Vec::new();
+    for node in nodes {
+        out.extend(node_items(node));
+    }
+    out
+}
+
+fn node_items(node: &DokeNode) -> Vec<(Item, Position)> {
+    let mut found = collect_items(&node.children);
+    found.extend(node.constituents.values().flat_map(node_items));
+
+    let mut own = Vec::new();
+    if let DokeNodeState::Resolved(resolved) = &node.state {
+        value_items(&resolved.to_godot(), &mut own);
+    }
+    // what children hold is already placed on them
+    for (item, _) in &found {
+        if let Some(i) = own.iter().position(|o| o == item) {
+            own.remove(i);
+        }
+    }
+    found.extend(own.into_iter().map(|item| (item, node.span.clone())));
+    found
+}
+
+fn value_items(value: &GodotValue, out: &mut Vec<Item>) {
+    match value {
+        GodotValue::Resource {
+            type_name, fields, ..
+        } => {
+            let string = |key: &str| match fields.get(key) {
+                Some(GodotValue::String(s)) => Some(s.clone()),
+                _ => None,
+            };
+            if type_name == EXT_REF_TYPE {
+                out.extend(string("id").map(Item::Ref));
+                return;
+            }
+            if type_name == "Formula"
+                && let Some(GodotValue::Array(variables)) = fields.get("variables")
+            {
+                for variable in variables {
+                    if let GodotValue::String(name) = variable {
+                        out.push(Item::Variable(name.clone()));
+                    }
+                }
+                return;
+            }
+            out.extend(string("id").map(Item::Id));
+            let mut keys: Vec<&String> = fields.keys().collect();
+            keys.sort();
+            for key in keys {
+                value_items(&fields[key], out);
+            }
+        }
+        GodotValue::Dict(map) => {
+            let mut keys: Vec<&String> = map.keys().collect();
+            keys.sort();
+            for key in keys {
+                value_items(&map[key], out);
+            }
+        }
+        GodotValue::Array(items) => {
+            for item in items {
+                value_items(item, out);
+            }
+        }
+        _ => {}
+    }
+}
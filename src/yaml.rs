@@ -0,0 +1,171 @@
+// yaml.rs
+//
+// Loading frontmatter YAML. `YamlLoader` fails on a key given twice, which
+// dropped the whole frontmatter, and keeps merge keys as a `<<` entry. Here
+//
+//     base: &base
+//       damage: 5
+//       speed: 2
+//     sword:
+//       <<: *base
+//       speed: 3
+//     damage: 4
+//     damage: 6
+//
+// `sword` gets `damage: 5` and its own `speed: 3` (keys written in the map win
+// over merged ones), and the second `damage` is ignored with a warning naming
+// its line. Aliases are copies of their anchored value, like `YamlLoader` does.
+
+use std::collections::BTreeMap;
+
+use yaml_rust2::parser::{MarkedEventReceiver, Parser, Tag};
+use yaml_rust2::scanner::{Marker, TScalarStyle};
+use yaml_rust2::yaml::Hash;
+use yaml_rust2::{Event, ScanError, Yaml};
+
+use crate::diagnostics::Diagnostic;
+
+/// Key merging the maps it's given into the map holding it
+const MERGE_KEY: &str = "<<";
+
+/// The first document of `source`, and warnings about its duplicate keys
+pub(crate) fn load(source: &str) -> Result<(Yaml, Vec<Diagnostic>), ScanError> {
+    let mut loader = Loader::default();
+    Parser::new_from_str(source).load(&mut loader, false)?;
+    let diagnostics = loader
+        .duplicates
+        .into_iter()
+        .map(|(key, line)| {
+            Diagnostic::warning(
+                "frontmatter-duplicate-key",
+                format!(
+                    "frontmatter key '{}' is given again at line {}, ignored",
+                    key,
+                    // after the opening `---`
+                    line + 1
+                ),
+            )
+        })
+        .collect();
+    Ok((loader.document.unwrap_or(Yaml::BadValue), diagnostics))
+}
+
+#[derive(Default)]
+struct Loader {
+    document: Option<Yaml>,
+    /// Open collections, with their anchor
+    stack: Vec<(Yaml, usize)>,
+    /// Pending key of each open map, and its line
+    keys: Vec<(Yaml, usize)>,
+    /// Maps merged into each open map
+    merges: Vec<Vec<Yaml>>,
+    anchors: BTreeMap<usize, Yaml>,
+    /// Keys given twice in a map, and the line of the second one
+    duplicates: Vec<(String, usize)>,
+}
+
+impl MarkedEventReceiver for Loader {
+    fn on_event(&mut self, event: Event, mark: Marker) {
+        match event {
+            Event::DocumentEnd => {
+                if self.document.is_none() {
+                    self.document = Some(self.stack.pop().map_or(Yaml::BadValue, |(n, _)| n));
+                }
+                self.stack.clear();
+            }
+            Event::SequenceStart(anchor, _) => self.stack.push((Yaml::Array(Vec::new()), anchor)),
+            Event::MappingStart(anchor, _) => {
+                self.stack.push((Yaml::Hash(Hash::new()), anchor));
+                self.keys.push((Yaml::BadValue, 0));
+                self.merges.push(Vec::new());
+            }
+            Event::SequenceEnd => {
+                if let Some(node) = self.stack.pop() {
+                    self.insert(node, mark);
+                }
+            }
+            Event::MappingEnd => {
+                self.keys.pop();
+                let merged = self.merges.pop().unwrap_or_default();
+                if let Some((mut node, anchor)) = self.stack.pop() {
+                    if let Yaml::Hash(map) = &mut node {
+                        merge(map, merged);
+                    }
+                    self.insert((node, anchor), mark);
+                }
+            }
+            Event::Scalar(value, style, anchor, tag) => {
+                self.insert((scalar(value, style, tag), anchor), mark)
+            }
+            Event::Alias(id) => {
+                let value = self.anchors.get(&id).cloned().unwrap_or(Yaml::BadValue);
+                self.insert((value, 0), mark);
+            }
+            Event::Nothing | Event::StreamStart | Event::StreamEnd | Event::DocumentStart => {}
+        }
+    }
+}
+
+impl Loader {
+    fn insert(&mut self, (node, anchor): (Yaml, usize), mark: Marker) {
+        // anchors start at 1
+        if anchor > 0 {
+            self.anchors.insert(anchor, node.clone());
+        }
+        match self.stack.last_mut() {
+            None => self.stack.push((node, anchor)),
+            Some((Yaml::Array(items), _)) => items.push(node),
+            Some((Yaml::Hash(map), _)) => {
+                let Some(slot) = self.keys.last_mut() else {
+                    return;
+                };
+                if slot.0.is_badvalue() {
+                    *slot = (node, mark.line());
+                    return;
+                }
+                let (key, line) = std::mem::replace(slot, (Yaml::BadValue, 0));
+                if key.as_str() == Some(MERGE_KEY) {
+                    if let Some(merges) = self.merges.last_mut() {
+                        merges.push(node);
+                    }
+                } else if map.contains_key(&key) {
+                    let name = match &key {
+                        Yaml::String(s) | Yaml::Real(s) => s.clone(),
+                        Yaml::Integer(i) => i.to_string(),
+                        other => format!("{:?}", other),
+                    };
+                    self.duplicates.push((name, line));
+                } else {
+                    map.insert(key, node);
+                }
+            }
+            Some(_) => {}
+        }
+    }
+}
+
+/// Add the entries of the `merged` maps (or lists of maps) that `map` doesn't have,
+/// earlier maps first
+fn merge(map: &mut Hash, merged: Vec<Yaml>) {
+    let sources = merged.into_iter().flat_map(|value| match value {
+        Yaml::Array(items) => items,
+        value => vec![value],
+    });
+    for source in sources {
+        if let Yaml::Hash(entries) = source {
+            for (key, value) in entries {
+                map.entry(key).or_insert(value);
+            }
+        }
+    }
+}
+
+/// A scalar as `YamlLoader` reads it: quoted ones and `!!str` are strings,
+/// plain ones are typed from their text
+fn scalar(value: String, style: TScalarStyle, tag: Option<Tag>) -> Yaml {
+    let is_str = tag.is_some_and(|t| t.handle == "tag:yaml.org,2002:" && t.suffix == "str");
+    match style != TScalarStyle::Plain || is_str {
+        true => Yaml::String(value),
+        false => Yaml::from_str(&value),
+    }
+}